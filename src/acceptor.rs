@@ -0,0 +1,141 @@
+// -------------------- FIX acceptor handle --------------------
+// The listening side of FIX ingest, as something the controls can stop,
+// start again and move to another port. The port is bound before `start`
+// returns, so one already in use is an error for the status line instead
+// of a listener thread that died. Stopping closes the port and has every
+// open session log out; their sequence numbers are kept as on any close.
+// Several can run side by side (production and test feeds, say); each has
+// a name that the spikes it takes in carry into the ledger. Every
+// connection has a thread of its own, so past the cap on open connections
+// (all listeners together) new ones are closed at once and counted.
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, TcpListener};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::fixdict::FixDictionary;
+use crate::fixqueue;
+use crate::session::Validation;
+use crate::{handle_fix_connection, tls};
+
+// The acceptor on the workspace's fix_addr
+pub const MAIN: &str = "main";
+
+// How often the listener looks up from waiting to check for a stop
+const POLL: Duration = Duration::from_millis(100);
+
+// Everything a listener needs besides its address; kept so it can be
+// started again
+#[derive(Clone)]
+pub struct Wiring {
+    pub tx: fixqueue::Sender,
+    pub validation: Validation,
+    pub dict: Arc<FixDictionary>,
+    pub tls: Option<Arc<rustls::ServerConfig>>,
+}
+
+pub struct Acceptor {
+    pub name: String,
+    pub addr: String,
+    // What the port was actually bound to, and whether it speaks TLS
+    pub bound: SocketAddr,
+    pub tls: bool,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Acceptor {
+    pub fn start(name: &str, addr: &str, wiring: &Wiring) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let bound = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        // Sessions of this listener only; a restarted one gets a fresh flag
+        let validation = Validation { closing: Arc::clone(&stop), ..wiring.validation.clone() };
+        let (tx, dict, tls) = (wiring.tx.clone(), Arc::clone(&wiring.dict), wiring.tls.clone());
+        let secured = if tls.is_some() { " (TLS)" } else { "" };
+        eprintln!("[FIX] {name}: listening on {addr}{secured}");
+        let listener_name = name.to_string();
+        let stopping = Arc::clone(&stop);
+        let thread = thread::spawn(move || {
+            while !stopping.load(Ordering::Relaxed) {
+                let (s, peer) = match listener.accept() {
+                    Ok((s, peer)) => (s, peer),
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                        thread::sleep(POLL);
+                        continue;
+                    }
+                    Err(e) => {
+                        eprintln!("[FIX] accept error: {e:?}");
+                        continue;
+                    }
+                };
+                if !validation.acl.check("FIX", peer) {
+                    continue;
+                }
+                let peer_ip = peer.ip();
+                let peer = peer.to_string();
+                if !validation.auth.admits_address(peer_ip) {
+                    validation.auth.refuse(&peer, "address not on the access list");
+                    continue;
+                }
+                let Some(open) = Open::claim(&validation) else {
+                    validation.crowded_out.fetch_add(1, Ordering::Relaxed);
+                    eprintln!("[FIX] {peer}: refused, {} connections already open", validation.max_connections);
+                    continue;
+                };
+                // Some platforms hand the listener's non-blocking mode on
+                if let Err(e) = s.set_nonblocking(false) {
+                    eprintln!("[FIX] {peer}: {e}");
+                    continue;
+                }
+                let txc = tx.clone();
+                let v = validation.clone();
+                let d = Arc::clone(&dict);
+                let tls = tls.clone();
+                let n = listener_name.clone();
+                thread::spawn(move || {
+                    let _open = open;
+                    match tls {
+                        None => handle_fix_connection(s, peer, &n, txc, v, d),
+                        Some(config) => match tls::accept(s, config) {
+                            Ok(s) => handle_fix_connection(s, peer, &n, txc, v, d),
+                            Err(e) => eprintln!("[FIX] {peer}: TLS handshake failed: {e}"),
+                        },
+                    }
+                });
+            }
+        });
+        Ok(Self { name: name.to_string(), addr: addr.to_string(), bound, tls: wiring.tls.is_some(), stop, thread: Some(thread) })
+    }
+
+    // Returns once the port is closed
+    pub fn stop(mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        eprintln!("[FIX] {}: stopped listening on {}", self.name, self.addr);
+    }
+}
+
+// One of the connections counted against Validation::max_connections,
+// given back when its thread ends
+struct Open(Arc<AtomicUsize>);
+
+impl Open {
+    fn claim(validation: &Validation) -> Option<Self> {
+        let max = validation.max_connections;
+        let open = &validation.connections;
+        open.fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| (max == 0 || n < max).then_some(n + 1)).ok()?;
+        Some(Self(Arc::clone(open)))
+    }
+}
+
+impl Drop for Open {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
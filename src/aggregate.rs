@@ -0,0 +1,126 @@
+// -------------------- Aggregate export --------------------
+// Activity patterns that can be shared publicly: counts per grid cell, per
+// region, per month, per weekday and per hour, with no event, note or
+// striker in the output. With an epsilon set, Laplace noise makes the
+// release differentially private at the event level: every event adds
+// exactly one to each of the histograms below (and the total), so the
+// budget is split evenly across them and each gets noise of scale
+// HISTOGRAMS / epsilon. Noisy counts are rounded and floored at zero,
+// which costs no privacy. The first and last month come from the data
+// and are not hidden. Without an epsilon the counts are exact and small
+// cells can still single someone out.
+use chrono::{Datelike, Timelike, Utc};
+use rand::Rng;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::regions::{self, Region};
+use crate::{IoError, NkisiNkondi};
+
+// Grid columns across the figure, as in the overlay grid
+const GRID_COLS: usize = 10;
+const NO_REGION: &str = "(none)";
+// Total, grid, regions, months, weekdays, hours
+const HISTOGRAMS: f64 = 6.0;
+
+#[derive(Serialize)]
+struct Count {
+    name: String,
+    count: u64,
+}
+
+#[derive(Serialize)]
+struct Aggregates<'a> {
+    figure: &'a str,
+    generated: String,
+    // None: exact counts
+    epsilon: Option<f64>,
+    total: u64,
+    // Cell counts, row by row from the top left; cells are square
+    grid: Vec<Vec<u64>>,
+    regions: Vec<Count>,
+    // "2025-03"
+    months: Vec<Count>,
+    // Monday first
+    weekdays: Vec<u64>,
+    // 0..24, UTC
+    hours: Vec<u64>,
+}
+
+// Laplace(0, scale) by inverse transform
+fn laplace(rng: &mut impl Rng, scale: f64) -> f64 {
+    let u: f64 = rng.gen_range(-0.5..0.5);
+    -scale * u.signum() * (1.0 - 2.0 * u.abs()).ln()
+}
+
+pub fn json(
+    figure: &str,
+    nkisi: &NkisiNkondi,
+    (fw, fh): (f32, f32),
+    figure_regions: &[Region],
+    epsilon: Option<f64>,
+) -> Result<String, IoError> {
+    if epsilon.is_some_and(|e| e.is_nan() || e <= 0.0) {
+        return Err(IoError::Write("epsilon must be positive".into()));
+    }
+    let step = fw / GRID_COLS as f32;
+    let rows = ((fh / step).ceil() as usize).max(1);
+    let mut grid = vec![vec![0u64; GRID_COLS]; rows];
+    // Every region is listed, so an absent one isn't a tell
+    let mut by_region: BTreeMap<String, u64> = figure_regions.iter().map(|r| (r.name.clone(), 0)).collect();
+    by_region.insert(NO_REGION.into(), 0);
+    let mut by_month: BTreeMap<String, u64> = BTreeMap::new();
+    let mut weekdays = vec![0u64; 7];
+    let mut hours = vec![0u64; 24];
+    for ev in &nkisi.events {
+        let col = ((ev.pos.0 / step).floor().max(0.0) as usize).min(GRID_COLS - 1);
+        let row = ((ev.pos.1 / step).floor().max(0.0) as usize).min(rows - 1);
+        grid[row][col] += 1;
+        let region = regions::hit(figure_regions, ev.pos).map_or(NO_REGION.to_string(), |r| r.name.clone());
+        *by_region.entry(region).or_default() += 1;
+        *by_month.entry(ev.date.format("%Y-%m").to_string()).or_default() += 1;
+        weekdays[ev.date.weekday().num_days_from_monday() as usize] += 1;
+        hours[ev.date.hour() as usize] += 1;
+    }
+    // Months are only known from the data; fill the gaps between the first
+    // and last so empty months get noise like the others
+    if let (Some(first), Some(last)) = (by_month.keys().next().cloned(), by_month.keys().last().cloned()) {
+        let mut month = first;
+        while month < last {
+            by_month.entry(month.clone()).or_default();
+            month = next_month(&month);
+        }
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut noisy = |n: u64| match epsilon {
+        Some(e) => (n as f64 + laplace(&mut rng, HISTOGRAMS / e)).round().max(0.0) as u64,
+        None => n,
+    };
+    let counts = |m: BTreeMap<String, u64>, noisy: &mut dyn FnMut(u64) -> u64| {
+        m.into_iter().map(|(name, n)| Count { name, count: noisy(n) }).collect()
+    };
+    let out = Aggregates {
+        figure,
+        generated: Utc::now().to_rfc3339(),
+        epsilon,
+        total: noisy(nkisi.events.len() as u64),
+        grid: grid.into_iter().map(|r| r.into_iter().map(&mut noisy).collect()).collect(),
+        regions: counts(by_region, &mut noisy),
+        months: counts(by_month, &mut noisy),
+        weekdays: weekdays.into_iter().map(&mut noisy).collect(),
+        hours: hours.into_iter().map(&mut noisy).collect(),
+    };
+    serde_json::to_string_pretty(&out).map_err(|e| IoError::Write(e.to_string()))
+}
+
+// "2025-12" -> "2026-01"
+fn next_month(month: &str) -> String {
+    let (y, m) = month.split_once('-').unwrap_or((month, "1"));
+    let (y, m): (i32, u32) = (y.parse().unwrap_or(0), m.parse().unwrap_or(1));
+    if m >= 12 {
+        format!("{:04}-01", y + 1)
+    } else {
+        format!("{y:04}-{:02}", m + 1)
+    }
+}
@@ -0,0 +1,91 @@
+// -------------------- API tokens --------------------
+// Who may use the HTTP endpoints on the metrics address (/metrics and the
+// event feed). Tokens are listed in the config file only, never as flags
+// where other users of the machine could read them off the process list:
+//   [[api_tokens]]
+//   name = "grafana"
+//   token = "…"
+//   scopes = ["read"]
+// A request carries one as "Authorization: Bearer <token>", or as ?token=
+// for clients that can't set headers. read is enough to see metrics and
+// follow the feed; write, for endpoints that change the ledger, includes
+// read. With no tokens listed the endpoints stay open as they always were.
+use serde::Deserialize;
+
+use crate::fixauth;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Read,
+    Write,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ApiToken {
+    pub name: String,
+    token: String,
+    scopes: Vec<Scope>,
+}
+
+impl ApiToken {
+    fn allows(&self, needed: Scope) -> bool {
+        self.scopes.iter().any(|s| *s == needed || *s == Scope::Write)
+    }
+}
+
+// Why a request was turned away: 401 without a token we know, 403 with
+// one that lacks the scope
+#[derive(Debug, PartialEq)]
+pub enum Denied {
+    Unauthorized,
+    Forbidden(String),
+}
+
+#[derive(Debug, Default)]
+pub struct ApiTokens {
+    tokens: Vec<ApiToken>,
+}
+
+impl ApiTokens {
+    pub fn new(tokens: Vec<ApiToken>) -> Result<Self, String> {
+        if let Some(t) = tokens.iter().find(|t| t.token.trim().is_empty()) {
+            return Err(format!("api token {} is empty", t.name));
+        }
+        if let Some(t) = tokens.iter().find(|t| t.scopes.is_empty()) {
+            return Err(format!("api token {} has no scopes", t.name));
+        }
+        Ok(Self { tokens })
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    // Name of the token presented, when it may do `needed`
+    pub fn check(&self, presented: Option<&str>, needed: Scope) -> Result<Option<&str>, Denied> {
+        if self.is_open() {
+            return Ok(None);
+        }
+        let found = presented
+            .and_then(|p| self.tokens.iter().find(|t| fixauth::same(t.token.as_bytes(), p.as_bytes())))
+            .ok_or(Denied::Unauthorized)?;
+        if !found.allows(needed) {
+            return Err(Denied::Forbidden(found.name.clone()));
+        }
+        Ok(Some(&found.name))
+    }
+}
+
+// The token of a request: its Authorization header, else ?token= on the
+// path; the path is returned without the query
+pub fn presented<'a>(target: &'a str, authorization: Option<&'a str>) -> (&'a str, Option<&'a str>) {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let bearer = authorization.and_then(|a| {
+        let (kind, token) = a.trim().split_once(' ')?;
+        kind.eq_ignore_ascii_case("bearer").then(|| token.trim())
+    });
+    let from_query = || query.split('&').find_map(|pair| pair.strip_prefix("token="));
+    (path, bearer.or_else(from_query))
+}
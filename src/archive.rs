@@ -0,0 +1,84 @@
+// -------------------- Archive --------------------
+// Resolved events past a cutoff move out of the active ledger into a
+// sibling `<ledger>.archive.json`, keeping the working file small on long
+// records. The archive is only opened when searched.
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::{ActivationEvent, IoError, NkisiNkondi, Outcome};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Archive {
+    pub figure_id: Option<Uuid>,
+    pub events: Vec<ActivationEvent>,
+}
+
+// nkisi_state.json -> nkisi_state.archive.json
+pub fn archive_path(save_path: &str) -> String {
+    let p = Path::new(save_path);
+    let stem = p.file_stem().and_then(|s| s.to_str()).unwrap_or("nkisi_state");
+    p.with_file_name(format!("{stem}.archive.json"))
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn load_archive(path: &str) -> Result<Archive, IoError> {
+    if !Path::new(path).exists() {
+        return Ok(Archive::default());
+    }
+    let bytes = std::fs::read(path).map_err(|e| IoError::Read(e.to_string()))?;
+    serde_json::from_slice(&bytes).map_err(|e| IoError::Parse(e.to_string()))
+}
+
+fn save_archive(path: &str, archive: &Archive) -> Result<(), IoError> {
+    let bytes = serde_json::to_vec_pretty(archive).map_err(|e| IoError::Write(e.to_string()))?;
+    std::fs::write(path, bytes).map_err(|e| IoError::Write(e.to_string()))
+}
+
+// Copy resolved events dated before `cutoff` into the archive next to
+// `save_path` and return their ids; recording them as archived then drops
+// them from the ledger. The archive is written first so a failure never
+// loses events.
+pub fn archive_resolved(
+    nkisi: &NkisiNkondi,
+    cutoff: DateTime<Utc>,
+    save_path: &str,
+) -> Result<Vec<Uuid>, IoError> {
+    let old: Vec<&ActivationEvent> = nkisi
+        .events
+        .iter()
+        .filter(|e| matches!(e.outcome, Outcome::Resolved) && e.date < cutoff)
+        .collect();
+    if old.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let path = archive_path(save_path);
+    let mut archive = load_archive(&path)?;
+    archive.figure_id.get_or_insert(nkisi.id);
+    for ev in &old {
+        // An earlier attempt may have got this far before failing
+        if !archive.events.iter().any(|a| a.id == ev.id) {
+            archive.events.push((*ev).clone());
+        }
+    }
+    save_archive(&path, &archive)?;
+    Ok(old.iter().map(|e| e.id).collect())
+}
+
+// Case-insensitive match on striker and notes
+pub fn search(path: &str, query: &str) -> Result<Vec<ActivationEvent>, IoError> {
+    let q = query.trim().to_lowercase();
+    let archive = load_archive(path)?;
+    Ok(archive
+        .events
+        .into_iter()
+        .filter(|e| {
+            q.is_empty()
+                || e.performed_by.to_lowercase().contains(&q)
+                || e.notes.as_deref().is_some_and(|n| n.to_lowercase().contains(&q))
+        })
+        .collect())
+}
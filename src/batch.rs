@@ -0,0 +1,203 @@
+// -------------------- Batch export --------------------
+// "Export all": every figure in the workspace written into one directory
+// as a CSV of its events, a layered SVG (figure plus overlay) and a PDF,
+// with a manifest.json listing what was written. Ledgers other than the
+// open one are read from disk, journal included, without being taken
+// over. A figure that fails is noted in the manifest and the rest go on.
+use chrono::Utc;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::path::Path;
+
+use crate::export::{self, Base, RasterOptions};
+use crate::regions::Region;
+use crate::report::{self, Locale};
+use crate::units::{Coords, Unit};
+use crate::workspace::{FigureRef, Workspace};
+use crate::{figure, layers, IoError, NkisiNkondi};
+
+// Resolution of the figure page in the PDF
+const PDF_DPI: f32 = 150.0;
+
+#[derive(Serialize)]
+struct Manifest<'a> {
+    workspace: &'a str,
+    exported_at: String,
+    locale: &'a str,
+    figures: Vec<Entry>,
+}
+
+#[derive(Serialize)]
+struct Entry {
+    name: String,
+    ledger: String,
+    events: usize,
+    // Relative to the export directory
+    files: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+// Draws the overlay for a ledger in a figure's coordinate space
+pub type Overlay = dyn Fn(&NkisiNkondi, (f32, f32), &[Region]) -> String;
+
+pub struct Summary {
+    pub exported: usize,
+    pub failed: usize,
+}
+
+// File name stem from a figure name; unique within the export
+fn slug(name: &str, taken: &mut Vec<String>) -> String {
+    let mut s: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    if s.is_empty() {
+        s = "figure".into();
+    }
+    let mut unique = s.clone();
+    let mut n = 2;
+    while taken.contains(&unique) {
+        unique = format!("{s}-{n}");
+        n += 1;
+    }
+    taken.push(unique.clone());
+    unique
+}
+
+fn photo_ext(src: &str) -> &str {
+    Path::new(src).extension().and_then(|e| e.to_str()).unwrap_or("png")
+}
+
+// The figure file with its coordinate space and, for an SVG that says, its
+// printed size in mm
+type Figure<'a> = (Base<'a>, (f32, f32), Option<(f32, f32)>);
+
+fn base(svg: &str) -> Result<Figure<'_>, IoError> {
+    match figure::kind(svg) {
+        figure::BaseKind::Raster => {
+            let dims = figure::raster_dims(svg)
+                .ok_or_else(|| IoError::Read(format!("{svg}: not a readable image")))?;
+            Ok((Base::Raster(svg), dims, None))
+        }
+        figure::BaseKind::Svg => {
+            let source =
+                std::fs::read_to_string(svg).map_err(|e| IoError::Read(format!("{svg}: {e}")))?;
+            let layered = layers::LayeredSvg::parse(source).map_err(IoError::Parse)?;
+            let dims = layered.view_box.unwrap_or_else(figure::default_dims);
+            Ok((Base::Svg(layered.source), dims, layered.printed))
+        }
+    }
+}
+
+fn write(dir: &Path, name: &str, contents: &[u8], files: &mut Vec<String>) -> Result<(), IoError> {
+    std::fs::write(dir.join(name), contents).map_err(|e| IoError::Write(format!("{name}: {e}")))?;
+    files.push(name.to_string());
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn export_figure(
+    dir: &Path,
+    stem: &str,
+    fig: &FigureRef,
+    nkisi: &NkisiNkondi,
+    locale: &Locale,
+    unit: Unit,
+    overlay: &Overlay,
+    files: &mut Vec<String>,
+) -> Result<(), IoError> {
+    let (base, src, printed) = base(&fig.svg)?;
+    let o = fig.orientation;
+    let dims = o.dims(src);
+    // Positions in the workspace's unit, for this figure
+    let coords = Coords { unit, dims, mm: printed.map(|mm| o.dims(mm)) };
+    let locale = &locale.clone().with_coords(coords);
+    write(dir, &format!("{stem}.csv"), report::csv(locale, nkisi, &fig.regions).as_bytes(), files)?;
+
+    let overlay = overlay(nkisi, dims, &fig.regions);
+    // A photo is copied next to the SVG that refers to it
+    let photo = match &base {
+        Base::Raster(src) => {
+            let name = format!("{stem}-photo.{}", photo_ext(src));
+            std::fs::copy(src, dir.join(&name))
+                .map_err(|e| IoError::Write(format!("{name}: {e}")))?;
+            files.push(name.clone());
+            Some(dir.join(name).to_string_lossy().into_owned())
+        }
+        Base::Svg(_) => None,
+    };
+    // A turned figure is wrapped in an SVG of its own, the photo referred
+    // to by name next to the exported SVG and by full path for the PDF
+    let svg_base = match (&base, &photo) {
+        _ if !o.is_identity() => {
+            let name = format!("{stem}-photo.{}", photo_ext(&fig.svg));
+            Base::Svg(export::orient(&base, o, src, &name))
+        }
+        (Base::Svg(src), _) => Base::Svg(src.clone()),
+        (Base::Raster(src), copied) => Base::Raster(copied.as_deref().unwrap_or(src)),
+    };
+    let base = if o.is_identity() { base } else { Base::Svg(export::orient(&base, o, src, &figure::href(&fig.svg))) };
+    write(dir, &format!("{stem}.svg"), export::svg(&svg_base, &overlay, dims).as_bytes(), files)?;
+
+    let options = RasterOptions { dpi: PDF_DPI, margin_mm: 0.0, transparent: false };
+    let pixmap = export::raster(&base, &overlay, dims, options)?;
+    let title = format!("{} — {}", locale.t("report.title"), fig.name);
+    let pdf_name = format!("{stem}.pdf");
+    crate::pdf::write(
+        &dir.join(&pdf_name).to_string_lossy(),
+        &title,
+        &pixmap,
+        &report::lines(locale, nkisi, &fig.regions),
+    )?;
+    files.push(pdf_name);
+    Ok(())
+}
+
+// `active` is the open figure's ledger as it stands in memory
+pub fn run(
+    dir: &str,
+    ws: &Workspace,
+    active: &NkisiNkondi,
+    locale: &Locale,
+    overlay: &Overlay,
+) -> Result<Summary, IoError> {
+    let dir = Path::new(dir);
+    std::fs::create_dir_all(dir).map_err(|e| IoError::Write(format!("{}: {e}", dir.display())))?;
+    let mut taken = vec![];
+    let mut figures = vec![];
+    for (i, fig) in ws.figures.iter().enumerate() {
+        let stem = slug(&fig.name, &mut taken);
+        let mut files = vec![];
+        let ledger = if i == ws.active {
+            Ok(Cow::Borrowed(active))
+        } else {
+            crate::read_ledger(&fig.ledger).map(Cow::Owned)
+        };
+        let (events, result) = match ledger {
+            Ok(nkisi) => (
+                nkisi.events.len(),
+                export_figure(dir, &stem, fig, &nkisi, locale, ws.settings.coordinate_unit, overlay, &mut files),
+            ),
+            Err(e) => (0, Err(e)),
+        };
+        figures.push(Entry {
+            name: fig.name.clone(),
+            ledger: fig.ledger.clone(),
+            events,
+            files,
+            error: result.err().map(|e| e.to_string()),
+        });
+    }
+    let failed = figures.iter().filter(|f| f.error.is_some()).count();
+    let manifest = Manifest {
+        workspace: &ws.name,
+        exported_at: Utc::now().to_rfc3339(),
+        locale: &locale.tag,
+        figures,
+    };
+    let json = serde_json::to_vec_pretty(&manifest).map_err(|e| IoError::Write(e.to_string()))?;
+    std::fs::write(dir.join("manifest.json"), json).map_err(|e| IoError::Write(e.to_string()))?;
+    Ok(Summary { exported: manifest.figures.len() - failed, failed })
+}
@@ -1,93 +1,609 @@
+// Test initiator: logs on to the acceptor, sends one U1 spike and reports
+// the acknowledgment. --proxy goes through a SOCKS5 or HTTP CONNECT proxy.
+// The session is the app's own (fix::Initiator): a dropped connection or
+// unanswered Logon is retried, and the spike sent again, until GIVE_UP.
+// --legacy writes the pre-0.2 35=SPK form (100/101/102, no session) for
+// old receivers; the acceptor doesn't take it. --repl keeps the session
+// open and reads spikes from stdin, one a line, numbering them on from
+// spike_id:
+//   spike 40 75 alice "broken oath"
+// Heartbeats and sequence numbers are the session's; spikes typed before
+// logon, or unanswered when the connection drops, go out once logged on.
+// quit (or the end of input) waits up to GIVE_UP for their answers.
+// send-file streams a CSV (x, y, who, note, timestamp) or JSON file of
+// spikes over one session, --delay milliseconds apart, for load tests and
+// historical imports; the timestamp goes out as TransactTime.
+// --fuzz N soak-tests the acceptor: N random spikes at --rate messages a
+// second (odd names and notes, some positions off the figure), about one
+// in twenty of them malformed on purpose. Broken frames (bad CheckSum or
+// BodyLength, junk between messages) are written between the session's
+// own, those that frame marked PossDup so a lenient acceptor drops them
+// too (it takes junk in with the next message, which may then be
+// rejected); spikes without a striker or with a position that isn't a
+// number go as real messages and should come back rejected. --seed
+// repeats a run.
+//
+//   cargo run --bin fixclient -- [--proxy socks5://host:port] [--legacy] [host:port] [spike_id] [who] [note] [x] [y]
+//   cargo run --bin fixclient -- --repl [--proxy socks5://host:port] [host:port] [spike_id]
+//   cargo run --bin fixclient -- [--delay ms] send-file spikes.csv [host:port] [first spike_id]
+//   cargo run --bin fixclient -- --fuzz 10000 [--rate 200] [--seed 7] [host:port] [first spike_id]
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
 use std::io::{self, Write};
 use std::net::TcpStream;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::sync::mpsc::{self, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-const SOH: u8 = 0x01;
+#[path = "../fix.rs"]
+#[allow(dead_code)]
+mod fix;
+#[path = "../proxy.rs"]
+mod proxy;
 
-/// Create a simple FIX spike message with `MsgType=SPK`.
-fn build_spike_message(spike_id: u32, who: &str, note: &str) -> Vec<u8> {
-    let mut out: Vec<u8> = Vec::with_capacity(256);
+use fix::{Event, Settings};
 
-    // Standard Header
-    push_fix_field(&mut out, 8, "FIX.4.2"); // BeginString
-    push_fix_field(&mut out, 9, "000");     // BodyLength placeholder
-    push_fix_field(&mut out, 35, "SPK");    // MsgType
+const SENDER: &str = "FIXCLIENT";
+const TARGET: &str = "NKISI";
+// How long to keep trying for the acknowledgment
+const GIVE_UP: Duration = Duration::from_secs(30);
+const TICK: Duration = Duration::from_millis(200);
+// Spikes send-file lets go unanswered at once
+const WINDOW: usize = 500;
+// One fuzzed message in this many is malformed
+const MALFORMED: u32 = 20;
+// How often a soak test says how far it is
+const PROGRESS: Duration = Duration::from_secs(10);
 
-    // Custom fields for spike
-    push_fix_field(&mut out, 100, &spike_id.to_string());
-    push_fix_field(&mut out, 101, who);
-    push_fix_field(&mut out, 102, note);
+/// A U1 spike; `spike_id` goes out as ClOrdID (11) and comes back on the ack.
+fn spike_fields(spike_id: u32, who: &str, note: &str, (x, y): (f32, f32)) -> Vec<(u32, String)> {
+    vec![
+        (11, spike_id.to_string()),
+        (55, "NKISI".into()),
+        (453, "1".into()),
+        (448, who.into()),
+        (452, "12".into()),
+        (58, note.into()),
+        (6010, x.to_string()),
+        (6011, y.to_string()),
+    ]
+    .into_iter()
+    .filter(|(tag, v)| *tag != 58 || !v.is_empty())
+    .collect()
+}
 
-    // Standard Trailer
-    let ts = current_fix_timestamp();
-    push_fix_field(&mut out, 52, &ts); // SendingTime
+/// The old form: 35=SPK with SpikeID (100), who (101) and note (102), no position.
+fn legacy_message(spike_id: u32, who: &str, note: &str) -> Vec<u8> {
+    let sent = chrono::Utc::now().format("%Y%m%d-%H:%M:%S");
+    let body = format!("35=SPK\u{1}100={spike_id}\u{1}101={who}\u{1}102={note}\u{1}52={sent}\u{1}");
+    let mut out = format!("8=FIX.4.2\u{1}9={}\u{1}{body}", body.len()).into_bytes();
+    let sum = fix::checksum(&out);
+    out.extend_from_slice(format!("10={sum:03}\u{1}").as_bytes());
+    out
+}
 
-    // Calculate BodyLength
-    let body_start = find_after_bodylen(&out).expect("body start");
-    let body_len = out.len() - body_start;
-    write_bodylen_in_place(&mut out, body_len).expect("rewrite 9=");
+fn printable(msg: &[u8]) -> String {
+    String::from_utf8_lossy(msg).replace('\u{1}', "|")
+}
 
-    // Checksum
-    let cksum = checksum(&out);
-    push_fix_field(&mut out, 10, &format!("{:03}", cksum));
+const REPL_HELP: &str = "spike <x> <y> <who> [note]   quotes keep spaces together\nquit";
 
-    out
+// Words of a typed line; "double quotes" keep spaces in one
+fn words(line: &str) -> Result<Vec<String>, &'static str> {
+    let (mut words, mut word, mut quoted, mut any) = (vec![], String::new(), false, false);
+    for c in line.chars() {
+        match c {
+            '"' => (quoted, any) = (!quoted, true),
+            c if c.is_whitespace() && !quoted => {
+                if any {
+                    words.push(std::mem::take(&mut word));
+                    any = false;
+                }
+            }
+            c => {
+                word.push(c);
+                any = true;
+            }
+        }
+    }
+    if quoted {
+        return Err("unclosed quote");
+    }
+    if any {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+// The fields of a typed `spike x y who [note]`
+fn typed_spike(spike_id: u32, args: &[String]) -> Result<Vec<(u32, String)>, String> {
+    let [x, y, who, note @ ..] = args else {
+        return Err(format!("usage: {}", REPL_HELP.lines().next().unwrap_or_default()));
+    };
+    let coord = |v: &str| v.parse::<f32>().map_err(|_| format!("{v}: not a number"));
+    let note = if note.is_empty() { "no note".to_string() } else { note.join(" ") };
+    Ok(spike_fields(spike_id, who, &note, (coord(x)?, coord(y)?)))
 }
 
-fn push_fix_field(buf: &mut Vec<u8>, tag: u32, value: &str) {
-    write!(buf, "{}={}", tag, value).unwrap();
-    buf.push(SOH);
+type Fields = Vec<(u32, String)>;
+
+// Spikes by ClOrdID waiting for logon, and sent ones by MsgSeqNum waiting
+// for their answer; a dropped connection puts those back in line
+#[derive(Default)]
+struct Outstanding {
+    waiting: BTreeMap<u32, Fields>,
+    sent: BTreeMap<u64, (u32, Fields)>,
 }
 
-fn current_fix_timestamp() -> String {
-    let now = chrono::Utc::now();
-    now.format("%Y%m%d-%H:%M:%S").to_string()
+// How one of ours was answered: "accepted", "pending" (held for review,
+// the final answer follows) or "rejected"
+struct Answer {
+    id: u32,
+    seq: u64,
+    how: &'static str,
+    event: Option<String>,
+    text: String,
 }
 
-fn find_after_bodylen(buf: &[u8]) -> Option<usize> {
-    let needle = b"9=";
-    let mut i = 0;
-    while i + 2 < buf.len() {
-        if &buf[i..i + 2] == needle {
-            let mut j = i + 2;
-            while j < buf.len() && buf[j] != SOH {
-                j += 1;
+impl Outstanding {
+    fn unanswered(&self) -> usize {
+        self.waiting.len() + self.sent.len()
+    }
+
+    // Polls the session once; an answer to one of ours comes back, and is
+    // forgotten unless it is only "pending"
+    fn poll(&mut self, session: &mut fix::Initiator, host: &str, wait: Duration) -> Option<Answer> {
+        match session.poll(wait)? {
+            Event::LoggedOn => println!("Logged on to {host}"),
+            Event::Message(reply) => {
+                let seq = reply.get(45).and_then(|v| v.parse::<u64>().ok())?;
+                let &(id, _) = self.sent.get(&seq)?;
+                let how = match (reply.msg_type(), reply.get(39)) {
+                    ("U2", Some("0")) => "accepted",
+                    ("U2", Some("A")) => "pending",
+                    ("U2" | "3" | "j", _) => "rejected",
+                    _ => return None,
+                };
+                if how != "pending" {
+                    self.sent.remove(&seq);
+                }
+                let (event, text) = (reply.get(9000).map(str::to_string), reply.get(58).unwrap_or("").to_string());
+                return Some(Answer { id, seq, how, event, text });
+            }
+            Event::Garbled(why) => eprintln!("Dropped a malformed message: {why}"),
+            Event::Down { why, retry } => {
+                if !self.sent.is_empty() {
+                    eprintln!("{} unanswered; they go again once logged on", self.sent.len());
+                }
+                let sent = std::mem::take(&mut self.sent);
+                self.waiting.extend(sent.into_values());
+                eprintln!("{host}: {why}; trying again in {}s", retry.as_secs());
+            }
+        }
+        None
+    }
+
+    // The next waiting spike sent, while logged on: its ClOrdID and MsgSeqNum
+    fn send_next(&mut self, session: &mut fix::Initiator) -> Option<(u32, u64)> {
+        if !session.logged_on() {
+            return None;
+        }
+        let (id, fields) = self.waiting.pop_first()?;
+        match session.send("U1", &fields) {
+            Ok(seq) => {
+                self.sent.insert(seq, (id, fields));
+                Some((id, seq))
+            }
+            Err(e) => {
+                eprintln!("Spike {id} not sent: {e}");
+                self.waiting.insert(id, fields);
+                None
             }
-            return if j < buf.len() { Some(j + 1) } else { None };
         }
-        i += 1;
     }
-    None
 }
 
-fn write_bodylen_in_place(buf: &mut Vec<u8>, len: usize) -> Option<()> {
-    let needle = b"9=";
-    let mut i = 0usize;
-    while i + 2 <= buf.len() {
-        if &buf[i..i + 2] == needle {
-            let mut j = i + 2;
-            while j < buf.len() && buf[j] != SOH {
-                j += 1;
+// One session for as many spikes as are typed, until quit or end of input
+fn repl(mut session: fix::Initiator, host: &str, mut next_id: u32) {
+    println!("Connecting to {host}. Type:\n{REPL_HELP}");
+    let (tx, lines) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in io::stdin().lines().map_while(Result::ok) {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    let mut out = Outstanding::default();
+    // After quit, or the end of input, until what was typed is answered
+    let mut leaving: Option<Instant> = None;
+    loop {
+        if let Some(deadline) = leaving {
+            let unanswered = out.unanswered();
+            if unanswered == 0 || Instant::now() > deadline {
+                if unanswered > 0 {
+                    eprintln!("Leaving {unanswered} unanswered");
+                }
+                session.logout();
+                return;
+            }
+        }
+        if let Some(a) = out.poll(&mut session, host, TICK) {
+            let event = a.event.map(|e| format!(" as event {e}")).unwrap_or_default();
+            println!("Spike {} {}{event}: {}", a.id, a.how, a.text);
+        }
+
+        while leaving.is_none() {
+            let line = match lines.try_recv() {
+                Ok(line) => line,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    leaving = Some(Instant::now() + GIVE_UP);
+                    break;
+                }
+            };
+            let typed = match words(&line) {
+                Ok(typed) => typed,
+                Err(why) => {
+                    eprintln!("{why}");
+                    continue;
+                }
+            };
+            match typed.first().map(String::as_str) {
+                None => {}
+                Some("quit" | "exit") => leaving = Some(Instant::now() + GIVE_UP),
+                Some("help") => println!("{REPL_HELP}"),
+                Some("spike") => match typed_spike(next_id, &typed[1..]) {
+                    Ok(fields) => {
+                        out.waiting.insert(next_id, fields);
+                        next_id += 1;
+                    }
+                    Err(why) => eprintln!("{why}"),
+                },
+                Some(other) => eprintln!("{other}: unknown command; type help"),
             }
-            if j >= buf.len() {
-                return None;
+        }
+
+        while let Some((id, seq)) = out.send_next(&mut session) {
+            println!("Sent spike {id} (MsgSeqNum {seq})");
+        }
+    }
+}
+
+// A spike read from a file; note and timestamp (TransactTime, ISO 8601 or
+// YYYYMMDD-HH:MM:SS) may be left blank
+#[derive(Debug, Deserialize)]
+struct Row {
+    x: f32,
+    y: f32,
+    who: String,
+    #[serde(default)]
+    note: String,
+    #[serde(default)]
+    timestamp: String,
+}
+
+// One CSV line: commas separate, "double quotes" keep commas, "" is a quote
+fn csv_cells(line: &str) -> Result<Vec<String>, &'static str> {
+    let (mut cells, mut cell, mut quoted) = (vec![], String::new(), false);
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
             }
-            let digits = len.to_string();
-            buf.splice(i + 2..j, digits.as_bytes().iter().copied());
-            return Some(());
+            '"' => quoted = !quoted,
+            ',' if !quoted => cells.push(std::mem::take(&mut cell)),
+            c => cell.push(c),
         }
-        i += 1;
     }
-    None
+    if quoted {
+        return Err("unclosed quote");
+    }
+    cells.push(cell);
+    Ok(cells)
 }
 
-fn checksum(buf: &[u8]) -> u32 {
-    buf.iter().fold(0u32, |acc, &b| acc + b as u32) % 256
+// A .json file is an array of rows; anything else is CSV, x, y, who, note,
+// timestamp, with a header line if the first doesn't start with a number
+fn read_rows(path: &str) -> Result<Vec<Row>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    if path.to_ascii_lowercase().ends_with(".json") {
+        return serde_json::from_str(&text).map_err(|e| format!("{path}: {e}"));
+    }
+    let (mut rows, mut first) = (vec![], true);
+    for (i, line) in text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        let at = |why: &str| format!("{path}:{}: {why}", i + 1);
+        let cells = csv_cells(line).map_err(at)?;
+        let cell = |j: usize| cells.get(j).map_or("", |c| c.trim());
+        let header = std::mem::replace(&mut first, false);
+        let (Ok(x), Ok(y)) = (cell(0).parse::<f32>(), cell(1).parse::<f32>()) else {
+            if header {
+                continue;
+            }
+            return Err(at("x and y must be numbers"));
+        };
+        if cell(2).is_empty() {
+            return Err(at("no striker"));
+        }
+        rows.push(Row { x, y, who: cell(2).into(), note: cell(3).into(), timestamp: cell(4).into() });
+    }
+    Ok(rows)
 }
 
+// Streams the spikes in `path` over one session, numbered on from
+// `first_id`, `delay` apart and at most WINDOW unanswered, then says how
+// they were answered. False if any were refused or went unanswered.
+fn send_file(mut session: fix::Initiator, host: &str, path: &str, first_id: u32, delay: Duration) -> bool {
+    let rows = match read_rows(path) {
+        Ok(rows) => rows,
+        Err(why) => {
+            eprintln!("{why}");
+            return false;
+        }
+    };
+    let mut out = Outstanding::default();
+    for (id, row) in (first_id..).zip(&rows) {
+        let mut fields = spike_fields(id, &row.who, &row.note, (row.x, row.y));
+        if !row.timestamp.is_empty() {
+            fields.push((60, row.timestamp.clone()));
+        }
+        out.waiting.insert(id, fields);
+    }
+    println!("Sending {} from {path} to {host}, {}ms apart", rows.len(), delay.as_millis());
+
+    let started = Instant::now();
+    let (mut due, mut progress) = (started, started);
+    let (mut accepted, mut held, mut rejected) = (0, 0, 0);
+    while out.unanswered() > 0 {
+        if progress.elapsed() > GIVE_UP {
+            eprintln!("Nothing from {host} for {}s; giving up", GIVE_UP.as_secs());
+            break;
+        }
+        let ready = !out.waiting.is_empty() && out.sent.len() < WINDOW && session.logged_on();
+        let wait = if ready { due.saturating_duration_since(Instant::now()).min(TICK) } else { TICK };
+        if let Some(a) = out.poll(&mut session, host, wait) {
+            progress = Instant::now();
+            match a.how {
+                "accepted" => accepted += 1,
+                "pending" => {
+                    // Held for review: answered as far as a load test goes
+                    out.sent.remove(&a.seq);
+                    held += 1;
+                }
+                _ => {
+                    rejected += 1;
+                    eprintln!("Row {} rejected: {}", a.id - first_id + 1, a.text);
+                }
+            }
+        }
+        while Instant::now() >= due && out.sent.len() < WINDOW && out.send_next(&mut session).is_some() {
+            progress = Instant::now();
+            due = progress + delay;
+        }
+    }
+
+    let secs = started.elapsed().as_secs_f64();
+    let unanswered = out.unanswered();
+    println!(
+        "{} in {secs:.1}s ({:.0}/s): {accepted} accepted, {held} held for review, {rejected} rejected, \
+         {unanswered} unanswered",
+        rows.len(),
+        rows.len() as f64 / secs.max(0.001)
+    );
+    session.logout();
+    rejected + unanswered == 0
+}
+
+// Strikers and notes for fuzzed spikes: accents, other scripts, and long
+// ones to see how the figure and the lists cope
+const NAMES: &[&str] = &[
+    "alice", "bob", "Ñgolo", "Mbemba", "Zoë", "李明", "Ọlọ́run", "José-María", "o'brien", "ngombo",
+    "a striker with a name much longer than anyone would type by hand",
+];
+const NOTES: &[&str] = &[
+    "", "oath", "broken oath", "healing, of a child", "dispute over land", "\"quoted\"", "nail, again",
+    "ὅρκος", "契約", "a note long enough to wrap in the event list and in the tooltip over the figure, twice over",
+];
+
+// A spike as a counterparty might send one, x and y mostly on the figure
+fn random_spike(rng: &mut StdRng, spike_id: u32) -> Fields {
+    let (x, y) = match rng.gen_ratio(1, 50) {
+        true => (rng.gen_range(-50.0..150.0), rng.gen_range(-50.0..200.0)),
+        false => (rng.gen_range(0.0..100.0), rng.gen_range(0.0..150.0)),
+    };
+    let mut who = NAMES.choose(rng).copied().unwrap_or("unknown").to_string();
+    if rng.gen_ratio(1, 4) {
+        who.push_str(&format!("-{}", rng.gen_range(1..1000)));
+    }
+    let note = NOTES.choose(rng).copied().unwrap_or_default();
+    spike_fields(spike_id, &who, note, ((x * 10.0f32).round() / 10.0, (y * 10.0f32).round() / 10.0))
+}
+
+// What a malformed message is: one sent as a real message that should be
+// rejected, or bytes that don't frame and should be dropped
+enum Malformed {
+    Rejected(&'static str, Fields),
+    Garbled(&'static str, Vec<u8>),
+}
+
+fn malformed(rng: &mut StdRng, spike_id: u32) -> Malformed {
+    let mut fields = random_spike(rng, spike_id);
+    let kind = rng.gen_range(0..6);
+    if kind == 0 {
+        fields.retain(|(tag, _)| *tag != 448);
+        return Malformed::Rejected("no striker", fields);
+    }
+    if kind == 1 {
+        fields.iter_mut().filter(|(tag, _)| *tag == 6010).for_each(|(_, v)| *v = "north-east".into());
+        return Malformed::Rejected("position not a number", fields);
+    }
+    // Sequence number 1 and PossDup: a lenient acceptor that frames it
+    // anyway drops it as already read instead of ending the session
+    fields.insert(0, (43, "Y".into()));
+    let header = fix::Header { begin: "FIX.4.4", sender: SENDER, target: TARGET };
+    let mut msg = fix::encode(&header, "U1", 1, &fields);
+    let soh = |msg: &[u8], n: usize| msg.iter().enumerate().filter(|(_, b)| **b == 1).nth(n).map_or(0, |(i, _)| i);
+    match kind {
+        2 => {
+            let at = msg.len() - 4;
+            msg[at] = if msg[at] == b'9' { b'0' } else { msg[at] + 1 };
+            Malformed::Garbled("bad CheckSum", msg)
+        }
+        3 => {
+            // BodyLength a few bytes short
+            let (from, to) = (soh(&msg, 0) + 3, soh(&msg, 1));
+            let len: usize = String::from_utf8_lossy(&msg[from..to]).parse().unwrap_or(0);
+            msg.splice(from..to, (len - rng.gen_range(1..10)).to_string().into_bytes());
+            Malformed::Garbled("BodyLength short", msg)
+        }
+        4 => {
+            let (from, to) = (soh(&msg, 0) + 3, soh(&msg, 1));
+            msg.splice(from..to, b"lots".iter().copied());
+            Malformed::Garbled("BodyLength not a number", msg)
+        }
+        _ => {
+            let n = rng.gen_range(1..200);
+            Malformed::Garbled("junk", (0..n).map(|_| rng.gen_range(b' '..=b'~')).collect())
+        }
+    }
+}
+
+// N random spikes at `rate` a second over one session, some malformed, and
+// a tally of the answers. False if any went unanswered, or weren't sent:
+// whatever was framed should be answered, however wrong it is.
+fn fuzz(mut session: fix::Initiator, wire: Arc<Mutex<Option<TcpStream>>>, host: &str, run: Fuzz) -> bool {
+    let mut rng = StdRng::seed_from_u64(run.seed);
+    let interval = Duration::from_secs_f64(1.0 / run.rate);
+    println!("Fuzzing {host}: {} messages at {}/s, seed {}", run.count, run.rate, run.seed);
+    let mut out = Outstanding::default();
+    // Spikes malformed on purpose, which should come back rejected
+    let mut bad: HashSet<u32> = HashSet::new();
+    let mut kinds: BTreeMap<&str, u32> = BTreeMap::new();
+    let (mut made, mut next_id) = (0, run.first_id);
+    let (mut accepted, mut held, mut rejected, mut refused, mut garbled) = (0, 0, 0, 0, 0);
+    let started = Instant::now();
+    let (mut due, mut progress, mut said) = (started, started, started);
+    while made < run.count || out.unanswered() > 0 {
+        if progress.elapsed() > GIVE_UP {
+            eprintln!("Nothing from {host} for {}s; giving up", GIVE_UP.as_secs());
+            break;
+        }
+        if said.elapsed() >= PROGRESS {
+            said = Instant::now();
+            println!("{made} made, {} unanswered, {:.0}s in", out.unanswered(), started.elapsed().as_secs_f64());
+        }
+        let ready = made < run.count && out.unanswered() < WINDOW && session.logged_on();
+        let wait = if ready { due.saturating_duration_since(Instant::now()).min(TICK) } else { TICK };
+        if let Some(a) = out.poll(&mut session, host, wait) {
+            progress = Instant::now();
+            match (a.how, bad.contains(&a.id)) {
+                ("accepted", true) => eprintln!("Spike {} malformed on purpose, but accepted", a.id),
+                ("accepted", false) => accepted += 1,
+                ("pending", _) => {
+                    out.sent.remove(&a.seq);
+                    held += 1;
+                }
+                (_, true) => refused += 1,
+                (_, false) => rejected += 1,
+            }
+        }
+        while made < run.count && Instant::now() >= due && out.unanswered() < WINDOW && session.logged_on() {
+            // Only as far behind as one tick, so a slow patch isn't made up in a burst
+            due = (due + interval).max(Instant::now() - TICK);
+            made += 1;
+            if !rng.gen_ratio(1, MALFORMED) {
+                out.waiting.insert(next_id, random_spike(&mut rng, next_id));
+            } else {
+                match malformed(&mut rng, next_id) {
+                    Malformed::Rejected(kind, fields) => {
+                        *kinds.entry(kind).or_default() += 1;
+                        bad.insert(next_id);
+                        out.waiting.insert(next_id, fields);
+                    }
+                    Malformed::Garbled(kind, bytes) => {
+                        if wire.lock().unwrap().as_mut().is_some_and(|s| s.write_all(&bytes).is_ok()) {
+                            *kinds.entry(kind).or_default() += 1;
+                            garbled += 1;
+                        }
+                        continue;
+                    }
+                }
+            }
+            next_id += 1;
+        }
+        while out.send_next(&mut session).is_some() {
+            progress = Instant::now();
+        }
+    }
+
+    let secs = started.elapsed().as_secs_f64();
+    let unanswered = out.unanswered();
+    println!(
+        "{made} in {secs:.1}s ({:.0}/s): {accepted} accepted, {held} held for review, {rejected} rejected, \
+         {refused} malformed refused, {garbled} garbled written, {unanswered} unanswered",
+        made as f64 / secs.max(0.001)
+    );
+    if !kinds.is_empty() {
+        let kinds: Vec<String> = kinds.iter().map(|(kind, n)| format!("{n} {kind}")).collect();
+        println!("Malformed: {}", kinds.join(", "));
+    }
+    session.logout();
+    made == run.count && unanswered == 0
+}
+
+struct Fuzz {
+    count: u32,
+    rate: f64,
+    seed: u64,
+    first_id: u32,
+}
+
+// Logs on, sends one spike, waits for its U2 acknowledgment, logs out.
 fn main() -> io::Result<()> {
-    let mut args = std::env::args().skip(1);
-    let host = args.next().unwrap_or_else(|| "127.0.0.1:9878".into());
+    let mut args = std::env::args().skip(1).peekable();
+    let (mut proxy, mut legacy, mut interactive, mut delay) = (None, false, false, Duration::ZERO);
+    let (mut fuzzed, mut rate, mut seed) = (None, 100.0, rand::random());
+    while let Some(flag) = args.next_if(|a| a.starts_with("--")) {
+        match flag.as_str() {
+            "--proxy" => proxy = Some(proxy::Proxy::parse(&args.next().unwrap_or_default()).expect("proxy")),
+            "--legacy" => legacy = true,
+            "--repl" => interactive = true,
+            "--delay" => delay = Duration::from_millis(args.next().unwrap_or_default().parse().expect("delay")),
+            "--fuzz" => fuzzed = Some(args.next().unwrap_or_default().parse::<u32>().expect("fuzz")),
+            "--rate" => rate = args.next().unwrap_or_default().parse::<f64>().expect("rate"),
+            "--seed" => seed = args.next().unwrap_or_default().parse().expect("seed"),
+            other => {
+                eprintln!(
+                    "Unknown flag {other}; flags are --proxy URL, --legacy, --repl, --delay MS, --fuzz N, --rate R \
+                     and --seed S"
+                );
+                std::process::exit(2);
+            }
+        }
+    }
+    let file = args.next_if(|a| a == "send-file").map(|_| args.next().unwrap_or_default());
+    if file.as_ref().is_some_and(|f| f.is_empty() || legacy || interactive) {
+        eprintln!("send-file takes a CSV or JSON file, and neither --legacy nor --repl");
+        std::process::exit(2);
+    }
+    if legacy && interactive {
+        eprintln!("--legacy has no session to keep open; leave out --repl");
+        std::process::exit(2);
+    }
+    if fuzzed.is_some() && (legacy || interactive || file.is_some()) {
+        eprintln!("--fuzz makes its own spikes; leave out --legacy, --repl and send-file");
+        std::process::exit(2);
+    }
+    if !(rate > 0.0 && rate.is_finite()) {
+        eprintln!("--rate is messages a second, more than 0");
+        std::process::exit(2);
+    }
+    let host = args.next().unwrap_or_else(|| "127.0.0.1:9898".into());
     let spike_id: u32 = args
         .next()
         .unwrap_or_else(|| "1".into())
@@ -95,12 +611,88 @@ fn main() -> io::Result<()> {
         .expect("spike_id");
     let who = args.next().unwrap_or_else(|| "unknown".into());
     let note = args.next().unwrap_or_else(|| "no note".into());
+    let x: f32 = args.next().map_or(50.0, |v| v.parse().expect("x"));
+    let y: f32 = args.next().map_or(75.0, |v| v.parse().expect("y"));
 
-    let msg = build_spike_message(spike_id, &who, &note);
+    if legacy {
+        let msg = legacy_message(spike_id, &who, &note);
+        println!("Sending FIX message to {host}:\n{}", printable(&msg));
+        let mut stream = match &proxy {
+            Some(p) => p.connect(&host)?,
+            None => TcpStream::connect(&host)?,
+        };
+        return stream.write_all(&msg);
+    }
 
-    println!("Sending FIX message to {host}:\n{}", String::from_utf8_lossy(&msg));
+    // A second handle on the connection, for --fuzz to write broken frames on
+    let wire: Arc<Mutex<Option<TcpStream>>> = Arc::default();
+    let tapped = Arc::clone(&wire);
+    let mut session = fix::Initiator::new(&host, Settings::new(SENDER, TARGET)).via(move |addr| {
+        let stream = match &proxy {
+            Some(p) => p.connect(addr)?,
+            None => TcpStream::connect(addr)?,
+        };
+        *tapped.lock().unwrap() = stream.try_clone().ok();
+        Ok(stream)
+    });
+    if let Some(count) = fuzzed {
+        if !fuzz(session, wire, &host, Fuzz { count, rate, seed, first_id: spike_id }) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if interactive {
+        repl(session, &host, spike_id);
+        return Ok(());
+    }
+    if let Some(path) = file {
+        if !send_file(session, &host, &path, spike_id, delay) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let fields = spike_fields(spike_id, &who, &note, (x, y));
+    let deadline = Instant::now() + GIVE_UP;
+    // MsgSeqNum of the spike on the current connection
+    let mut sent: Option<u64> = None;
+
+    // Skip heartbeats and the like until our spike is answered
+    let accepted = loop {
+        if Instant::now() > deadline {
+            eprintln!("No acknowledgment from {host} within {}s", GIVE_UP.as_secs());
+            std::process::exit(1);
+        }
+        match session.poll(TICK) {
+            Some(Event::LoggedOn) => {
+                println!("Sending spike {spike_id} to {host} for {who} at ({x}, {y})");
+                sent = session.send("U1", &fields).ok();
+            }
+            Some(Event::Message(reply)) => match reply.msg_type() {
+                "U2" if sent.is_some() && reply.get(45).and_then(|v| v.parse().ok()) == sent => {
+                    let event = reply.get(9000).unwrap_or("?");
+                    let text = reply.get(58).unwrap_or("");
+                    let accepted = reply.get(39) == Some("0");
+                    println!("{} as event {event}: {text}", if accepted { "Accepted" } else { "Rejected" });
+                    break accepted;
+                }
+                "3" | "j" => {
+                    println!("Rejected: {}", reply.get(58).unwrap_or(&printable(&reply.raw)));
+                    break false;
+                }
+                _ => {}
+            },
+            Some(Event::Garbled(why)) => eprintln!("Dropped a malformed message: {why}"),
+            Some(Event::Down { why, retry }) => {
+                sent = None;
+                eprintln!("{host}: {why}; trying again in {}s", retry.as_secs());
+            }
+            None => {}
+        }
+    };
 
-    let mut stream = TcpStream::connect(host)?;
-    stream.write_all(&msg)?;
+    session.logout();
+    if !accepted {
+        std::process::exit(1);
+    }
     Ok(())
-}
\ No newline at end of file
+}
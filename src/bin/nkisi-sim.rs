@@ -0,0 +1,208 @@
+// Simulated FIX counterparty for local testing.
+//
+//   cargo run --bin nkisi-sim -- assets/scenarios/demo.yaml [host:port]
+//
+// Logs on, keeps the session alive with heartbeats (answering test
+// requests), plays the scripted steps of a YAML scenario, then logs out.
+use serde::Deserialize;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[path = "../fix.rs"]
+#[allow(dead_code)]
+mod fix;
+
+use fix::{Decoded, Decoder, Header, Kind};
+
+#[derive(Debug, Deserialize)]
+struct Scenario {
+    #[serde(default = "default_target")]
+    target: String,
+    #[serde(default = "default_sender")]
+    sender_comp_id: String,
+    #[serde(default = "default_target_comp")]
+    target_comp_id: String,
+    #[serde(default = "default_heartbeat")]
+    heartbeat_secs: u64,
+    // Play the steps this many times before logging out
+    #[serde(default = "default_repeat")]
+    repeat: u32,
+    steps: Vec<Step>,
+}
+
+fn default_target() -> String {
+    "127.0.0.1:9898".into()
+}
+fn default_sender() -> String {
+    "SIM".into()
+}
+fn default_target_comp() -> String {
+    "NKISI".into()
+}
+fn default_heartbeat() -> u64 {
+    30
+}
+fn default_repeat() -> u32 {
+    1
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Step {
+    // Pause, in milliseconds
+    Wait(u64),
+    Spike(Spike),
+    Heartbeat,
+    // Ask the other side to prove it's alive (35=1)
+    TestRequest,
+}
+
+#[derive(Debug, Deserialize)]
+struct Spike {
+    who: String,
+    x: f32,
+    y: f32,
+    note: Option<String>,
+    account: Option<String>,
+    broker: Option<String>,
+    category: Option<String>,
+    #[serde(default)]
+    witnesses: Vec<String>,
+}
+
+// Outbound half of the session: sequence numbers and idle tracking
+struct Session {
+    stream: TcpStream,
+    sender: String,
+    target: String,
+    seq: u64,
+    last_sent: Instant,
+}
+
+impl Session {
+    fn send(&mut self, msg_type: &str, fields: &[(u32, String)]) -> io::Result<()> {
+        self.seq += 1;
+        let header = Header { begin: "FIX.4.4", sender: &self.sender, target: &self.target };
+        let out = fix::encode(&header, msg_type, self.seq, fields);
+        println!("-> {}", printable(&out));
+        self.stream.write_all(&out)?;
+        self.last_sent = Instant::now();
+        Ok(())
+    }
+}
+
+fn spike_fields(s: &Spike) -> Vec<(u32, String)> {
+    let mut f = vec![
+        (55, "NKISI".to_string()),
+        (6010, s.x.to_string()),
+        (6011, s.y.to_string()),
+        (60, chrono::Utc::now().to_rfc3339()),
+    ];
+    for (tag, v) in [(58, &s.note), (1, &s.account), (76, &s.broker), (167, &s.category)] {
+        if let Some(v) = v {
+            f.push((tag, v.clone()));
+        }
+    }
+    // Striker and witnesses as a NoPartyIDs group (see PartyRole in main)
+    f.push((453, (1 + s.witnesses.len()).to_string()));
+    f.push((448, s.who.clone()));
+    f.push((452, "12".into()));
+    for w in &s.witnesses {
+        f.push((448, w.clone()));
+        f.push((452, "4000".into()));
+    }
+    f
+}
+
+fn printable(msg: &[u8]) -> String {
+    String::from_utf8_lossy(msg).replace('\u{1}', "|")
+}
+
+// Print what the other side sends and answer its test requests
+fn read_loop(mut stream: TcpStream, session: Arc<Mutex<Session>>) {
+    let mut buf = [0u8; 4096];
+    let mut decoder = Decoder::new(true);
+    loop {
+        let n = match stream.read(&mut buf) {
+            Ok(0) | Err(_) => {
+                println!("<- connection closed");
+                return;
+            }
+            Ok(n) => n,
+        };
+        decoder.feed(&buf[..n]);
+        while let Some(Decoded::Message(msg)) = decoder.next() {
+            println!("<- {}", printable(&msg.raw));
+            if msg.kind() == Kind::TestRequest {
+                let id = msg.get(112).unwrap_or("").to_string();
+                let _ = session.lock().unwrap().send("0", &[(112, id)]);
+            }
+        }
+    }
+}
+
+fn main() -> io::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let Some(path) = args.next() else {
+        eprintln!("usage: nkisi-sim <scenario.yaml> [host:port]");
+        std::process::exit(2);
+    };
+    let text = std::fs::read_to_string(&path)?;
+    let scenario: Scenario = serde_yaml::from_str(&text)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{path}: {e}")))?;
+    let target = args.next().unwrap_or(scenario.target.clone());
+
+    let stream = TcpStream::connect(&target)?;
+    println!("connected to {target}");
+    let session = Arc::new(Mutex::new(Session {
+        stream: stream.try_clone()?,
+        sender: scenario.sender_comp_id.clone(),
+        target: scenario.target_comp_id.clone(),
+        seq: 0,
+        last_sent: Instant::now(),
+    }));
+    {
+        let s = Arc::clone(&session);
+        thread::spawn(move || read_loop(stream, s));
+    }
+
+    let hb = Duration::from_secs(scenario.heartbeat_secs.max(1));
+    session
+        .lock()
+        .unwrap()
+        .send("A", &[(98, "0".into()), (108, hb.as_secs().to_string())])?;
+
+    // Heartbeat whenever the session has been idle for the interval
+    {
+        let s = Arc::clone(&session);
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_millis(250));
+            let mut s = s.lock().unwrap();
+            if s.last_sent.elapsed() >= hb && s.send("0", &[]).is_err() {
+                return;
+            }
+        });
+    }
+
+    for _ in 0..scenario.repeat.max(1) {
+        for step in &scenario.steps {
+            match step {
+                Step::Wait(ms) => thread::sleep(Duration::from_millis(*ms)),
+                Step::Spike(spike) => session.lock().unwrap().send("U1", &spike_fields(spike))?,
+                Step::Heartbeat => session.lock().unwrap().send("0", &[])?,
+                Step::TestRequest => {
+                    let id = chrono::Utc::now().timestamp_millis().to_string();
+                    session.lock().unwrap().send("1", &[(112, id)])?
+                }
+            }
+        }
+    }
+
+    session.lock().unwrap().send("5", &[(58, "scenario complete".into())])?;
+    // Give the other side a moment to answer the logout
+    thread::sleep(Duration::from_millis(500));
+    Ok(())
+}
@@ -0,0 +1,101 @@
+// -------------------- Intensity --------------------
+// How charged the figure is: BASE plus one for every spike. With a half-life
+// set in the workspace a spike's share halves every half-life since it was
+// struck, so a figure nobody has driven a nail into for a while cools down,
+// as the charge of a nkisi is held to fade without new activations. The
+// reading is recomputed every RECOMPUTE and whenever the ledger changes,
+// together with the curve drawn under it: intensity from the first spike
+// (or the last WINDOW half-lives, if that is shorter) up to now.
+use chrono::{DateTime, Duration as Span, Utc};
+use std::fmt::Write;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::ActivationEvent;
+
+pub const BASE: f64 = 3.0;
+pub const RECOMPUTE: Duration = Duration::from_secs(30);
+const WINDOW: f64 = 4.0;
+const SAMPLES: i64 = 120;
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+#[derive(Debug, Clone, Default)]
+pub struct Reading {
+    pub now: f64,
+    pub curve: Vec<(DateTime<Utc>, f64)>,
+    // Events, the latest one and the half-life it was computed for, to
+    // tell when the ledger changed; None until the first reading
+    key: Option<(usize, Option<Uuid>, Option<u64>)>,
+}
+
+impl Reading {
+    pub fn stale(&self, events: &[ActivationEvent], half_life: Option<f64>) -> bool {
+        self.key != Some(key(events, half_life))
+    }
+}
+
+fn key(events: &[ActivationEvent], half_life: Option<f64>) -> (usize, Option<Uuid>, Option<u64>) {
+    (events.len(), events.last().map(|e| e.id), half_life.map(f64::to_bits))
+}
+
+// Intensity at `t`, from the spikes struck by then; without a half-life
+// every spike counts in full, as it always has
+pub fn at(events: &[ActivationEvent], half_life_days: Option<f64>, t: DateTime<Utc>) -> f64 {
+    let Some(half_life) = half_life_days.filter(|h| *h > 0.0) else {
+        return BASE + events.iter().filter(|e| e.date <= t).count() as f64;
+    };
+    let charge: f64 = events
+        .iter()
+        .filter(|e| e.date <= t)
+        .map(|e| {
+            let age = (t - e.date).num_seconds() as f64 / SECONDS_PER_DAY;
+            0.5f64.powf(age / half_life)
+        })
+        .sum();
+    BASE + charge
+}
+
+pub fn read(events: &[ActivationEvent], half_life_days: Option<f64>, now: DateTime<Utc>) -> Reading {
+    let first = events.iter().map(|e| e.date).min().unwrap_or(now);
+    let start = match half_life_days {
+        Some(h) => first.max(now - Span::seconds((h * WINDOW * SECONDS_PER_DAY) as i64)),
+        None => first,
+    };
+    let step = (now - start) / SAMPLES as i32;
+    let curve = if step > Span::zero() {
+        (0..=SAMPLES).map(|i| start + step * i as i32).map(|t| (t, at(events, half_life_days, t))).collect()
+    } else {
+        vec![]
+    };
+    // Future-dated spikes (a sender's clock ahead) count at full strength
+    let now_value = match half_life_days {
+        Some(_) => at(events, half_life_days, now) + events.iter().filter(|e| e.date > now).count() as f64,
+        None => BASE + events.len() as f64,
+    };
+    Reading { now: now_value, curve, key: Some(key(events, half_life_days)) }
+}
+
+// The curve as a small SVG: a line from the oldest sample to now, the
+// scale from zero to the highest value, with the ends labelled
+pub fn curve_svg(reading: &Reading, line: &str) -> String {
+    let (w, h, pad) = (360.0, 90.0, 14.0);
+    let top = reading.curve.iter().map(|(_, v)| *v).fold(BASE, f64::max);
+    let n = reading.curve.len().max(2) as f64 - 1.0;
+    let mut points = String::new();
+    for (i, (_, v)) in reading.curve.iter().enumerate() {
+        let x = i as f64 / n * w;
+        let y = pad + (1.0 - v / top) * (h - 2.0 * pad);
+        let _ = write!(points, "{x:.1},{y:.1} ");
+    }
+    let day = |t: Option<&(DateTime<Utc>, f64)>| t.map_or(String::new(), |(t, _)| t.format("%Y-%m-%d").to_string());
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">
+<polyline points="{points}" fill="none" stroke="{line}" stroke-width="1.5"/>
+<text x="2" y="10" font-size="10" fill="#aaaacc">{top:.1}</text>
+<text x="2" y="{bottom}" font-size="10" fill="#aaaacc">{first}</text>
+<text x="{w}" y="{bottom}" font-size="10" fill="#aaaacc" text-anchor="end">now</text>
+</svg>"##,
+        bottom = h - 2.0,
+        first = day(reading.curve.first()),
+    )
+}
@@ -0,0 +1,183 @@
+// -------------------- Scoped clearing --------------------
+// Part of the ledger moved to the trash instead of all of it: events
+// dated between two days, by a striker, from a source (a FIX sender, a
+// pack or merged file, "fix", "pack", or "here" for spikes struck in this
+// ledger), matching the search box, or any mix of these. A criterion left
+// blank takes everything, so an empty scope is the old Clear all. Preview
+// counts and lists what the scope takes; clearing it still asks first, and
+// the trash gives the events back until it is emptied.
+use chrono::NaiveDate;
+use iced::widget::{button, checkbox, column, row, text, text_input};
+use iced::{alignment, Color, Element, Length};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::{confirm, ActivationEvent, Message};
+
+// Events listed under a preview
+const SHOWN: usize = 10;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scope {
+    // YYYY-MM-DD, both days included
+    pub from: String,
+    pub to: String,
+    pub striker: String,
+    pub source: String,
+    // Only events the search box finds
+    pub search: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum Edit {
+    From(String),
+    To(String),
+    Striker(String),
+    Source(String),
+    Search(bool),
+}
+
+#[derive(Debug, Clone)]
+pub struct Preview {
+    // Live events the scope takes, and how many there were
+    pub ids: Vec<Uuid>,
+    pub of: usize,
+    pub what: Option<String>,
+}
+
+impl Scope {
+    pub fn edit(&mut self, how: Edit) {
+        match how {
+            Edit::From(v) => self.from = v,
+            Edit::To(v) => self.to = v,
+            Edit::Striker(v) => self.striker = v,
+            Edit::Source(v) => self.source = v,
+            Edit::Search(v) => self.search = v,
+        }
+    }
+
+    // The criteria in words; None for everything
+    pub fn describe(&self, query: &str) -> Option<String> {
+        let mut what = vec![];
+        match (self.from.trim(), self.to.trim()) {
+            ("", "") => {}
+            (from, "") => what.push(format!("dated {from} or later")),
+            ("", to) => what.push(format!("dated {to} or earlier")),
+            (from, to) => what.push(format!("dated {from} to {to}")),
+        }
+        if !self.striker.trim().is_empty() {
+            what.push(format!("by {}", self.striker.trim()));
+        }
+        if !self.source.trim().is_empty() {
+            what.push(format!("from {}", self.source.trim()));
+        }
+        if self.search && !query.trim().is_empty() {
+            what.push(format!("matching \"{}\"", query.trim()));
+        }
+        (!what.is_empty()).then(|| what.join(", "))
+    }
+
+    // Events the scope takes; `found` is what the search box finds, when
+    // the scope is limited to it
+    pub fn preview(
+        &self,
+        events: &[ActivationEvent],
+        found: Option<&HashSet<Uuid>>,
+        query: &str,
+    ) -> Result<Preview, String> {
+        let day = |s: &str| match s.trim() {
+            "" => Ok(None),
+            s => NaiveDate::parse_from_str(s, "%Y-%m-%d").map(Some).map_err(|_| format!("{s}: not a YYYY-MM-DD date")),
+        };
+        let (from, to) = (day(&self.from)?, day(&self.to)?);
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                return Err(format!("{from} is after {to}"));
+            }
+        }
+        let striker = self.striker.trim();
+        let source = self.source.trim();
+        let ids = events
+            .iter()
+            .filter(|ev| {
+                let date = ev.date.date_naive();
+                from.is_none_or(|f| date >= f)
+                    && to.is_none_or(|t| date <= t)
+                    && (striker.is_empty() || ev.performed_by.trim().eq_ignore_ascii_case(striker))
+                    && (source.is_empty() || from_source(ev, source))
+                    && found.is_none_or(|f| f.contains(&ev.id))
+            })
+            .map(|ev| ev.id)
+            .collect();
+        Ok(Preview { ids, of: events.len(), what: self.describe(query) })
+    }
+}
+
+// The first hop is where an event came from; one without any was struck here
+fn from_source(ev: &ActivationEvent, source: &str) -> bool {
+    match ev.provenance.first() {
+        Some(hop) => hop.source.eq_ignore_ascii_case(source) || hop.via.eq_ignore_ascii_case(source),
+        None => source.eq_ignore_ascii_case("here"),
+    }
+}
+
+pub struct Panel<'a> {
+    pub scope: &'a Scope,
+    pub preview: Option<&'a Result<Preview, String>>,
+    pub events: &'a [ActivationEvent],
+}
+
+pub fn view(panel: Panel<'_>) -> Element<'_, Message> {
+    let dim = Color::from_rgb(0.7, 0.7, 0.8);
+    let scope = panel.scope;
+    let input = |hint, value, how: fn(String) -> Edit| {
+        text_input(hint, value).on_input(move |v| Message::ClearScopeEdited(how(v))).on_submit(Message::PreviewClear)
+    };
+    let mut col = column![
+        row![
+            text("Clear events:").size(16).width(Length::Fill),
+            button("Close").style(button::secondary).on_press(Message::ShowClear(false)),
+        ]
+        .spacing(8)
+        .align_y(alignment::Vertical::Center),
+        row![
+            input("from YYYY-MM-DD", &scope.from, Edit::From).width(130),
+            input("to YYYY-MM-DD", &scope.to, Edit::To).width(130),
+            input("striker", &scope.striker, Edit::Striker),
+            input("source (a sender, fix, pack, here)", &scope.source, Edit::Source),
+        ]
+        .spacing(8),
+        row![
+            checkbox("Only events the search box finds", scope.search)
+                .on_toggle(|on| Message::ClearScopeEdited(Edit::Search(on)))
+                .width(Length::Fill),
+            button("Preview").on_press(Message::PreviewClear),
+        ]
+        .spacing(8)
+        .align_y(alignment::Vertical::Center),
+    ]
+    .spacing(4);
+    match panel.preview {
+        None => {}
+        Some(Err(why)) => col = col.push(text(why).size(13).color(dim)),
+        Some(Ok(p)) => {
+            let head = match &p.what {
+                Some(what) => format!("{} of {} {what}", confirm::count(p.ids.len(), "event"), p.of),
+                None => format!("All {} (no criteria given)", confirm::count(p.ids.len(), "event")),
+            };
+            let mut line = row![text(head).width(Length::Fill)].spacing(8).align_y(alignment::Vertical::Center);
+            if !p.ids.is_empty() {
+                line = line.push(button("Move to trash").style(button::danger).on_press(Message::ClearScoped));
+            }
+            col = col.push(line);
+            for ev in panel.events.iter().filter(|e| p.ids.contains(&e.id)).take(SHOWN) {
+                let what = format!("{} • {}", ev.date.format("%Y-%m-%d %H:%M"), ev.performed_by);
+                col = col.push(text(what).size(12).color(dim));
+            }
+            if p.ids.len() > SHOWN {
+                col = col.push(text(format!("… and {} more", p.ids.len() - SHOWN)).size(12).color(dim));
+            }
+        }
+    }
+    col.into()
+}
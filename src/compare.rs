@@ -0,0 +1,41 @@
+// -------------------- Before/after split view --------------------
+// The figure twice, side by side, as it stood at two moments: each half
+// shows only the spikes struck by then, so the change in density between,
+// say, the start and the end of a year is there at a glance. Both halves
+// share the viewport, and each moment is picked on a slider running from
+// the first spike to now.
+use chrono::{DateTime, Duration as Span, Utc};
+
+use crate::NkisiNkondi;
+
+const SECONDS_PER_DAY: f64 = 86_400.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Split {
+    pub before: DateTime<Utc>,
+    pub after: DateTime<Utc>,
+}
+
+// First spike to now (or the latest spike, if dated later); None without
+// spikes
+pub fn span(nkisi: &NkisiNkondi, now: DateTime<Utc>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let first = nkisi.events.iter().map(|e| e.date).min()?;
+    let last = nkisi.events.iter().map(|e| e.date).max()?;
+    Some((first, last.max(now)))
+}
+
+// Days from the start of `span`, where the sliders measure
+pub fn days((start, _): (DateTime<Utc>, DateTime<Utc>), t: DateTime<Utc>) -> f64 {
+    (t - start).num_seconds() as f64 / SECONDS_PER_DAY
+}
+
+pub fn at((start, end): (DateTime<Utc>, DateTime<Utc>), days: f64) -> DateTime<Utc> {
+    (start + Span::seconds((days * SECONDS_PER_DAY) as i64)).clamp(start, end)
+}
+
+// The ledger as it stood at `t`, for drawing: the spikes struck by then
+pub fn snapshot(nkisi: &NkisiNkondi, t: DateTime<Utc>) -> NkisiNkondi {
+    let events: Vec<_> = nkisi.events.iter().filter(|e| e.date <= t).cloned().collect();
+    let pins = events.iter().map(|e| e.pos).collect();
+    NkisiNkondi { events, pins, ..NkisiNkondi::default() }
+}
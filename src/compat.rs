@@ -0,0 +1,186 @@
+// -------------------- Format versions & release notes --------------------
+// Every saved ledger carries the data format version and the app version
+// that wrote it. Files without one predate versioning (format 0) and load
+// as-is; files from a newer format are only opened read-only.
+use serde::Deserialize;
+
+use crate::{IoError, NkisiNkondi};
+
+// 2: events may carry FIX metadata (case ref, institution, category)
+// 3: snapshots record the last journal entry they include (journal_seq);
+//    an older build saving one would make its journal replay twice
+// 4: stamped event status for merging copies; dropping it would let a
+//    merge bring deleted events back
+// 5: events carry the stamp of their last revision, which wins merges
+// 6: events keep the provenance hops they arrived through
+// 7: events carry values for workspace-defined custom fields
+// 8: ledgers keep tombstones of cancelled spikes, events keep tags applied
+//    in bulk and provenance hops name the FIX listener they came in on
+pub const FORMAT_VERSION: u32 = 8;
+pub const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Last app version that ran here, to show release notes once per upgrade
+const LAST_VERSION_MARKER: &str = ".nkisi_last_version";
+
+pub const RELEASE_NOTES: &[(&str, &[&str])] = &[(
+    "0.2.0",
+    &[
+        "Compact layout for narrow windows, with the spike form as a bottom sheet.",
+        "First-run tour and a bundled sample ledger.",
+        "Destructive actions ask for confirmation; deleted events go to a restorable trash.",
+        "Archive old resolved events and search the archive on demand.",
+        "Workspaces (.nkisiproj) with several figures, regions, layers and overlay profiles.",
+        "PNG/JPEG photos can be used as the base figure.",
+        "Ledgers now record their format version; newer files open read-only.",
+        "FIX spikes carry account, broker and instrument type as event details.",
+        "Every change is journaled: unsaved changes are recovered on load and can be undone.",
+        "Copies of a ledger edited offline merge deterministically; peers mirroring each other over drop-copy no longer duplicate spikes.",
+        "Conflicting edits found by a merge are shown side by side to keep, take or combine.",
+        "Export searched events as signed spike packs and import packs from other institutions.",
+        "Event details show the provenance chain of spikes that came in over FIX, packs or merges.",
+        "Workspaces can define custom text, number, yes/no and choice fields for events.",
+        "The pending-spike form can be laid out per workspace: order, defaults and required inputs.",
+        "Workspace validation rules are checked on confirm and on FIX or pack ingestion.",
+        "CSV and printable reports in English, French or Portuguese (--locale, or LANG).",
+        "Reports can be laid out with user templates (Jinja syntax; see assets/templates).",
+        "The FIX acceptor runs a real session (Logon, Heartbeat, TestRequest, Logout, sequence numbers) for engines that log on.",
+        "PNG export of the figure and overlay at a chosen DPI, with margin and transparent background options.",
+        "Export all: CSV, SVG and PDF for every figure in the workspace, with a manifest.json.",
+        "FIX frames are checked against BodyLength and CheckSum; dropped ones are counted in the status line (--lenient-fix relaxes this).",
+        "Aggregate export: grid, region and time counts only, with optional differential-privacy noise.",
+        "Logged-on FIX senders get a U2 acknowledgment per spike (39=0 recorded, 39=8 refused) carrying the event id.",
+        "Unreadable FIX spikes get a session Reject (35=3) naming the tag at fault (371) and why (373, 58).",
+        "A selected pin can be dragged or given corrected coordinates; the move is kept as a revision.",
+        "FIX spike tags and message types can be remapped with a TOML dictionary (--fix-dictionary).",
+        "Lasso and box selection on the figure, with bulk tagging, outcome changes and CSV export of the picked pins.",
+        "Review events in time order with Previous/Next or the arrow keys; the current pin is marked with guides.",
+        "One FIX message can carry many spikes in a NoSpikes (6009) group; the message is recorded whole or refused whole.",
+        "FIX initiator (--forward-to): spikes confirmed here are published to an upstream engine over a logged-on session.",
+        "Figures can be turned in 90° steps and mirrored to line up with existing ledgers; pins keep their coordinates.",
+        "Color-blind safe overlay palettes (Okabe–Ito, Tol bright) and a high-contrast mode, also used by exports.",
+        "TLS for the FIX acceptor (--fix-tls-cert/--fix-tls-key), with optional client certificates (--fix-client-ca).",
+        "Interface zoom from 50% to 300% (Ctrl+/Ctrl−, Ctrl+0 to reset, or --ui-scale) for 4K displays and projectors.",
+        "FIX access list (--fix-auth): allowed addresses, Logon credentials (553/554) and strikers; refusals are counted.",
+        "FIX sequence numbers are kept per counterparty across reconnects; gaps are requested again and resends deduplicated.",
+        "Help overlay (F1, or ? beside a panel) covering every panel and the FIX tags this instance expects.",
+        "FIX message store (--fix-store): every message in and out kept per counterparty and day, with replay into the ledger.",
+        "Self-test (button, or --self-test at the command line): pass/fail checks of assets, ledger, ports, config and a FIX round trip.",
+        "The FIX acceptor can be stopped, restarted or moved to another port from the controls; a busy port is reported, not fatal.",
+        "Network panel: the addresses the FIX acceptor and metrics endpoint answer on, settings to copy, firewall hints and a test spike.",
+        "Several named FIX listeners (--fix-listener NAME=ADDR or the workspace's ingest.listeners); spikes record the listener they came in on.",
+        "Usage panel: sessions, spikes added and features used, counted locally in .nkisi_usage.json and never sent anywhere.",
+        "FIX flood protection: per-connection rate limit (--fix-rate-limit), a bounded ledger queue and queued/slowed/refused counts.",
+        "Save hook (--on-save): a shell command run after each ledger save with the saved path, e.g. to push it to a repository.",
+        "FIX 5.0 SP2 counterparties can log on over FIXT.1.1; DefaultApplVerID (1137) is negotiated at Logon.",
+        "Event feed: GET /events/stream on the metrics address streams each new event as NDJSON, for curl and scripts.",
+        "Event webhook (--webhook URL): each new event POSTed as JSON, HMAC-signed with --webhook-secret so receivers can verify it.",
+        "Standard NewOrderSingles (35=D) can be read as spikes via [new_order_single] in the FIX dictionary (Price/OrderQty, Account).",
+        "FIXML: listeners also take XML-encoded spikes and orders, detected by a leading '<'.",
+        "Outbox: undelivered webhook/drop-copy/upstream events persist and back off; dead letters can be re-driven.",
+        "Sessions panel: open FIX connections with counts, last heartbeat and sequence numbers; disconnect from the GUI.",
+        "Intensity decay: a workspace half-life makes the intensity fade between spikes, with a plotted curve.",
+        "FIX resends (PossDupFlag 43=Y) and spikes with a repeated idempotency key (9001) are recorded once.",
+        "Sandbox: try spikes and edits out on a copy of the ledger, then apply them as a batch or discard them.",
+        "Scroll to magnify the figure, right-drag to pan, and save named views with their overlay and layers.",
+        "Outcome updates over FIX (35=U3): an event id and a new outcome mark a recorded spike resolved or failed.",
+        "FIX cancels (35=U4) remove a spike's pin and keep the event as a tombstone naming who cancelled it.",
+        "Before/after: the figure at two dates side by side, sharing magnification, to show how density changed.",
+        "Drop-copy publisher: consumers connect to --drop-copy-listen and get a live FIX copy of every accepted spike.",
+        "Check ledger: finds unpinned events, stray pins, future dates, duplicate ids and off-figure spikes, and fixes them.",
+        "FIX spikes reach the ledger as soon as they arrive instead of on a 200 ms timer.",
+        "Loading a ledger whose pins disagree with its events rebuilds the pins and says what it repaired.",
+        "Coordinates can be shown in figure units, percent of the figure or millimetres, in panels and reports.",
+        "A FIX inspector shows the last raw messages, colored by what became of them, tag by tag on a click.",
+        "FIX spikes outside the figure can be moved to the edge, refused, or held in a quarantine for review.",
+        "New positions can be stored to a chosen precision, such as 0.1 figure units.",
+        "Every event in a region or selection can be given a purpose or tag, previewed and undone as one.",
+        "A striker summary ranks contributors and exports them as CSV or a Markdown acknowledgment.",
+        "FIX feeds are framed incrementally, so busy connections no longer slow down as their backlog grows.",
+        "The metrics address can require API tokens with read or write scope, listed in the config file.",
+        "The upstream FIX session and fixclient can connect through a SOCKS5 or HTTP CONNECT proxy.",
+        "Every listener honours --allow-from and --deny-from CIDR rules; refused connections are logged and counted.",
+        "fixclient reconnects and retries its spike with backoff, using the same FIX session as upstream forwarding.",
+        "Stats show apply-to-drawn and TransactTime-to-drawn latency of FIX spikes, also served on /metrics.",
+        "A stored FIX day exports as standard message logs; the upstream session is now stored too.",
+        "Ingest transforms rename strikers, rescale positions and fill in notes of incoming FIX spikes.",
+        "fixclient --legacy still sends the old 35=SPK form for receivers that expect it.",
+        "FIX spikes the rules refuse are kept in the quarantine, where they can be fixed and resubmitted.",
+        "fixclient --repl keeps one session open and sends each spike typed on its input.",
+        "Clear All became Clear…: clear by date range, striker, source or search, with a preview count first.",
+        "fixclient send-file streams spikes from a CSV or JSON file over one session, --delay apart.",
+        "Figures of a workspace that are copies of one ledger are listed as forked, to merge or pick one.",
+        "fixclient --fuzz N --rate R soak-tests an acceptor with random spikes, some of them malformed.",
+        "Launched without a ledger, the app asks which to open rather than start a blank one over nkisi_state.json.",
+        "At most --fix-max-connections FIX connections are open at once (256 by default); more are refused and counted.",
+        "FIX ResendRequests get the acknowledgments and Rejects asked for again (43=Y) instead of a gap fill.",
+        "Webhook requests are signed with HMAC-SHA256 in X-Nkisi-Signature-256; the SHA-1 header is still sent.",
+    ],
+)];
+
+#[derive(Deserialize)]
+struct Header {
+    #[serde(default)]
+    format_version: u32,
+    saved_by: Option<String>,
+}
+
+// Parse a ledger, refusing formats newer than this build understands
+pub fn parse(bytes: &[u8]) -> Result<NkisiNkondi, IoError> {
+    let header: Header =
+        serde_json::from_slice(bytes).map_err(|e| IoError::Parse(e.to_string()))?;
+    if header.format_version > FORMAT_VERSION {
+        return Err(IoError::Newer {
+            found: header.format_version,
+            saved_by: header.saved_by.unwrap_or_else(|| "unknown".into()),
+        });
+    }
+    parse_lenient(bytes)
+}
+
+// Best-effort parse that ignores the version; fields this build doesn't know
+// are dropped, so the result must not be written back.
+pub fn parse_lenient(bytes: &[u8]) -> Result<NkisiNkondi, IoError> {
+    let mut nkisi: NkisiNkondi =
+        serde_json::from_slice(bytes).map_err(|e| IoError::Parse(e.to_string()))?;
+    upgrade(&mut nkisi);
+    Ok(nkisi)
+}
+
+// Older formats are brought up to date in memory and saved as current
+fn upgrade(nkisi: &mut NkisiNkondi) {
+    if nkisi.format_version <= FORMAT_VERSION {
+        nkisi.format_version = FORMAT_VERSION;
+        nkisi.saved_by = Some(APP_VERSION.into());
+    }
+}
+
+// Notes for versions newer than the one that last ran, or None on a fresh
+// install and when nothing changed
+pub fn pending_release_notes() -> Option<Vec<(&'static str, &'static [&'static str])>> {
+    let last = std::fs::read_to_string(LAST_VERSION_MARKER).ok();
+    let Some(last) = last.map(|s| s.trim().to_string()) else {
+        mark_version_seen();
+        return None;
+    };
+    if last == APP_VERSION {
+        return None;
+    }
+    let notes: Vec<_> = RELEASE_NOTES
+        .iter()
+        .copied()
+        .filter(|(v, _)| version_key(v) > version_key(&last))
+        .collect();
+    if notes.is_empty() {
+        mark_version_seen();
+        return None;
+    }
+    Some(notes)
+}
+
+pub fn mark_version_seen() {
+    let _ = std::fs::write(LAST_VERSION_MARKER, APP_VERSION);
+}
+
+fn version_key(v: &str) -> Vec<u32> {
+    v.split('.').map(|p| p.parse().unwrap_or(0)).collect()
+}
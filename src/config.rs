@@ -0,0 +1,299 @@
+// -------------------- Startup configuration --------------------
+// Settings are layered: built-in defaults < config file (nkisi.toml) <
+// NKISI_* environment variables < command-line flags. Anything left unset
+// falls through to the workspace (or its defaults).
+use clap::Parser;
+use serde::Deserialize;
+
+use crate::apiauth::ApiToken;
+use crate::IoError;
+
+const DEFAULT_CONFIG: &str = "nkisi.toml";
+// Well above any real desk, well below what freezes the ledger
+const DEFAULT_FIX_RATE_LIMIT: u32 = 200;
+// Open FIX connections across all listeners, each a thread of its own
+const DEFAULT_FIX_MAX_CONNECTIONS: usize = 256;
+
+#[derive(Debug, Parser)]
+#[command(name = "RusticNkisi", version, about = "Nkisi nkondi ledger with FIX ingest")]
+struct Cli {
+    /// Workspace (.nkisiproj) to open
+    #[arg(env = "NKISI_WORKSPACE")]
+    workspace: Option<String>,
+
+    /// Config file; a missing default nkisi.toml is not an error
+    #[arg(long, env = "NKISI_CONFIG")]
+    config: Option<String>,
+
+    /// Address the FIX acceptor listens on, e.g. 0.0.0.0:9898
+    #[arg(long, env = "NKISI_FIX_ADDR")]
+    fix_addr: Option<String>,
+
+    /// Another FIX acceptor as NAME=ADDR, e.g. test=0.0.0.0:9899; spikes record the name they came in on
+    #[arg(long = "fix-listener", env = "NKISI_FIX_LISTENERS", value_delimiter = ',', value_parser = listener)]
+    fix_listeners: Vec<(String, String)>,
+
+    /// Monitoring endpoint (host:port) that gets a FIX copy of every event
+    #[arg(long, env = "NKISI_DROP_COPY")]
+    drop_copy: Option<String>,
+
+    /// Address (host:port) consumers connect to for a live FIX copy of every event
+    #[arg(long, env = "NKISI_DROP_COPY_LISTEN")]
+    drop_copy_listen: Option<String>,
+
+    /// Upstream FIX engine (host:port) that locally confirmed spikes are published to
+    #[arg(long, env = "NKISI_FORWARD_TO")]
+    forward_to: Option<String>,
+
+    /// Proxy the upstream session goes through, socks5://[user:pass@]host:port or http://…
+    #[arg(long, env = "NKISI_FORWARD_PROXY", hide_env_values = true)]
+    forward_proxy: Option<String>,
+
+    /// URL (http://) every new event is POSTed to as JSON
+    #[arg(long, env = "NKISI_WEBHOOK")]
+    webhook: Option<String>,
+
+    /// Secret webhook requests are signed with (X-Nkisi-Signature-256 and X-Nkisi-Signature)
+    #[arg(long, env = "NKISI_WEBHOOK_SECRET", hide_env_values = true)]
+    webhook_secret: Option<String>,
+
+    /// Serve ingest latency metrics over HTTP, e.g. 127.0.0.1:9899
+    #[arg(long, env = "NKISI_METRICS_ADDR")]
+    metrics_addr: Option<String>,
+
+    /// Shared secret spike packs are signed and checked with
+    #[arg(long, env = "NKISI_PACK_KEY", hide_env_values = true)]
+    pack_key: Option<String>,
+
+    /// Accept FIX frames without checking BodyLength and CheckSum (test feeds)
+    #[arg(long, env = "NKISI_LENIENT_FIX")]
+    lenient_fix: bool,
+
+    /// FIX messages a connection may send per second before it is slowed down (0: no limit)
+    #[arg(long, env = "NKISI_FIX_RATE_LIMIT")]
+    fix_rate_limit: Option<u32>,
+
+    /// FIX connections open at once, all listeners together, before new ones are refused (0: no limit)
+    #[arg(long, env = "NKISI_FIX_MAX_CONNECTIONS")]
+    fix_max_connections: Option<usize>,
+
+    /// Certificate chain (PEM) the FIX acceptor presents; turns on TLS
+    #[arg(long, env = "NKISI_FIX_TLS_CERT")]
+    fix_tls_cert: Option<String>,
+
+    /// Private key (PEM) of the FIX acceptor's certificate
+    #[arg(long, env = "NKISI_FIX_TLS_KEY")]
+    fix_tls_key: Option<String>,
+
+    /// CA (PEM) FIX counterparties' client certificates must be issued by
+    #[arg(long, env = "NKISI_FIX_CLIENT_CA")]
+    fix_client_ca: Option<String>,
+
+    /// TOML file listing the addresses, senders and parties allowed to send spikes
+    #[arg(long, env = "NKISI_FIX_AUTH")]
+    fix_auth: Option<String>,
+
+    /// IP or CIDR range allowed to connect to any listener (FIX, drop-copy, metrics); others are refused
+    #[arg(long = "allow-from", env = "NKISI_ALLOW_FROM", value_delimiter = ',')]
+    allow_from: Vec<String>,
+
+    /// IP or CIDR range refused by every listener, even when an allow rule covers it
+    #[arg(long = "deny-from", env = "NKISI_DENY_FROM", value_delimiter = ',')]
+    deny_from: Vec<String>,
+
+    /// Directory every FIX message in and out is kept in, one file per counterparty and day
+    #[arg(long, env = "NKISI_FIX_STORE")]
+    fix_store: Option<String>,
+
+    /// Replay every day in the FIX store into the ledger at startup
+    #[arg(long, env = "NKISI_REPLAY_FIX_STORE")]
+    replay_fix_store: bool,
+
+    /// TOML file mapping spike fields to FIX tags and message types
+    #[arg(long, env = "NKISI_FIX_DICTIONARY")]
+    fix_dictionary: Option<String>,
+
+    /// Language of exported reports (en, fr, pt); defaults to LANG
+    #[arg(long, env = "NKISI_LOCALE")]
+    locale: Option<String>,
+
+    /// Zoom of the interface, e.g. 1.5 on a 4K display or projector
+    #[arg(long, env = "NKISI_UI_SCALE")]
+    ui_scale: Option<f32>,
+
+    /// Check the setup (assets, ledger, ports, config, FIX loopback), print a report and exit
+    #[arg(long)]
+    self_test: bool,
+
+    /// Ledger file of the active figure
+    #[arg(long, env = "NKISI_SAVE_PATH")]
+    save_path: Option<String>,
+
+    /// Shell command run after each ledger save, with the saved file as $1 (and NKISI_SAVED_PATH)
+    #[arg(long, env = "NKISI_ON_SAVE")]
+    on_save: Option<String>,
+
+    /// Base figure (SVG or PNG/JPEG) of the active figure
+    #[arg(long, env = "NKISI_SVG_PATH")]
+    svg_path: Option<String>,
+}
+
+// Same keys as the flags, e.g. `fix_addr = "127.0.0.1:9898"`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    workspace: Option<String>,
+    fix_addr: Option<String>,
+    // "name=addr" entries
+    fix_listeners: Option<Vec<String>>,
+    drop_copy: Option<String>,
+    drop_copy_listen: Option<String>,
+    forward_to: Option<String>,
+    forward_proxy: Option<String>,
+    webhook: Option<String>,
+    webhook_secret: Option<String>,
+    metrics_addr: Option<String>,
+    // [[api_tokens]] tables; there is no flag or variable for them
+    api_tokens: Option<Vec<ApiToken>>,
+    pack_key: Option<String>,
+    lenient_fix: Option<bool>,
+    fix_rate_limit: Option<u32>,
+    fix_max_connections: Option<usize>,
+    fix_tls_cert: Option<String>,
+    fix_tls_key: Option<String>,
+    fix_client_ca: Option<String>,
+    fix_auth: Option<String>,
+    allow_from: Option<Vec<String>>,
+    deny_from: Option<Vec<String>>,
+    fix_store: Option<String>,
+    replay_fix_store: Option<bool>,
+    fix_dictionary: Option<String>,
+    locale: Option<String>,
+    ui_scale: Option<f32>,
+    save_path: Option<String>,
+    on_save: Option<String>,
+    svg_path: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Config {
+    pub workspace: Option<String>,
+    pub fix_addr: Option<String>,
+    // Name and address of each extra acceptor; None leaves the workspace's
+    pub fix_listeners: Option<Vec<(String, String)>>,
+    pub drop_copy: Option<String>,
+    pub drop_copy_listen: Option<String>,
+    pub forward_to: Option<String>,
+    // A machine's way out rather than the workspace's, so never saved with it
+    pub forward_proxy: Option<String>,
+    pub webhook: Option<String>,
+    pub webhook_secret: Option<String>,
+    pub metrics_addr: Option<String>,
+    // Tokens the metrics address asks for, from the config file only
+    pub api_tokens: Vec<ApiToken>,
+    pub pack_key: Option<String>,
+    pub lenient_fix: bool,
+    pub fix_rate_limit: u32,
+    pub fix_max_connections: usize,
+    pub fix_tls_cert: Option<String>,
+    pub fix_tls_key: Option<String>,
+    pub fix_client_ca: Option<String>,
+    pub fix_auth: Option<String>,
+    // Rules for every listener (netacl.rs); flags replace the file's list
+    pub allow_from: Vec<String>,
+    pub deny_from: Vec<String>,
+    pub fix_store: Option<String>,
+    pub replay_fix_store: bool,
+    pub fix_dictionary: Option<String>,
+    pub locale: Option<String>,
+    pub ui_scale: Option<f32>,
+    pub save_path: Option<String>,
+    pub on_save: Option<String>,
+    pub svg_path: Option<String>,
+    pub self_test: bool,
+    // Config files that were ignored, and why
+    pub problems: Vec<String>,
+}
+
+// Resolve all layers; exits with usage on bad flags
+pub fn load() -> Config {
+    let cli = Cli::parse();
+    let mut problems = vec![];
+    let path = match &cli.config {
+        Some(path) => Some(path.as_str()),
+        None if std::path::Path::new(DEFAULT_CONFIG).exists() => Some(DEFAULT_CONFIG),
+        None => None,
+    };
+    let file = path.map_or_else(FileConfig::default, |path| {
+        read_file(path).unwrap_or_else(|e| {
+            eprintln!("[config] {path}: {e}; ignoring it");
+            problems.push(format!("{path}: {e}"));
+            FileConfig::default()
+        })
+    });
+    let from_file = file.fix_listeners.map(|entries| {
+        entries
+            .iter()
+            .filter_map(|entry| {
+                listener(entry)
+                    .inspect_err(|e| {
+                        eprintln!("[config] fix_listeners: {e}; ignoring it");
+                        problems.push(format!("fix_listeners: {e}"));
+                    })
+                    .ok()
+            })
+            .collect()
+    });
+    // clap already put env vars under the flags
+    Config {
+        workspace: cli.workspace.or(file.workspace),
+        fix_addr: cli.fix_addr.or(file.fix_addr),
+        fix_listeners: if cli.fix_listeners.is_empty() { from_file } else { Some(cli.fix_listeners) },
+        drop_copy: cli.drop_copy.or(file.drop_copy),
+        drop_copy_listen: cli.drop_copy_listen.or(file.drop_copy_listen),
+        forward_to: cli.forward_to.or(file.forward_to),
+        forward_proxy: cli.forward_proxy.or(file.forward_proxy),
+        webhook: cli.webhook.or(file.webhook),
+        webhook_secret: cli.webhook_secret.or(file.webhook_secret),
+        metrics_addr: cli.metrics_addr.or(file.metrics_addr),
+        api_tokens: file.api_tokens.unwrap_or_default(),
+        pack_key: cli.pack_key.or(file.pack_key),
+        lenient_fix: cli.lenient_fix || file.lenient_fix.unwrap_or(false),
+        fix_rate_limit: cli.fix_rate_limit.or(file.fix_rate_limit).unwrap_or(DEFAULT_FIX_RATE_LIMIT),
+        fix_max_connections: cli
+            .fix_max_connections
+            .or(file.fix_max_connections)
+            .unwrap_or(DEFAULT_FIX_MAX_CONNECTIONS),
+        fix_tls_cert: cli.fix_tls_cert.or(file.fix_tls_cert),
+        fix_tls_key: cli.fix_tls_key.or(file.fix_tls_key),
+        fix_client_ca: cli.fix_client_ca.or(file.fix_client_ca),
+        fix_auth: cli.fix_auth.or(file.fix_auth),
+        allow_from: if cli.allow_from.is_empty() { file.allow_from.unwrap_or_default() } else { cli.allow_from },
+        deny_from: if cli.deny_from.is_empty() { file.deny_from.unwrap_or_default() } else { cli.deny_from },
+        fix_store: cli.fix_store.or(file.fix_store),
+        replay_fix_store: cli.replay_fix_store || file.replay_fix_store.unwrap_or(false),
+        fix_dictionary: cli.fix_dictionary.or(file.fix_dictionary),
+        locale: cli.locale.or(file.locale),
+        ui_scale: cli.ui_scale.or(file.ui_scale),
+        save_path: cli.save_path.or(file.save_path),
+        on_save: cli.on_save.or(file.on_save),
+        svg_path: cli.svg_path.or(file.svg_path),
+        self_test: cli.self_test,
+        problems,
+    }
+}
+
+// "test=0.0.0.0:9899"
+fn listener(entry: &str) -> Result<(String, String), String> {
+    match entry.split_once('=') {
+        Some((name, addr)) if !name.trim().is_empty() && !addr.trim().is_empty() => {
+            Ok((name.trim().to_string(), addr.trim().to_string()))
+        }
+        _ => Err(format!("\"{entry}\" is not NAME=ADDR")),
+    }
+}
+
+fn read_file(path: &str) -> Result<FileConfig, IoError> {
+    let text = std::fs::read_to_string(path).map_err(|e| IoError::Read(e.to_string()))?;
+    toml::from_str(&text).map_err(|e| IoError::Parse(e.to_string()))
+}
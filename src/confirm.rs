@@ -0,0 +1,176 @@
+// -------------------- Confirmation dialog --------------------
+// Destructive actions are parked in `State::confirm` and only run once the
+// user accepts the modal, which spells out what will be lost.
+use iced::widget::{button, center, column, container, mouse_area, opaque, row, stack, text};
+use chrono::{DateTime, Utc};
+use iced::{Border, Color, Element, Length, Theme};
+use uuid::Uuid;
+
+use crate::units::Coords;
+use crate::{Message, NkisiNkondi};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Destructive {
+    // Events picked by a clearing scope, described unless it took all
+    Clear { ids: Vec<Uuid>, what: Option<String> },
+    DeleteEvent(Uuid),
+    EmptyTrash,
+    LoadOverwrite(String),
+    LoadSample,
+    // Back to the spikes struck by then; later ones go to the trash
+    RestoreSnapshot(DateTime<Utc>),
+    OpenWorkspace(String),
+    OpenReadOnly(String),
+    TakeOverLock(String),
+    ResetUsage,
+}
+
+impl Destructive {
+    pub fn title(&self) -> &'static str {
+        match self {
+            Destructive::Clear { what: None, .. } => "Clear all events?",
+            Destructive::Clear { .. } => "Clear these events?",
+            Destructive::DeleteEvent(_) => "Delete event?",
+            Destructive::EmptyTrash => "Empty the trash?",
+            Destructive::LoadOverwrite(_) | Destructive::LoadSample => "Replace the current ledger?",
+            Destructive::RestoreSnapshot(_) => "Restore this snapshot?",
+            Destructive::OpenWorkspace(_) => "Open another workspace?",
+            Destructive::OpenReadOnly(_) => "Ledger from a newer version",
+            Destructive::TakeOverLock(_) => "Take over the ledger?",
+            Destructive::ResetUsage => "Reset the usage statistics?",
+        }
+    }
+
+    // What accepting will cost, in terms of the current ledger
+    pub fn consequence(&self, nkisi: &NkisiNkondi, coords: Coords) -> String {
+        match self {
+            Destructive::Clear { ids, what } => {
+                let n = count(nkisi.events.iter().filter(|e| ids.contains(&e.id)).count(), "event");
+                match what {
+                    Some(what) => format!("This moves {n} {what} and their pins to the trash."),
+                    None => format!("This moves all {n} and their pins to the trash."),
+                }
+            }
+            Destructive::DeleteEvent(id) => match nkisi.events.iter().find(|e| e.id == *id) {
+                Some(ev) => format!(
+                    "This moves the spike by {} at {} from {} to the trash.",
+                    ev.performed_by,
+                    coords.format(ev.pos),
+                    ev.date.format("%Y-%m-%d %H:%M")
+                ),
+                None => "The event no longer exists.".into(),
+            },
+            Destructive::EmptyTrash => format!(
+                "This permanently deletes {}. It cannot be undone.",
+                count(nkisi.trash.len(), "trashed event")
+            ),
+            Destructive::LoadOverwrite(path) => format!(
+                "This discards {} not yet saved and loads {path}.",
+                count(nkisi.events.len(), "event")
+            ),
+            Destructive::LoadSample => format!(
+                "This discards {} not yet saved and loads the sample ledger, which saves to a file of its own.",
+                count(nkisi.events.len(), "event")
+            ),
+            Destructive::RestoreSnapshot(t) => format!(
+                "This moves {} struck after {} to the trash.",
+                count(nkisi.events.iter().filter(|e| e.date > *t).count(), "event"),
+                t.format("%Y-%m-%d %H:%M")
+            ),
+            Destructive::OpenWorkspace(path) => format!(
+                "This discards {} not yet saved and opens {path}.",
+                count(nkisi.events.len(), "event")
+            ),
+            Destructive::TakeOverLock(holder) => format!(
+                "Another instance ({holder}) is editing this ledger. Taking over reloads \
+                 the file and lets this window save; changes the other instance has not \
+                 saved yet will be lost when it next saves over yours. This discards {} \
+                 shown here.",
+                count(nkisi.events.len(), "event")
+            ),
+            Destructive::OpenReadOnly(path) => format!(
+                "{path} uses a newer data format than this version understands. \
+                 It can be opened read-only; fields this version doesn't know are \
+                 not shown and nothing will be written back. This discards {} not yet saved.",
+                count(nkisi.events.len(), "event")
+            ),
+            Destructive::ResetUsage => {
+                "This sets the session, spike and feature counts back to zero. The ledger is not touched.".into()
+            }
+        }
+    }
+
+    pub fn accept_label(&self) -> &'static str {
+        match self {
+            Destructive::Clear { what: None, .. } => "Clear all",
+            Destructive::Clear { .. } => "Clear",
+            Destructive::DeleteEvent(_) => "Delete",
+            Destructive::EmptyTrash => "Empty trash",
+            Destructive::LoadOverwrite(_) | Destructive::LoadSample => "Load",
+            Destructive::RestoreSnapshot(_) => "Restore",
+            Destructive::OpenWorkspace(_) => "Open",
+            Destructive::OpenReadOnly(_) => "Open read-only",
+            Destructive::TakeOverLock(_) => "Take over",
+            Destructive::ResetUsage => "Reset",
+        }
+    }
+}
+
+// "1,243 events"
+pub fn count(n: usize, noun: &str) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    let plural = if n == 1 { "" } else { "s" };
+    format!("{grouped} {noun}{plural}")
+}
+
+// Lay the dialog for `action` over `base`; clicking outside dismisses it
+pub fn modal<'a>(
+    base: Element<'a, Message>,
+    action: &Destructive,
+    nkisi: &NkisiNkondi,
+    coords: Coords,
+) -> Element<'a, Message> {
+    let dialog = container(
+        column![
+            text(action.title()).size(18),
+            text(action.consequence(nkisi, coords)),
+            row![
+                button(action.accept_label())
+                    .style(button::danger)
+                    .on_press(Message::ConfirmAccepted),
+                button("Cancel")
+                    .style(button::secondary)
+                    .on_press(Message::ConfirmDismissed),
+            ]
+            .spacing(12),
+        ]
+        .spacing(12),
+    )
+    .padding(16)
+    .max_width(420)
+    .style(|_theme: &Theme| container::Style {
+        background: Some(Color::from_rgb(0.13, 0.13, 0.16).into()),
+        border: Border { radius: 12.0.into(), ..Default::default() },
+        ..Default::default()
+    });
+
+    let backdrop = mouse_area(
+        center(opaque(dialog))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .style(|_theme: &Theme| container::Style {
+                background: Some(Color::from_rgba(0.0, 0.0, 0.0, 0.6).into()),
+                ..Default::default()
+            }),
+    )
+    .on_press(Message::ConfirmDismissed);
+
+    stack![base, opaque(backdrop)].into()
+}
@@ -0,0 +1,214 @@
+// -------------------- Replicated ledger --------------------
+// Copies of one figure's ledger edited offline by different parties merge
+// without a server. The ledger is a state-based CRDT: events form a
+// grow-only set keyed by id, and whether each one is live, trashed or gone
+// is a last-writer-wins register stamped with (time, replica). Merging is
+// commutative, associative and idempotent, so every party converges on the
+// same ledger whatever order copies are exchanged in. Gone events keep only
+// their tombstone, so a merge never brings them back.
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+use crate::{ActivationEvent, NkisiNkondi, Outcome};
+
+// Identifies this installation in stamps
+const REPLICA_MARKER: &str = ".nkisi_replica";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Stamp {
+    pub at: DateTime<Utc>,
+    pub replica: Uuid,
+}
+
+// Later variants win ties between equal stamps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Presence {
+    Live,
+    Trashed,
+    Gone,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Status {
+    pub stamp: Stamp,
+    pub presence: Presence,
+}
+
+pub struct Merged {
+    pub ledger: NkisiNkondi,
+    pub conflicts: Vec<Conflict>,
+}
+
+// One event with different contents on the two sides, where neither edit
+// is known to supersede the other
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub local: ActivationEvent,
+    pub remote: ActivationEvent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Local,
+    Remote,
+    // Both notes kept; the more settled outcome wins
+    Combine,
+}
+
+impl Resolution {
+    pub fn label(self) -> &'static str {
+        match self {
+            Resolution::Local => "kept this copy",
+            Resolution::Remote => "kept their copy",
+            Resolution::Combine => "combined both",
+        }
+    }
+}
+
+impl Conflict {
+    pub fn resolve(&self, how: Resolution) -> ActivationEvent {
+        match how {
+            Resolution::Local => self.local.clone(),
+            Resolution::Remote => self.remote.clone(),
+            Resolution::Combine => {
+                let mut ev = self.local.clone();
+                ev.notes = match (&self.local.notes, &self.remote.notes) {
+                    (Some(a), Some(b)) if a != b => Some(format!("{a}\n—\n{b}")),
+                    (a, b) => a.clone().or_else(|| b.clone()),
+                };
+                if matches!(ev.outcome, Outcome::Pending) {
+                    ev.outcome = self.remote.outcome.clone();
+                }
+                ev
+            }
+        }
+    }
+}
+
+pub fn replica_id() -> Uuid {
+    static ID: OnceLock<Uuid> = OnceLock::new();
+    *ID.get_or_init(|| {
+        if let Some(id) = std::fs::read_to_string(REPLICA_MARKER)
+            .ok()
+            .and_then(|s| Uuid::parse_str(s.trim()).ok())
+        {
+            return id;
+        }
+        let id = Uuid::new_v4();
+        if let Err(e) = std::fs::write(REPLICA_MARKER, id.to_string()) {
+            eprintln!("[replica] {REPLICA_MARKER}: {e}; using a per-run id");
+        }
+        id
+    })
+}
+
+// Stamp for files written before stamps existed: older than any change
+fn origin() -> Stamp {
+    Stamp { at: DateTime::<Utc>::UNIX_EPOCH, replica: Uuid::nil() }
+}
+
+impl NkisiNkondi {
+    // Status of an event, falling back to where it sits in the ledger
+    pub fn status_of(&self, id: Uuid) -> Option<Status> {
+        if let Some(s) = self.status.get(&id) {
+            return Some(*s);
+        }
+        let presence = if self.events.iter().any(|e| e.id == id) {
+            Presence::Live
+        } else if self.trash.iter().any(|e| e.id == id) {
+            Presence::Trashed
+        } else {
+            return None;
+        };
+        Some(Status { stamp: origin(), presence })
+    }
+
+    pub fn set_status(&mut self, id: Uuid, presence: Presence, stamp: Stamp) {
+        let new = Status { stamp, presence };
+        match self.status.get(&id) {
+            Some(old) if *old >= new => {}
+            _ => {
+                self.status.insert(id, new);
+            }
+        }
+    }
+
+    pub fn knows(&self, id: Uuid) -> bool {
+        self.status.contains_key(&id)
+            || self.events.iter().any(|e| e.id == id)
+            || self.trash.iter().any(|e| e.id == id)
+    }
+}
+
+// Join of two copies of the same figure. Ledger-level fields come from
+// `local`. Where the two sides hold different contents for an event, the
+// later revision wins, then the larger serialized form, so both parties
+// pick the same. Differences not explained by one side alone having
+// revised the event are reported as conflicts.
+pub fn merge(local: &NkisiNkondi, remote: &NkisiNkondi) -> Merged {
+    let mut contents: BTreeMap<Uuid, ActivationEvent> = BTreeMap::new();
+    let mut conflicts = vec![];
+    for ev in local.events.iter().chain(&local.trash) {
+        contents.insert(ev.id, ev.clone());
+    }
+    for ev in remote.events.iter().chain(&remote.trash) {
+        match contents.get(&ev.id) {
+            None => {
+                contents.insert(ev.id, ev.clone());
+            }
+            Some(mine) => {
+                let (a, b) = ((mine.revised, key(mine)), (ev.revised, key(ev)));
+                if a.1 != b.1 {
+                    if mine.revised.is_some() == ev.revised.is_some() {
+                        conflicts.push(Conflict { local: mine.clone(), remote: ev.clone() });
+                    }
+                    if b > a {
+                        contents.insert(ev.id, ev.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    let ids: HashSet<Uuid> = contents
+        .keys()
+        .chain(local.status.keys())
+        .chain(remote.status.keys())
+        .copied()
+        .collect();
+    let mut out = NkisiNkondi { events: vec![], pins: vec![], trash: vec![], ..local.clone() };
+    out.status = BTreeMap::new();
+    for id in ids {
+        let status = local.status_of(id).max(remote.status_of(id)).expect("id came from a side");
+        if status.stamp != origin() {
+            out.status.insert(id, status);
+        }
+        let Some(ev) = contents.remove(&id) else { continue };
+        match status.presence {
+            Presence::Live => out.events.push(ev),
+            Presence::Trashed => out.trash.push(ev),
+            Presence::Gone => {}
+        }
+    }
+    out.events.sort_by_key(|e| (e.date, e.id));
+    out.trash.sort_by_key(|e| (e.date, e.id));
+    out.pins = out.events.iter().map(|e| e.pos).collect();
+    // Tombstones from both sides, once each
+    for ev in &remote.cancelled {
+        if !out.cancelled.iter().any(|c| c.id == ev.id) {
+            out.cancelled.push(ev.clone());
+        }
+    }
+    conflicts.sort_by_key(|c| c.local.id);
+    Merged { ledger: out, conflicts }
+}
+
+// Provenance is local to each copy (how it got there) and not compared
+fn key(ev: &ActivationEvent) -> String {
+    let ev = ActivationEvent { provenance: vec![], ..ev.clone() };
+    serde_json::to_string(&ev).unwrap_or_default()
+}
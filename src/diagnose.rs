@@ -0,0 +1,252 @@
+// -------------------- Self-test --------------------
+// A pass/fail report on the setup, for when something doesn't work and it
+// isn't clear what: config files, figure and template, write access to the
+// ledger, the ports, and a FIX round trip (Logon, one spike, its
+// acknowledgment, Logout) against a private acceptor on loopback that
+// shares nothing with the real one. Run from the Self-test button, or with
+// --self-test before anything starts (the report is printed and the exit
+// status is 1 if a check failed).
+use iced::widget::{button, column, row, text};
+use iced::{Color, Element};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::fixdict::FixDictionary;
+use crate::fix::{self, tag, Decoded, Decoder, Fields, Header};
+use crate::session;
+use crate::{figure, fixauth, tls, workspace, Message};
+
+// How long a port or the loopback session gets to answer
+const PATIENCE: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: &'static str,
+    // What was found, or what is wrong
+    pub outcome: Result<String, String>,
+}
+
+// What the checks look at; the paths and addresses are the ones in effect
+#[derive(Debug, Clone, Default)]
+pub struct Setup {
+    pub config_problems: Vec<String>,
+    pub workspace: Option<String>,
+    pub fix_dictionary: Option<String>,
+    pub fix_auth: Option<String>,
+    // Certificate, key and client CA
+    pub tls: (Option<String>, Option<String>, Option<String>),
+    pub figure: String,
+    pub ledger: String,
+    pub template: String,
+    // Listening addresses should accept connections; the others should be
+    // free to bind
+    pub fix_addr: String,
+    pub fix_listening: bool,
+    pub metrics_addr: Option<String>,
+    pub metrics_listening: bool,
+    pub drop_copy_listen: Option<String>,
+    pub drop_copy_listening: bool,
+}
+
+pub fn run(setup: &Setup) -> Vec<Check> {
+    let mut checks = vec![];
+    let mut check = |name, outcome| checks.push(Check { name, outcome });
+
+    check(
+        "Config file",
+        match setup.config_problems.as_slice() {
+            [] => Ok("read, or none to read".into()),
+            problems => Err(format!("ignored: {}", problems.join("; "))),
+        },
+    );
+    if let Some(path) = &setup.workspace {
+        let opened = workspace::open(path).map(|ws| format!("{path}, {} figure(s)", ws.figures.len()));
+        check("Workspace", opened.map_err(|e| format!("{path}: {e}")));
+    }
+    let dict = match &setup.fix_dictionary {
+        Some(path) => {
+            let loaded = FixDictionary::load(path);
+            check("FIX dictionary", loaded.as_ref().map(|_| path.clone()).map_err(|e| format!("{path}: {e}")));
+            loaded.unwrap_or_default()
+        }
+        None => FixDictionary::default(),
+    };
+    if let Some(path) = &setup.fix_auth {
+        let loaded = fixauth::FixAuth::load(path).map(|_| path.clone());
+        check("FIX access list", loaded.map_err(|e| format!("{path}: {e}")));
+    }
+    let (cert, key, ca) = &setup.tls;
+    if cert.is_some() || key.is_some() || ca.is_some() {
+        let loaded = tls::server_config(cert.as_deref(), key.as_deref(), ca.as_deref());
+        let mode = if ca.is_some() { "with client certificates" } else { "server certificate only" };
+        check("FIX TLS", loaded.map(|_| format!("certificate and key load, {mode}")).map_err(say));
+    }
+    check("Figure", base_figure(&setup.figure));
+    let template = &setup.template;
+    let found = std::fs::metadata(template).map(|_| template.clone());
+    check("Report template", found.map_err(|e| format!("{template}: {e}")));
+    check("Ledger", writable(&setup.ledger));
+    check("FIX port", port(&setup.fix_addr, setup.fix_listening));
+    if let Some(addr) = &setup.metrics_addr {
+        check("Metrics port", port(addr, setup.metrics_listening));
+    }
+    if let Some(addr) = &setup.drop_copy_listen {
+        check("Drop-copy port", port(addr, setup.drop_copy_listening));
+    }
+    check("FIX round trip", round_trip(Arc::new(dict)));
+    checks
+}
+
+fn say(e: impl std::fmt::Display) -> String {
+    e.to_string()
+}
+
+fn base_figure(path: &str) -> Result<String, String> {
+    match figure::kind(path) {
+        figure::BaseKind::Raster => figure::raster_dims(path)
+            .map(|(w, h)| format!("{path}, {w}×{h} px photo"))
+            .ok_or_else(|| format!("{path}: not a readable PNG or JPEG")),
+        figure::BaseKind::Svg => {
+            let svg = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+            roxmltree::Document::parse(&svg).map_err(|e| format!("{path}: {e}"))?;
+            Ok(format!("{path}, SVG"))
+        }
+    }
+}
+
+// Opened for appending, or a scratch file made beside it, so nothing
+// already there is touched
+fn writable(path: &str) -> Result<String, String> {
+    let target = std::path::Path::new(path);
+    if target.exists() {
+        std::fs::OpenOptions::new().append(true).open(target).map_err(|e| format!("{path}: {e}"))?;
+        return Ok(format!("{path} can be written"));
+    }
+    let dir = target.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(std::path::Path::new("."));
+    let probe = dir.join(format!(".nkisi_selftest_{}", std::process::id()));
+    std::fs::write(&probe, b"").map_err(|e| format!("{}: {e}", dir.display()))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(format!("{path} can be created"))
+}
+
+fn port(addr: &str, listening: bool) -> Result<String, String> {
+    if !listening {
+        return TcpListener::bind(addr).map(|_| format!("{addr} is free")).map_err(|e| format!("{addr}: {e}"));
+    }
+    let target = loopback(addr)?;
+    TcpStream::connect_timeout(&target, PATIENCE)
+        .map(|_| format!("{addr} accepts connections"))
+        .map_err(|e| format!("{addr}: {e}"))
+}
+
+// Where to reach a listener from this machine: 0.0.0.0 means any address
+fn loopback(addr: &str) -> Result<SocketAddr, String> {
+    let mut target = addr.to_socket_addrs().ok().and_then(|mut a| a.next()).ok_or(format!("{addr}: not an address"))?;
+    if target.ip().is_unspecified() {
+        target.set_ip(if target.is_ipv4() { [127, 0, 0, 1].into() } else { std::net::Ipv6Addr::LOCALHOST.into() });
+    }
+    Ok(target)
+}
+
+fn round_trip(dict: Arc<FixDictionary>) -> Result<String, String> {
+    let listener = TcpListener::bind("127.0.0.1:0").map_err(say)?;
+    let addr = listener.local_addr().map_err(say)?;
+    let acceptor = Arc::clone(&dict);
+    thread::spawn(move || {
+        let Ok((stream, peer)) = listener.accept() else { return };
+        // The spike is read as a real one would be, then only acknowledged
+        session::run(stream, peer.to_string(), Default::default(), Arc::clone(&acceptor), |msg, reply| {
+            crate::parse_fix_spikes(msg, &acceptor)?;
+            if let Some(reply) = reply {
+                reply.accepted(Uuid::nil(), "self-test");
+            }
+            Ok(())
+        });
+    });
+
+    let started = Instant::now();
+    let mut stream = TcpStream::connect_timeout(&addr, PATIENCE).map_err(say)?;
+    stream.set_read_timeout(Some(PATIENCE)).map_err(say)?;
+    let header = Header { begin: "FIX.4.4", sender: "SELFTEST", target: "NKISI" };
+    let mut decoder = Decoder::new(true);
+    let mut exchange = |seq: u64, msg_type: &str, body: &[(u32, String)], expect: &str| -> Result<Fields, String> {
+        stream.write_all(&fix::encode(&header, msg_type, seq, body)).map_err(say)?;
+        loop {
+            let answer = next_message(&mut stream, &mut decoder).map_err(|e| format!("no answer to 35={msg_type}: {e}"))?;
+            match tag(&answer, 35) {
+                Some(t) if t == expect => return Ok(answer),
+                Some("3") => return Err(format!("35={msg_type} rejected: {}", tag(&answer, 58).unwrap_or("?"))),
+                _ => {}
+            }
+        }
+    };
+    exchange(1, "A", &[(98, "0".into()), (108, "30".into()), (141, "Y".into())], "A")?;
+    let spike = [
+        (55, dict.symbol.clone()),
+        (dict.who, "self-test".into()),
+        (dict.x, "50".into()),
+        (dict.y, "50".into()),
+        (11, "selftest-1".into()),
+    ];
+    let ack = exchange(2, &dict.spike_type, &spike, &dict.ack_type)?;
+    if tag(&ack, 39) != Some("0") {
+        return Err(format!("spike not accepted: {}", tag(&ack, 58).unwrap_or("?")));
+    }
+    exchange(3, "5", &[], "5")?;
+    let ms = started.elapsed().as_millis();
+    Ok(format!("Logon, spike (35={}), acknowledgment and Logout in {ms} ms", dict.spike_type))
+}
+
+// One whole message off the wire, up to the SOH that ends its CheckSum
+pub fn next_message(stream: &mut TcpStream, decoder: &mut Decoder) -> io::Result<Fields> {
+    let mut buf = [0u8; 4096];
+    loop {
+        if let Some(Decoded::Message(msg)) = decoder.next() {
+            return Ok(msg.fields);
+        }
+        match stream.read(&mut buf)? {
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n => decoder.feed(&buf[..n]),
+        }
+    }
+}
+
+// Printed by --self-test
+pub fn print(checks: &[Check]) {
+    for c in checks {
+        match &c.outcome {
+            Ok(found) => println!("PASS  {:<16} {found}", c.name),
+            Err(why) => println!("FAIL  {:<16} {why}", c.name),
+        }
+    }
+    let failed = checks.iter().filter(|c| c.outcome.is_err()).count();
+    println!("{} of {} checks passed", checks.len() - failed, checks.len());
+}
+
+pub fn view(checks: &[Check]) -> Element<'_, Message> {
+    let failed = checks.iter().filter(|c| c.outcome.is_err()).count();
+    let summary = match failed {
+        0 => format!("Self-test: all {} checks passed", checks.len()),
+        n => format!("Self-test: {n} of {} checks failed", checks.len()),
+    };
+    let mut col = column![row![
+        text(summary).size(16),
+        button("Run again").on_press(Message::RunSelfTest),
+        button("Close").style(button::secondary).on_press(Message::CloseSelfTest),
+    ]
+    .spacing(10)]
+    .spacing(4);
+    for c in checks {
+        let (mark, color, detail) = match &c.outcome {
+            Ok(found) => ("✔", Color::from_rgb(0.4, 0.85, 0.5), found),
+            Err(why) => ("✘", Color::from_rgb(1.0, 0.45, 0.4), why),
+        };
+        let line = row![text(mark).color(color), text(c.name).width(140), text(detail.as_str()).size(13)];
+        col = col.push(line.spacing(8));
+    }
+    col.into()
+}
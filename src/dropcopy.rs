@@ -0,0 +1,221 @@
+// -------------------- Drop-copy feed --------------------
+// Every event that lands in the ledger (manual, FIX or restored from the
+// trash) is mirrored as a FIX message to a monitoring endpoint. Messages
+// use the same layout the acceptor reads (35=U1 unless the FIX dictionary
+// says otherwise), so another Nkisi with the same dictionary can act as the
+// monitor. A background thread owns the connection, reconnects with a
+// backoff and queues events while the endpoint is down (see outbox.rs).
+//
+// The other way round, a publisher listens for consumers (mirrored
+// displays, audit systems) and streams the same messages to each one that
+// connects, from the moment it connects. Every connection numbers its
+// messages from 1; one that falls BACKLOG events behind is cut off.
+use crossbeam_channel::{bounded, unbounded, Receiver, RecvTimeoutError, Sender};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::fixdict::{self, FixDictionary};
+use crate::netacl::NetAcl;
+use crate::outbox::{Backoff, Channel, Outbox, MAX_ATTEMPTS};
+use crate::fix::{self, Header};
+use crate::{ActivationEvent, ActivationPurpose, PARTY_ROLE_STRIKER, PARTY_ROLE_WITNESS};
+
+// Oldest events become dead letters beyond this while disconnected
+const MAX_QUEUED: usize = 10_000;
+const RETRY: Duration = Duration::from_secs(2);
+// Events a publisher's consumer may fall behind before it is dropped
+const BACKLOG: usize = 4096;
+
+pub struct DropCopy {
+    tx: Sender<ActivationEvent>,
+    outbox: Arc<Outbox>,
+}
+
+impl DropCopy {
+    pub fn start(addr: &str, dict: Arc<FixDictionary>, outbox: Arc<Outbox>) -> Self {
+        let (tx, rx) = unbounded::<ActivationEvent>();
+        let addr = addr.to_string();
+        let shared = Arc::clone(&outbox);
+        thread::spawn(move || {
+            let mut queue: VecDeque<ActivationEvent> = VecDeque::new();
+            let mut conn: Option<TcpStream> = None;
+            let mut next_try = Instant::now();
+            let mut backoff = Backoff::new(RETRY);
+            // Failed tries of the event at the front
+            let mut attempts = 0;
+            let mut seq: u64 = 0;
+            eprintln!("[drop-copy] streaming events to {addr}");
+            loop {
+                let wait = if queue.is_empty() {
+                    RETRY
+                } else {
+                    next_try.saturating_duration_since(Instant::now()).max(Duration::from_millis(100))
+                };
+                match rx.recv_timeout(wait) {
+                    Ok(ev) => queue.push_back(ev),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+                while queue.len() > MAX_QUEUED {
+                    if let Some(ev) = queue.pop_front() {
+                        shared.dead(Channel::DropCopy, ev, 0, "queue full while disconnected");
+                    }
+                }
+                let mut failed = None;
+                if conn.is_none() && !queue.is_empty() && Instant::now() >= next_try {
+                    match TcpStream::connect(&addr) {
+                        Ok(s) => conn = Some(s),
+                        Err(e) => failed = Some(e.to_string()),
+                    }
+                }
+                if let Some(stream) = conn.as_mut() {
+                    while let Some(ev) = queue.front() {
+                        if let Err(e) = stream.write_all(&encode(ev, seq + 1, &dict)) {
+                            failed = Some(format!("write failed: {e}"));
+                            conn = None;
+                            break;
+                        }
+                        shared.delivered(Channel::DropCopy, ev.id);
+                        backoff.reset();
+                        attempts = 0;
+                        seq += 1;
+                        queue.pop_front();
+                    }
+                }
+                let Some(why) = failed else { continue };
+                attempts += 1;
+                if attempts >= MAX_ATTEMPTS {
+                    if let Some(ev) = queue.pop_front() {
+                        shared.dead(Channel::DropCopy, ev, attempts, &why);
+                    }
+                    attempts = 0;
+                }
+                let pause = backoff.failed();
+                eprintln!("[drop-copy] {addr}: {why}; {} event(s) queued, next try in {pause:?}", queue.len());
+                next_try = Instant::now() + pause;
+            }
+        });
+        Self { tx, outbox }
+    }
+
+    pub fn send<'a>(&self, events: impl IntoIterator<Item = &'a ActivationEvent>) {
+        for ev in events {
+            self.outbox.queued(Channel::DropCopy, ev);
+            let _ = self.tx.send(ev.clone());
+        }
+    }
+}
+
+// Consumers connect to this to get a copy of every event from then on
+pub struct Publisher {
+    subscribers: Mutex<Vec<Sender<ActivationEvent>>>,
+    pub bound: SocketAddr,
+}
+
+impl Publisher {
+    // Listen on `addr`; None when it can't be had
+    pub fn start(addr: &str, dict: Arc<FixDictionary>, acl: Arc<NetAcl>) -> Option<Arc<Self>> {
+        let listener = match TcpListener::bind(addr) {
+            Ok(l) => l,
+            Err(e) => {
+                eprintln!("[drop-copy] bind {addr}: {e}; no drop-copy publisher");
+                return None;
+            }
+        };
+        let bound = listener.local_addr().ok()?;
+        let publisher = Arc::new(Self { subscribers: Mutex::new(vec![]), bound });
+        let shared = Arc::clone(&publisher);
+        eprintln!("[drop-copy] publishing events on {bound}");
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { continue };
+                if !stream.peer_addr().is_ok_and(|peer| acl.check("drop-copy", peer)) {
+                    continue;
+                }
+                let events = shared.subscribe();
+                let dict = Arc::clone(&dict);
+                thread::spawn(move || serve(stream, &events, &dict));
+            }
+        });
+        Some(publisher)
+    }
+
+    pub fn publish<'a>(&self, events: impl IntoIterator<Item = &'a ActivationEvent>) {
+        let Ok(mut subscribers) = self.subscribers.lock() else { return };
+        if subscribers.is_empty() {
+            return;
+        }
+        for ev in events {
+            subscribers.retain(|s| s.try_send(ev.clone()).is_ok());
+        }
+    }
+
+    // Consumers connected (or not yet noticed gone)
+    pub fn count(&self) -> usize {
+        self.subscribers.lock().map_or(0, |s| s.len())
+    }
+
+    fn subscribe(&self) -> Receiver<ActivationEvent> {
+        let (tx, rx) = bounded(BACKLOG);
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(tx);
+        }
+        rx
+    }
+}
+
+// Streams to one consumer until it goes away or falls behind
+fn serve(mut stream: TcpStream, events: &Receiver<ActivationEvent>, dict: &FixDictionary) {
+    let peer = stream.peer_addr().map_or_else(|_| "?".into(), |a| a.to_string());
+    eprintln!("[drop-copy] {peer} subscribed");
+    let mut seq: u64 = 0;
+    for ev in events {
+        seq += 1;
+        if let Err(e) = stream.write_all(&encode(&ev, seq, dict)) {
+            eprintln!("[drop-copy] {peer}: {e}; unsubscribed");
+            return;
+        }
+    }
+    eprintln!("[drop-copy] {peer} fell {BACKLOG} events behind; closing its connection");
+}
+
+// One event as a complete FIX message (BodyLength and CheckSum filled in)
+fn encode(ev: &ActivationEvent, seq: u64, dict: &FixDictionary) -> Vec<u8> {
+    let header = Header { begin: "FIX.4.4", sender: "NKISI", target: "DROPCOPY" };
+    fix::encode(&header, &dict.spike_type, seq, &spike_fields(ev, dict))
+}
+
+// The body of a spike message for `ev`, after the standard header; the
+// FIX initiator sends the same
+pub fn spike_fields(ev: &ActivationEvent, dict: &FixDictionary) -> Vec<(u32, String)> {
+    let mut fields = vec![
+        (55, dict.symbol.clone()),
+        (dict.event_id, ev.id.to_string()), // ledger event id
+        (dict.x, format!("{:.3}", ev.pos.0)),
+        (dict.y, format!("{:.3}", ev.pos.1)),
+        (dict.timestamp, ev.date.to_rfc3339()),
+    ];
+    if let Some(note) = &ev.notes {
+        fields.push((dict.note, note.clone()));
+    }
+    let ActivationPurpose::Other(purpose) = &ev.purpose;
+    fields.push((dict.purpose, purpose.clone()));
+    fields.push((dict.outcome, fixdict::outcome_code(&ev.outcome).into()));
+    for (tag, v) in [(1, &ev.meta.case_ref), (76, &ev.meta.institution), (167, &ev.meta.category)] {
+        if let Some(v) = v {
+            fields.push((tag, v.clone()));
+        }
+    }
+    fields.push((453, (1 + ev.meta.witnesses.len()).to_string()));
+    fields.push((dict.who, ev.performed_by.clone()));
+    fields.push((452, PARTY_ROLE_STRIKER.into()));
+    for w in &ev.meta.witnesses {
+        fields.push((dict.who, w.clone()));
+        fields.push((452, PARTY_ROLE_WITNESS.into()));
+    }
+    fields
+}
@@ -0,0 +1,200 @@
+// -------------------- Raster export --------------------
+// The figure with its overlay as a PNG for print: rendered with resvg at a
+// chosen DPI (96 DPI is the on-screen size), with an optional margin and a
+// white or transparent background. The DPI is stored in the PNG (pHYs) so
+// layout tools place it at the intended physical size. The same pieces
+// give a layered SVG for editing elsewhere.
+use resvg::{tiny_skia, usvg};
+use std::path::Path;
+
+use crate::figure::Orientation;
+use crate::template::escape_html;
+use crate::{IoError, SCREEN_W};
+
+// Largest side we agree to render, in pixels
+const MAX_SIDE: f32 = 20_000.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RasterOptions {
+    pub dpi: f32,
+    pub margin_mm: f32,
+    pub transparent: bool,
+}
+
+pub enum Base<'a> {
+    // SVG source (hidden layers already cut out)
+    Svg(String),
+    // Photo file
+    Raster(&'a str),
+}
+
+fn options(resources_dir: Option<&Path>) -> usvg::Options<'static> {
+    let mut opt = usvg::Options {
+        resources_dir: resources_dir.map(Path::to_path_buf),
+        ..Default::default()
+    };
+    opt.fontdb_mut().load_system_fonts();
+    opt
+}
+
+fn tree(svg: &str, opt: &usvg::Options) -> Result<usvg::Tree, IoError> {
+    usvg::Tree::from_str(svg, opt).map_err(|e| IoError::Parse(e.to_string()))
+}
+
+// Write the PNG; returns its pixel size
+pub fn png(
+    path: &str,
+    base: Base<'_>,
+    overlay: &str,
+    dims: (f32, f32),
+    o: RasterOptions,
+) -> Result<(u32, u32), IoError> {
+    let pixmap = raster(&base, overlay, dims, o)?;
+    write_png(path, &pixmap, o.dpi)?;
+    Ok((pixmap.width(), pixmap.height()))
+}
+
+// The figure and overlay drawn into a pixmap
+pub fn raster(
+    base: &Base<'_>,
+    overlay: &str,
+    (fw, fh): (f32, f32),
+    o: RasterOptions,
+) -> Result<tiny_skia::Pixmap, IoError> {
+    if o.dpi.is_nan() || o.dpi <= 0.0 || o.margin_mm < 0.0 {
+        return Err(IoError::Write("DPI must be positive and the margin not negative".into()));
+    }
+    // Figure units to output pixels
+    let scale = SCREEN_W / fw * o.dpi / 96.0;
+    let margin = o.margin_mm / 25.4 * o.dpi;
+    let (w, h) = (fw * scale + 2.0 * margin, fh * scale + 2.0 * margin);
+    if w > MAX_SIDE || h > MAX_SIDE {
+        return Err(IoError::Write(format!("{w:.0}×{h:.0} px is too large; lower the DPI")));
+    }
+    let mut pixmap = tiny_skia::Pixmap::new(w.round() as u32, h.round() as u32)
+        .ok_or_else(|| IoError::Write("empty image".into()))?;
+    if !o.transparent {
+        pixmap.fill(tiny_skia::Color::WHITE);
+    }
+
+    let base = match base {
+        Base::Svg(src) => tree(src, &options(None))?,
+        Base::Raster(file) => {
+            let p = Path::new(file);
+            let name = p.file_name().and_then(|n| n.to_str()).unwrap_or(file);
+            let wrapper = format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" viewBox="0 0 {fw} {fh}" width="{fw}" height="{fh}"><image width="{fw}" height="{fh}" xlink:href="{}"/></svg>"#,
+                escape_html(name)
+            );
+            tree(&wrapper, &options(p.parent()))?
+        }
+    };
+    let overlay = tree(overlay, &options(None))?;
+    // Each tree is stretched over the figure area, inside the margin
+    for t in [&base, &overlay] {
+        let size = t.size();
+        let transform = tiny_skia::Transform::from_row(
+            fw * scale / size.width(),
+            0.0,
+            0.0,
+            fh * scale / size.height(),
+            margin,
+            margin,
+        );
+        resvg::render(t, transform, &mut pixmap.as_mut());
+    }
+    Ok(pixmap)
+}
+
+// One SVG holding the base and the overlay as nested documents, both
+// stretched over the figure area. A photo is referenced by file name, so
+// it has to sit next to the SVG.
+pub fn svg(base: &Base<'_>, overlay: &str, (fw, fh): (f32, f32)) -> String {
+    let base = match base {
+        Base::Svg(src) => fit_root(strip_prolog(src), fw, fh),
+        Base::Raster(file) => {
+            let name = Path::new(file).file_name().and_then(|n| n.to_str()).unwrap_or(file);
+            format!(r#"<image width="{fw}" height="{fh}" xlink:href="{}"/>"#, escape_html(name))
+        }
+    };
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+         viewBox=\"0 0 {fw} {fh}\" width=\"{fw}\" height=\"{fh}\">\n{base}\n{}\n</svg>\n",
+        strip_prolog(overlay)
+    )
+}
+
+// The base as an SVG turned and mirrored per `o`, measuring the oriented
+// size of `src` (the file's own size). A photo is referred to as
+// `photo_href`: an absolute path to render it, a file name next to an
+// exported SVG.
+pub fn orient(base: &Base<'_>, o: Orientation, src: (f32, f32), photo_href: &str) -> String {
+    let (w, h) = src;
+    let inner = match base {
+        Base::Svg(svg) => fit_root(strip_prolog(svg), w, h),
+        Base::Raster(_) => format!(r#"<image width="{w}" height="{h}" xlink:href="{}"/>"#, escape_html(photo_href)),
+    };
+    let (fw, fh) = o.dims(src);
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" viewBox="0 0 {fw} {fh}" width="{fw}" height="{fh}"><g transform="{}">{inner}</g></svg>"#,
+        o.transform(src)
+    )
+}
+
+// The root element sized to the figure area: its own width and height
+// (often a screen size) would otherwise spill out of the outer viewport
+fn fit_root(svg: &str, fw: f32, fh: f32) -> String {
+    let Some(end) = svg.find('>') else {
+        return svg.to_string();
+    };
+    let (tag, rest) = svg.split_at(end);
+    let mut kept = String::new();
+    let mut attrs = tag.trim_start_matches("<svg");
+    // Attributes are name="value" or name='value' pairs
+    while let Some(eq) = attrs.find('=') {
+        let name = attrs[..eq].trim();
+        let after = attrs[eq + 1..].trim_start();
+        let Some(quote) = after.chars().next() else { break };
+        let Some(close) = after[1..].find(quote) else { break };
+        let value = &after[..close + 2];
+        if name != "width" && name != "height" {
+            kept.push_str(&format!(" {name}={value}"));
+        }
+        attrs = &after[close + 2..];
+    }
+    format!(r#"<svg width="{fw}" height="{fh}"{kept}{}{rest}"#, attrs.trim_end_matches(char::is_whitespace))
+}
+
+// From the root element on: no XML declaration, doctype or leading comments
+fn strip_prolog(src: &str) -> &str {
+    // "<svg" inside a comment doesn't count
+    src.match_indices("<svg")
+        .map(|(i, _)| i)
+        .find(|&i| src[..i].matches("<!--").count() == src[..i].matches("-->").count())
+        .map_or(src, |i| &src[i..])
+}
+
+fn write_png(path: &str, pixmap: &tiny_skia::Pixmap, dpi: f32) -> Result<(), IoError> {
+    let file = std::fs::File::create(path).map_err(|e| IoError::Write(e.to_string()))?;
+    let mut enc = png::Encoder::new(std::io::BufWriter::new(file), pixmap.width(), pixmap.height());
+    enc.set_color(png::ColorType::Rgba);
+    enc.set_depth(png::BitDepth::Eight);
+    let per_metre = (dpi / 0.0254).round() as u32;
+    enc.set_pixel_dims(Some(png::PixelDimensions {
+        xppu: per_metre,
+        yppu: per_metre,
+        unit: png::Unit::Meter,
+    }));
+    // tiny-skia keeps premultiplied alpha; PNG wants it straight
+    let data: Vec<u8> = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect();
+    let mut writer = enc.write_header().map_err(|e| IoError::Write(e.to_string()))?;
+    writer.write_image_data(&data).map_err(|e| IoError::Write(e.to_string()))
+}
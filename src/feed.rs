@@ -0,0 +1,74 @@
+// -------------------- Event feed --------------------
+// GET /events/stream on the metrics address: a chunked HTTP response that
+// stays open and carries one JSON event per line (NDJSON) as each is added
+// to the open ledger, struck here, over FIX, restored, revised or merged in.
+// Meant for curl and scripts (curl -N http://host:port/events/stream). A
+// lone newline is sent when nothing happened for a while, so a consumer
+// that went away is noticed; one that falls BACKLOG events behind is cut off.
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender};
+use std::io::Write;
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::ActivationEvent;
+
+const BACKLOG: usize = 4096;
+const KEEPALIVE: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Default)]
+pub struct Feed {
+    subscribers: Mutex<Vec<Sender<Arc<str>>>>,
+}
+
+impl Feed {
+    pub fn publish<'a>(&self, events: impl IntoIterator<Item = &'a ActivationEvent>) {
+        let Ok(mut subscribers) = self.subscribers.lock() else { return };
+        if subscribers.is_empty() {
+            return;
+        }
+        for ev in events {
+            let line: Arc<str> = match serde_json::to_string(ev) {
+                Ok(json) => format!("{json}\n").into(),
+                Err(e) => {
+                    eprintln!("[feed] event {}: {e}", ev.id);
+                    continue;
+                }
+            };
+            subscribers.retain(|s| s.try_send(Arc::clone(&line)).is_ok());
+        }
+    }
+
+    fn subscribe(&self) -> Receiver<Arc<str>> {
+        let (tx, rx) = bounded(BACKLOG);
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(tx);
+        }
+        rx
+    }
+}
+
+// Answers one request for the stream; returns when the consumer leaves
+pub fn stream(mut stream: TcpStream, feed: &Feed) {
+    let events = feed.subscribe();
+    let head = "HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nTransfer-Encoding: chunked\r\n\
+                Cache-Control: no-cache\r\nConnection: close\r\n\r\n";
+    if stream.write_all(head.as_bytes()).is_err() {
+        return;
+    }
+    loop {
+        let line = match events.recv_timeout(KEEPALIVE) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => "\n".into(),
+            Err(RecvTimeoutError::Disconnected) => {
+                eprintln!("[feed] consumer fell {BACKLOG} events behind; closing its stream");
+                let _ = stream.write_all(b"0\r\n\r\n");
+                return;
+            }
+        };
+        let chunk = format!("{:x}\r\n{line}\r\n", line.len());
+        if stream.write_all(chunk.as_bytes()).and_then(|()| stream.flush()).is_err() {
+            return;
+        }
+    }
+}
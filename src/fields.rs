@@ -0,0 +1,448 @@
+// -------------------- Custom fields --------------------
+// Institutions can capture their own data on events without changing the
+// model: typed fields are defined in the workspace settings, shown as extra
+// inputs on the pending-spike form and stored per event by key. Values for
+// fields later removed from the workspace stay in the ledger untouched.
+use iced::widget::{button, checkbox, column, pick_list, row, text, text_input, toggler};
+use iced::{alignment, Element, Length};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::Message;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldDef {
+    pub key: String,
+    pub label: String,
+    pub kind: FieldKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FieldKind {
+    Text,
+    Number,
+    Boolean,
+    Choice { options: Vec<String> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FieldValue {
+    Bool(bool),
+    Number(f64),
+    Text(String),
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Bool(b) => write!(f, "{}", if *b { "yes" } else { "no" }),
+            FieldValue::Number(n) => write!(f, "{n}"),
+            FieldValue::Text(s) => write!(f, "{s}"),
+        }
+    }
+}
+
+// Kinds offered when adding a field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KindChoice {
+    Text,
+    Number,
+    Boolean,
+    Choice,
+}
+
+impl KindChoice {
+    pub const ALL: [KindChoice; 4] =
+        [KindChoice::Text, KindChoice::Number, KindChoice::Boolean, KindChoice::Choice];
+}
+
+impl fmt::Display for KindChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            KindChoice::Text => "text",
+            KindChoice::Number => "number",
+            KindChoice::Boolean => "yes/no",
+            KindChoice::Choice => "choice",
+        })
+    }
+}
+
+// Definition from the add-field form; options are comma-separated
+pub fn define(label: &str, kind: KindChoice, options: &str) -> Result<FieldDef, String> {
+    let label = label.trim();
+    let key: String = label
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    if key.trim_matches('_').is_empty() {
+        return Err("Name the field first.".into());
+    }
+    let kind = match kind {
+        KindChoice::Text => FieldKind::Text,
+        KindChoice::Number => FieldKind::Number,
+        KindChoice::Boolean => FieldKind::Boolean,
+        KindChoice::Choice => {
+            let options: Vec<String> = options
+                .split(',')
+                .map(|o| o.trim().to_string())
+                .filter(|o| !o.is_empty())
+                .collect();
+            if options.is_empty() {
+                return Err("A choice field needs comma-separated options.".into());
+            }
+            FieldKind::Choice { options }
+        }
+    };
+    Ok(FieldDef { key, label: label.into(), kind })
+}
+
+// Typed values from the form's raw inputs; blank inputs are left out
+pub fn parse(
+    defs: &[FieldDef],
+    raw: &BTreeMap<String, String>,
+) -> Result<BTreeMap<String, FieldValue>, String> {
+    let mut out = BTreeMap::new();
+    for def in defs {
+        let Some(input) = raw.get(&def.key).map(|s| s.trim()).filter(|s| !s.is_empty()) else {
+            continue;
+        };
+        let value = match &def.kind {
+            FieldKind::Text => FieldValue::Text(input.into()),
+            FieldKind::Number => FieldValue::Number(
+                input.parse().map_err(|_| format!("{} must be a number.", def.label))?,
+            ),
+            FieldKind::Boolean => FieldValue::Bool(input == "true"),
+            FieldKind::Choice { options } => {
+                if !options.iter().any(|o| o == input) {
+                    return Err(format!("{} must be one of: {}.", def.label, options.join(", ")));
+                }
+                FieldValue::Text(input.into())
+            }
+        };
+        out.insert(def.key.clone(), value);
+    }
+    Ok(out)
+}
+
+// Label for a stored key, falling back to the key for retired fields
+pub fn label<'a>(defs: &'a [FieldDef], key: &'a str) -> &'a str {
+    defs.iter().find(|d| d.key == key).map_or(key, |d| d.label.as_str())
+}
+
+// Capture form: which inputs the pending-spike panel shows, in what order, what they fall
+// back to when left blank and whether they must end up filled. Fields the
+// layout doesn't mention yet (new custom fields) are shown at the end.
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Slot {
+    Striker,
+    Message,
+    // Comma-separated, into the event's witnesses
+    Witnesses,
+    Custom(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormEntry {
+    pub slot: Slot,
+    #[serde(default = "shown")]
+    pub shown: bool,
+    #[serde(default)]
+    pub required: bool,
+    // Used when the input is blank or hidden
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub default: String,
+}
+
+fn shown() -> bool {
+    true
+}
+
+#[derive(Debug, Clone)]
+pub enum FormEdit {
+    Up,
+    Down,
+    Shown(bool),
+    Required(bool),
+    Default(String),
+}
+
+// What the form produced, before custom values are typed
+pub struct Filled {
+    pub striker: String,
+    pub message: String,
+    pub witnesses: Vec<String>,
+    pub custom: BTreeMap<String, String>,
+}
+
+impl FormEntry {
+    fn new(slot: Slot) -> Self {
+        // The ledger always needs a striker
+        let required = slot == Slot::Striker;
+        Self { slot, shown: true, required, default: String::new() }
+    }
+}
+
+// The stored layout with retired custom fields dropped and missing ones added
+pub fn layout(form: &[FormEntry], defs: &[FieldDef]) -> Vec<FormEntry> {
+    let exists = |slot: &Slot| match slot {
+        Slot::Custom(key) => defs.iter().any(|d| &d.key == key),
+        _ => true,
+    };
+    let mut out: Vec<FormEntry> = form.iter().filter(|e| exists(&e.slot)).cloned().collect();
+    let slots = [Slot::Striker, Slot::Message, Slot::Witnesses]
+        .into_iter()
+        .chain(defs.iter().map(|d| Slot::Custom(d.key.clone())));
+    for slot in slots {
+        if !out.iter().any(|e| e.slot == slot) {
+            out.push(FormEntry::new(slot));
+        }
+    }
+    out
+}
+
+// Apply one edit from the form builder
+pub fn edit(layout: &mut [FormEntry], i: usize, how: FormEdit) {
+    if i >= layout.len() {
+        return;
+    }
+    match how {
+        FormEdit::Up if i > 0 => layout.swap(i, i - 1),
+        FormEdit::Down if i + 1 < layout.len() => layout.swap(i, i + 1),
+        FormEdit::Up | FormEdit::Down => {}
+        FormEdit::Shown(v) => layout[i].shown = v,
+        FormEdit::Required(v) => layout[i].required = v || layout[i].slot == Slot::Striker,
+        FormEdit::Default(v) => layout[i].default = v,
+    }
+}
+
+pub fn slot_label<'a>(defs: &'a [FieldDef], slot: &'a Slot) -> &'a str {
+    match slot {
+        Slot::Striker => "Striker",
+        Slot::Message => "Message",
+        Slot::Witnesses => "Witnesses",
+        Slot::Custom(key) => label(defs, key),
+    }
+}
+
+// Raw inputs run through the layout: hidden or blank inputs take their
+// default, and required ones must then have a value
+pub fn fill(
+    layout: &[FormEntry],
+    defs: &[FieldDef],
+    striker: &str,
+    message: &str,
+    witnesses: &str,
+    custom: &BTreeMap<String, String>,
+) -> Result<Filled, String> {
+    let mut out = Filled {
+        striker: String::new(),
+        message: String::new(),
+        witnesses: vec![],
+        custom: BTreeMap::new(),
+    };
+    for e in layout {
+        let raw = match (&e.slot, e.shown) {
+            (_, false) => "",
+            (Slot::Striker, _) => striker,
+            (Slot::Message, _) => message,
+            (Slot::Witnesses, _) => witnesses,
+            (Slot::Custom(key), _) => custom.get(key).map_or("", String::as_str),
+        };
+        let value = if raw.trim().is_empty() { e.default.trim() } else { raw.trim() };
+        if e.required && value.is_empty() {
+            return Err(format!("Please enter a {} before confirming.", slot_label(defs, &e.slot)));
+        }
+        match &e.slot {
+            Slot::Striker => out.striker = value.into(),
+            Slot::Message => out.message = value.into(),
+            Slot::Witnesses => {
+                out.witnesses = value
+                    .split(',')
+                    .map(|w| w.trim().to_string())
+                    .filter(|w| !w.is_empty())
+                    .collect();
+            }
+            Slot::Custom(key) => {
+                out.custom.insert(key.clone(), value.into());
+            }
+        }
+    }
+    Ok(out)
+}
+
+// The pending-spike inputs, laid out as configured
+pub fn form<'a>(
+    layout: &[FormEntry],
+    defs: &'a [FieldDef],
+    striker: &'a str,
+    message: &'a str,
+    witnesses: &'a str,
+    raw: &'a BTreeMap<String, String>,
+) -> Element<'a, Message> {
+    let mut col = column![].spacing(8);
+    for e in layout.iter().filter(|e| e.shown) {
+        let hint = |fallback: &str| {
+            if e.default.is_empty() {
+                fallback.to_string()
+            } else {
+                format!("default: {}", e.default)
+            }
+        };
+        let (label, input): (String, Element<'a, Message>) = match &e.slot {
+            Slot::Striker => (
+                "Striker".into(),
+                text_input(&hint("who is adding the spike"), striker)
+                    .on_input(Message::StrikerChanged)
+                    .padding(6)
+                    .width(Length::Fill)
+                    .into(),
+            ),
+            Slot::Message => (
+                "Message".into(),
+                text_input(&hint("context / reason"), message)
+                    .on_input(Message::SpikeMessageChanged)
+                    .padding(6)
+                    .width(Length::Fill)
+                    .into(),
+            ),
+            Slot::Witnesses => (
+                "Witnesses".into(),
+                text_input(&hint("names, comma-separated"), witnesses)
+                    .on_input(Message::WitnessesChanged)
+                    .padding(6)
+                    .width(Length::Fill)
+                    .into(),
+            ),
+            Slot::Custom(key) => {
+                let Some(def) = defs.iter().find(|d| &d.key == key) else { continue };
+                (def.label.clone(), custom_input(def, raw, &hint(&def.label), &e.default))
+            }
+        };
+        let marker = if e.required { " *" } else { "" };
+        col = col.push(
+            row![text(format!("{label}{marker}:")), input]
+                .spacing(8)
+                .align_y(alignment::Vertical::Center),
+        );
+    }
+    col.into()
+}
+
+fn custom_input<'a>(
+    def: &'a FieldDef,
+    raw: &BTreeMap<String, String>,
+    placeholder: &str,
+    default: &str,
+) -> Element<'a, Message> {
+    let value = raw.get(&def.key).cloned().unwrap_or_default();
+    let key = def.key.clone();
+    match &def.kind {
+        FieldKind::Text | FieldKind::Number => text_input(placeholder, &value)
+            .on_input(move |v| Message::CustomFieldChanged(key.clone(), v))
+            .padding(6)
+            .width(Length::Fill)
+            .into(),
+        // Pre-set to the default until touched
+        FieldKind::Boolean => toggler(raw.get(&def.key).map_or(default, String::as_str) == "true")
+            .on_toggle(move |v| Message::CustomFieldChanged(key.clone(), v.to_string()))
+            .into(),
+        FieldKind::Choice { options } => {
+            let value = raw.get(&def.key).map_or(default, String::as_str);
+            let selected = options.iter().find(|o| *o == value).cloned();
+            pick_list(options.clone(), selected, move |v| Message::CustomFieldChanged(key.clone(), v))
+                .placeholder("—")
+                .into()
+        }
+    }
+}
+
+// Form builder: order, visibility, required-ness and defaults per input
+pub fn builder<'a>(layout: &[FormEntry], defs: &'a [FieldDef]) -> Element<'a, Message> {
+    let mut list = column![text("Capture form").size(16)].spacing(4);
+    let last = layout.len().saturating_sub(1);
+    for (i, e) in layout.iter().enumerate() {
+        let edit = move |how| Message::FormEdited(i, how);
+        let mut up = button("↑");
+        if i > 0 {
+            up = up.on_press(edit(FormEdit::Up));
+        }
+        let mut down = button("↓");
+        if i < last {
+            down = down.on_press(edit(FormEdit::Down));
+        }
+        let mut required = checkbox("required", e.required);
+        if e.slot != Slot::Striker {
+            required = required.on_toggle(move |v| edit(FormEdit::Required(v)));
+        }
+        list = list.push(
+            row![
+                up,
+                down,
+                text(slot_label(defs, &e.slot).to_string()).width(Length::Fixed(120.0)),
+                checkbox("shown", e.shown).on_toggle(move |v| edit(FormEdit::Shown(v))),
+                required,
+                text_input("default", &e.default)
+                    .on_input(move |v| edit(FormEdit::Default(v)))
+                    .padding(4)
+                    .width(Length::Fill),
+            ]
+            .spacing(8)
+            .align_y(alignment::Vertical::Center),
+        );
+    }
+    list.into()
+}
+
+// Workspace-level definitions: list with delete, plus the add form
+pub fn editor<'a>(
+    defs: &'a [FieldDef],
+    label_input: &'a str,
+    kind: KindChoice,
+    options_input: &'a str,
+) -> Element<'a, Message> {
+    let mut list = column![].spacing(4);
+    for (i, def) in defs.iter().enumerate() {
+        let kind = match &def.kind {
+            FieldKind::Text => "text".to_string(),
+            FieldKind::Number => "number".to_string(),
+            FieldKind::Boolean => "yes/no".to_string(),
+            FieldKind::Choice { options } => format!("choice of {}", options.join(", ")),
+        };
+        list = list.push(
+            row![
+                text(format!("{} ({kind})", def.label)).width(Length::Fill),
+                button("Delete").on_press(Message::RemoveField(i)),
+            ]
+            .spacing(8)
+            .align_y(alignment::Vertical::Center),
+        );
+    }
+    let mut form = row![
+        text_input("field name", label_input)
+            .on_input(Message::FieldLabelChanged)
+            .on_submit(Message::AddField)
+            .padding(6),
+        pick_list(KindChoice::ALL, Some(kind), Message::FieldKindChanged),
+    ]
+    .spacing(8);
+    if kind == KindChoice::Choice {
+        form = form.push(
+            text_input("options, comma-separated", options_input)
+                .on_input(Message::FieldOptionsChanged)
+                .padding(6),
+        );
+    }
+    column![
+        text("Custom fields").size(16),
+        list,
+        form.push(button("Add field").on_press(Message::AddField)),
+    ]
+    .spacing(6)
+    .into()
+}
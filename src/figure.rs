@@ -0,0 +1,113 @@
+// -------------------- Base figure --------------------
+// The figure under the pins is either an SVG (coordinates in its viewBox)
+// or a photograph (coordinates in image pixels). Either way the on-screen
+// width is fixed and the height follows the figure's aspect ratio.
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::{FIGURE_H, FIGURE_W, SCREEN_W};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BaseKind {
+    Svg,
+    Raster,
+}
+
+pub fn kind(path: &str) -> BaseKind {
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    match ext.as_deref() {
+        Some("png" | "jpg" | "jpeg") => BaseKind::Raster,
+        _ => BaseKind::Svg,
+    }
+}
+
+// Pixel size of a PNG/JPEG, read from its header
+pub fn raster_dims(path: &str) -> Option<(f32, f32)> {
+    let size = imagesize::size(path).ok()?;
+    (size.width > 0 && size.height > 0).then_some((size.width as f32, size.height as f32))
+}
+
+// Last modification time, None if the file can't be read
+pub fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+// Figure-space size when nothing better is known
+pub fn default_dims() -> (f32, f32) {
+    (FIGURE_W, FIGURE_H)
+}
+
+// On-screen size for a figure of `dims`
+pub fn screen_size((w, h): (f32, f32)) -> (f32, f32) {
+    (SCREEN_W, SCREEN_W * (h / w))
+}
+
+// A path the renderer can open from anywhere, for a photo referenced from
+// a generated SVG
+pub fn href(path: &str) -> String {
+    std::path::absolute(path).map_or_else(|_| path.to_string(), |p| p.to_string_lossy().into_owned())
+}
+
+// How the figure file is laid under the ledger: mirrored left to right
+// first, then turned clockwise in quarter turns. Pins and regions are in
+// the oriented space, so turning the file to match an existing ledger
+// leaves the ledger as it is; a quarter turn swaps the figure's width and
+// height.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Orientation {
+    #[serde(default)]
+    pub quarter_turns: u8,
+    #[serde(default)]
+    pub mirrored: bool,
+}
+
+impl Orientation {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn turned(self, clockwise: bool) -> Self {
+        let step = if clockwise { 1 } else { 3 };
+        Self { quarter_turns: (self.quarter_turns + step) % 4, ..self }
+    }
+
+    pub fn flipped(self) -> Self {
+        Self { mirrored: !self.mirrored, ..self }
+    }
+
+    // Oriented size of a file measuring `(w, h)`, and back
+    pub fn dims(&self, (w, h): (f32, f32)) -> (f32, f32) {
+        if self.quarter_turns % 2 == 1 { (h, w) } else { (w, h) }
+    }
+
+    pub fn source_dims(&self, dims: (f32, f32)) -> (f32, f32) {
+        self.dims(dims)
+    }
+
+    // SVG transform taking content drawn over the file's 0..w, 0..h to
+    // the oriented space
+    pub fn transform(&self, (w, h): (f32, f32)) -> String {
+        let turn = match self.quarter_turns % 4 {
+            1 => format!("matrix(0 1 -1 0 {h} 0)"),
+            2 => format!("matrix(-1 0 0 -1 {w} {h})"),
+            3 => format!("matrix(0 -1 1 0 0 {w})"),
+            _ => String::new(),
+        };
+        let mirror = if self.mirrored { format!("matrix(-1 0 0 1 {w} 0)") } else { String::new() };
+        format!("{turn} {mirror}").trim().to_string()
+    }
+}
+
+impl std::fmt::Display for Orientation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}°", u32::from(self.quarter_turns % 4) * 90)?;
+        if self.mirrored {
+            f.write_str(", mirrored")?;
+        }
+        Ok(())
+    }
+}
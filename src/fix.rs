@@ -0,0 +1,840 @@
+// -------------------- FIX protocol core --------------------
+// The wire format alone, with no sockets, threads or ledger: framing and
+// reading of tag=value messages and the writing of ours. A Decoder is fed
+// bytes as they arrive and hands back whole messages, typed by MsgType
+// (35); a frame whose BodyLength (9) or CheckSum (10) doesn't hold is
+// handed back as garbled, and junk between messages is skipped. encode
+// writes a message with its standard header, BodyLength and CheckSum.
+// The acceptor's session layer (session.rs), drop copy and self-test
+// build on it. Initiator, at the end, is a whole connecting session over
+// TCP that reconnects by itself. None of it depends on anything else in
+// the crate, so the fixclient and nkisi-sim binaries share it (#[path])
+// rather than framing messages their own way.
+use chrono::Utc;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const SOH: u8 = 0x01;
+
+// Every BeginString starts like this (FIX.4.x, FIXT.1.1); a bare "8=" can
+// be the tail of another tag such as 58=
+const BEGIN: &[u8] = b"8=FIX";
+
+// Parsed tag=value pairs, in order
+pub type Fields = Vec<(i32, String)>;
+
+// Fields that can be read; a broken one is skipped
+pub fn fields(raw: &[u8]) -> Fields {
+    raw.split(|b| *b == SOH)
+        .filter_map(|f| {
+            let eq = f.iter().position(|b| *b == b'=')?;
+            let tag = std::str::from_utf8(&f[..eq]).ok()?.parse().ok()?;
+            let val = String::from_utf8_lossy(&f[eq + 1..]).into_owned();
+            Some((tag, val))
+        })
+        .collect()
+}
+
+// Every field, or a refusal naming the first that can't be read
+pub fn parse(raw: &[u8]) -> Result<Fields, Refusal> {
+    let mut fields = vec![];
+    for field in raw.split(|b| *b == SOH).filter(|f| !f.is_empty()) {
+        let Some(eq) = field.iter().position(|b| *b == b'=') else { continue };
+        let name = String::from_utf8_lossy(&field[..eq]);
+        let key = name.parse::<i32>().map_err(|_| {
+            Refusal::new(RejectReason::InvalidTag, None, format!("\"{name}\" is not a tag number"))
+        })?;
+        let val = std::str::from_utf8(&field[eq + 1..])
+            .map_err(|_| Refusal::new(RejectReason::IncorrectDataFormat, Some(key as u32), "value is not UTF-8"))?;
+        fields.push((key, val.to_string()));
+    }
+    Ok(fields)
+}
+
+pub fn tag(fields: &Fields, t: i32) -> Option<&str> {
+    fields.iter().find(|(k, _)| *k == t).map(|(_, v)| v.as_str())
+}
+
+// Sum of the bytes, as CheckSum (10) carries it
+pub fn checksum(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0u32, |acc, &b| acc + b as u32) % 256
+}
+
+// SessionRejectReason (373) values we use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    InvalidTag = 0,
+    RequiredTagMissing = 1,
+    ValueIncorrect = 5,
+    IncorrectDataFormat = 6,
+    IncorrectNumInGroupCount = 16,
+    UnsupportedApplVer = 18,
+    // Not a session reason: answered with a BusinessMessageReject (j)
+    UnsupportedMsgType,
+}
+
+// Why an application message could not be taken
+#[derive(Debug)]
+pub struct Refusal {
+    pub reason: RejectReason,
+    // RefTagID (371)
+    pub tag: Option<u32>,
+    pub text: String,
+}
+
+impl Refusal {
+    pub fn new(reason: RejectReason, tag: Option<u32>, text: impl Into<String>) -> Self {
+        Self { reason, tag, text: text.into() }
+    }
+}
+
+impl std::fmt::Display for Refusal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.tag {
+            Some(tag) => write!(f, "tag {tag}: {}", self.text),
+            None => f.write_str(&self.text),
+        }
+    }
+}
+
+// MsgType (35): the session messages by name, anything else as it came
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind<'a> {
+    Heartbeat,
+    TestRequest,
+    ResendRequest,
+    Reject,
+    SequenceReset,
+    Logout,
+    Logon,
+    Application(&'a str),
+}
+
+impl<'a> Kind<'a> {
+    pub fn of(msg_type: &'a str) -> Self {
+        match msg_type {
+            "0" => Kind::Heartbeat,
+            "1" => Kind::TestRequest,
+            "2" => Kind::ResendRequest,
+            "3" => Kind::Reject,
+            "4" => Kind::SequenceReset,
+            "5" => Kind::Logout,
+            "A" => Kind::Logon,
+            other => Kind::Application(other),
+        }
+    }
+}
+
+// One whole message as it came, and its fields
+#[derive(Debug, Clone, PartialEq)]
+pub struct Msg {
+    pub raw: Vec<u8>,
+    pub fields: Fields,
+}
+
+impl Msg {
+    pub fn new(raw: Vec<u8>) -> Self {
+        let fields = fields(&raw);
+        Self { raw, fields }
+    }
+
+    pub fn get(&self, t: i32) -> Option<&str> {
+        tag(&self.fields, t)
+    }
+
+    pub fn msg_type(&self) -> &str {
+        self.get(35).unwrap_or("")
+    }
+
+    pub fn kind(&self) -> Kind<'_> {
+        Kind::of(self.msg_type())
+    }
+
+    // MsgSeqNum (34), 0 when missing
+    pub fn seq(&self) -> u64 {
+        self.get(34).and_then(|v| v.parse().ok()).unwrap_or(0)
+    }
+
+    // PossDupFlag (43)
+    pub fn poss_dup(&self) -> bool {
+        self.get(43) == Some("Y")
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Decoded {
+    Message(Msg),
+    // Bad BodyLength or CheckSum; the bytes up to where the next message
+    // might begin, of which only the first is dropped
+    Garbled { why: &'static str, raw: Vec<u8> },
+}
+
+// The same, still in the decoder's buffer: no copy is made, and the slice
+// lasts until the decoder is next used
+#[derive(Debug, PartialEq)]
+pub enum Raw<'a> {
+    Message(&'a [u8]),
+    Garbled { why: &'static str, raw: &'a [u8] },
+}
+
+enum Frame {
+    // Not worth framing again until there are this many bytes; where to
+    // resume the search for the trailer
+    Incomplete(usize, usize),
+    // Bytes before the next BeginString, thrown away quietly
+    Junk(usize),
+    Garbled(&'static str),
+    // No trailer within MAX_BODY (lenient): this many bytes are dropped
+    // as garbled, so the buffer doesn't grow waiting for one
+    Overflow(usize),
+    // Length of the whole message
+    Message(usize),
+}
+
+// Longest BeginString and BodyLength fields; past that the header is garbled
+// rather than waited for
+const MAX_BEGIN: usize = 16;
+const MAX_BODY_LENGTH: usize = 12;
+// Largest body waited for; a BodyLength past it is garbled rather than
+// buffered for, as is a lenient frame with no trailer by then
+const MAX_BODY: usize = 1 << 20;
+
+// Bytes in, messages out. Strict framing goes by BodyLength and checks
+// the CheckSum; lenient framing (hand-typed test feeds) runs to the SOH
+// after "10=", whatever the length and checksum say.
+// Each byte is looked at a bounded number of times however the stream is
+// cut up: taken messages are only skipped over, and dropped from the
+// buffer once they make up half of it; a part message isn't framed again
+// until the bytes it needs are there (strict), and the search for the
+// trailer resumes where it stopped (lenient). The buffer is a Vec with an
+// offset rather than a ring, so a message is always one slice to hand out;
+// the `throughput` test (ignored, run with --release -- --ignored) checks
+// it against a sustained feed.
+#[derive(Debug, Default)]
+pub struct Decoder {
+    buf: Vec<u8>,
+    // Bytes before this are taken
+    start: usize,
+    // Bytes past `start` needed before framing again, and those already
+    // searched for the trailer
+    need: usize,
+    scanned: usize,
+    lenient: bool,
+}
+
+impl Decoder {
+    pub fn new(lenient: bool) -> Self {
+        Self { lenient, ..Self::default() }
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) {
+        if self.start > self.buf.len() / 2 {
+            self.buf.drain(..self.start);
+            self.start = 0;
+        }
+        self.buf.extend_from_slice(bytes);
+    }
+
+    fn take(&mut self, n: usize) -> usize {
+        let at = self.start;
+        self.start += n;
+        (self.need, self.scanned) = (0, 0);
+        at
+    }
+
+    // The next message, or None until more bytes are fed
+    pub fn next(&mut self) -> Option<Decoded> {
+        Some(match self.next_raw()? {
+            Raw::Message(raw) => Decoded::Message(Msg::new(raw.to_vec())),
+            Raw::Garbled { why, raw } => Decoded::Garbled { why, raw: raw.to_vec() },
+        })
+    }
+
+    // As `next`, without copying the message out
+    pub fn next_raw(&mut self) -> Option<Raw<'_>> {
+        loop {
+            let pending = &self.buf[self.start..];
+            if pending.len() < self.need {
+                return None;
+            }
+            let framed = if self.lenient { frame_lenient(pending, self.scanned) } else { frame_strict(pending) };
+            match framed {
+                Frame::Incomplete(need, scanned) => {
+                    (self.need, self.scanned) = (need, scanned);
+                    return None;
+                }
+                Frame::Junk(skip) => {
+                    self.take(skip);
+                }
+                Frame::Garbled(why) => {
+                    let next = pending[1..].windows(BEGIN.len()).position(|w| w == BEGIN);
+                    let end = next.map_or(pending.len(), |at| at + 1);
+                    let at = self.take(1);
+                    return Some(Raw::Garbled { why, raw: &self.buf[at..at + end] });
+                }
+                Frame::Overflow(len) => {
+                    let at = self.take(len);
+                    let why = "no CheckSum (10) within the largest message taken";
+                    return Some(Raw::Garbled { why, raw: &self.buf[at..at + len] });
+                }
+                Frame::Message(len) => {
+                    let at = self.take(len);
+                    return Some(Raw::Message(&self.buf[at..at + len]));
+                }
+            }
+        }
+    }
+}
+
+// A message framed by BodyLength and verified by CheckSum
+fn frame_strict(buf: &[u8]) -> Frame {
+    let Some(start) = buf.windows(BEGIN.len()).position(|w| w == BEGIN) else {
+        // Keep a tail that may be the start of the next message
+        let keep = (1..BEGIN.len()).rev().find(|&k| buf.ends_with(&BEGIN[..k])).unwrap_or(0);
+        return match buf.len() - keep {
+            0 => Frame::Incomplete(buf.len() + 1, 0),
+            n => Frame::Junk(n),
+        };
+    };
+    if start > 0 {
+        return Frame::Junk(start);
+    }
+    let Some(begin_end) = buf.iter().take(MAX_BEGIN).position(|b| *b == SOH) else {
+        if buf.len() >= MAX_BEGIN {
+            return Frame::Garbled("BeginString (8) too long");
+        }
+        return Frame::Incomplete(buf.len() + 1, 0);
+    };
+    let rest = &buf[begin_end + 1..];
+    let Some(len_end) = rest.iter().take(MAX_BODY_LENGTH).position(|b| *b == SOH) else {
+        if rest.len() >= MAX_BODY_LENGTH {
+            return Frame::Garbled("BodyLength (9) missing or not a number");
+        }
+        return Frame::Incomplete(buf.len() + 1, 0);
+    };
+    let Some(body_len) = rest[..len_end]
+        .strip_prefix(b"9=")
+        .and_then(|v| std::str::from_utf8(v).ok())
+        .and_then(|v| v.parse::<usize>().ok())
+    else {
+        return Frame::Garbled("BodyLength (9) missing or not a number");
+    };
+    if body_len > MAX_BODY {
+        return Frame::Garbled("BodyLength (9) larger than any message taken");
+    }
+    let body_start = begin_end + 1 + len_end + 1;
+    let trailer = body_start + body_len;
+    // "10=" + three digits + SOH
+    let total = trailer + 7;
+    if buf.len() < total {
+        return Frame::Incomplete(total, 0);
+    }
+    let found = &buf[trailer..total];
+    if !found.starts_with(b"10=") || found[6] != SOH {
+        return Frame::Garbled("no CheckSum (10) where BodyLength (9) says the body ends");
+    }
+    if found[3..6] != *format!("{:03}", checksum(&buf[..trailer])).as_bytes() {
+        return Frame::Garbled("CheckSum (10) does not match");
+    }
+    Frame::Message(total)
+}
+
+// Up to the SOH after "10=", whatever the length and checksum say; "10="
+// counts after an SOH only, not as the end of a tag such as 6010. The
+// first `from` bytes are known not to hold it.
+fn frame_lenient(buf: &[u8], from: usize) -> Frame {
+    const TRAILER: &[u8] = b"\x0110=";
+    let Some(at) = buf[from..].windows(TRAILER.len()).position(|w| w == TRAILER).map(|at| from + at) else {
+        // Part of it may end what was searched
+        let scanned = buf.len().saturating_sub(TRAILER.len() - 1);
+        if buf.len() > MAX_BODY {
+            return Frame::Overflow(scanned);
+        }
+        return Frame::Incomplete(buf.len() + 1, scanned);
+    };
+    let at = at + 1;
+    match buf[at..].iter().position(|b| *b == SOH) {
+        Some(soh) => Frame::Message(at + soh + 1),
+        None if buf.len() > MAX_BODY => Frame::Overflow(buf.len()),
+        None => Frame::Incomplete(buf.len() + 1, at - 1),
+    }
+}
+
+// BeginString and CompIDs of an outgoing message
+pub struct Header<'a> {
+    pub begin: &'a str,
+    pub sender: &'a str,
+    pub target: &'a str,
+}
+
+// A complete message: standard header, `fields` in order, BodyLength and
+// CheckSum filled in
+pub fn encode(header: &Header, msg_type: &str, seq: u64, fields: &[(u32, String)]) -> Vec<u8> {
+    let mut body: Vec<u8> = vec![];
+    let mut push = |tag: u32, value: &str| {
+        let _ = write!(body, "{tag}={value}");
+        body.push(SOH);
+    };
+    push(35, msg_type);
+    push(49, header.sender);
+    push(56, header.target);
+    push(34, &seq.to_string());
+    push(52, &Utc::now().format("%Y%m%d-%H:%M:%S%.3f").to_string());
+    for (tag, value) in fields {
+        push(*tag, value);
+    }
+    let mut out = format!("8={}\u{1}9={}\u{1}", header.begin, body.len()).into_bytes();
+    out.extend_from_slice(&body);
+    let sum = checksum(&out);
+    out.extend_from_slice(format!("10={sum:03}\u{1}").as_bytes());
+    out
+}
+
+// The connecting side of a session, for the app's upstream forwarding and
+// fixclient alike. It connects (through `via` when set), logs on from
+// MsgSeqNum 1, answers TestRequests and ResendRequests and sends
+// Heartbeats. When the connection drops, the Logon goes unanswered or the
+// counterparty falls silent it connects again, waiting `retry` at first
+// and twice as long after each failure up to `max_retry`. The caller
+// drives it with `poll` and gets the rest of the traffic as events.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub begin: String,
+    pub sender: String,
+    pub target: String,
+    pub heartbeat: Duration,
+    // Logon fields besides EncryptMethod and HeartBtInt: 141=Y, credentials
+    pub logon: Vec<(u32, String)>,
+    pub logon_timeout: Duration,
+    pub retry: Duration,
+    pub max_retry: Duration,
+}
+
+impl Settings {
+    pub fn new(sender: &str, target: &str) -> Self {
+        Self {
+            begin: "FIX.4.4".into(),
+            sender: sender.into(),
+            target: target.into(),
+            heartbeat: Duration::from_secs(30),
+            logon: vec![],
+            logon_timeout: Duration::from_secs(10),
+            retry: Duration::from_secs(5),
+            max_retry: Duration::from_secs(300),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Event {
+    LoggedOn,
+    // Application messages and rejects; session traffic is handled
+    Message(Msg),
+    Garbled(&'static str),
+    // The connection is gone, or didn't come; the next try is `retry` away
+    Down { why: String, retry: Duration },
+}
+
+type Connect = Box<dyn FnMut(&str) -> io::Result<TcpStream> + Send>;
+type Tap = Box<dyn FnMut(bool, &[u8]) + Send>;
+
+struct Link {
+    stream: TcpStream,
+    decoder: Decoder,
+    out_seq: u64,
+    logged_on: bool,
+    opened: Instant,
+    last_in: Instant,
+    last_out: Instant,
+}
+
+pub struct Initiator {
+    pub addr: String,
+    settings: Settings,
+    connect: Connect,
+    tap: Option<Tap>,
+    link: Option<Link>,
+    failures: u32,
+    next_try: Instant,
+    // Why a send just lost the connection, for the next poll to tell
+    lost: Option<String>,
+}
+
+impl Initiator {
+    pub fn new(addr: &str, settings: Settings) -> Self {
+        Self {
+            addr: addr.to_string(),
+            settings,
+            connect: Box::new(|addr| TcpStream::connect(addr)),
+            tap: None,
+            link: None,
+            failures: 0,
+            next_try: Instant::now(),
+            lost: None,
+        }
+    }
+
+    // Connect some other way than directly, through a proxy say
+    pub fn via(mut self, connect: impl FnMut(&str) -> io::Result<TcpStream> + Send + 'static) -> Self {
+        self.connect = Box::new(connect);
+        self
+    }
+
+    // Sees every message read (true) or written, as on the wire
+    pub fn tap(mut self, tap: impl FnMut(bool, &[u8]) + Send + 'static) -> Self {
+        self.tap = Some(Box::new(tap));
+        self
+    }
+
+    pub fn logged_on(&self) -> bool {
+        self.link.as_ref().is_some_and(|l| l.logged_on)
+    }
+
+    // Waits up to `wait` for something to happen: connects when it is time
+    // to, keeps the session alive and reads what the counterparty sent
+    pub fn poll(&mut self, wait: Duration) -> Option<Event> {
+        if let Some(why) = self.lost.take() {
+            return Some(self.failed(why));
+        }
+        if self.link.is_none() {
+            let left = self.next_try.saturating_duration_since(Instant::now());
+            if left > wait {
+                thread::sleep(wait);
+                return None;
+            }
+            thread::sleep(left);
+            return self.open().err().map(|why| self.failed(why));
+        }
+        if let Some(event) = self.take() {
+            return Some(event);
+        }
+        if let Err(why) = self.keep_alive().and_then(|()| self.read(wait)) {
+            return Some(self.failed(why));
+        }
+        self.take()
+    }
+
+    // An application message; its MsgSeqNum, which answers refer to
+    pub fn send(&mut self, msg_type: &str, fields: &[(u32, String)]) -> io::Result<u64> {
+        if !self.logged_on() {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, "not logged on"));
+        }
+        self.write(msg_type, fields)
+    }
+
+    // Says goodbye, if there is anyone to say it to
+    pub fn logout(&mut self) {
+        if self.logged_on() {
+            let _ = self.write("5", &[]);
+        }
+        self.link = None;
+    }
+
+    fn open(&mut self) -> Result<(), String> {
+        let stream = (self.connect)(&self.addr).map_err(|e| e.to_string())?;
+        let now = Instant::now();
+        let decoder = Decoder::new(false);
+        let link = Link { stream, decoder, out_seq: 0, logged_on: false, opened: now, last_in: now, last_out: now };
+        self.link = Some(link);
+        let mut logon = vec![(98, "0".to_string()), (108, self.settings.heartbeat.as_secs().to_string())];
+        logon.extend(self.settings.logon.iter().cloned());
+        self.write("A", &logon).map(|_| ()).map_err(|e| format!("Logon not sent: {e}"))
+    }
+
+    fn failed(&mut self, why: String) -> Event {
+        self.link = None;
+        let retry = self.settings.retry.saturating_mul(1 << self.failures.min(16)).min(self.settings.max_retry);
+        self.failures += 1;
+        self.next_try = Instant::now() + retry;
+        Event::Down { why, retry }
+    }
+
+    fn keep_alive(&mut self) -> Result<(), String> {
+        let Some(link) = &self.link else { return Ok(()) };
+        let heartbeat = self.settings.heartbeat;
+        if !link.logged_on {
+            if link.opened.elapsed() > self.settings.logon_timeout {
+                return Err("no answer to Logon".into());
+            }
+            return Ok(());
+        }
+        if link.last_in.elapsed() > heartbeat * 2 {
+            return Err("counterparty silent".into());
+        }
+        if link.last_out.elapsed() >= heartbeat {
+            self.write("0", &[]).map_err(|e| format!("write failed: {e}"))?;
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, wait: Duration) -> Result<(), String> {
+        let Some(link) = &mut self.link else { return Ok(()) };
+        let mut buf = [0u8; 8192];
+        link.stream.set_read_timeout(Some(wait.max(Duration::from_millis(1)))).map_err(|e| e.to_string())?;
+        match link.stream.read(&mut buf) {
+            Ok(0) => Err("connection closed".into()),
+            Ok(n) => {
+                link.decoder.feed(&buf[..n]);
+                link.last_in = Instant::now();
+                Ok(())
+            }
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => Ok(()),
+            Err(e) => Err(format!("read error: {e}")),
+        }
+    }
+
+    // The next message read that is for the caller; session traffic is
+    // answered on the way
+    fn take(&mut self) -> Option<Event> {
+        loop {
+            let link = self.link.as_mut()?;
+            let decoded = link.decoder.next()?;
+            if let Some(tap) = &mut self.tap {
+                let (Decoded::Message(Msg { raw, .. }) | Decoded::Garbled { raw, .. }) = &decoded;
+                tap(true, raw);
+            }
+            let msg = match decoded {
+                Decoded::Message(msg) => msg,
+                Decoded::Garbled { why, .. } => return Some(Event::Garbled(why)),
+            };
+            let answered = match msg.kind() {
+                Kind::Logon => {
+                    link.logged_on = true;
+                    self.failures = 0;
+                    return Some(Event::LoggedOn);
+                }
+                Kind::Heartbeat => Ok(0),
+                Kind::TestRequest => self.write("0", &[(112, msg.get(112).unwrap_or("").to_string())]),
+                // No store of what was sent: skip the counterparty past it
+                Kind::ResendRequest => {
+                    let next = (link.out_seq + 2).to_string();
+                    self.write("4", &[(123, "N".into()), (36, next)])
+                }
+                Kind::Logout => {
+                    let _ = self.write("5", &[]);
+                    return Some(self.failed(format!("logged out: {}", msg.get(58).unwrap_or(""))));
+                }
+                _ => return Some(Event::Message(msg)),
+            };
+            if let Err(e) = answered {
+                return Some(self.failed(format!("write failed: {e}")));
+            }
+        }
+    }
+
+    // Any message; a failed write ends the connection
+    fn write(&mut self, msg_type: &str, fields: &[(u32, String)]) -> io::Result<u64> {
+        let s = &self.settings;
+        let header = Header { begin: &s.begin, sender: &s.sender, target: &s.target };
+        let Some(link) = &mut self.link else {
+            return Err(io::Error::new(io::ErrorKind::NotConnected, "not connected"));
+        };
+        link.out_seq += 1;
+        link.last_out = Instant::now();
+        let msg = encode(&header, msg_type, link.out_seq, fields);
+        match link.stream.write_all(&msg) {
+            Ok(()) => {
+                if let Some(tap) = &mut self.tap {
+                    tap(false, &msg);
+                }
+                Ok(link.out_seq)
+            }
+            Err(e) => {
+                self.link = None;
+                self.lost = Some(format!("write failed: {e}"));
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: Header = Header { begin: "FIX.4.4", sender: "CLIENT", target: "NKISI" };
+
+    fn spike(seq: u64) -> Vec<u8> {
+        encode(&HEADER, "D", seq, &[(11, format!("order-{seq}"))])
+    }
+
+    fn decode_all(decoder: &mut Decoder) -> Vec<Decoded> {
+        std::iter::from_fn(|| decoder.next()).collect()
+    }
+
+    // `raw` with the CheckSum worked out again after `edit`
+    fn resummed(raw: &[u8], edit: impl FnOnce(&mut Vec<u8>)) -> Vec<u8> {
+        let mut body = raw[..raw.len() - 7].to_vec();
+        edit(&mut body);
+        let sum = checksum(&body);
+        body.extend_from_slice(format!("10={sum:03}\u{1}").as_bytes());
+        body
+    }
+
+    #[test]
+    fn encoded_messages_decode_as_sent() {
+        // BodyLength frames it, not a BeginString inside a value
+        let raw = encode(&HEADER, "D", 7, &[(11, "order-7".into()), (58, "pin at 8=FIX".into())]);
+        let mut decoder = Decoder::new(false);
+        decoder.feed(&raw);
+        let Some(Decoded::Message(msg)) = decoder.next() else { panic!("not decoded") };
+        assert_eq!(msg.raw, raw);
+        assert_eq!(msg.kind(), Kind::Application("D"));
+        assert_eq!(msg.seq(), 7);
+        assert_eq!(msg.get(49), Some("CLIENT"));
+        assert_eq!(msg.get(11), Some("order-7"));
+        assert_eq!(msg.get(58), Some("pin at 8=FIX"));
+        assert!(decoder.next().is_none());
+    }
+
+    #[test]
+    fn frames_split_across_reads_come_out_whole() {
+        let stream: Vec<u8> = (1..=3).flat_map(spike).collect();
+        for cut in [1, 2, 5, 13, 64] {
+            for lenient in [false, true] {
+                let mut decoder = Decoder::new(lenient);
+                let mut seqs = vec![];
+                for chunk in stream.chunks(cut) {
+                    decoder.feed(chunk);
+                    for decoded in decode_all(&mut decoder) {
+                        let Decoded::Message(msg) = decoded else { panic!("garbled at {cut}-byte reads") };
+                        seqs.push(msg.seq());
+                    }
+                }
+                assert_eq!(seqs, [1, 2, 3], "{cut}-byte reads, lenient {lenient}");
+            }
+        }
+    }
+
+    #[test]
+    fn bad_body_length_is_garbled() {
+        let raw = spike(1);
+        let text = String::from_utf8(raw.clone()).unwrap();
+        let len = text.split('\u{1}').nth(1).unwrap().to_string();
+        let short = resummed(&raw, |b| {
+            let at = text.find(&len).unwrap();
+            b.splice(at..at + len.len(), *b"9=20");
+        });
+        let not_a_number = resummed(&raw, |b| {
+            let at = text.find(&len).unwrap();
+            b.splice(at..at + len.len(), *b"9=x");
+        });
+        let cases = [
+            (short, "no CheckSum (10) where BodyLength (9) says the body ends"),
+            (not_a_number, "BodyLength (9) missing or not a number"),
+        ];
+        for (bad, why) in cases {
+            let mut decoder = Decoder::new(false);
+            decoder.feed(&bad);
+            decoder.feed(&spike(2));
+            let decoded = decode_all(&mut decoder);
+            assert_eq!(decoded[0], Decoded::Garbled { why, raw: bad.clone() });
+            // Nothing of the next message is lost
+            assert!(matches!(&decoded[1..], [Decoded::Message(msg)] if msg.seq() == 2), "{why}");
+        }
+    }
+
+    #[test]
+    fn huge_body_length_is_garbled_not_waited_for() {
+        let head = b"8=FIX.4.4\x019=999999999\x0135=D\x01".to_vec();
+        let mut decoder = Decoder::new(false);
+        decoder.feed(&head);
+        let why = "BodyLength (9) larger than any message taken";
+        assert_eq!(decoder.next(), Some(Decoded::Garbled { why, raw: head }));
+        decoder.feed(&spike(2));
+        assert!(matches!(decoder.next(), Some(Decoded::Message(msg)) if msg.seq() == 2));
+
+        // At the limit it is still a message in the making
+        let mut decoder = Decoder::new(false);
+        decoder.feed(format!("8=FIX.4.4\x019={MAX_BODY}\x0135=D\x01").as_bytes());
+        assert!(decoder.next().is_none());
+    }
+
+    #[test]
+    fn lenient_frames_without_a_trailer_are_dropped_past_the_limit() {
+        let mut decoder = Decoder::new(true);
+        decoder.feed(b"8=FIX.4.4\x0135=D\x01");
+        let mut dropped = 0;
+        for _ in 0..=MAX_BODY / 4096 {
+            decoder.feed(&[b'x'; 4096]);
+            while let Some(decoded) = decoder.next() {
+                let Decoded::Garbled { why, raw } = decoded else { panic!("framed without a trailer") };
+                assert_eq!(why, "no CheckSum (10) within the largest message taken");
+                dropped += raw.len();
+            }
+        }
+        assert!(dropped > MAX_BODY);
+        assert!(decoder.buf.len() - decoder.start <= MAX_BODY);
+        // The next message that ends is framed
+        decoder.feed(&spike(2));
+        let decoded = decode_all(&mut decoder);
+        assert!(matches!(&decoded[..], [Decoded::Message(msg)] if msg.get(11) == Some("order-2")));
+    }
+
+    #[test]
+    fn bad_checksum_is_garbled() {
+        let mut bad = spike(1);
+        let at = bad.len() - 2;
+        bad[at] = if bad[at] == b'9' { b'0' } else { bad[at] + 1 };
+        let mut decoder = Decoder::new(false);
+        decoder.feed(&bad);
+        assert_eq!(decoder.next(), Some(Decoded::Garbled { why: "CheckSum (10) does not match", raw: bad }));
+        assert!(decoder.next().is_none());
+    }
+
+    #[test]
+    fn leading_junk_is_skipped() {
+        for junk in [&b"hello"[..], b"8=", b"58=8=FI", b"\x01\x01garbage 8=F"] {
+            let mut decoder = Decoder::new(false);
+            decoder.feed(junk);
+            assert!(decoder.next().is_none());
+            decoder.feed(&spike(1));
+            let decoded = decode_all(&mut decoder);
+            assert!(matches!(&decoded[..], [Decoded::Message(msg)] if msg.seq() == 1), "after {junk:?}");
+        }
+    }
+
+    #[test]
+    fn lenient_framing_ends_at_the_checksum_alone() {
+        // Hand-typed: no BodyLength to go by and a CheckSum that's wrong
+        let typed = b"8=FIX.4.4\x0135=D\x0134=1\x016010=5\x0158=x\x0110=000\x01";
+        let mut decoder = Decoder::new(true);
+        decoder.feed(typed);
+        let Some(Decoded::Message(msg)) = decoder.next() else { panic!("not framed") };
+        // 6010= isn't taken for the trailer
+        assert_eq!(msg.raw, typed);
+        assert_eq!(msg.get(6010), Some("5"));
+        assert_eq!(msg.get(58), Some("x"));
+
+        let mut strict = Decoder::new(false);
+        strict.feed(typed);
+        assert!(matches!(strict.next(), Some(Decoded::Garbled { .. })));
+    }
+    // Far above the 10k msg/s a busy feed sends, at any size of read
+    #[test]
+    #[ignore]
+    fn throughput() {
+        const MESSAGES: u64 = 200_000;
+        let stream: Vec<u8> = (1..=MESSAGES).flat_map(spike).collect();
+        for lenient in [false, true] {
+            for cut in [1, 7, 64, 1500, 65536] {
+                let mut decoder = Decoder::new(lenient);
+                let (mut read, mut bytes) = (0, 0);
+                let started = Instant::now();
+                for chunk in stream.chunks(cut) {
+                    decoder.feed(chunk);
+                    while let Some(raw) = decoder.next_raw() {
+                        let Raw::Message(raw) = raw else { panic!("garbled at {cut}-byte reads") };
+                        (read, bytes) = (read + 1, bytes + raw.len());
+                    }
+                }
+                let rate = read as f64 / started.elapsed().as_secs_f64();
+                println!("lenient {lenient:5} reads of {cut:5} bytes: {rate:>12.0} msg/s");
+                assert_eq!((read, bytes), (MESSAGES, stream.len()));
+                assert!(rate > 10_000.0, "{rate:.0} msg/s");
+            }
+        }
+    }
+}
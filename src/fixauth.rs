@@ -0,0 +1,93 @@
+// -------------------- FIX access control --------------------
+// Who may put spikes on the figure through the acceptor, from a TOML file
+// (--fix-auth). Without one, anything that reaches the port may. Each list
+// that is set narrows it down:
+//   addresses: peer IPs or CIDR ranges allowed to connect; others are
+//              dropped before a byte is read
+//   senders:   counterparties must log on (raw feeds are refused) as one of
+//              these SenderCompIDs, with Username (553) and Password (554)
+//              when the entry has them; a bad Logon is answered with Logout
+//   parties:   strikers (PartyID) spikes may be credited to
+// Every refusal is logged and counted for the status line.
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::fix::{tag, Fields};
+use crate::netacl::{in_range, parse_range};
+use crate::IoError;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FixAuth {
+    addresses: Vec<String>,
+    senders: Vec<Credentials>,
+    parties: Vec<String>,
+    // Connections, logons and spikes turned away since startup
+    #[serde(skip)]
+    pub refused: Arc<AtomicU64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Credentials {
+    comp_id: String,
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+}
+
+impl FixAuth {
+    pub fn load(path: &str) -> Result<Self, IoError> {
+        let text = std::fs::read_to_string(path).map_err(|e| IoError::Read(e.to_string()))?;
+        let auth: Self = toml::from_str(&text).map_err(|e| IoError::Parse(e.to_string()))?;
+        if let Some(bad) = auth.addresses.iter().find(|a| parse_range(a).is_none()) {
+            return Err(IoError::Parse(format!("{bad} is not an IP address or CIDR range")));
+        }
+        Ok(auth)
+    }
+
+    pub fn admits_address(&self, ip: IpAddr) -> bool {
+        self.addresses.is_empty()
+            || self.addresses.iter().filter_map(|a| parse_range(a)).any(|(net, bits)| in_range(ip, net, bits))
+    }
+
+    // Raw feeds can't say who they are
+    pub fn requires_logon(&self) -> bool {
+        !self.senders.is_empty()
+    }
+
+    pub fn check_logon(&self, f: &Fields) -> Result<(), String> {
+        if self.senders.is_empty() {
+            return Ok(());
+        }
+        let comp_id = tag(f, 49).unwrap_or("");
+        let Some(entry) = self.senders.iter().find(|s| s.comp_id == comp_id) else {
+            return Err(format!("SenderCompID {comp_id:?} is not allowed"));
+        };
+        let matches = |expected: &Option<String>, got: Option<&str>| {
+            expected.as_deref().is_none_or(|e| got.is_some_and(|g| same(e.as_bytes(), g.as_bytes())))
+        };
+        if !matches(&entry.username, tag(f, 553)) || !matches(&entry.password, tag(f, 554)) {
+            return Err(format!("bad credentials for {comp_id}"));
+        }
+        Ok(())
+    }
+
+    pub fn admits_party(&self, who: &str) -> bool {
+        self.parties.is_empty() || self.parties.iter().any(|p| p == who)
+    }
+
+    pub fn refuse(&self, peer: &str, why: &str) {
+        self.refused.fetch_add(1, Ordering::Relaxed);
+        eprintln!("[FIX] {peer}: refused: {why}");
+    }
+}
+
+// Compared in full whatever the first difference, so timing doesn't give
+// the password away byte by byte
+pub fn same(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
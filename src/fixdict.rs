@@ -0,0 +1,182 @@
+// -------------------- FIX dictionary --------------------
+// Which tags and message types carry a spike. The built-in layout (35=U1,
+// 6010/6011 for the position, 448 for the striker, ...) can be remapped
+// from a TOML file so a site with its own tag conventions can connect
+// without a rebuild. The acceptor reads spikes with it, acknowledgments
+// and the drop-copy feed are written with it, so two Nkisi instances
+// mirroring each other need the same dictionary. Session-level tags (8, 9,
+// 10, 34, 35, 49, 52, 56) and the parties group (453/452) are fixed.
+// For upstream systems that only send standard messages, a
+// [new_order_single] table has NewOrderSingles (35=D) with the spike's
+// Symbol read as spikes: Price (44) and OrderQty (38) give the position,
+// Account (1) the striker, unless the table names other tags.
+use serde::Deserialize;
+
+use crate::{IoError, Outcome};
+
+// Header and trailer tags a spike field must not take over
+const RESERVED: [u32; 8] = [8, 9, 10, 34, 35, 49, 52, 56];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FixDictionary {
+    // MsgType (35) of a spike and of its acknowledgment
+    pub spike_type: String,
+    pub ack_type: String,
+    // MsgType of an outcome update: an event id and its new outcome
+    pub update_type: String,
+    // MsgType of a cancel: the event id of a spike its sender takes back
+    pub cancel_type: String,
+    // Required value of Symbol (55)
+    pub symbol: String,
+    pub who: u32,
+    pub x: u32,
+    pub y: u32,
+    pub note: u32,
+    pub timestamp: u32,
+    pub purpose: u32,
+    pub outcome: u32,
+    // NoSpikes: count of a group carrying several spikes in one message
+    pub spikes: u32,
+    // Ledger event id, so mirrored events aren't duplicated
+    pub event_id: u32,
+    // Sender's own key for a spike: redelivered under the same key, it is
+    // recorded once
+    pub idempotency_key: u32,
+    // Off unless the file has the table
+    pub new_order_single: Option<OrderMapping>,
+}
+
+// Tags of a NewOrderSingle that carry the spike
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OrderMapping {
+    pub x: u32,
+    pub y: u32,
+    pub who: u32,
+}
+
+impl Default for OrderMapping {
+    fn default() -> Self {
+        Self { x: 44, y: 38, who: 1 }
+    }
+}
+
+impl Default for FixDictionary {
+    fn default() -> Self {
+        Self {
+            spike_type: "U1".into(),
+            ack_type: "U2".into(),
+            update_type: "U3".into(),
+            cancel_type: "U4".into(),
+            symbol: "NKISI".into(),
+            who: 448,
+            x: 6010,
+            y: 6011,
+            note: 58,
+            timestamp: 60,
+            purpose: 6012,
+            outcome: 6013,
+            spikes: 6009,
+            event_id: 9000,
+            idempotency_key: 9001,
+            new_order_single: None,
+        }
+    }
+}
+
+impl FixDictionary {
+    pub fn load(path: &str) -> Result<Self, IoError> {
+        let text = std::fs::read_to_string(path).map_err(|e| IoError::Read(e.to_string()))?;
+        let dict: Self = toml::from_str(&text).map_err(|e| IoError::Parse(e.to_string()))?;
+        dict.check().map_err(IoError::Parse)?;
+        Ok(dict)
+    }
+
+    fn tags(&self) -> [(&'static str, u32); 10] {
+        [
+            ("who", self.who),
+            ("x", self.x),
+            ("y", self.y),
+            ("note", self.note),
+            ("timestamp", self.timestamp),
+            ("purpose", self.purpose),
+            ("outcome", self.outcome),
+            ("spikes", self.spikes),
+            ("event_id", self.event_id),
+            ("idempotency_key", self.idempotency_key),
+        ]
+    }
+
+    // Every field on its own tag, clear of the session layer
+    fn check(&self) -> Result<(), String> {
+        let types = [&self.spike_type, &self.ack_type, &self.update_type, &self.cancel_type];
+        if types.iter().any(|t| t.trim().is_empty()) {
+            return Err("spike_type, ack_type, update_type and cancel_type must not be empty".into());
+        }
+        if types.iter().enumerate().any(|(i, t)| types[..i].contains(t)) {
+            return Err("spike_type, ack_type, update_type and cancel_type must differ".into());
+        }
+        let tags = self.tags();
+        for (i, (name, tag)) in tags.iter().enumerate() {
+            if *tag == 0 || RESERVED.contains(tag) {
+                return Err(format!("{name} = {tag} is not available for spike data"));
+            }
+            if let Some((other, _)) = tags[..i].iter().find(|(_, t)| t == tag) {
+                return Err(format!("{other} and {name} both use tag {tag}"));
+            }
+        }
+        if let Some(order) = &self.new_order_single {
+            let tags = [("x", order.x), ("y", order.y), ("who", order.who)];
+            for (i, (name, tag)) in tags.iter().enumerate() {
+                if *tag == 0 || RESERVED.contains(tag) {
+                    return Err(format!("new_order_single.{name} = {tag} is not available for spike data"));
+                }
+                if let Some((other, _)) = tags[..i].iter().find(|(_, t)| t == tag) {
+                    return Err(format!("new_order_single.{other} and {name} both use tag {tag}"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // A NewOrderSingle rewritten as the spike it stands for, or None when
+    // orders aren't mapped or this is another message. The order's own
+    // parties give way to the mapped striker.
+    pub fn as_spike(&self, fields: &[(i32, String)]) -> Option<Vec<(i32, String)>> {
+        let order = self.new_order_single.as_ref()?;
+        if !fields.iter().any(|(t, v)| *t == 35 && v == "D") {
+            return None;
+        }
+        let renamed = [(order.x, self.x), (order.y, self.y), (order.who, self.who)].map(|(a, b)| (a as i32, b as i32));
+        let taken = [self.x, self.y, self.who, 453].map(|t| t as i32);
+        let spike = fields
+            .iter()
+            .filter_map(|(t, v)| match renamed.iter().find(|(from, _)| from == t) {
+                Some((_, to)) => Some((*to, v.clone())),
+                None if *t == 35 => Some((35, self.spike_type.clone())),
+                None if taken.contains(t) => None,
+                None => Some((*t, v.clone())),
+            })
+            .collect();
+        Some(spike)
+    }
+}
+
+// Outcome as written to the wire, and back: the name or 0/1/2
+pub fn outcome_code(outcome: &Outcome) -> &'static str {
+    match outcome {
+        Outcome::Pending => "pending",
+        Outcome::Resolved => "resolved",
+        Outcome::Failed => "failed",
+    }
+}
+
+pub fn parse_outcome(value: &str) -> Option<Outcome> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "pending" | "0" => Some(Outcome::Pending),
+        "resolved" | "1" => Some(Outcome::Resolved),
+        "failed" | "2" => Some(Outcome::Failed),
+        _ => None,
+    }
+}
@@ -0,0 +1,147 @@
+// -------------------- FIXML --------------------
+// FIX in its XML encoding, on the same listeners as tag=value: a connection
+// whose first byte (after whitespace) is '<' is read as a stream of
+// <FIXML> documents. Each message in one, also inside a <Batch>, is
+// rewritten as the tag=value message it stands for and goes through the
+// same parser, rules and store, so the rest of ingest never sees XML.
+// FIXML has no Logon here: such a connection is a raw feed.
+//
+//   <FIXML>
+//     <Spike ClOrdID="c1" X="42.5" Y="60" Txt="note" Outcome="pending">
+//       <Hdr SID="MIDDLEWARE"/>
+//       <Instrmt Sym="NKISI"/>
+//       <Pty ID="Nsimba" R="12"/>
+//       <Spk X="10" Y="20" Who="Mbemba"/>  (several spikes: one Spk each)
+//     </Spike>
+//   </FIXML>
+//
+// <Spike> is the dictionary's spike message; its X, Y, Who, Txt, TxnTm,
+// Purp, Outcome and EvntID land on the dictionary's tags, as do those of
+// <SpikeUpd>, the outcome update (EvntID and Outcome), and <SpikeCxl>, the
+// cancel (EvntID). <Order> is a
+// NewOrderSingle (35=D), read as a spike when the dictionary maps orders.
+// The standard abbreviations below carry their usual tags.
+use roxmltree::{Document, Node};
+
+use crate::fixdict::FixDictionary;
+
+// A message: MsgType (35), SenderCompID (49) and the body in order
+pub type Message = (String, Option<String>, Vec<(u32, String)>);
+
+const END: &[u8] = b"</FIXML>";
+
+// Standard attribute abbreviations of the message element and its
+// components, and their tags
+const ABBREVIATIONS: [(&str, u32); 9] = [
+    ("ClOrdID", 11),
+    ("Acct", 1),
+    ("Side", 54),
+    ("Px", 44),
+    ("OrdTyp", 40),
+    ("Sym", 55),
+    ("SecTyp", 167),
+    ("CFI", 461),
+    ("Qty", 38),
+];
+
+// Length of the first whole document in `buf`, if one has arrived
+pub fn frame(buf: &[u8]) -> Option<usize> {
+    buf.windows(END.len()).position(|w| w == END).map(|at| at + END.len())
+}
+
+pub fn messages(doc: &[u8], dict: &FixDictionary) -> Result<Vec<Message>, String> {
+    let text = std::str::from_utf8(doc).map_err(|_| "not UTF-8".to_string())?.trim_start();
+    let doc = Document::parse(text).map_err(|e| e.to_string())?;
+    let root = doc.root_element();
+    if root.tag_name().name() != "FIXML" {
+        return Err(format!("<{}> where <FIXML> was expected", root.tag_name().name()));
+    }
+    let mut out = vec![];
+    for node in root.children().filter(Node::is_element) {
+        if node.tag_name().name() == "Batch" {
+            for msg in node.children().filter(Node::is_element) {
+                out.push(message(msg, dict)?);
+            }
+        } else {
+            out.push(message(node, dict)?);
+        }
+    }
+    if out.is_empty() {
+        return Err("no message in the document".into());
+    }
+    Ok(out)
+}
+
+fn message(node: Node, dict: &FixDictionary) -> Result<Message, String> {
+    let name = node.tag_name().name();
+    let (msg_type, spike) = match name {
+        "Spike" => (dict.spike_type.clone(), true),
+        "SpikeUpd" => (dict.update_type.clone(), true),
+        "SpikeCxl" => (dict.cancel_type.clone(), true),
+        "Order" => ("D".to_string(), false),
+        other => return Err(format!("<{other}> is not a message we take")),
+    };
+    // Spike attributes on the dictionary's tags; for an order only the
+    // standard ones mean anything
+    let spike_tags = [
+        ("X", dict.x),
+        ("Y", dict.y),
+        ("Who", dict.who),
+        ("Txt", dict.note),
+        ("TxnTm", dict.timestamp),
+        ("Purp", dict.purpose),
+        ("Outcome", dict.outcome),
+        ("EvntID", dict.event_id),
+    ];
+    let standard = [("Txt", 58), ("TxnTm", 60)];
+    let own: &[(&str, u32)] = if spike { &spike_tags } else { &standard };
+    let lookup = |attr: &str| own.iter().chain(&ABBREVIATIONS).find(|(a, _)| *a == attr).map(|(_, t)| *t);
+    let mut body = vec![];
+    let mut sender = None;
+    let put = |n: Node, body: &mut Vec<(u32, String)>| {
+        for a in n.attributes() {
+            if let Some(t) = lookup(a.name()) {
+                body.push((t, a.value().to_string()));
+            }
+        }
+    };
+    put(node, &mut body);
+    let children: Vec<Node> = node.children().filter(Node::is_element).collect();
+    for child in &children {
+        match child.tag_name().name() {
+            "Hdr" => sender = child.attribute("SID").map(str::to_string),
+            "Instrmt" | "OrdQty" => put(*child, &mut body),
+            "Pty" | "Spk" => {}
+            other => return Err(format!("<{other}> inside <{name}> is not understood")),
+        }
+    }
+    // Repeating groups: the count, then each entry with its first tag first
+    let parties: Vec<&Node> = children.iter().filter(|c| c.tag_name().name() == "Pty").collect();
+    if !parties.is_empty() {
+        body.push((453, parties.len().to_string()));
+        for p in parties {
+            let id = p.attribute("ID").ok_or("<Pty> without ID")?;
+            body.push((dict.who, id.to_string()));
+            if let Some(src) = p.attribute("Src") {
+                body.push((447, src.to_string()));
+            }
+            if let Some(role) = p.attribute("R") {
+                body.push((452, role.to_string()));
+            }
+        }
+    }
+    let entries: Vec<&Node> = children.iter().filter(|c| c.tag_name().name() == "Spk").collect();
+    if spike && !entries.is_empty() {
+        body.push((dict.spikes, entries.len().to_string()));
+        for e in entries {
+            let x = e.attribute("X").ok_or("<Spk> without X")?;
+            body.push((dict.x, x.to_string()));
+            for (attr, tag) in &spike_tags[1..] {
+                if let Some(v) = e.attribute(*attr) {
+                    body.push((*tag, v.to_string()));
+                }
+            }
+        }
+    }
+    Ok((msg_type, sender, body))
+}
@@ -0,0 +1,75 @@
+// -------------------- FIX queue --------------------
+// Spikes the acceptors have read wait here for the ledger, one message (one
+// or more spikes) per entry. The window doesn't poll for them: a
+// subscription sleeps on the queue and hands over everything waiting the
+// moment something arrives. While the ledger takes no changes (read-only,
+// or a sandbox open) the subscription is off and spikes wait here, bounded,
+// so a full queue still slows senders down and then answers "ledger busy".
+// Only this hand-over is async. The acceptors (acceptor.rs) still block, a
+// thread per connection up to --fix-max-connections; a tokio listener
+// needs tokio's net feature, which this tree doesn't build with.
+use iced::futures::stream::{self, Stream};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::Mutex;
+
+use crate::ExternalSpike;
+
+// How often a sender held up by a full queue looks again
+const RETRY: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone)]
+pub struct Sender(mpsc::Sender<Vec<ExternalSpike>>);
+
+// Shared, so the subscription can be dropped and started again without
+// losing what is waiting
+#[derive(Debug, Clone)]
+pub struct Queue(Arc<Mutex<mpsc::Receiver<Vec<ExternalSpike>>>>);
+
+pub fn bounded(size: usize) -> (Sender, Queue) {
+    let (tx, rx) = mpsc::channel(size);
+    (Sender(tx), Queue(Arc::new(Mutex::new(rx))))
+}
+
+impl Sender {
+    // Wait up to `wait` for room; the spikes come back if there was none
+    // (or nobody reads the queue any more)
+    pub fn send_timeout(&self, spikes: Vec<ExternalSpike>, wait: Duration) -> Result<(), Vec<ExternalSpike>> {
+        let deadline = Instant::now() + wait;
+        let mut spikes = spikes;
+        loop {
+            match self.0.try_send(spikes) {
+                Ok(()) => return Ok(()),
+                Err(TrySendError::Full(back)) if Instant::now() < deadline => {
+                    spikes = back;
+                    thread::sleep(RETRY);
+                }
+                Err(TrySendError::Full(back) | TrySendError::Closed(back)) => return Err(back),
+            }
+        }
+    }
+}
+
+impl Queue {
+    // Messages waiting; 0 while the subscription is taking them
+    pub fn waiting(&self) -> usize {
+        self.0.try_lock().map_or(0, |rx| rx.len())
+    }
+
+    // Everything waiting, as soon as there is something
+    pub fn arrivals(&self) -> impl Stream<Item = Vec<Vec<ExternalSpike>>> {
+        stream::unfold(Arc::clone(&self.0), |rx| async move {
+            let batches = {
+                let mut queue = rx.lock().await;
+                let mut batches = vec![queue.recv().await?];
+                while let Ok(more) = queue.try_recv() {
+                    batches.push(more);
+                }
+                batches
+            };
+            Some((batches, rx))
+        })
+    }
+}
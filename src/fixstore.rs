@@ -0,0 +1,221 @@
+// -------------------- FIX message store --------------------
+// Every message an acceptor session reads or writes, appended as it goes
+// to <store>/<YYYY-MM-DD>/<counterparty>.log (the SenderCompID once logged
+// on, the peer address for raw feeds; days in UTC). Each record is one
+// line: time, IN or OUT, the byte length and the raw message, SOHs and
+// all, so nothing in a message can break the framing. Replaying a day
+// reads its inbound spikes back through the same parser and ledger rules
+// as live ones. Spikes without a ledger event id get one derived from the
+// message (see `spike_id`), also when read live with the store on, so a
+// replay only adds what the ledger lacks; a raw feed's messages without
+// MsgSeqNum or SendingTime can't be told apart and are added again.
+// A day can also be exported as standard FIX message logs (the QuickFIX
+// "messages.log" layout, one per counterparty) for the counterparty's own
+// support tooling. The upstream session (initiator.rs) is recorded too.
+use chrono::{DateTime, Utc};
+use sha1::{Digest, Sha1};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::fix::{self, fields};
+use crate::IoError;
+
+// Header and trailer tags that change when a message is sent again
+const RESEND_TAGS: [i32; 6] = [9, 10, 43, 52, 97, 122];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    In,
+    Out,
+}
+
+#[derive(Debug)]
+pub struct Record {
+    pub at: DateTime<Utc>,
+    pub direction: Direction,
+    pub raw: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct Store {
+    dir: PathBuf,
+}
+
+impl Store {
+    pub fn new(dir: &str) -> Self {
+        Self { dir: PathBuf::from(dir) }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // Failures are logged: losing the copy must not stop the session
+    pub fn record(&self, counterparty: &str, direction: Direction, raw: &[u8]) {
+        let now = Utc::now();
+        let day = self.dir.join(now.format("%Y-%m-%d").to_string());
+        let path = day.join(format!("{}.log", file_name(counterparty)));
+        let tag = match direction {
+            Direction::In => "IN",
+            Direction::Out => "OUT",
+        };
+        let written = std::fs::create_dir_all(&day).and_then(|()| {
+            let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+            let mut line = format!("{} {tag} {} ", now.format("%Y-%m-%dT%H:%M:%S%.6fZ"), raw.len()).into_bytes();
+            line.extend_from_slice(raw);
+            line.push(b'\n');
+            file.write_all(&line)
+        });
+        if let Err(e) = written {
+            eprintln!("[FIX] store {}: {e}", path.display());
+        }
+    }
+
+    // Recorded days, oldest first
+    pub fn days(&self) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else { return vec![] };
+        let mut days: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_dir())
+            .filter_map(|e| e.file_name().to_str().map(str::to_string))
+            .filter(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").is_ok())
+            .collect();
+        days.sort();
+        days
+    }
+
+    // The message last sent to `counterparty` under each MsgSeqNum over the
+    // latest `days` days, so one sent again after a sequence reset wins
+    pub fn sent(&self, counterparty: &str, days: usize) -> BTreeMap<u64, Vec<u8>> {
+        let mut sent = BTreeMap::new();
+        let recorded = self.days();
+        for day in &recorded[recorded.len().saturating_sub(days)..] {
+            let path = self.dir.join(day).join(format!("{}.log", file_name(counterparty)));
+            let Ok(bytes) = std::fs::read(&path) else { continue };
+            let records = match parse(&bytes) {
+                Ok(records) => records,
+                Err(e) => {
+                    eprintln!("[FIX] store {}: {e}", path.display());
+                    continue;
+                }
+            };
+            for record in records.into_iter().filter(|r| r.direction == Direction::Out) {
+                let seq = fix::tag(&fields(&record.raw), 34).and_then(|v| v.parse().ok());
+                if let Some(seq) = seq {
+                    sent.insert(seq, record.raw);
+                }
+            }
+        }
+        sent
+    }
+
+    // Every record of `day`, file by file (counterparties in name order),
+    // each file in the order written
+    pub fn read_day(&self, day: &str) -> Result<Vec<(String, Record)>, IoError> {
+        let dir = self.dir.join(day);
+        let entries = std::fs::read_dir(&dir).map_err(|e| IoError::Read(format!("{}: {e}", dir.display())))?;
+        let mut files: Vec<PathBuf> =
+            entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.extension().is_some_and(|x| x == "log")).collect();
+        files.sort();
+        let mut out = vec![];
+        for path in files {
+            let bytes = std::fs::read(&path).map_err(|e| IoError::Read(format!("{}: {e}", path.display())))?;
+            let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("").to_string();
+            let records = parse(&bytes).map_err(|e| IoError::Parse(format!("{}: {e}", path.display())))?;
+            out.extend(records.into_iter().map(|r| (name.clone(), r)));
+        }
+        Ok(out)
+    }
+
+    // `day` as one <counterparty>.messages.log per counterparty in `to`;
+    // the files written
+    pub fn export_log(&self, day: &str, to: &Path) -> Result<Vec<PathBuf>, IoError> {
+        let records = self.read_day(day)?;
+        std::fs::create_dir_all(to).map_err(|e| IoError::Write(format!("{}: {e}", to.display())))?;
+        let mut written: Vec<PathBuf> = vec![];
+        for chunk in records.chunk_by(|(a, _), (b, _)| a == b) {
+            let path = to.join(format!("{}.messages.log", chunk[0].0));
+            let log: Vec<u8> = chunk.iter().flat_map(|(_, r)| log_line(r)).collect();
+            std::fs::write(&path, log).map_err(|e| IoError::Write(format!("{}: {e}", path.display())))?;
+            written.push(path);
+        }
+        Ok(written)
+    }
+}
+
+// "20240131-09:30:00.123 : 8=FIX.4.4^A9=…^A10=…^A", as FIX engines log
+// messages; in and out alike, the CompIDs tell them apart
+fn log_line(record: &Record) -> Vec<u8> {
+    let mut line = format!("{} : ", record.at.format("%Y%m%d-%H:%M:%S%.3f")).into_bytes();
+    line.extend_from_slice(&record.raw);
+    line.push(b'\n');
+    line
+}
+
+fn parse(mut bytes: &[u8]) -> Result<Vec<Record>, String> {
+    let mut out = vec![];
+    while !bytes.is_empty() {
+        // "<time> <IN|OUT> <len> " then len bytes and a newline
+        let mut head = bytes.splitn(4, |b| *b == b' ');
+        let (Some(at), Some(dir), Some(len), Some(rest)) = (head.next(), head.next(), head.next(), head.next()) else {
+            return Err(format!("truncated record after {} good ones", out.len()));
+        };
+        let text = |b: &[u8]| String::from_utf8_lossy(b).into_owned();
+        let at = DateTime::parse_from_rfc3339(&text(at)).map_err(|e| format!("record {}: {e}", out.len() + 1))?;
+        let direction = match dir {
+            b"IN" => Direction::In,
+            b"OUT" => Direction::Out,
+            other => return Err(format!("record {}: direction {:?}", out.len() + 1, text(other))),
+        };
+        let len: usize = text(len).parse().map_err(|_| format!("record {}: bad length", out.len() + 1))?;
+        if rest.len() < len + 1 || rest[len] != b'\n' {
+            // A record cut short by a crash ends the file
+            eprintln!("[FIX] store: last record incomplete; ignored");
+            break;
+        }
+        out.push(Record { at: at.with_timezone(&Utc), direction, raw: rest[..len].to_vec() });
+        bytes = &rest[len + 1..];
+    }
+    Ok(out)
+}
+
+// "10.0.0.5:51234" -> "10.0.0.5_51234"
+fn file_name(counterparty: &str) -> String {
+    let name: String =
+        counterparty.chars().map(|c| if c.is_ascii_alphanumeric() || "-_.".contains(c) { c } else { '_' }).collect();
+    if name.is_empty() || name.starts_with('.') { format!("_{name}") } else { name }
+}
+
+// Id of the `n`th spike in a message that carries none. A sender's
+// idempotency key (`key_tag`) makes it the same for every delivery under
+// that key, whatever else changed. Without one it takes a MsgSeqNum (34)
+// and the time the message was first sent, OrigSendingTime (122) on a
+// resend and SendingTime (52) otherwise, so a resend gets the ids of the
+// original while two spikes sent alike, even under the same number after a
+// sequence reset, don't. A message with neither gets no id of its own.
+pub fn spike_id(raw: &[u8], n: usize, key_tag: u32) -> Option<Uuid> {
+    let all = fields(raw);
+    let tag = |t: i32| fix::tag(&all, t);
+    let mut hash = Sha1::new();
+    match tag(key_tag as i32) {
+        Some(key) => {
+            let sender = tag(49).unwrap_or("");
+            hash.update(format!("key\x01{sender}\x01{key}\x01").as_bytes());
+        }
+        None => {
+            tag(34)?;
+            let sent = tag(122).or_else(|| tag(52))?;
+            for (t, v) in all.iter().filter(|(t, _)| !RESEND_TAGS.contains(t)) {
+                hash.update(format!("{t}={v}\x01").as_bytes());
+            }
+            hash.update(format!("sent\x01{sent}\x01").as_bytes());
+        }
+    }
+    hash.update((n as u64).to_le_bytes());
+    let digest = hash.finalize();
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    Some(uuid::Builder::from_sha1_bytes(bytes).into_uuid())
+}
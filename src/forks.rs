@@ -0,0 +1,155 @@
+// -------------------- Forked figures --------------------
+// Two figures of a workspace whose ledger files are the same figure (the
+// same ledger id) under different paths: most often a ledger copied for
+// fieldwork and added back as a figure of its own, each copy then struck
+// and edited apart. Left alone they become two records of one object.
+// They are looked for when a workspace is opened, a figure added or a copy
+// merged, and listed with what each has that the other lacks. The operator
+// merges one into the other (as Merge does: both sides' edits kept, those
+// edited on both going to Conflicts), keeps one as it is, or leaves them;
+// the copy not kept leaves the workspace, its file stays where it is.
+use iced::widget::{button, column, row, text};
+use iced::{Color, Element, Length};
+use std::collections::HashMap;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::workspace::FigureRef;
+use crate::{confirm, crdt, ActivationEvent, IoError, Message, NkisiNkondi};
+
+#[derive(Debug, Clone)]
+pub struct Fork {
+    pub id: Uuid,
+    // Figures by position in the workspace, the one listed first first
+    pub a: usize,
+    pub b: usize,
+    // Live events one has and the other lacks
+    pub only_a: usize,
+    pub only_b: usize,
+    // Events in both, edited on one side since they parted, or differently
+    // on each
+    pub edited: usize,
+    pub conflicting: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Choice {
+    // The other figure merged into this one, then dropped
+    MergeInto(usize),
+    // This figure kept as it is, the other dropped
+    Keep(usize),
+    Ignore,
+}
+
+impl Fork {
+    pub fn other(&self, i: usize) -> usize {
+        if i == self.a {
+            self.b
+        } else {
+            self.a
+        }
+    }
+
+    pub fn describe(&self, (a, b): (&str, &str)) -> String {
+        let mut apart = vec![];
+        if self.only_a > 0 {
+            apart.push(format!("{a} has {} {b} lacks", confirm::count(self.only_a, "event")));
+        }
+        if self.only_b > 0 {
+            apart.push(format!("{b} has {} {a} lacks", confirm::count(self.only_b, "event")));
+        }
+        if self.edited > 0 {
+            apart.push(format!("{} edited since they parted", confirm::count(self.edited, "event")));
+        }
+        if self.conflicting > 0 {
+            apart.push(format!("{} differently on each side", self.conflicting));
+        }
+        let id = &self.id.to_string()[..8];
+        match apart.is_empty() {
+            true => format!("{a} and {b} are identical copies of one figure ({id})."),
+            false => format!("{a} and {b} are copies of one figure ({id}) that have diverged: {}.", apart.join(", ")),
+        }
+    }
+}
+
+fn by_id(n: &NkisiNkondi) -> HashMap<Uuid, &ActivationEvent> {
+    n.events.iter().map(|e| (e.id, e)).collect()
+}
+
+fn compare(id: Uuid, (a, left): (usize, &NkisiNkondi), (b, right): (usize, &NkisiNkondi)) -> Fork {
+    let (l, r) = (by_id(left), by_id(right));
+    Fork {
+        id,
+        a,
+        b,
+        only_a: l.keys().filter(|id| !r.contains_key(id)).count(),
+        only_b: r.keys().filter(|id| !l.contains_key(id)).count(),
+        edited: l.iter().filter(|(id, e)| r.get(id).is_some_and(|o| o.revised != e.revised)).count(),
+        conflicting: crdt::merge(left, right).conflicts.len(),
+    }
+}
+
+// Every pair of figures sharing a ledger id; `open` is the figure open
+// now, compared as it is in memory. Ledgers not written yet are skipped,
+// those that can't be read are returned.
+pub fn find(
+    figures: &[FigureRef],
+    open: (usize, &NkisiNkondi),
+    read: impl Fn(&str) -> Result<NkisiNkondi, IoError>,
+) -> (Vec<Fork>, Vec<String>) {
+    let mut ledgers: Vec<(usize, NkisiNkondi)> = vec![];
+    let mut unreadable = vec![];
+    for (i, fig) in figures.iter().enumerate() {
+        if i == open.0 || !Path::new(&fig.ledger).exists() {
+            continue;
+        }
+        match read(&fig.ledger) {
+            Ok(n) => ledgers.push((i, n)),
+            Err(e) => unreadable.push(format!("{}: {e}", fig.ledger)),
+        }
+    }
+    let mut all: Vec<(usize, &NkisiNkondi)> = ledgers.iter().map(|(i, n)| (*i, n)).collect();
+    if open.0 < figures.len() {
+        all.push(open);
+        all.sort_by_key(|(i, _)| *i);
+    }
+    let mut forks = vec![];
+    for (k, &(a, left)) in all.iter().enumerate() {
+        for &(b, right) in &all[k + 1..] {
+            if left.id == right.id && figures[a].ledger != figures[b].ledger {
+                forks.push(compare(left.id, (a, left), (b, right)));
+            }
+        }
+    }
+    (forks, unreadable)
+}
+
+pub fn view<'a>(forks: &'a [Fork], figures: &'a [FigureRef]) -> Element<'a, Message> {
+    let dim = Color::from_rgb(0.7, 0.7, 0.8);
+    let mut col = column![].spacing(6);
+    if forks.is_empty() {
+        return col.into();
+    }
+    col = col.push(text(format!("Forked figures ({})", forks.len())).size(16));
+    for (i, fork) in forks.iter().enumerate() {
+        let (Some(a), Some(b)) = (figures.get(fork.a), figures.get(fork.b)) else { continue };
+        let pick = |label: String, choice| button(text(label)).on_press(Message::ResolveFork(i, choice));
+        col = col.push(text(fork.describe((&a.name, &b.name))).size(13)).push(
+            row![
+                pick(format!("Merge {} into {}", b.name, a.name), Choice::MergeInto(fork.a)),
+                pick(format!("Merge {} into {}", a.name, b.name), Choice::MergeInto(fork.b)),
+                pick(format!("Keep {}", a.name), Choice::Keep(fork.a)).style(button::secondary),
+                pick(format!("Keep {}", b.name), Choice::Keep(fork.b)).style(button::secondary),
+                pick("Leave both".into(), Choice::Ignore).style(button::text),
+            ]
+            .spacing(8),
+        );
+    }
+    col.push(
+        text("The copy not kept leaves the workspace; its ledger file stays on disk.")
+            .size(12)
+            .color(dim)
+            .width(Length::Fill),
+    )
+    .into()
+}
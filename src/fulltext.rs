@@ -0,0 +1,144 @@
+// -------------------- Full-text index --------------------
+// In-memory tantivy index over striker names, notes and metadata of the
+// open ledger. It is updated alongside every event change so searches never scan the
+// whole ledger. Adds and removes are only staged; `flush` commits them, once
+// per message the app handles, so a burst of FIX spikes or a replay costs one
+// commit and reader reload rather than one per event.
+use tantivy::collector::TopDocs;
+use tantivy::query::QueryParser;
+use tantivy::schema::{Field, Schema, Value, STORED, STRING, TEXT};
+use tantivy::{doc, Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, TantivyError, Term};
+use uuid::Uuid;
+
+use crate::ActivationEvent;
+
+// Smallest writer heap tantivy accepts
+const WRITER_HEAP: usize = 15_000_000;
+pub const MAX_HITS: usize = 1000;
+
+pub struct NoteIndex {
+    index: Index,
+    reader: IndexReader,
+    writer: IndexWriter,
+    id: Field,
+    text: Field,
+    // Staged changes not yet visible to searches
+    dirty: bool,
+}
+
+impl NoteIndex {
+    pub fn new() -> Result<Self, TantivyError> {
+        let mut schema = Schema::builder();
+        let id = schema.add_text_field("id", STRING | STORED);
+        let text = schema.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema.build());
+        let writer = index.writer_with_num_threads(1, WRITER_HEAP)?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        Ok(Self { index, reader, writer, id, text, dirty: false })
+    }
+
+    pub fn add<'a>(
+        &mut self,
+        events: impl IntoIterator<Item = &'a ActivationEvent>,
+    ) -> Result<(), TantivyError> {
+        for ev in events {
+            self.writer.add_document(doc!(
+                self.id => ev.id.to_string(),
+                self.text => ev.search_text(),
+            ))?;
+            self.dirty = true;
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, ids: impl IntoIterator<Item = Uuid>) -> Result<(), TantivyError> {
+        for id in ids {
+            self.writer.delete_term(Term::from_field_text(self.id, &id.to_string()));
+            self.dirty = true;
+        }
+        Ok(())
+    }
+
+    // Drop everything and index `events` from scratch (load, clear, archive),
+    // searchable at once
+    pub fn rebuild(&mut self, events: &[ActivationEvent]) -> Result<(), TantivyError> {
+        self.writer.delete_all_documents()?;
+        self.add(events)?;
+        self.flush()
+    }
+
+    // Ids of matching events, best match first
+    pub fn search(&self, query: &str) -> Result<Vec<Uuid>, TantivyError> {
+        let parser = QueryParser::for_index(&self.index, vec![self.text]);
+        let (query, _errors) = parser.parse_query_lenient(query);
+        let searcher = self.reader.searcher();
+        let top = searcher.search(&query, &TopDocs::with_limit(MAX_HITS))?;
+
+        let mut ids = Vec::with_capacity(top.len());
+        for (_score, addr) in top {
+            let doc: TantivyDocument = searcher.doc(addr)?;
+            if let Some(id) = doc
+                .get_first(self.id)
+                .and_then(|v| v.as_str())
+                .and_then(|s| Uuid::parse_str(s).ok())
+            {
+                ids.push(id);
+            }
+        }
+        Ok(ids)
+    }
+
+    // Make what was staged since the last flush searchable
+    pub fn flush(&mut self) -> Result<(), TantivyError> {
+        if !self.dirty {
+            return Ok(());
+        }
+        self.writer.commit()?;
+        self.dirty = false;
+        self.reader.reload()
+    }
+}
+
+// Index failures don't block ledger edits; they only degrade search
+pub fn log(result: Result<(), TantivyError>) {
+    if let Err(e) = result {
+        eprintln!("[index] update failed: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spike(notes: &str) -> ActivationEvent {
+        serde_json::from_value(serde_json::json!({
+            "id": Uuid::new_v4(),
+            "date": "2024-01-01T00:00:00Z",
+            "performed_by": "desk",
+            "purpose": {"Other": "test"},
+            "outcome": "Pending",
+            "notes": notes,
+            "pos": [1.0, 1.0],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn staged_changes_show_after_one_flush() {
+        let mut index = NoteIndex::new().unwrap();
+        let burst: Vec<ActivationEvent> = (0..50).map(|_| spike("copper")).collect();
+        for ev in &burst {
+            index.add([ev]).unwrap();
+        }
+        assert!(index.search("copper").unwrap().is_empty());
+        index.flush().unwrap();
+        assert_eq!(index.search("copper").unwrap().len(), burst.len());
+
+        index.remove([burst[0].id]).unwrap();
+        index.flush().unwrap();
+        assert!(!index.search("copper").unwrap().contains(&burst[0].id));
+    }
+}
@@ -0,0 +1,275 @@
+// -------------------- Ledger check --------------------
+// "Check ledger" looks for what no command should ever leave behind but a
+// hand-edited file, an old version or a bad merge can: events without a
+// pin, pins without an event, events dated in the future, two events with
+// one id and positions outside the figure. Each finding comes with a fix.
+// Wrong dates and positions are revised like any edit; the rest are
+// journaled as repairs, since no other command can express them.
+//
+// Pins that disagree with the events are also put right whenever a ledger
+// is loaded (see `resync`): files written before the two were kept in step
+// would otherwise carry the mismatch on with every save.
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::journal::{Command, Journal, LedgerEvent};
+use crate::quarantine::{clamp, inside};
+use crate::units::Coords;
+use crate::{ActivationEvent, NkisiNkondi};
+
+// Clocks disagree by a little; later than this ahead is an anomaly
+const SKEW: Duration = Duration::minutes(5);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Anomaly {
+    Unpinned { id: Uuid, pos: (f32, f32) },
+    StrayPin { pos: (f32, f32) },
+    FutureDated { id: Uuid, date: DateTime<Utc> },
+    // `identical` when the copies are the same event listed twice
+    Duplicate { id: Uuid, identical: bool },
+    OutOfBounds { id: Uuid, pos: (f32, f32) },
+}
+
+// A change only the ledger check makes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "fix", rename_all = "snake_case")]
+pub enum Repair {
+    // A pin for an event that had none
+    Pin { pos: (f32, f32) },
+    // A pin that stood for no event
+    Unpin { pos: (f32, f32) },
+    // The later of two identical copies of an event
+    DropCopy { id: Uuid },
+    // The later of two different events sharing an id, given a new one
+    Reissue { id: Uuid, new_id: Uuid },
+}
+
+fn key((x, y): (f32, f32)) -> (u32, u32) {
+    (x.to_bits(), y.to_bits())
+}
+
+// Live events at `pos` minus pins there: above 0 some lack a pin, below 0
+// some pins stand for nothing
+fn pin_balance(nkisi: &NkisiNkondi, pos: (f32, f32)) -> i64 {
+    let events = nkisi.events.iter().filter(|e| key(e.pos) == key(pos)).count() as i64;
+    let pins = nkisi.pins.iter().filter(|p| key(**p) == key(pos)).count() as i64;
+    events - pins
+}
+
+// The second event (live, then trashed) carrying `id`
+fn second(nkisi: &NkisiNkondi, id: Uuid) -> Option<&ActivationEvent> {
+    nkisi.events.iter().chain(&nkisi.trash).filter(|e| e.id == id).nth(1)
+}
+
+// Live events without their pin, and pins that stand for no event
+fn pin_anomalies(nkisi: &NkisiNkondi) -> Vec<Anomaly> {
+    let mut found = vec![];
+    let mut pins: HashMap<(u32, u32), usize> = HashMap::new();
+    for p in &nkisi.pins {
+        *pins.entry(key(*p)).or_default() += 1;
+    }
+    for ev in &nkisi.events {
+        match pins.get_mut(&key(ev.pos)) {
+            Some(n) if *n > 0 => *n -= 1,
+            _ => found.push(Anomaly::Unpinned { id: ev.id, pos: ev.pos }),
+        }
+    }
+    // Each stray pin once, in the order they were placed
+    for p in &nkisi.pins {
+        if let Some(n) = pins.get_mut(&key(*p)).filter(|n| **n > 0) {
+            *n -= 1;
+            found.push(Anomaly::StrayPin { pos: *p });
+        }
+    }
+    found
+}
+
+pub fn scan(nkisi: &NkisiNkondi, dims: (f32, f32), now: DateTime<Utc>) -> Vec<Anomaly> {
+    let mut found = pin_anomalies(nkisi);
+    let mut seen: HashMap<Uuid, &ActivationEvent> = HashMap::new();
+    for ev in nkisi.events.iter().chain(&nkisi.trash) {
+        match seen.get(&ev.id) {
+            Some(first) => {
+                let identical = serde_json::to_value(first).ok() == serde_json::to_value(ev).ok();
+                found.push(Anomaly::Duplicate { id: ev.id, identical });
+            }
+            None => {
+                seen.insert(ev.id, ev);
+            }
+        }
+        if ev.date > now + SKEW {
+            found.push(Anomaly::FutureDated { id: ev.id, date: ev.date });
+        }
+        if !inside(ev.pos, dims) {
+            found.push(Anomaly::OutOfBounds { id: ev.id, pos: ev.pos });
+        }
+    }
+    found
+}
+
+impl Anomaly {
+    // Positions in the shown unit
+    pub fn describe(&self, coords: Coords) -> String {
+        let at = |pos| coords.format(pos);
+        match self {
+            Anomaly::Unpinned { pos, .. } => format!("Event at {} has no pin", at(*pos)),
+            Anomaly::StrayPin { pos } => format!("Pin at {} belongs to no event", at(*pos)),
+            Anomaly::FutureDated { date, .. } => format!("Event dated {}, in the future", date.format("%Y-%m-%d %H:%M")),
+            Anomaly::Duplicate { id, identical: true } => format!("Event {id} is listed twice"),
+            Anomaly::Duplicate { id, identical: false } => format!("Two different events share the id {id}"),
+            Anomaly::OutOfBounds { pos, .. } => format!("Event at {} lies outside the figure", at(*pos)),
+        }
+    }
+
+    // What the fix does, for its button
+    pub fn remedy(&self) -> &'static str {
+        match self {
+            Anomaly::Unpinned { .. } => "Pin it",
+            Anomaly::StrayPin { .. } => "Remove the pin",
+            Anomaly::FutureDated { .. } => "Date it when recorded",
+            Anomaly::Duplicate { identical: true, .. } => "Drop the copy",
+            Anomaly::Duplicate { identical: false, .. } => "Give one a new id",
+            Anomaly::OutOfBounds { .. } => "Move it to the edge",
+        }
+    }
+
+    // The event it is about, if one
+    pub fn event(&self) -> Option<Uuid> {
+        match self {
+            Anomaly::Unpinned { id, .. }
+            | Anomaly::FutureDated { id, .. }
+            | Anomaly::Duplicate { id, .. }
+            | Anomaly::OutOfBounds { id, .. } => Some(*id),
+            Anomaly::StrayPin { .. } => None,
+        }
+    }
+}
+
+// The command that fixes `anomaly`. A future date becomes the time the
+// ledger recorded the spike, or now if the journal no longer says.
+pub fn fix(
+    nkisi: &NkisiNkondi,
+    journal: &Journal,
+    anomaly: &Anomaly,
+    dims: (f32, f32),
+    now: DateTime<Utc>,
+) -> Option<Command> {
+    let find = |id: Uuid| nkisi.events.iter().chain(&nkisi.trash).find(|e| e.id == id);
+    Some(match *anomaly {
+        Anomaly::Unpinned { pos, .. } => Command::Repair(Repair::Pin { pos }),
+        Anomaly::StrayPin { pos } => Command::Repair(Repair::Unpin { pos }),
+        Anomaly::Duplicate { id, identical: true } => Command::Repair(Repair::DropCopy { id }),
+        Anomaly::Duplicate { id, identical: false } => {
+            Command::Repair(Repair::Reissue { id, new_id: Uuid::new_v4() })
+        }
+        Anomaly::FutureDated { id, date } => {
+            let recorded = journal.history(nkisi.id).find_map(|e| match &e.event {
+                LedgerEvent::Struck { event } if event.id == id => Some(e.at),
+                _ => None,
+            });
+            let to = recorded.filter(|at| *at <= now).unwrap_or(now);
+            let note = format!("dated {} in the future; set to {}", date.format("%Y-%m-%d %H:%M"), to.format("%Y-%m-%d %H:%M"));
+            Command::Revise { event: ActivationEvent { date: to, ..find(id)?.clone() }, note }
+        }
+        Anomaly::OutOfBounds { id, pos: (x, y) } => {
+            let pos = clamp((x, y), dims);
+            let note = format!("outside the figure at ({x:.1}, {y:.1}); moved to ({:.1}, {:.1})", pos.0, pos.1);
+            Command::Revise { event: ActivationEvent { pos, ..find(id)?.clone() }, note }
+        }
+    })
+}
+
+impl Repair {
+    // Whether there is still something to repair
+    pub fn needed(&self, nkisi: &NkisiNkondi) -> bool {
+        match self {
+            Repair::Pin { pos } => pin_balance(nkisi, *pos) > 0,
+            Repair::Unpin { pos } => pin_balance(nkisi, *pos) < 0,
+            Repair::DropCopy { id } | Repair::Reissue { id, .. } => second(nkisi, *id).is_some(),
+        }
+    }
+
+    // Idempotent like the rest of the projection: a repair already made
+    // changes nothing
+    pub fn apply(&self, nkisi: &mut NkisiNkondi) {
+        if !self.needed(nkisi) {
+            return;
+        }
+        match self {
+            Repair::Pin { pos } => nkisi.pins.push(*pos),
+            Repair::Unpin { pos } => {
+                if let Some(i) = nkisi.pins.iter().rposition(|p| key(*p) == key(*pos)) {
+                    nkisi.pins.remove(i);
+                }
+            }
+            Repair::DropCopy { id } => {
+                let live = nkisi.events.iter().filter(|e| e.id == *id).count();
+                if live > 1 {
+                    let i = nkisi.events.iter().rposition(|e| e.id == *id).expect("counted");
+                    let ev = nkisi.events.remove(i);
+                    if pin_balance(nkisi, ev.pos) < 0 {
+                        Repair::Unpin { pos: ev.pos }.apply(nkisi);
+                    }
+                } else if let Some(i) = nkisi.trash.iter().rposition(|e| e.id == *id) {
+                    nkisi.trash.remove(i);
+                }
+            }
+            Repair::Reissue { id, new_id } => {
+                let copy = nkisi.events.iter_mut().chain(nkisi.trash.iter_mut()).filter(|e| e.id == *id).nth(1);
+                if let Some(ev) = copy {
+                    ev.id = *new_id;
+                }
+            }
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let at = |(x, y): (f32, f32)| format!("({x:.1}, {y:.1})");
+        match self {
+            Repair::Pin { pos } => format!("pin added at {}", at(*pos)),
+            Repair::Unpin { pos } => format!("stray pin at {} removed", at(*pos)),
+            Repair::DropCopy { id } => format!("second copy of {id} dropped"),
+            Repair::Reissue { id, new_id } => format!("second event with id {id} is now {new_id}"),
+        }
+    }
+}
+
+// What `resync` changed
+#[derive(Debug, Default)]
+pub struct Resync {
+    pub pinned: Vec<(f32, f32)>,
+    pub dropped: Vec<(f32, f32)>,
+}
+
+// Pins rebuilt from the events, one per live event at its position, when
+// the two disagree; None when they already match
+pub fn resync(nkisi: &mut NkisiNkondi) -> Option<Resync> {
+    let mut done = Resync::default();
+    for anomaly in pin_anomalies(nkisi) {
+        match anomaly {
+            Anomaly::Unpinned { pos, .. } => done.pinned.push(pos),
+            Anomaly::StrayPin { pos } => done.dropped.push(pos),
+            _ => {}
+        }
+    }
+    if done.pinned.is_empty() && done.dropped.is_empty() {
+        return None;
+    }
+    nkisi.pins = nkisi.events.iter().map(|e| e.pos).collect();
+    Some(done)
+}
+
+impl Resync {
+    pub fn describe(&self) -> String {
+        let mut parts = vec![];
+        if !self.pinned.is_empty() {
+            parts.push(format!("{} re-pinned", crate::confirm::count(self.pinned.len(), "event")));
+        }
+        if !self.dropped.is_empty() {
+            parts.push(format!("{} without an event removed", crate::confirm::count(self.dropped.len(), "pin")));
+        }
+        parts.join(", ")
+    }
+}
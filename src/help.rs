@@ -0,0 +1,312 @@
+// -------------------- Help --------------------
+// The help overlay (F1, or the "?" beside a panel) with one topic per part
+// of the window. Topics are plain text kept here rather than in the views;
+// the FIX topic is written from the running dictionary and listen address,
+// so it always names the tags this instance actually expects.
+use iced::widget::{button, center, column, container, mouse_area, opaque, row, scrollable, stack, text};
+use iced::{Border, Color, Element, Length, Theme};
+
+use crate::fixdict::FixDictionary;
+use crate::Message;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Topic {
+    Figure,
+    Events,
+    Overlay,
+    Regions,
+    Workspace,
+    Ledger,
+    Exports,
+    Fix,
+    Keys,
+}
+
+impl Topic {
+    pub const ALL: [Topic; 9] = [
+        Topic::Figure,
+        Topic::Events,
+        Topic::Overlay,
+        Topic::Regions,
+        Topic::Workspace,
+        Topic::Ledger,
+        Topic::Exports,
+        Topic::Fix,
+        Topic::Keys,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Topic::Figure => "Figure and spikes",
+            Topic::Events => "Events and review",
+            Topic::Overlay => "Overlay and appearance",
+            Topic::Regions => "Regions",
+            Topic::Workspace => "Workspace and figures",
+            Topic::Ledger => "Ledger files",
+            Topic::Exports => "Exports",
+            Topic::Fix => "FIX integration",
+            Topic::Keys => "Keyboard",
+        }
+    }
+
+    fn body(self) -> &'static str {
+        match self {
+            Topic::Figure => {
+                "Click the figure to propose a spike at that spot; the Pending Spike form asks for the \
+                 striker, a message and any workspace fields, and Confirm records it. Drag a pin to move \
+                 it. With Lasso or Box picked, dragging selects every pin inside for the bulk actions. \
+                 The ⟲ ⟳ and Mirror buttons beside the figure path turn the artwork to match a ledger; \
+                 pins keep their coordinates. An edited SVG reloads by itself.\n\n\
+                 Intensity is 3 plus one per spike. With a half-life (days) each spike's share halves \
+                 that often, so the charge fades when no spikes come; Curve plots it up to now."
+            }
+            Topic::Events => {
+                "Events are listed newest first. Select one for its details: outcome, notes, provenance \
+                 and history, with Previous/Next to walk through them in time order. Deleted events go \
+                 to the trash until it is emptied; conflicts from merges wait under Conflicts.\n\n\
+                 Clear… moves part of the ledger to the trash: events between two dates (YYYY-MM-DD), by \
+                 a striker, from a source (a FIX sender or file, fix, pack, or here for spikes struck in \
+                 this ledger) and, if ticked, found by the search box. Blank criteria take everything. \
+                 Preview counts and lists them; clearing asks once more."
+            }
+            Topic::Overlay => {
+                "The overlay draws the grid, pins colored by outcome, a heatmap, striker labels and \
+                 regions. Save a combination as a profile. The palette row picks color-blind safe colors \
+                 and high contrast; Zoom scales the whole interface. Exports use the same overlay.\n\n\
+                 Scroll over the figure to magnify it around the pointer and drag with the right button \
+                 to pan; Fit shows all of it again. Save view keeps the magnified part with the overlay \
+                 and hidden layers under a name, per figure, to come back to it from the View list. \
+                 Exports always show the whole figure. Before/after shows the figure twice, each half with \
+                 the spikes struck by the date on its slider, magnified and panned together; Restore Before \
+                 moves the spikes struck since the Before date to the trash, once you confirm it.\n\n\
+                 Coordinates on the same row shows positions in figure units, as a percentage of the \
+                 figure, or in millimetres when an SVG gives its printed size; the panels, the status line \
+                 and reports all follow it, and pin positions are typed in it. The list beside it stores new \
+                 positions (clicks, moved pins and FIX spikes) to a step such as 0.1 figure units, so the \
+                 same place is always the same number."
+            }
+            Topic::Regions => {
+                "Named regions are polygons drawn on the figure. Events are counted per region in \
+                 reports and aggregates, and rules can restrict spikes to some of them.\n\n\
+                 Reclassify gives a purpose or tag to every event inside a region, or among the pins \
+                 selected with the lasso or box. Preview lists the events it would change; Apply revises \
+                 them, each with a note naming the region, and Undo reclassification takes the whole \
+                 round back while nothing has been changed since."
+            }
+            Topic::Workspace => {
+                "A workspace (.nkisiproj) holds several figures, each with its own ledger, artwork and \
+                 regions, plus the overlay profiles, form fields, validation rules and ingest settings. \
+                 Settings changed here are kept once the workspace is saved. Self-test checks the setup: \
+                 config, figure, template, ledger, ports and a FIX round trip on loopback. Usage shows \
+                 sessions, spikes added and features used, counted on this machine only (.nkisi_usage.json).\n\n\
+                 Two figures whose ledgers are copies of one figure (a ledger copied for fieldwork and added \
+                 back, say) are listed under Forked figures when the workspace opens, with what each has \
+                 that the other lacks: merge one into the other, keep one, or leave both. The copy not kept \
+                 leaves the workspace; its ledger file stays on disk."
+            }
+            Topic::Ledger => {
+                "Launched without a workspace or --save-path, the app opens on a start screen: ledgers \
+                 opened lately, or a path to open (a ledger, a workspace or a spike pack), start a new figure \
+                 at, or import a pack into a ledger of its own. FIX spikes wait until one is picked.\n\n\
+                 Save writes the ledger as JSON next to an append-only journal; Load reads it back. Merge \
+                 brings in another ledger, keeping both sides' edits and flagging real conflicts. Old \
+                 events can be archived out of the ledger and searched later.\n\n\
+                 With --metrics-addr, GET /events/stream there streams each new event as a line of JSON \
+                 (curl -N). [[api_tokens]] entries in the config file (name, token, scopes = [\"read\"]) \
+                 make /metrics and the stream ask for Authorization: Bearer <token> or ?token=; a token \
+                 list that doesn't load keeps them off. --webhook POSTs each new event to a URL; with \
+                 --webhook-secret the request \
+                 carries X-Nkisi-Timestamp and X-Nkisi-Signature-256, sha256= and the hex HMAC-SHA256 of \
+                 the timestamp, a dot and the body; X-Nkisi-Signature still carries the HMAC-SHA1 (sha1=) \
+                 for older receivers. With --on-save, a command runs after every save with the \
+                 saved file as its argument; its outcome shows on the status line.\n\n\
+                 Outbox: what the webhook, drop-copy and upstream FIX session haven't delivered survives a \
+                 restart (.nkisi_outbox.json) and is retried, waiting longer after each failure. Events \
+                 refused by the receiver or failing 8 times are dead letters; the Outbox panel re-drives \
+                 or discards them. Where outbound TCP is blocked, --forward-proxy sends the upstream \
+                 session through a socks5:// or http:// (CONNECT) proxy, user:pass@ included.\n\n\
+                 Sandbox tries changes out on a copy of the ledger: add, delete or restore spikes, edit \
+                 notes, undo. Nothing is saved, sent or forwarded, and FIX spikes wait until you leave. \
+                 Discard drops the changes; Apply makes them on the real ledger in one go.\n\n\
+                 Check ledger looks for events without a pin, pins without an event, events dated in the \
+                 future, two events with one id and positions outside the figure, each with a fix: dates \
+                 and positions are revised like any edit, the rest recorded in the journal as repairs, \
+                 which can't be undone. Pins out of step with the events (older files) are rebuilt from \
+                 the events' positions when a ledger loads; the status line says what changed."
+            }
+            Topic::Exports => {
+                "Reports (CSV, HTML templates), PNG images at any DPI, spike packs for other desks and \
+                 a batch export of every figure (CSV, SVG and PDF). Aggregates give counts without any \
+                 event detail, optionally with differential-privacy noise.\n\n\
+                 Strikers lists everyone who struck the figure, most events first, with their first and \
+                 last event, the regions they hit and the share resolved. Export writes it as CSV and as \
+                 a Markdown list of contributors for a project's acknowledgments."
+            }
+            Topic::Fix => "",
+            Topic::Keys => {
+                "F1: this help\n\
+                 → ↓ or j: next event   ← ↑ or k: previous event   Esc: close the event details\n\
+                 Ctrl + / Ctrl −: zoom the interface   Ctrl 0: back to 100%\n\
+                 Keys typed into a text field stay in the field."
+            }
+        }
+    }
+}
+
+// The FIX topic for this instance
+fn fix(fix_addr: &str, d: &FixDictionary) -> String {
+    let orders = match &d.new_order_single {
+        Some(o) => format!(
+            "\n\nOrders: NewOrderSingle (35=D) with 55={} is read as a spike, {} / {} giving the position and \
+             {} the striker; it is answered with an ExecutionReport (35=8, 37 the event id, 39=0 or 8).",
+            d.symbol, o.x, o.y, o.who
+        ),
+        None => String::new(),
+    };
+    format!(
+        "The acceptor listens on {fix_addr} for FIX 4.x tag=value messages (SOH-separated, BodyLength \
+         and CheckSum checked). The FIX acceptor row stops it, starts it again or moves it to another \
+         address; stopping logs open sessions out. The Network panel lists every address it answers on, \
+         with engine settings to copy and a Test spike button that sends one through that address. More \
+         listeners (--fix-listener NAME=ADDR, or the workspace's ingest settings) run beside it; each \
+         spike records the listener it came in on.\n\n\
+         Session: log on with 35=A (HeartBtInt 108, ResetSeqNumFlag 141=Y to start numbering over). \
+         MsgSeqNum is kept per SenderCompID (49) across reconnects; gaps are requested with 35=2. A \
+         feed that never logs on is read as a raw feed and gets no answers. FIX 5.0 engines log on with \
+         8=FIXT.1.1 and DefaultApplVerID 1137 (2 to 9, FIX 4.0 to FIX 5.0 SP2); the Logon answer confirms \
+         it, and a message whose ApplVerID (1128) is outside that range gets a Reject with 373=18. \
+         The Sessions panel lists open connections with message counts, the last Heartbeat and \
+         sequence numbers; Disconnect ends one, sending a Logout if it is logged on. The Inspector \
+         shows the last 200 messages either way as sent (SOH as |), colored by what became of them: read, \
+         session, refused with the reason, malformed or sent by us; click one to list its tags. \
+         Passwords (554) are masked.\n\n\
+         FIXML: a connection that opens with '<' sends <FIXML> documents instead, read like a raw feed. \
+         <Spike> carries X, Y, Who, Txt, TxnTm, Purp, Outcome and EvntID with <Hdr SID>, <Instrmt Sym>, \
+         <Pty ID R> and one <Spk> per spike of several; <SpikeUpd EvntID Outcome> is an outcome update, \
+         <SpikeCxl EvntID> a cancel and <Order> a NewOrderSingle. <Batch> holds many.\n\n\
+         Spike: 35={} with 55={}\n\
+         {}  striker (PartyID, bare or in NoPartyIDs 453 with 452=12; witnesses 452=4000)\n\
+         {} / {}  position in figure units\n\
+         {}  note   {}  time (UTCTimestamp)   {}  purpose\n\
+         {}  outcome: pending, resolved, failed (or 0, 1, 2)\n\
+         {}  event id (UUID), so a spike sent twice is recorded once\n\
+         {}  idempotency key: the sender's own id for the spike, recorded once per key\n\
+         11  ClOrdID, echoed in the acknowledgment\n\
+         Several spikes: {}=count, each entry starting with {}.\n\n\
+         Outcome update: 35={} with 55, {} the event id, {} the new outcome and the PartyID of who \
+         changes it; {} is kept as the reason. It is answered like a spike and the event's history keeps \
+         the change.\n\
+         Cancel: 35={} with 55, {} the event id and the PartyID of who cancels it, 58 the reason. The pin \
+         goes; the event stays listed under the trash as cancelled, with who cancelled it and when.\n\n\
+         Answer: 35={} per spike with 39=0 (recorded) or 39=8 (refused), 58 explaining, {} the event \
+         id and 45 the spike's MsgSeqNum. Unreadable messages get a session Reject (35=3) naming the \
+         tag (371) and reason (373). A ResendRequest (35=2) gets those answers again with 43=Y; without \
+         a message store only the ones sent on the same connection are kept for it. A message resent with PossDupFlag (43=Y) under a MsgSeqNum \
+         already read is dropped, and a spike whose idempotency key was recorded before is answered \
+         \"already recorded\" without a second pin. Other spikes alike are separate spikes.\n\n\
+         Outside the figure: a spike whose position lies off the figure is moved to the nearest edge, \
+         refused, or held for review, as picked next to the acceptor (and kept with the workspace). A held \
+         spike is answered 39=A (pending) at once; the Quarantine panel accepts it, at the edge and \
+         subject to the rules, or discards it, and the sender gets 39=0 or 39=8 then if still connected. \
+         Spikes the rules refuse are held the same way; with the other choices they, and spikes refused \
+         for lying off the figure, are answered 39=8 at once but kept in the quarantine all the same. \
+         Fix opens a held spike's striker, note and position for correcting, and Resubmit puts it \
+         through the rules again; the corrections join its provenance.\n\n\
+         Flooding: a connection sending more than --fix-rate-limit messages a second (200 unless set) is \
+         slowed down, not cut off. Spikes wait in a queue for the ledger; when it stays full a spike is \
+         answered 39=8 \"ledger busy\". Past --fix-max-connections open connections (256 unless set, \
+         all listeners together) a new one is closed at once. The status line counts queued, slowed and \
+         refused messages and refused connections.\n\n\
+         Network rules: --allow-from and --deny-from take IPs or CIDR ranges (10.0.0.0/8) for every \
+         listener, FIX, drop-copy and metrics alike. A deny rule wins; with any allow rule only the ranges \
+         it names get in. Refused connections are logged and counted on the status line.\n\n\
+         Transforms: ingest.transforms in the workspace file normalize spikes before they are recorded, \
+         for every feed or one sender or acceptor: rename strikers, scale and offset positions, give a \
+         note to spikes sent without one. They apply in order; the list beside the rules switches each off.\n\n\
+         Store: with --fix-store every message in and out is kept on disk, one file per counterparty and \
+         day. The FIX message store panel replays a day into the ledger (--replay-fix-store replays them \
+         all at startup); spikes the ledger already has are skipped, known by idempotency key or by \
+         MsgSeqNum and first SendingTime, and raw-feed messages that carry neither are added again. \
+         Export log writes the day as standard FIX message logs (time : message, one file per \
+         counterparty, upstream session included) next to the reports, for a counterparty's support team \
+         and their tools.\n\n\
+         Drop copy: --drop-copy HOST:PORT sends every event the ledger takes, struck here or over FIX, \
+         to a monitor as a 35={} message; --drop-copy-listen ADDR does the same for every consumer that \
+         connects there (mirrored displays, audit), from the moment it connects, numbering from 1 on \
+         each connection. The Network panel shows how many are connected.{orders}",
+        d.spike_type,
+        d.symbol,
+        d.who,
+        d.x,
+        d.y,
+        d.note,
+        d.timestamp,
+        d.purpose,
+        d.outcome,
+        d.event_id,
+        d.idempotency_key,
+        d.spikes,
+        d.x,
+        d.update_type,
+        d.event_id,
+        d.outcome,
+        d.note,
+        d.cancel_type,
+        d.event_id,
+        d.ack_type,
+        d.event_id,
+        d.spike_type,
+    )
+}
+
+// "?" beside a panel, opening its topic
+pub fn hint(topic: Topic) -> Element<'static, Message> {
+    button(text("?").size(12))
+        .padding([0, 6])
+        .style(button::secondary)
+        .on_press(Message::ShowHelp(Some(topic)))
+        .into()
+}
+
+// Lay the help for `topic` over `base`; clicking outside closes it
+pub fn modal<'a>(base: Element<'a, Message>, topic: Topic, fix_addr: &str, dict: &FixDictionary) -> Element<'a, Message> {
+    let mut topics = column![].spacing(4).width(Length::Fixed(190.0));
+    for t in Topic::ALL {
+        let style = if t == topic { button::primary } else { button::text };
+        let entry = button(text(t.title())).style(style).width(Length::Fill);
+        topics = topics.push(entry.on_press(Message::ShowHelp(Some(t))));
+    }
+    let body = match topic {
+        Topic::Fix => fix(fix_addr, dict),
+        t => t.body().to_string(),
+    };
+    let page = column![
+        row![text(topic.title()).size(18).width(Length::Fill), button("Close").on_press(Message::ShowHelp(None))]
+            .spacing(12),
+        scrollable(text(body)).height(Length::Fill),
+    ]
+    .spacing(12);
+
+    let dialog = container(row![topics, page].spacing(16))
+        .padding(16)
+        .max_width(760)
+        .height(Length::Fixed(460.0))
+        .style(|_theme: &Theme| container::Style {
+            background: Some(Color::from_rgb(0.13, 0.13, 0.16).into()),
+            border: Border { radius: 12.0.into(), ..Default::default() },
+            ..Default::default()
+        });
+
+    let backdrop = mouse_area(
+        center(opaque(dialog))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .style(|_theme: &Theme| container::Style {
+                background: Some(Color::from_rgba(0.0, 0.0, 0.0, 0.6).into()),
+                ..Default::default()
+            }),
+    )
+    .on_press(Message::ShowHelp(None));
+
+    stack![base, opaque(backdrop)].into()
+}
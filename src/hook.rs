@@ -0,0 +1,73 @@
+// -------------------- Save hook --------------------
+// A command run after every ledger save, for pipelines such as pushing the
+// file to an institutional repository. It goes through the shell (sh -c, or
+// cmd /C on Windows) with the saved path as its first argument and in
+// NKISI_SAVED_PATH, on its own thread so a slow upload never holds the
+// window up. How it ended comes back for the status line.
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+pub struct Hook {
+    command: String,
+    tx: Sender<String>,
+    // One report per run, in the order they finish
+    done: Receiver<String>,
+    // Runs not reported yet
+    running: AtomicUsize,
+}
+
+impl Hook {
+    pub fn new(command: &str) -> Self {
+        let (tx, done) = unbounded();
+        Self { command: command.to_string(), tx, done, running: AtomicUsize::new(0) }
+    }
+
+    pub fn run(&self, saved: &str, events: usize) {
+        self.running.fetch_add(1, Ordering::Relaxed);
+        let (command, saved, tx) = (self.command.clone(), saved.to_string(), self.tx.clone());
+        thread::spawn(move || {
+            let _ = tx.send(report(&saved, shell(&command, &saved, events).output()));
+        });
+    }
+
+    pub fn busy(&self) -> bool {
+        self.running.load(Ordering::Relaxed) > 0
+    }
+
+    // How the oldest finished run went, if one has
+    pub fn finished(&self) -> Option<String> {
+        let report = self.done.try_recv().ok()?;
+        self.running.fetch_sub(1, Ordering::Relaxed);
+        Some(report)
+    }
+}
+
+fn shell(command: &str, saved: &str, events: usize) -> Command {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command, saved]);
+        cmd
+    } else {
+        // $0 names the hook in the shell's own messages, $1 is the file
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command, "nkisi-save-hook", saved]);
+        cmd
+    };
+    cmd.env("NKISI_SAVED_PATH", saved).env("NKISI_EVENT_COUNT", events.to_string());
+    cmd
+}
+
+fn report(saved: &str, output: std::io::Result<std::process::Output>) -> String {
+    match output {
+        Ok(out) if out.status.success() => format!("Save hook done for {saved}"),
+        Ok(out) => {
+            let stderr = String::from_utf8_lossy(&out.stderr);
+            let why = stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("no output");
+            eprintln!("[hook] {saved}: {}: {}", out.status, stderr.trim_end());
+            format!("Save hook failed for {saved} ({}): {}", out.status, why.trim())
+        }
+        Err(e) => format!("Save hook could not start: {e}"),
+    }
+}
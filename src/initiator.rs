@@ -0,0 +1,169 @@
+// -------------------- FIX initiator --------------------
+// Outbound FIX session to an upstream engine. We connect, log on (35=A,
+// sequence numbers reset with 141=Y) and publish every spike confirmed at
+// this desk as a spike message in the drop-copy layout, event id included.
+// Spikes that came in over FIX, packs or merges are not forwarded, so desks
+// forwarding to each other don't echo. A spike stays queued until the
+// counterparty answers it: an acknowledgment (U2) or a reject (3, j)
+// settles it, and anything still open when the connection drops is sent
+// again on the next one; the event id lets the other side skip repeats.
+// A refused spike becomes a dead letter (outbox.rs), and reconnecting backs
+// off while the counterparty stays away. The session itself, logon,
+// heartbeats and reconnecting, is fix::Initiator, shared with fixclient;
+// where direct outbound TCP is blocked it goes through a proxy (proxy.rs).
+use crossbeam_channel::{unbounded, Sender, TryRecvError};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::dropcopy;
+use crate::fix::{self, Event, Msg, Settings};
+use crate::fixdict::FixDictionary;
+use crate::fixstore::{Direction, Store};
+use crate::outbox::{Channel, Outbox};
+use crate::proxy::Proxy;
+use crate::ActivationEvent;
+
+const TICK: Duration = Duration::from_millis(200);
+// Oldest spikes become dead letters beyond this while the counterparty is away
+const MAX_QUEUED: usize = 10_000;
+const SENDER: &str = "NKISI";
+const TARGET: &str = "UPSTREAM";
+
+// What the status line shows
+#[derive(Debug, Default)]
+pub struct Link {
+    pub logged_on: AtomicBool,
+    // Spikes not yet answered by the counterparty
+    pub waiting: AtomicUsize,
+}
+
+pub struct Initiator {
+    tx: Sender<ActivationEvent>,
+    pub addr: String,
+    pub link: Arc<Link>,
+    outbox: Arc<Outbox>,
+}
+
+impl Initiator {
+    pub fn start(
+        addr: &str,
+        proxy: Option<Proxy>,
+        store: Option<Arc<Store>>,
+        dict: Arc<FixDictionary>,
+        outbox: Arc<Outbox>,
+    ) -> Self {
+        let (tx, rx) = unbounded::<ActivationEvent>();
+        let link = Arc::new(Link::default());
+        let shared = Arc::clone(&link);
+        let letters = Arc::clone(&outbox);
+        let target = addr.to_string();
+        let mut settings = Settings::new(SENDER, TARGET);
+        settings.logon.push((141, "Y".into()));
+        let mut session = fix::Initiator::new(addr, settings);
+        if let Some(store) = store {
+            session = session.tap(move |inbound, raw| {
+                store.record(TARGET, if inbound { Direction::In } else { Direction::Out }, raw);
+            });
+        }
+        match proxy {
+            Some(p) => {
+                eprintln!("[FIX out] forwarding confirmed spikes to {target} through {p}");
+                session = session.via(move |addr| p.connect(addr));
+            }
+            None => eprintln!("[FIX out] forwarding confirmed spikes to {target}"),
+        }
+        thread::spawn(move || {
+            let mut queue: VecDeque<ActivationEvent> = VecDeque::new();
+            // Spikes sent on this connection by MsgSeqNum, until answered
+            let mut in_flight: BTreeMap<u64, ActivationEvent> = BTreeMap::new();
+            loop {
+                loop {
+                    match rx.try_recv() {
+                        Ok(ev) => queue.push_back(ev),
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => {
+                            session.logout();
+                            return;
+                        }
+                    }
+                }
+                match session.poll(TICK) {
+                    Some(Event::LoggedOn) => {
+                        shared.logged_on.store(true, Ordering::Relaxed);
+                        eprintln!("[FIX out] {target}: logged on");
+                    }
+                    Some(Event::Message(msg)) => answered(&msg, &dict, &mut in_flight, &letters, &target),
+                    Some(Event::Garbled(why)) => eprintln!("[FIX out] {target}: malformed message dropped: {why}"),
+                    Some(Event::Down { why, retry }) => {
+                        shared.logged_on.store(false, Ordering::Relaxed);
+                        // Unanswered spikes go again on the next connection
+                        for ev in std::mem::take(&mut in_flight).into_values().rev() {
+                            queue.push_front(ev);
+                        }
+                        while queue.len() > MAX_QUEUED {
+                            if let Some(ev) = queue.pop_front() {
+                                letters.dead(Channel::Upstream, ev, 0, "queue full while the counterparty was away");
+                            }
+                        }
+                        let wait = retry.as_secs();
+                        eprintln!("[FIX out] {target}: {why}; retrying in {wait}s, {} spike(s) queued", queue.len());
+                    }
+                    None => {}
+                }
+                while session.logged_on() {
+                    let Some(ev) = queue.pop_front() else { break };
+                    match session.send(&dict.spike_type, &dropcopy::spike_fields(&ev, &dict)) {
+                        Ok(seq) => {
+                            in_flight.insert(seq, ev);
+                        }
+                        Err(_) => queue.push_front(ev),
+                    }
+                }
+                shared.waiting.store(in_flight.len() + queue.len(), Ordering::Relaxed);
+            }
+        });
+        Self { tx, addr: addr.to_string(), link, outbox }
+    }
+
+    pub fn send(&self, ev: &ActivationEvent) {
+        self.outbox.queued(Channel::Upstream, ev);
+        let _ = self.tx.send(ev.clone());
+    }
+}
+
+// Settles the spike an acknowledgment or reject is about
+fn answered(
+    msg: &Msg,
+    dict: &FixDictionary,
+    in_flight: &mut BTreeMap<u64, ActivationEvent>,
+    outbox: &Outbox,
+    peer: &str,
+) {
+    let ref_seq = msg.get(45).and_then(|v| v.parse::<u64>().ok());
+    match msg.msg_type() {
+        "3" | "j" => {
+            let text = msg.get(58).unwrap_or("");
+            match ref_seq.and_then(|seq| in_flight.remove(&seq)) {
+                Some(ev) => outbox.dead(Channel::Upstream, ev, 1, &format!("rejected: {text}")),
+                None => eprintln!("[FIX out] {peer}: a message rejected: {text}"),
+            }
+        }
+        t if t == dict.ack_type => {
+            let event = msg.get(dict.event_id as i32).and_then(|v| Uuid::parse_str(v).ok());
+            let seq = ref_seq.or_else(|| in_flight.iter().find(|(_, ev)| Some(ev.id) == event).map(|(seq, _)| *seq));
+            if let Some(ev) = seq.and_then(|seq| in_flight.remove(&seq)) {
+                if msg.get(39) == Some("8") {
+                    let text = format!("refused: {}", msg.get(58).unwrap_or(""));
+                    outbox.dead(Channel::Upstream, ev, 1, &text);
+                } else {
+                    outbox.delivered(Channel::Upstream, ev.id);
+                }
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,221 @@
+// -------------------- FIX inspector --------------------
+// The last raw messages the acceptors read and wrote, for the Inspector
+// panel: what a counterparty really sends, without reaching for tcpdump.
+// SOH is shown as '|' and each message is colored by what became of it:
+// read as spikes, a session message, refused (and why), dropped as
+// malformed before it could be read, or one of ours going out. A click
+// lists its tags one per line, named from the dictionary where it can.
+// FIXML is shown as the tag=value message it was rewritten to. Only the
+// most recent are kept, and only in memory.
+use chrono::{DateTime, Utc};
+use iced::widget::{button, column, container, row, scrollable, text};
+use iced::{Color, Element, Length};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::fixdict::FixDictionary;
+use crate::fix;
+use crate::{confirm, Message, SOH};
+
+// Messages kept, either way
+pub const KEEP: usize = 200;
+// Password (554) is never kept
+const PASSWORD: &[u8] = b"554=";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    // Delivered to the ledger's queue as spikes (or an update or cancel)
+    Read,
+    Session,
+    Refused(String),
+    Malformed(String),
+    Sent,
+}
+
+#[derive(Debug, Clone)]
+pub struct Seen {
+    pub id: u64,
+    pub at: DateTime<Utc>,
+    pub peer: String,
+    pub verdict: Verdict,
+    pub raw: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+pub struct Inspector {
+    seen: Mutex<VecDeque<Seen>>,
+    next_id: AtomicU64,
+}
+
+impl Inspector {
+    pub fn record(&self, peer: &str, verdict: Verdict, raw: &[u8]) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut seen) = self.seen.lock() {
+            if seen.len() == KEEP {
+                seen.pop_front();
+            }
+            let raw = masked(raw);
+            seen.push_back(Seen { id, at: Utc::now(), peer: peer.to_string(), verdict, raw });
+        }
+        id
+    }
+
+    // What became of a message recorded before it was handled, so it is
+    // listed ahead of the answers it got
+    pub fn settle(&self, id: u64, verdict: Verdict) {
+        if let Ok(mut seen) = self.seen.lock() {
+            if let Some(s) = seen.iter_mut().rev().find(|s| s.id == id) {
+                s.verdict = verdict;
+            }
+        }
+    }
+
+    // Newest first
+    pub fn recent(&self) -> Vec<Seen> {
+        self.seen.lock().map(|seen| seen.iter().rev().cloned().collect()).unwrap_or_default()
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut seen) = self.seen.lock() {
+            seen.clear();
+        }
+    }
+}
+
+fn masked(raw: &[u8]) -> Vec<u8> {
+    let fields: Vec<&[u8]> = raw
+        .split(|b| *b == SOH)
+        .map(|f| if f.starts_with(PASSWORD) { b"554=***".as_slice() } else { f })
+        .collect();
+    fields.join(&SOH)
+}
+
+// One line, SOH as '|'
+pub fn printable(raw: &[u8]) -> String {
+    String::from_utf8_lossy(raw).replace(SOH as char, "|")
+}
+
+// Name of a tag: the dictionary's own first, so a remapped tag reads as
+// what it carries here
+fn name(tag: u32, dict: &FixDictionary) -> Option<&'static str> {
+    let own = [
+        (dict.who, "Striker"),
+        (dict.x, "X"),
+        (dict.y, "Y"),
+        (dict.note, "Note"),
+        (dict.timestamp, "StruckAt"),
+        (dict.purpose, "Purpose"),
+        (dict.outcome, "Outcome"),
+        (dict.spikes, "NoSpikes"),
+        (dict.event_id, "EventID"),
+        (dict.idempotency_key, "IdempotencyKey"),
+    ];
+    if let Some((_, name)) = own.iter().find(|(t, _)| *t == tag) {
+        return Some(name);
+    }
+    Some(match tag {
+        1 => "Account",
+        7 => "BeginSeqNo",
+        8 => "BeginString",
+        9 => "BodyLength",
+        10 => "CheckSum",
+        11 => "ClOrdID",
+        16 => "EndSeqNo",
+        34 => "MsgSeqNum",
+        35 => "MsgType",
+        36 => "NewSeqNo",
+        38 => "OrderQty",
+        39 => "OrdStatus",
+        43 => "PossDupFlag",
+        44 => "Price",
+        45 => "RefSeqNum",
+        49 => "SenderCompID",
+        52 => "SendingTime",
+        54 => "Side",
+        55 => "Symbol",
+        56 => "TargetCompID",
+        58 => "Text",
+        98 => "EncryptMethod",
+        108 => "HeartBtInt",
+        112 => "TestReqID",
+        123 => "GapFillFlag",
+        141 => "ResetSeqNumFlag",
+        150 => "ExecType",
+        371 => "RefTagID",
+        372 => "RefMsgType",
+        373 => "SessionRejectReason",
+        380 => "BusinessRejectReason",
+        447 => "PartyIDSource",
+        448 => "PartyID",
+        452 => "PartyRole",
+        453 => "NoPartyIDs",
+        553 => "Username",
+        554 => "Password",
+        1128 => "ApplVerID",
+        1137 => "DefaultApplVerID",
+        _ => return None,
+    })
+}
+
+fn color(verdict: &Verdict) -> Color {
+    match verdict {
+        Verdict::Read => Color::from_rgb(0.45, 0.85, 0.5),
+        Verdict::Session => Color::from_rgb(0.7, 0.7, 0.8),
+        Verdict::Refused(_) => Color::from_rgb(1.0, 0.6, 0.3),
+        Verdict::Malformed(_) => Color::from_rgb(1.0, 0.4, 0.4),
+        Verdict::Sent => Color::from_rgb(0.5, 0.7, 1.0),
+    }
+}
+
+fn label(verdict: &Verdict) -> String {
+    match verdict {
+        Verdict::Read => "read".into(),
+        Verdict::Session => "session".into(),
+        Verdict::Refused(why) => format!("refused: {why}"),
+        Verdict::Malformed(why) => format!("malformed: {why}"),
+        Verdict::Sent => "sent".into(),
+    }
+}
+
+pub fn view<'a>(inspector: &Inspector, expanded: Option<u64>, dict: &FixDictionary) -> Element<'a, Message> {
+    let dim = Color::from_rgb(0.7, 0.7, 0.8);
+    let seen = inspector.recent();
+    let header = row![
+        text(format!("FIX inspector • last {}", confirm::count(seen.len(), "message"))).size(16),
+        button("Clear").style(button::secondary).on_press(Message::ClearInspector),
+        button("Close").style(button::secondary).on_press(Message::ShowInspector(false)),
+    ]
+    .spacing(10);
+    let mut list = column![].spacing(2);
+    if seen.is_empty() {
+        list = list.push(text("Nothing read or sent yet.").size(12).color(dim));
+    }
+    for s in seen {
+        let open = expanded == Some(s.id);
+        let line = row![
+            text(s.at.format("%H:%M:%S%.3f").to_string()).size(12).color(dim).width(95),
+            text(s.peer.clone()).size(12).color(dim).width(150),
+            text(label(&s.verdict)).size(12).color(color(&s.verdict)).width(220),
+            text(printable(&s.raw)).size(12).font(iced::Font::MONOSPACE).color(color(&s.verdict)),
+        ]
+        .spacing(8);
+        list = list.push(button(line).style(button::text).padding(1).on_press(Message::InspectFix(s.id)));
+        if open {
+            let mut tags = column![].spacing(1).padding([0, 24]);
+            for (tag, value) in fix::fields(&s.raw) {
+                let name = u32::try_from(tag).ok().and_then(|t| name(t, dict)).unwrap_or("");
+                tags = tags.push(
+                    row![
+                        text(tag.to_string()).size(12).font(iced::Font::MONOSPACE).width(50),
+                        text(name).size(12).color(dim).width(170),
+                        text(value).size(12).font(iced::Font::MONOSPACE),
+                    ]
+                    .spacing(8),
+                );
+            }
+            list = list.push(tags);
+        }
+    }
+    column![header, container(scrollable(list)).height(Length::Fixed(260.0))].spacing(4).into()
+}
@@ -0,0 +1,309 @@
+// -------------------- Event journal --------------------
+// Every change to a ledger starts as a domain command, is checked against
+// the current ledger and becomes an immutable `LedgerEvent`, appended to
+// `<ledger>.journal.jsonl` before the in-memory ledger (a projection of
+// those events) is updated. A saved ledger is a snapshot stamped with the
+// last sequence number it contains, so anything journaled after the last
+// save is replayed on load. Undo never rewrites history: it appends the
+// inverse event.
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::Path;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::crdt::{self, Stamp};
+use crate::health::Repair;
+use crate::{ActivationEvent, Hop, IoError, NkisiNkondi};
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    Strike(ActivationEvent),
+    Trash(Vec<Uuid>),
+    Restore(Vec<Uuid>),
+    EmptyTrash,
+    // Events already copied to the archive file
+    Archive(Vec<Uuid>),
+    // Another party's copy of this figure's ledger
+    Merge { source: String, remote: Box<NkisiNkondi> },
+    // New contents for an existing event, with why
+    Revise { event: ActivationEvent, note: String },
+    // The sender took its spike back: `by` says who, from where and when
+    Cancel { id: Uuid, by: Hop, note: String },
+    // A fix from the ledger check
+    Repair(Repair),
+    Undo,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LedgerEvent {
+    Struck { event: ActivationEvent },
+    // A strike taken back by undo; the event is gone, not trashed
+    Retracted { id: Uuid },
+    Trashed { ids: Vec<Uuid> },
+    Restored { ids: Vec<Uuid> },
+    TrashEmptied { ids: Vec<Uuid> },
+    Archived { ids: Vec<Uuid> },
+    // The whole remote copy is kept so a replay merges exactly the same
+    Merged { source: String, remote: Box<NkisiNkondi> },
+    Revised { event: ActivationEvent, previous: Box<ActivationEvent>, note: String },
+    // The event and its pin leave the ledger; the tombstone is the event as
+    // it was, its last provenance hop the cancellation
+    Cancelled { tombstone: ActivationEvent, note: String },
+    Repaired { repair: Repair },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub seq: u64,
+    pub at: DateTime<Utc>,
+    // Figure id, so a journal shared by path never replays into another ledger
+    pub ledger: Uuid,
+    // Replica that made the change (nil before replicas existed)
+    #[serde(default)]
+    pub origin: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undoes: Option<u64>,
+    pub event: LedgerEvent,
+}
+
+#[derive(Debug, Error)]
+pub enum Rejected {
+    #[error("event is no longer in the ledger")]
+    Gone,
+    #[error("event is already in the ledger")]
+    Duplicate,
+    #[error("that ledger belongs to another figure")]
+    OtherFigure,
+    #[error("event is no longer in the trash")]
+    NotInTrash,
+    #[error("the trash is already empty")]
+    TrashEmpty,
+    #[error("nothing to undo")]
+    NothingToUndo,
+    #[error("already repaired")]
+    Repaired,
+    #[error("can't undo: {0} is permanent")]
+    Permanent(&'static str),
+}
+
+pub struct Journal {
+    pub path: String,
+    pub entries: Vec<Entry>,
+    // Kept in memory only (the sandbox's)
+    memory: bool,
+}
+
+// nkisi_state.json -> nkisi_state.journal.jsonl
+pub fn journal_path(save_path: &str) -> String {
+    let p = Path::new(save_path);
+    let stem = p.file_stem().and_then(|s| s.to_str()).unwrap_or("nkisi_state");
+    p.with_file_name(format!("{stem}.journal.jsonl"))
+        .to_string_lossy()
+        .into_owned()
+}
+
+impl Journal {
+    // The journal next to `save_path`; a missing file is an empty journal.
+    // A torn last line (crash mid-append) is skipped.
+    pub fn open(save_path: &str) -> Result<Self, IoError> {
+        let path = journal_path(save_path);
+        let mut entries = vec![];
+        if Path::new(&path).exists() {
+            let text = std::fs::read_to_string(&path).map_err(|e| IoError::Read(e.to_string()))?;
+            for (i, line) in text.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+                match serde_json::from_str::<Entry>(line) {
+                    Ok(e) => entries.push(e),
+                    Err(e) => eprintln!("[journal] {path}:{}: {e}; skipped", i + 1),
+                }
+            }
+        }
+        Ok(Self { path, entries, memory: false })
+    }
+
+    pub fn empty(save_path: &str) -> Self {
+        Self { path: journal_path(save_path), entries: vec![], memory: false }
+    }
+
+    // A journal for `save_path` that never touches the disk
+    pub fn in_memory(save_path: &str) -> Self {
+        Self { memory: true, ..Self::empty(save_path) }
+    }
+
+    pub fn last_seq(&self) -> u64 {
+        self.entries.last().map_or(0, |e| e.seq)
+    }
+
+    // Entries for `ledger` newer than a snapshot taken at `seq`
+    pub fn since(&self, ledger: Uuid, seq: u64) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().filter(move |e| e.ledger == ledger && e.seq > seq)
+    }
+
+    // Entries for `ledger`, newest first
+    pub fn history(&self, ledger: Uuid) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().rev().filter(move |e| e.ledger == ledger)
+    }
+
+    // Write the entry to disk, then keep it. A failed write still keeps the
+    // entry in memory (the change is applied either way) and is reported.
+    pub fn append(
+        &mut self,
+        ledger: Uuid,
+        event: LedgerEvent,
+        undoes: Option<u64>,
+    ) -> (&Entry, Result<(), IoError>) {
+        let entry = Entry {
+            seq: self.last_seq() + 1,
+            at: Utc::now(),
+            ledger,
+            origin: crdt::replica_id(),
+            undoes,
+            event,
+        };
+        let written = serde_json::to_string(&entry)
+            .map_err(|e| IoError::Write(e.to_string()))
+            .and_then(|line| {
+                if self.memory {
+                    return Ok(());
+                }
+                let mut f = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)
+                    .map_err(|e| IoError::Write(e.to_string()))?;
+                writeln!(f, "{line}").map_err(|e| IoError::Write(e.to_string()))
+            });
+        self.entries.push(entry);
+        (self.entries.last().expect("just pushed"), written)
+    }
+
+    // The latest change to `ledger` not yet undone, if it can be undone
+    pub fn undo_target(&self, ledger: Uuid) -> Result<&Entry, Rejected> {
+        let undone: HashSet<u64> = self.entries.iter().filter_map(|e| e.undoes).collect();
+        let entry = self
+            .history(ledger)
+            .find(|e| e.undoes.is_none() && !undone.contains(&e.seq))
+            .ok_or(Rejected::NothingToUndo)?;
+        match entry.event {
+            LedgerEvent::TrashEmptied { .. } => Err(Rejected::Permanent("emptying the trash")),
+            LedgerEvent::Archived { .. } => Err(Rejected::Permanent("archiving")),
+            LedgerEvent::Merged { .. } => Err(Rejected::Permanent("merging")),
+            LedgerEvent::Cancelled { .. } => Err(Rejected::Permanent("a cancellation by the sender")),
+            LedgerEvent::Repaired { .. } => Err(Rejected::Permanent("a ledger repair")),
+            _ => Ok(entry),
+        }
+    }
+}
+
+// Check a command against the ledger and turn it into the event to record,
+// with the entry it undoes
+pub fn decide(
+    nkisi: &NkisiNkondi,
+    journal: &Journal,
+    cmd: Command,
+) -> Result<(LedgerEvent, Option<u64>), Rejected> {
+    let live = |ids: Vec<Uuid>| -> Vec<Uuid> {
+        ids.into_iter().filter(|id| nkisi.events.iter().any(|e| e.id == *id)).collect()
+    };
+    let trashed = |ids: Vec<Uuid>| -> Vec<Uuid> {
+        ids.into_iter().filter(|id| nkisi.trash.iter().any(|e| e.id == *id)).collect()
+    };
+    let event = match cmd {
+        // Ids travel with synced spikes; one we already know is an echo
+        Command::Strike(event) if nkisi.knows(event.id) => return Err(Rejected::Duplicate),
+        Command::Strike(event) => LedgerEvent::Struck { event },
+        Command::Trash(ids) => match live(ids) {
+            ids if ids.is_empty() => return Err(Rejected::Gone),
+            ids => LedgerEvent::Trashed { ids },
+        },
+        Command::Restore(ids) => match trashed(ids) {
+            ids if ids.is_empty() => return Err(Rejected::NotInTrash),
+            ids => LedgerEvent::Restored { ids },
+        },
+        Command::EmptyTrash if nkisi.trash.is_empty() => return Err(Rejected::TrashEmpty),
+        Command::EmptyTrash => LedgerEvent::TrashEmptied {
+            ids: nkisi.trash.iter().map(|e| e.id).collect(),
+        },
+        Command::Archive(ids) => match live(ids) {
+            ids if ids.is_empty() => return Err(Rejected::Gone),
+            ids => LedgerEvent::Archived { ids },
+        },
+        Command::Merge { remote, .. } if remote.id != nkisi.id => return Err(Rejected::OtherFigure),
+        Command::Merge { source, remote } => LedgerEvent::Merged { source, remote },
+        Command::Revise { event, note } => {
+            let previous = nkisi
+                .events
+                .iter()
+                .chain(&nkisi.trash)
+                .find(|e| e.id == event.id)
+                .ok_or(Rejected::Gone)?;
+            LedgerEvent::Revised { event, previous: Box::new(previous.clone()), note }
+        }
+        Command::Cancel { id, by, note } => {
+            let event = nkisi.events.iter().chain(&nkisi.trash).find(|e| e.id == id).ok_or(Rejected::Gone)?;
+            let mut tombstone = event.clone();
+            tombstone.provenance.push(by);
+            LedgerEvent::Cancelled { tombstone, note }
+        }
+        Command::Repair(repair) if !repair.needed(nkisi) => return Err(Rejected::Repaired),
+        Command::Repair(repair) => LedgerEvent::Repaired { repair },
+        Command::Undo => {
+            let target = journal.undo_target(nkisi.id)?;
+            let inverse = match &target.event {
+                LedgerEvent::Struck { event } => match live(vec![event.id]) {
+                    ids if ids.is_empty() => return Err(Rejected::Gone),
+                    _ => LedgerEvent::Retracted { id: event.id },
+                },
+                LedgerEvent::Trashed { ids } => match trashed(ids.clone()) {
+                    ids if ids.is_empty() => return Err(Rejected::NotInTrash),
+                    ids => LedgerEvent::Restored { ids },
+                },
+                LedgerEvent::Restored { ids } => match live(ids.clone()) {
+                    ids if ids.is_empty() => return Err(Rejected::Gone),
+                    ids => LedgerEvent::Trashed { ids },
+                },
+                LedgerEvent::Revised { event, previous, note } => LedgerEvent::Revised {
+                    event: (**previous).clone(),
+                    previous: Box::new(event.clone()),
+                    note: format!("undo of \"{note}\""),
+                },
+                _ => return Err(Rejected::NothingToUndo),
+            };
+            return Ok((inverse, Some(target.seq)));
+        }
+    };
+    Ok((event, None))
+}
+
+impl Entry {
+    pub fn stamp(&self) -> Stamp {
+        Stamp { at: self.at, replica: self.origin }
+    }
+
+    // One line for the history list
+    pub fn describe(&self) -> String {
+        let what = match &self.event {
+            LedgerEvent::Struck { event } => format!("spike by {}", event.performed_by),
+            LedgerEvent::Retracted { .. } => "spike retracted".into(),
+            LedgerEvent::Trashed { ids } => format!("{} moved to trash", count(ids.len())),
+            LedgerEvent::Restored { ids } => format!("{} restored", count(ids.len())),
+            LedgerEvent::TrashEmptied { ids } => format!("trash emptied ({})", count(ids.len())),
+            LedgerEvent::Archived { ids } => format!("{} archived", count(ids.len())),
+            LedgerEvent::Merged { source, .. } => format!("merged {source}"),
+            LedgerEvent::Revised { event, note, .. } => {
+                format!("spike by {} revised: {note}", event.performed_by)
+            }
+            LedgerEvent::Cancelled { tombstone, note } => format!("spike by {} {note}", tombstone.performed_by),
+            LedgerEvent::Repaired { repair } => format!("ledger check: {}", repair.describe()),
+        };
+        let undo = self.undoes.map_or(String::new(), |s| format!(" (undo #{s})"));
+        format!("#{} {} • {what}{undo}", self.seq, self.at.format("%Y-%m-%d %H:%M:%S"))
+    }
+}
+
+fn count(n: usize) -> String {
+    crate::confirm::count(n, "event")
+}
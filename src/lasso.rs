@@ -0,0 +1,93 @@
+// -------------------- Lasso selection --------------------
+// Picking many pins at once by drawing on the figure: a freehand lasso, or
+// a box dragged from one corner to the other. The pins inside become the
+// bulk selection that the bulk actions (tag, set outcome, export) apply to.
+// The stroke is kept in figure coordinates and only lives while the mouse
+// button is down.
+use std::fmt;
+use uuid::Uuid;
+
+use crate::regions;
+use crate::ActivationEvent;
+
+// Cursor travel (figure units at 100 wide) before a lasso gets a new point
+const MIN_STEP: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Lasso,
+    Box,
+}
+
+impl fmt::Display for Tool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Tool::Lasso => "Lasso",
+            Tool::Box => "Box",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Stroke {
+    pub tool: Tool,
+    points: Vec<(f32, f32)>,
+}
+
+impl Stroke {
+    pub fn new(tool: Tool, at: (f32, f32)) -> Self {
+        Self { tool, points: vec![at] }
+    }
+
+    // `k` scales MIN_STEP to the figure's coordinate space
+    pub fn extend(&mut self, p: (f32, f32), k: f32) {
+        match self.tool {
+            Tool::Box => {
+                self.points.truncate(1);
+                self.points.push(p);
+            }
+            Tool::Lasso => {
+                let last = self.points[self.points.len() - 1];
+                if (p.0 - last.0).hypot(p.1 - last.1) >= MIN_STEP * k {
+                    self.points.push(p);
+                }
+            }
+        }
+    }
+
+    // The closed area drawn so far
+    pub fn outline(&self) -> Vec<(f32, f32)> {
+        match (self.tool, self.points.as_slice()) {
+            (Tool::Box, [(x0, y0), .., (x1, y1)]) => vec![(*x0, *y0), (*x1, *y0), (*x1, *y1), (*x0, *y1)],
+            _ => self.points.clone(),
+        }
+    }
+
+    // Events whose pins fall inside, in ledger order
+    pub fn select(&self, events: &[ActivationEvent]) -> Vec<Uuid> {
+        let outline = self.outline();
+        events.iter().filter(|e| regions::polygon_contains(&outline, e.pos)).map(|e| e.id).collect()
+    }
+}
+
+// SVG fragment: the stroke being drawn and a ring around every selected pin
+pub fn render(stroke: Option<&Stroke>, selected: &[(f32, f32)], k: f32) -> String {
+    let mut s = String::new();
+    if let Some(stroke) = stroke {
+        let points: Vec<String> = stroke.outline().iter().map(|(x, y)| format!("{x:.2},{y:.2}")).collect();
+        s.push_str(&format!(
+            r##"<polygon points="{}" fill="#4dd2ff22" stroke="#4dd2ff" stroke-width="{:.2}" stroke-dasharray="{:.2}"/>"##,
+            points.join(" "),
+            0.4 * k,
+            1.5 * k
+        ));
+    }
+    if !selected.is_empty() {
+        s.push_str(&format!(r##"<g fill="none" stroke="#4dd2ff" stroke-width="{:.2}">"##, 0.6 * k));
+        for (x, y) in selected {
+            s.push_str(&format!(r#"<circle cx="{x:.2}" cy="{y:.2}" r="{:.2}"/>"#, 2.8 * k));
+        }
+        s.push_str("</g>");
+    }
+    s
+}
@@ -0,0 +1,271 @@
+// -------------------- Ingest latency --------------------
+// For every external spike we keep how long it sat between the acceptor
+// reading it and the ledger applying it, then until the first frame with
+// its pin was drawn, and — when the sender stamped TransactTime (60) — how
+// far its clock is from ours and the whole way from that stamp to the
+// pin on screen. Only the most recent samples are kept. The figures show
+// in the stats section and, when a metrics address is configured, are
+// served as Prometheus text on /metrics; the same address serves the event
+// feed (feed.rs) on /events/stream. Both want a token with the read scope
+// when any are configured (apiauth.rs).
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::apiauth::{self, ApiTokens, Denied, Scope};
+use crate::feed::{self, Feed};
+use crate::netacl::NetAcl;
+
+const WINDOW: usize = 2048;
+// Longest request head read, and the time it has to arrive in full
+const MAX_HEAD: u64 = 8 * 1024;
+const HEAD_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Default)]
+pub struct Latency {
+    // Receive-to-apply, milliseconds
+    apply: VecDeque<f64>,
+    // Our receive time minus the sender's tag 60, milliseconds; negative
+    // when the sender's clock runs ahead
+    skew: VecDeque<f64>,
+    // Apply-to-drawn, and tag 60 to drawn, milliseconds
+    render: VecDeque<f64>,
+    end_to_end: VecDeque<f64>,
+    // Applied spikes not drawn yet: when received and applied, and skew
+    undrawn: Vec<(Instant, Instant, Option<f64>)>,
+    // All-time count of spikes applied
+    pub total: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Summary {
+    pub count: usize,
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Latency {
+    // A spike applied now, read at `received`
+    pub fn record(&mut self, received: Instant, skew_ms: Option<f64>) {
+        let now = Instant::now();
+        push(&mut self.apply, ms(now - received));
+        if let Some(s) = skew_ms {
+            push(&mut self.skew, s);
+        }
+        // Nothing drawn for a while (no window): keep the newest only
+        if self.undrawn.len() == WINDOW {
+            self.undrawn.remove(0);
+        }
+        self.undrawn.push((received, now, skew_ms));
+        self.total += 1;
+    }
+
+    // Spikes applied and waiting for a frame with their pins
+    pub fn undrawn(&self) -> bool {
+        !self.undrawn.is_empty()
+    }
+
+    // A frame with every applied spike's pin was drawn at `now`
+    pub fn drawn(&mut self, now: Instant) {
+        for (received, applied, skew_ms) in std::mem::take(&mut self.undrawn) {
+            push(&mut self.render, ms(now - applied));
+            if let Some(s) = skew_ms {
+                push(&mut self.end_to_end, s + ms(now - received));
+            }
+        }
+    }
+
+    pub fn apply(&self) -> Option<Summary> {
+        summarize(&self.apply)
+    }
+
+    pub fn skew(&self) -> Option<Summary> {
+        summarize(&self.skew)
+    }
+
+    pub fn render(&self) -> Option<Summary> {
+        summarize(&self.render)
+    }
+
+    pub fn end_to_end(&self) -> Option<Summary> {
+        summarize(&self.end_to_end)
+    }
+
+    // Prometheus text exposition: one summary per measurement
+    pub fn metrics(&self) -> String {
+        let mut out = String::new();
+        out.push_str("# HELP nkisi_external_spikes_total External spikes applied to the ledger.\n");
+        out.push_str("# TYPE nkisi_external_spikes_total counter\n");
+        out.push_str(&format!("nkisi_external_spikes_total {}\n", self.total));
+        for (name, help, samples) in [
+            ("nkisi_ingest_apply_ms", "Receive-to-apply latency of external spikes (recent window).", &self.apply),
+            ("nkisi_ingest_skew_ms", "Receive time minus sender TransactTime (recent window).", &self.skew),
+            ("nkisi_ingest_render_ms", "Apply-to-drawn latency of external spikes (recent window).", &self.render),
+            ("nkisi_ingest_end_to_end_ms", "Sender TransactTime to pin drawn (recent window).", &self.end_to_end),
+        ] {
+            out.push_str(&format!("# HELP {name} {help}\n# TYPE {name} summary\n"));
+            if let Some(s) = summarize(samples) {
+                for (q, v) in [("0.5", s.p50), ("0.95", s.p95), ("0.99", s.p99), ("1", s.max)] {
+                    out.push_str(&format!("{name}{{quantile=\"{q}\"}} {v:.3}\n"));
+                }
+            }
+            out.push_str(&format!("{name}_sum {:.3}\n", samples.iter().sum::<f64>()));
+            out.push_str(&format!("{name}_count {}\n", samples.len()));
+        }
+        out
+    }
+}
+
+impl Summary {
+    pub fn line(&self) -> String {
+        format!(
+            "p50 {:.1} ms • p95 {:.1} ms • p99 {:.1} ms • max {:.1} ms (n={})",
+            self.p50, self.p95, self.p99, self.max, self.count
+        )
+    }
+}
+
+fn ms(d: std::time::Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+fn push(samples: &mut VecDeque<f64>, v: f64) {
+    if samples.len() == WINDOW {
+        samples.pop_front();
+    }
+    samples.push_back(v);
+}
+
+// Nearest-rank percentiles
+fn summarize(samples: &VecDeque<f64>) -> Option<Summary> {
+    if samples.is_empty() {
+        return None;
+    }
+    let mut sorted: Vec<f64> = samples.iter().copied().collect();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let rank = |p: f64| sorted[((p * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len()) - 1];
+    Some(Summary {
+        count: sorted.len(),
+        p50: rank(0.50),
+        p95: rank(0.95),
+        p99: rank(0.99),
+        max: sorted[sorted.len() - 1],
+    })
+}
+
+// Minimal HTTP endpoint: GET /events/stream gets the event feed, any other
+// GET the current metrics. Returns the address bound, None when the port
+// couldn't be had
+pub fn serve(
+    addr: &str,
+    latency: Arc<Mutex<Latency>>,
+    events: Arc<Feed>,
+    tokens: Arc<ApiTokens>,
+    acl: Arc<NetAcl>,
+) -> Option<SocketAddr> {
+    let listener = match TcpListener::bind(addr) {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("[metrics] bind {addr}: {e}; metrics disabled");
+            return None;
+        }
+    };
+    let bound = listener.local_addr().ok();
+    eprintln!("[metrics] serving http://{addr}/metrics and /events/stream");
+    if tokens.is_open() && !bound.is_some_and(|a| a.ip().is_loopback()) {
+        eprintln!("[metrics] no api_tokens configured: anyone who can reach {addr} can read metrics and events");
+    }
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            if !stream.peer_addr().is_ok_and(|peer| acl.check("metrics", peer)) {
+                continue;
+            }
+            // Each on its own thread, so a client slow to send its request
+            // holds up no one else's
+            let (latency, events, tokens) = (Arc::clone(&latency), Arc::clone(&events), Arc::clone(&tokens));
+            thread::spawn(move || answer(stream, &latency, &events, &tokens));
+        }
+    });
+    bound
+}
+
+fn answer(mut stream: TcpStream, latency: &Mutex<Latency>, events: &Arc<Feed>, tokens: &ApiTokens) {
+    let peer = stream.peer_addr().map_or("?".into(), |a| a.to_string());
+    let path = match read_head(&stream, tokens) {
+        Ok(path) => path,
+        Err(None) => {
+            eprintln!("[metrics] {peer}: no request head within {HEAD_TIMEOUT:?} and {MAX_HEAD} bytes");
+            let _ = write!(stream, "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+            return;
+        }
+        Err(Some((path, denied))) => {
+            let status = match denied {
+                Denied::Unauthorized => {
+                    eprintln!("[metrics] {peer}: {path} refused, no valid token");
+                    "401 Unauthorized\r\nWWW-Authenticate: Bearer"
+                }
+                Denied::Forbidden(name) => {
+                    eprintln!("[metrics] {peer}: {path} refused, token {name} lacks the read scope");
+                    "403 Forbidden"
+                }
+            };
+            let _ = write!(stream, "HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+            return;
+        }
+    };
+    if path == "/events/stream" {
+        feed::stream(stream, events);
+        return;
+    }
+    let body = latency.lock().map(|l| l.metrics()).unwrap_or_default();
+    let _ = write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+}
+
+// The path asked for, once the request head is read and the token it
+// presents (Authorization, else ?token=) allowed. A wrong Authorization is
+// refused as soon as it is read, and a head without a token as soon as it
+// ends or stops coming; Err(None) when the head runs past MAX_HEAD or
+// isn't all there within HEAD_TIMEOUT
+fn read_head(stream: &TcpStream, tokens: &ApiTokens) -> Result<String, Option<(String, Denied)>> {
+    let deadline = Instant::now() + HEAD_TIMEOUT;
+    stream.set_read_timeout(Some(HEAD_TIMEOUT)).map_err(|_| None)?;
+    let mut reader = BufReader::new(Read::take(stream, MAX_HEAD));
+    let mut line = || {
+        let mut line = String::new();
+        let read = reader.read_line(&mut line).ok()?;
+        // Cut short by the cap or the connection closing
+        (read > 0 && line.ends_with('\n') && Instant::now() < deadline).then_some(line)
+    };
+    let request = line().ok_or(None)?;
+    let target = request.split_whitespace().nth(1).unwrap_or("/");
+    let allowed = |authorization: Option<&str>| {
+        let (path, token) = apiauth::presented(target, authorization);
+        tokens.check(token, Scope::Read).map(|_| path.to_string()).map_err(|denied| Some((path.to_string(), denied)))
+    };
+    let mut authorization = None;
+    loop {
+        let Some(header) = line() else {
+            allowed(authorization.as_deref())?;
+            return Err(None);
+        };
+        if header.trim_end().is_empty() {
+            return allowed(authorization.as_deref());
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("authorization") {
+                allowed(Some(value.trim()))?;
+                authorization = Some(value.trim().to_string());
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+// -------------------- Base SVG layers --------------------
+// Top-level <g> elements of the figure SVG are treated as layers that can
+// be hidden individually. Hidden layers are cut out of the source text and
+// the remainder is handed to the SVG widget.
+use std::ops::Range;
+
+use crate::units;
+
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub name: String,
+    pub visible: bool,
+    range: Range<usize>, // byte range of the <g> element in the source
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LayeredSvg {
+    pub source: String,
+    pub layers: Vec<Layer>,
+    pub view_box: Option<(f32, f32)>, // width/height of the viewBox
+    pub printed: Option<(f32, f32)>,  // width/height in mm, from its width and height
+}
+
+impl LayeredSvg {
+    // Layers are named from id, inkscape:label, the comment just before
+    // the group, or their position, in that order.
+    pub fn parse(source: String) -> Result<Self, String> {
+        let (layers, view_box, printed) = {
+            let doc = roxmltree::Document::parse(&source).map_err(|e| e.to_string())?;
+            let root = doc.root_element();
+            let printed = units::printed(root.attribute("width"), root.attribute("height"));
+            let view_box = doc.root_element().attribute("viewBox").and_then(|vb| {
+                let nums: Vec<f32> = vb
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter_map(|n| n.parse().ok())
+                    .collect();
+                match nums[..] {
+                    [_, _, w, h] if w > 0.0 && h > 0.0 => Some((w, h)),
+                    _ => None,
+                }
+            });
+            let mut layers = vec![];
+            let mut last_comment: Option<String> = None;
+            for node in doc.root_element().children() {
+                if let Some(c) = node.is_comment().then(|| node.text()).flatten() {
+                    last_comment = Some(c.trim().to_string());
+                    continue;
+                }
+                if !node.is_element() {
+                    continue;
+                }
+                if node.tag_name().name() == "g" {
+                    let label = node.attributes().find(|a| a.name() == "label").map(|a| a.value());
+                    let name = node
+                        .attribute("id")
+                        .or(label)
+                        .map(str::to_string)
+                        .or_else(|| last_comment.clone())
+                        .unwrap_or_else(|| format!("Layer {}", layers.len() + 1));
+                    layers.push(Layer { name, visible: true, range: node.range() });
+                }
+                last_comment = None;
+            }
+            (layers, view_box, printed)
+        };
+        Ok(Self { source, layers, view_box, printed })
+    }
+
+    pub fn has_hidden(&self) -> bool {
+        self.layers.iter().any(|l| !l.visible)
+    }
+
+    // Source with hidden layers removed
+    pub fn render(&self) -> String {
+        let mut out = self.source.clone();
+        let mut hidden: Vec<&Range<usize>> =
+            self.layers.iter().filter(|l| !l.visible).map(|l| &l.range).collect();
+        hidden.sort_by_key(|r| std::cmp::Reverse(r.start));
+        for r in hidden {
+            out.replace_range(r.clone(), "");
+        }
+        out
+    }
+
+    // Keep visibility choices for layers that still exist after a reload
+    pub fn carry_visibility(&mut self, previous: &LayeredSvg) {
+        for layer in &mut self.layers {
+            if let Some(old) = previous.layers.iter().find(|l| l.name == layer.name) {
+                layer.visible = old.visible;
+            }
+        }
+    }
+}
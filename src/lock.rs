@@ -0,0 +1,128 @@
+// -------------------- Ledger file locking --------------------
+// Advisory `<ledger>.lock` file naming the instance (pid + hostname) that
+// is editing the ledger. A second instance sees the lock and falls back to
+// read-only unless the user takes over. Locks of dead processes on this
+// host are treated as stale and reclaimed.
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use crate::IoError;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockInfo {
+    pub pid: u32,
+    pub hostname: String,
+    pub since: DateTime<Utc>,
+}
+
+impl LockInfo {
+    fn ours() -> Self {
+        Self { pid: std::process::id(), hostname: hostname(), since: Utc::now() }
+    }
+
+    fn is_ours(&self) -> bool {
+        self.pid == std::process::id() && self.hostname == hostname()
+    }
+
+    // Only processes on this host can be checked; remote holders are
+    // assumed alive
+    fn is_stale(&self) -> bool {
+        self.hostname == hostname()
+            && cfg!(target_os = "linux")
+            && !std::path::Path::new(&format!("/proc/{}", self.pid)).exists()
+    }
+}
+
+impl std::fmt::Display for LockInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "pid {} on {} since {}",
+            self.pid,
+            self.hostname,
+            self.since.format("%Y-%m-%d %H:%M")
+        )
+    }
+}
+
+pub enum Acquire {
+    Acquired(LedgerLock),
+    Held(LockInfo),
+}
+
+// Held lock; removed again on drop if it is still ours
+#[derive(Debug)]
+pub struct LedgerLock {
+    pub ledger: String,
+    lock_path: String,
+}
+
+pub fn lock_path(ledger: &str) -> String {
+    format!("{ledger}.lock")
+}
+
+pub fn acquire(ledger: &str) -> Result<Acquire, IoError> {
+    let path = lock_path(ledger);
+    match read(&path) {
+        Some(info) if info.is_ours() || info.is_stale() => write(&path, true)?,
+        Some(info) => return Ok(Acquire::Held(info)),
+        None => write(&path, false)?,
+    }
+    Ok(Acquire::Acquired(LedgerLock { ledger: ledger.to_string(), lock_path: path }))
+}
+
+// Replace another instance's lock with ours
+pub fn take_over(ledger: &str) -> Result<LedgerLock, IoError> {
+    let path = lock_path(ledger);
+    write(&path, true)?;
+    Ok(LedgerLock { ledger: ledger.to_string(), lock_path: path })
+}
+
+impl LedgerLock {
+    // Another instance may have taken over since we acquired the lock
+    pub fn check(&self) -> Result<(), LockInfo> {
+        match read(&self.lock_path) {
+            Some(info) if !info.is_ours() => Err(info),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Drop for LedgerLock {
+    fn drop(&mut self) {
+        if self.check().is_ok() {
+            let _ = std::fs::remove_file(&self.lock_path);
+        }
+    }
+}
+
+fn read(path: &str) -> Option<LockInfo> {
+    let bytes = std::fs::read(path).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+fn write(path: &str, replace: bool) -> Result<(), IoError> {
+    let bytes = serde_json::to_vec(&LockInfo::ours()).map_err(|e| IoError::Write(e.to_string()))?;
+    let mut opts = OpenOptions::new();
+    opts.write(true);
+    if replace {
+        opts.create(true).truncate(true);
+    } else {
+        // Fails if another instance created the lock in the meantime
+        opts.create_new(true);
+    }
+    let mut f = opts.open(path).map_err(|e| IoError::Write(format!("{path}: {e}")))?;
+    f.write_all(&bytes).map_err(|e| IoError::Write(e.to_string()))
+}
+
+fn hostname() -> String {
+    std::fs::read_to_string("/etc/hostname")
+        .ok()
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .unwrap_or_else(|| "unknown-host".into())
+}
@@ -1,49 +1,242 @@
 use chrono::{DateTime, Utc};
-use crossbeam_channel::{unbounded, Receiver, Sender};
-use iced::{alignment, time};
-use iced::widget::{button, column, container, row, svg, text_input, toggler, Svg};
-use iced::{application, Color, Element, Length, Point, Theme, Renderer, Subscription};
+use crossbeam_channel::Receiver;
+use iced::{alignment, keyboard, mouse, time};
+use iced::widget::{
+    button, column, container, pick_list, row, scrollable, svg, text_input, toggler, Svg,
+};
+use iced::{application, window, Color, Element, Length, Point, Theme, Renderer, Size, Subscription};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::io::{Read};
-use std::net::{TcpListener, TcpStream};
-use std::thread;
-use std::time::Duration;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 use uuid::Uuid;
 
+mod acceptor;
+mod apiauth;
+mod archive;
+mod aggregate;
+mod batch;
+mod charge;
+mod clear;
+mod compare;
+mod compat;
+mod config;
+mod crdt;
+mod diagnose;
+mod dropcopy;
+mod confirm;
+mod export;
+mod feed;
+mod fields;
+mod figure;
+mod fix;
+mod fixauth;
+mod fixdict;
+mod fixml;
+mod fixqueue;
+mod fixstore;
+mod forks;
+mod fulltext;
+mod health;
+mod help;
+mod hook;
+mod initiator;
+mod inspector;
+mod journal;
+mod lasso;
+mod latency;
+mod layers;
+mod lock;
+mod netacl;
+mod outbox;
+mod overlay;
+mod pack;
+mod palette;
+mod pdf;
+mod proxy;
+mod quarantine;
+mod reach;
+mod reclassify;
+mod regions;
+mod report;
+mod rules;
+mod sandbox;
+mod search;
+mod session;
+mod sessions;
+mod start;
+mod strikers;
+mod template;
+mod tls;
+mod tour;
+mod transform;
+mod units;
+mod usage;
+mod views;
+mod webhook;
+mod workspace;
+
+use confirm::Destructive;
+use journal::{Command, LedgerEvent};
+use workspace::{FigureRef, Workspace};
+
 // ===== Figure coordinate system (must match assets/nkisi.svg viewBox) =====
 const FIGURE_W: f32 = 100.0;
 const FIGURE_H: f32 = 150.0;
 
-// On-screen size for the figure
+// On-screen width for the figure (height follows its aspect ratio)
 const SCREEN_W: f32 = 360.0;
-const SCREEN_H: f32 = SCREEN_W * (FIGURE_H / FIGURE_W);
+
+// Below this window width the controls stack under the figure and the
+// pending-spike form docks to the bottom of the window as a sheet.
+const COMPACT_BREAKPOINT: f32 = 760.0;
+// Interface zoom limits and the step of one Ctrl+/Ctrl- press
+const UI_SCALE_MIN: f32 = 0.5;
+const UI_SCALE_MAX: f32 = 3.0;
+const UI_SCALE_STEP: f32 = 0.1;
 
 // FIX constants
-const SOH: u8 = 0x01;
+use fix::SOH;
 const FIX_ADDR: &str = "0.0.0.0:9898";
+// Messages waiting for the ledger, and how long a connection waits for room
+// before its spikes are refused
+const FIX_QUEUE: usize = 1024;
+const FIX_QUEUE_WAIT: Duration = Duration::from_secs(2);
 
 // -------------------- Domain --------------------
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NkisiNkondi {
+    #[serde(default)]
+    pub format_version: u32, // 0 = written before versioning
+    #[serde(default)]
+    pub saved_by: Option<String>, // app version that wrote the file
     pub id: Uuid,
     pub culture: String,
     pub events: Vec<ActivationEvent>,
-    pub pins: Vec<(f32, f32)>, // SVG-space coords (0..FIGURE_W/H)
+    pub pins: Vec<(f32, f32)>, // figure-space coords (SVG viewBox or image pixels)
+    #[serde(default)]
+    pub trash: Vec<ActivationEvent>, // deleted events, kept until emptied
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cancelled: Vec<ActivationEvent>, // tombstones of spikes their senders cancelled
+    #[serde(default)]
+    pub journal_seq: u64, // last journal entry included in this snapshot
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub status: BTreeMap<Uuid, crdt::Status>, // stamped presence, for merging copies
 }
 
 impl NkisiNkondi {
     fn new(culture: impl Into<String>) -> Self {
         Self {
+            format_version: compat::FORMAT_VERSION,
+            saved_by: Some(compat::APP_VERSION.into()),
             id: Uuid::new_v4(),
             culture: culture.into(),
             events: vec![],
             pins: vec![],
+            trash: vec![],
+            cancelled: vec![],
+            journal_seq: 0,
+            status: BTreeMap::new(),
+        }
+    }
+
+    // The ledger is a projection of its journal: every change arrives here
+    // as a recorded event, stamped for merging. Applying is idempotent, so
+    // replaying entries a snapshot already contains changes nothing.
+    fn apply(&mut self, event: &LedgerEvent, stamp: crdt::Stamp) {
+        use crdt::Presence;
+        match event {
+            LedgerEvent::Struck { event } => {
+                if !self.knows(event.id) {
+                    self.pins.push(event.pos);
+                    self.events.push(event.clone());
+                }
+            }
+            LedgerEvent::Retracted { id } => {
+                self.take_event(*id);
+                self.set_status(*id, Presence::Gone, stamp);
+            }
+            LedgerEvent::Trashed { ids } => {
+                for id in ids {
+                    if let Some(ev) = self.take_event(*id) {
+                        self.trash.push(ev);
+                    }
+                    self.set_status(*id, Presence::Trashed, stamp);
+                }
+            }
+            LedgerEvent::Restored { ids } => {
+                for id in ids {
+                    // Put a trashed event back into the ledger in date order, its pin
+                    // at the same index so pins[i] still stands for events[i]
+                    if let Some(idx) = self.trash.iter().position(|e| e.id == *id) {
+                        let ev = self.trash.remove(idx);
+                        let at = self.events.partition_point(|e| e.date <= ev.date);
+                        self.pins.insert(at.min(self.pins.len()), ev.pos);
+                        self.events.insert(at, ev);
+                    }
+                    self.set_status(*id, Presence::Live, stamp);
+                }
+            }
+            LedgerEvent::TrashEmptied { ids } => {
+                self.trash.retain(|e| !ids.contains(&e.id));
+                for id in ids {
+                    self.set_status(*id, Presence::Gone, stamp);
+                }
+            }
+            LedgerEvent::Archived { ids } => {
+                for id in ids {
+                    self.take_event(*id);
+                    self.set_status(*id, Presence::Gone, stamp);
+                }
+                self.events.shrink_to_fit();
+                self.pins.shrink_to_fit();
+            }
+            LedgerEvent::Merged { source, remote } => {
+                let known: HashSet<Uuid> =
+                    self.events.iter().chain(&self.trash).map(|e| e.id).collect();
+                *self = crdt::merge(self, remote).ledger;
+                let hop = Hop { via: "merge".into(), source: source.clone(), at: stamp.at, listener: None };
+                for ev in self.events.iter_mut().chain(self.trash.iter_mut()) {
+                    if !known.contains(&ev.id) {
+                        ev.provenance.push(hop.clone());
+                    }
+                }
+            }
+            LedgerEvent::Revised { event, .. } => {
+                let revised = ActivationEvent { revised: Some(stamp), ..event.clone() };
+                if let Some(ev) = self.events.iter_mut().find(|e| e.id == event.id) {
+                    if let Some(pin) = self.pins.iter_mut().find(|p| **p == ev.pos) {
+                        *pin = revised.pos;
+                    }
+                    *ev = revised;
+                } else if let Some(ev) = self.trash.iter_mut().find(|e| e.id == event.id) {
+                    *ev = revised;
+                }
+            }
+            LedgerEvent::Cancelled { tombstone, .. } => {
+                let id = tombstone.id;
+                if self.take_event(id).is_none() {
+                    self.trash.retain(|e| e.id != id);
+                }
+                if !self.cancelled.iter().any(|e| e.id == id) {
+                    self.cancelled.push(tombstone.clone());
+                }
+                self.set_status(id, Presence::Gone, stamp);
+            }
+            LedgerEvent::Repaired { repair } => repair.apply(self),
         }
     }
-    fn intensity(&self) -> u32 {
-        self.events.len() as u32 + 3
+
+    // Remove an event and its pin from the ledger
+    fn take_event(&mut self, id: Uuid) -> Option<ActivationEvent> {
+        let idx = self.events.iter().position(|e| e.id == id)?;
+        let ev = self.events.remove(idx);
+        if let Some(pin) = self.pins.iter().position(|&p| p == ev.pos) {
+            self.pins.remove(pin);
+        }
+        Some(ev)
     }
 }
 impl Default for NkisiNkondi {
@@ -61,6 +254,82 @@ pub struct ActivationEvent {
     pub outcome: Outcome,
     pub notes: Option<String>,         // message
     pub pos: (f32, f32),               // SVG coords
+    #[serde(default, skip_serializing_if = "EventMeta::is_empty")]
+    pub meta: EventMeta,               // extra FIX-sourced fields
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revised: Option<crdt::Stamp>,  // last edit after the strike
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub provenance: Vec<Hop>,          // how it reached this ledger, oldest first
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom: BTreeMap<String, fields::FieldValue>, // workspace-defined fields
+}
+
+// One step of an event's journey into this ledger
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hop {
+    pub via: String,       // "fix", "pack", "merge" or "quarantine"
+    pub source: String,    // file, figure or party it came from
+    pub at: DateTime<Utc>, // when this ledger took it in
+    // FIX acceptor it came in on, by name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub listener: Option<String>,
+}
+
+// Optional details an external submitter can attach (see parse_fix_spikes)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub case_ref: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub institution: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub witnesses: Vec<String>,
+    // Free labels added in bulk from a lasso selection
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl EventMeta {
+    fn is_empty(&self) -> bool {
+        self.fields().is_empty()
+    }
+
+    // Labelled, present fields in display order
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut out: Vec<_> = [
+            ("case", &self.case_ref),
+            ("institution", &self.institution),
+            ("category", &self.category),
+        ]
+        .into_iter()
+        .filter_map(|(label, v)| v.clone().map(|v| (label, v)))
+        .collect();
+        if !self.witnesses.is_empty() {
+            out.push(("witness", self.witnesses.join(", ")));
+        }
+        if !self.tags.is_empty() {
+            out.push(("tags", self.tags.join(", ")));
+        }
+        out
+    }
+}
+
+impl ActivationEvent {
+    // Text the searches look at: striker, notes and metadata values
+    pub fn search_text(&self) -> String {
+        let mut s = format!("{} {}", self.performed_by, self.notes.as_deref().unwrap_or(""));
+        for (_, v) in self.meta.fields() {
+            s.push(' ');
+            s.push_str(&v);
+        }
+        for v in self.custom.values() {
+            s.push(' ');
+            s.push_str(&v.to_string());
+        }
+        s
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -68,7 +337,7 @@ pub enum ActivationPurpose {
     Other(String),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Outcome {
     Pending,
     Resolved,
@@ -81,39 +350,384 @@ struct State {
     status: String,
 
     // Global toggles/paths
-    show_grid: bool,
+    overlay: overlay::OverlayOptions,
+    active_profile: Option<String>,
+    profile_name_input: String,
     save_path: String,
     svg_path: String,
 
+    // Parsed base SVG, for per-layer visibility (None for photos)
+    base_svg: Option<layers::LayeredSvg>,
+    // Figure-space size: SVG viewBox, or pixel size of a photo
+    figure_dims: (f32, f32),
+    // Modification time of the base figure when last read; polled so edits
+    // to the artwork show up without re-typing the path
+    figure_mtime: Option<std::time::SystemTime>,
+
     // Mouse tracking (local to mouse_area)
     last_cursor: Option<Point>,
+    // Part of the figure on screen, the named view it came from, and where
+    // a right-button pan last was
+    viewport: views::Viewport,
+    active_view: Option<String>,
+    // Before/after split view, when on
+    compare: Option<compare::Split>,
+    view_name_input: String,
+    panning: Option<Point>,
+
+    // Window width, used to pick the regular or compact layout
+    window_width: f32,
+
+    // Onboarding tour: index of the step being shown
+    tour_step: Option<usize>,
+    // Launched without a ledger: the start screen shows until one is picked
+    start_screen: bool,
+    start_path: String,
+    recent: start::Recent,
+    // Help overlay, open on this topic
+    help: Option<help::Topic>,
+    // Tags the acceptor reads, for the FIX help
+    fix_dict: Arc<fixdict::FixDictionary>,
+
+    // Archive: age cutoff (days) and on-demand search of the archive file
+    archive_days_input: String,
+    archive_query: String,
+    archive_results: Vec<ActivationEvent>,
+
+    // Workspace (.nkisiproj): figures, settings, ingest config. The
+    // active figure's ledger/SVG are mirrored in save_path/svg_path.
+    workspace: Workspace,
+    workspace_path: String,
+    figure_name_input: String,
+    figure_ledger_input: String,
+
+    // Region editor: Some while drawing (clicks add vertices)
+    region_draft: Option<Vec<(f32, f32)>>,
+    region_name_input: String,
+
+    // Global search across the workspace's ledger files
+    global_query: String,
+    global_hits: Vec<search::Hit>,
+    global_index: search::GlobalIndex,
+
+    // Full-text index over the open ledger's strikers and notes
+    note_index: fulltext::NoteIndex,
+
+    // Append-only record of every change to the open ledger
+    journal: journal::Journal,
+    // Another party's copy of the open figure, to merge in
+    merge_path: String,
+    // Events the last merge found edited differently on each side
+    conflicts: Vec<crdt::Conflict>,
+    // Figures of the workspace that are copies of one ledger
+    forks: Vec<forks::Fork>,
+
+    // Spike packs: file to export to / import from, and the shared key
+    // packs are signed with (from config, never saved in the workspace)
+    pack_path: String,
+    // Reports: language and output path without extension
+    report_locale: String,
+    report_path: String,
+    // User report template (Jinja subset, see template.rs)
+    report_template: String,
+    // Directory "Export all" writes into
+    batch_dir: String,
+    // Privacy budget for aggregate exports; empty for exact counts
+    aggregate_epsilon: String,
+
+    // PNG export
+    png_path: String,
+    png_dpi_input: String,
+    png_margin_input: String,
+    png_transparent: bool,
+    pack_key: Option<String>,
+
+    // Event picked from search results, ringed on the figure
+    selected_event: Option<Uuid>,
+    // The selected pin while it is being dragged, and where to
+    dragging: Option<(Uuid, (f32, f32))>,
+    // Corrected coordinates typed for the selected pin
+    move_x_input: String,
+    move_y_input: String,
+
+    // Area selection: the tool in use (None: clicks place spikes), the
+    // stroke while the button is down, and the pins it picked
+    select_tool: Option<lasso::Tool>,
+    stroke: Option<lasso::Stroke>,
+    bulk: Vec<Uuid>,
+    bulk_tag_input: String,
+    // Reclassify: where, what, its preview, and the last round applied (the
+    // ledger, what it did and the journal entries it made, for its undo)
+    reclass_scope: Option<reclassify::Scope>,
+    reclass_kind: reclassify::Kind,
+    reclass_input: String,
+    reclass_preview: Option<reclassify::Preview>,
+    reclassified: Option<(Uuid, String, Vec<u64>)>,
+    // Clearing part of the ledger: the panel, its scope and what it takes
+    show_clear: bool,
+    clear_scope: clear::Scope,
+    clear_preview: Option<Result<clear::Preview, String>>,
+
+    // Set when the open ledger must not be written, and why
+    read_only: Option<ReadOnly>,
+    // Advisory lock on save_path while we may write it
+    ledger_lock: Option<lock::LedgerLock>,
+    // What's new since the last version that ran here
+    release_notes: Option<Vec<(&'static str, &'static [&'static str])>>,
+
+    // Destructive action waiting for the confirmation dialog
+    confirm: Option<Destructive>,
 
     // Confirmation UI state (for local clicks)
     pending_pos: Option<(f32, f32)>,
+    // What-if mode: the real ledger and journal set aside while changes go
+    // to a copy
+    sandbox: Option<sandbox::Sandbox>,
+    // What the last ledger check found, while its list is open
+    health: Option<Vec<health::Anomaly>>,
     striker_input: String,
     message_input: String,
+    // Comma-separated witnesses
+    witness_input: String,
+    // Raw inputs for the workspace's custom fields, by key
+    custom_inputs: BTreeMap<String, String>,
+
+    // Add-field form for custom field definitions
+    field_label_input: String,
+    field_kind: fields::KindChoice,
+    field_options_input: String,
 
-    // FIX: channel to receive spikes from acceptor thread
-    fix_rx: Receiver<ExternalSpike>,
+    // FIX: queue of spikes from the acceptor threads, one message (one or
+    // more spikes) at a time, and those that arrived while the ledger took
+    // no changes
+    fix_rx: fixqueue::Queue,
+    fix_held: Vec<Vec<ExternalSpike>>,
+    // Mirror of every applied event, when a drop-copy endpoint is set
+    drop_copy: Option<dropcopy::DropCopy>,
+    // Consumers that connected for a copy of every event, when listening
+    drop_copy_pub: Option<Arc<dropcopy::Publisher>>,
+    // New events for the HTTP stream consumers and the webhook
+    feed: Arc<feed::Feed>,
+    webhook: Option<webhook::Webhook>,
+    // Outbound FIX session that spikes confirmed here are published on
+    upstream: Option<initiator::Initiator>,
+    // What the three above still have to deliver, and what they gave up on;
+    // whether the Outbox panel is open
+    outbox: Arc<outbox::Outbox>,
+    show_outbox: bool,
+    // Receive-to-apply latency and sender clock skew of external spikes;
+    // shared with the metrics endpoint
+    latency: Arc<Mutex<latency::Latency>>,
+    // Malformed FIX frames the acceptor dropped; shared with it
+    fix_dropped: Arc<AtomicU64>,
+    // Messages slowed by the rate limit, and spikes refused on a full queue
+    fix_throttled: Arc<AtomicU64>,
+    fix_overflowed: Arc<AtomicU64>,
+    fix_crowded_out: Arc<AtomicU64>,
+    // Connections, logons and spikes refused by the FIX access list
+    fix_refused: Arc<AtomicU64>,
+    // Connections any listener turned away by address (netacl.rs)
+    net_acl: Arc<netacl::NetAcl>,
+    // Open FIX connections, and whether the Sessions panel lists them
+    fix_sessions: Arc<sessions::Sessions>,
+    show_sessions: bool,
+    // Last raw FIX messages, and the one whose tags are listed
+    fix_inspector: Arc<inspector::Inspector>,
+    show_inspector: bool,
+    inspected: Option<u64>,
+    // FIX spikes outside the figure or refused, waiting for an operator,
+    // and the one being fixed
+    quarantine: quarantine::Quarantine,
+    quarantine_fix: Option<quarantine::Fix>,
+    // Who may send spikes; replays are held to it too
+    fix_auth: Arc<fixauth::FixAuth>,
+    // Raw FIX messages kept on disk, the days in it and the one to replay
+    fix_store: Option<Arc<fixstore::Store>>,
+    fix_days: Vec<String>,
+    replay_day: Option<String>,
+    // Last self-test report, and the startup settings it checks
+    self_test: Option<Vec<diagnose::Check>>,
+    self_test_setup: diagnose::Setup,
+    // The FIX listener while it runs, what it is started with (or why it
+    // can't be) and the address typed for it
+    acceptor: Option<acceptor::Acceptor>,
+    fix_wiring: Result<acceptor::Wiring, String>,
+    fix_addr_input: String,
+    // Intensity now and its curve (see charge.rs), the half-life being
+    // typed and whether the curve is shown
+    intensity: charge::Reading,
+    half_life_input: String,
+    show_intensity_curve: bool,
+    // The workspace's named listeners that are running
+    listeners: Vec<acceptor::Acceptor>,
+    // Run after each save, with the file saved
+    save_hook: Option<hook::Hook>,
+    // Local usage counts, and whether the Usage panel is open
+    usage: usage::Usage,
+    show_usage: bool,
+    // Striker leaderboard panel
+    show_strikers: bool,
+    // Addresses of this machine, where the metrics endpoint is bound, and
+    // the test spike under way
+    interfaces: Vec<reach::Interface>,
+    metrics_bound: Option<std::net::SocketAddr>,
+    test_spike: Option<Receiver<String>>,
+    // For `update_and_copy` to hand to the clipboard
+    to_clipboard: Option<String>,
 }
 
 impl State {
-    fn new(fix_rx: Receiver<ExternalSpike>) -> Self {
-        Self {
+    fn new(
+        fix_rx: fixqueue::Queue,
+        workspace: Workspace,
+        workspace_path: Option<String>,
+    ) -> Self {
+        let figure = workspace
+            .active_figure()
+            .cloned()
+            .unwrap_or_else(|| Workspace::default().figures[0].clone());
+        let mut state = Self {
             nkisi: NkisiNkondi::new("Kongo peoples"),
-            status: format!("Ready. FIX acceptor on {}", FIX_ADDR),
-            show_grid: false,
-            save_path: "nkisi_state.json".into(),
-            svg_path: "assets/nkisi.svg".into(),
+            status: format!("Ready. FIX acceptor on {}", workspace.ingest.fix_addr),
+            overlay: workspace.settings.overlay,
+            active_profile: None,
+            profile_name_input: String::new(),
+            viewport: views::Viewport::default(),
+            active_view: None,
+            compare: None,
+            view_name_input: String::new(),
+            panning: None,
+            save_path: figure.ledger.clone(),
+            base_svg: None,
+            figure_dims: figure::default_dims(),
+            figure_mtime: None,
+            svg_path: figure.svg,
             last_cursor: None,
+            window_width: 1024.0,
+            tour_step: tour::first_run().then_some(0),
+            start_screen: false,
+            start_path: String::new(),
+            recent: start::Recent::default(),
+            help: None,
+            fix_dict: Arc::default(),
+            archive_days_input: "365".into(),
+            archive_query: String::new(),
+            archive_results: vec![],
+            workspace,
+            workspace_path: workspace_path
+                .unwrap_or_else(|| format!("workspace.{}", workspace::EXTENSION)),
+            figure_name_input: String::new(),
+            figure_ledger_input: String::new(),
+            region_draft: None,
+            region_name_input: String::new(),
+            global_query: String::new(),
+            global_hits: vec![],
+            global_index: search::GlobalIndex::default(),
+            note_index: fulltext::NoteIndex::new().expect("in-memory note index"),
+            journal: journal::Journal::empty(&figure.ledger),
+            merge_path: String::new(),
+            conflicts: vec![],
+            forks: vec![],
+            pack_path: format!("selection.{}", pack::EXTENSION),
+            report_locale: report::system_tag(),
+            report_path: "report".into(),
+            report_template: report::DEFAULT_TEMPLATE.into(),
+            batch_dir: "exports".into(),
+            aggregate_epsilon: "1.0".into(),
+            png_path: "figure.png".into(),
+            png_dpi_input: "300".into(),
+            png_margin_input: "5".into(),
+            png_transparent: false,
+            pack_key: None,
+            selected_event: None,
+            dragging: None,
+            move_x_input: String::new(),
+            move_y_input: String::new(),
+            select_tool: None,
+            stroke: None,
+            bulk: vec![],
+            reclass_scope: None,
+            reclass_kind: reclassify::Kind::default(),
+            reclass_input: String::new(),
+            reclass_preview: None,
+            reclassified: None,
+            show_clear: false,
+            clear_scope: clear::Scope::default(),
+            clear_preview: None,
+            bulk_tag_input: String::new(),
+            read_only: None,
+            ledger_lock: None,
+            release_notes: compat::pending_release_notes(),
+            confirm: None,
             pending_pos: None,
+            sandbox: None,
+            health: None,
             striker_input: String::new(),
             message_input: String::new(),
+            witness_input: String::new(),
+            custom_inputs: BTreeMap::new(),
+            field_label_input: String::new(),
+            field_kind: fields::KindChoice::Text,
+            field_options_input: String::new(),
             fix_rx,
-        }
+            fix_held: vec![],
+            drop_copy: None,
+            drop_copy_pub: None,
+            feed: Arc::default(),
+            webhook: None,
+            upstream: None,
+            outbox: Arc::default(),
+            show_outbox: false,
+            latency: Arc::default(),
+            fix_dropped: Arc::default(),
+            fix_throttled: Arc::default(),
+            fix_overflowed: Arc::default(),
+            fix_crowded_out: Arc::default(),
+            fix_refused: Arc::default(),
+            net_acl: Arc::default(),
+            fix_sessions: Arc::default(),
+            show_sessions: false,
+            fix_inspector: Arc::default(),
+            show_inspector: false,
+            inspected: None,
+            quarantine: quarantine::Quarantine::default(),
+            quarantine_fix: None,
+            fix_auth: Arc::default(),
+            fix_store: None,
+            fix_days: vec![],
+            replay_day: None,
+            self_test: None,
+            self_test_setup: diagnose::Setup::default(),
+            acceptor: None,
+            listeners: vec![],
+            save_hook: None,
+            usage: usage::Usage::default(),
+            show_usage: false,
+            show_strikers: false,
+            fix_wiring: Err("not set up".into()),
+            fix_addr_input: String::new(),
+            intensity: charge::Reading::default(),
+            half_life_input: String::new(),
+            show_intensity_curve: false,
+            interfaces: reach::interfaces(),
+            metrics_bound: None,
+            test_spike: None,
+            to_clipboard: None,
+        };
+        state.fix_addr_input = state.workspace.ingest.fix_addr.clone();
+        state.half_life_input = half_life_text(state.workspace.settings.half_life_days);
+        reload_base_svg(&mut state);
+        state
     }
 }
 
+enum ReadOnly {
+    // Saved by a newer data format than this build writes
+    NewerFormat,
+    // Another instance holds the ledger's lock file
+    Locked(lock::LockInfo),
+}
+
 // -------------------- Messages --------------------
 #[derive(Debug, Clone)]
 enum Message {
@@ -124,72 +738,499 @@ enum Message {
     CancelSpike,
     Save,
     Load,
-    ClearAll,
-    ToggleGrid(bool),
+    LoadSample,
+    StartPathChanged(String),
+    StartOpen(String),
+    StartNew,
+    StartImport,
+    ShowClear(bool),
+    ClearScopeEdited(clear::Edit),
+    PreviewClear,
+    ClearScoped,
+    DeleteEvent(Uuid),
+    RestoreEvent(Uuid),
+    EmptyTrash,
+    Undo,
+    MergePathChanged(String),
+    MergeLedger,
+    ResolveConflict(usize, crdt::Resolution),
+    ResolveFork(usize, forks::Choice),
+    SelectEvent(Option<Uuid>),
+    // Step through events in time order: true forward, false back
+    StepEvent(bool),
+    FigureReleased,
+    MoveXChanged(String),
+    MoveYChanged(String),
+    MovePin,
+    SetSelectTool(Option<lasso::Tool>),
+    // A purpose or tag for every event in a region or the selection,
+    // previewed, applied, and undone as one
+    ReclassifyScope(reclassify::Scope),
+    ReclassifyKind(reclassify::Kind),
+    ReclassifyInput(String),
+    PreviewReclassify,
+    ApplyReclassify,
+    UndoReclassify,
+    ClearBulk,
+    BulkTagChanged(String),
+    BulkTag,
+    BulkOutcome(Outcome),
+    BulkExport,
+    PackPathChanged(String),
+    ExportPack,
+    ReportLocaleChanged(String),
+    ReportPathChanged(String),
+    ExportCsv,
+    ExportReport,
+    ReportTemplateChanged(String),
+    ExportTemplate,
+    BatchDirChanged(String),
+    ExportAll,
+    AggregateEpsilonChanged(String),
+    ExportAggregate,
+    // Per-striker summary: the leaderboard, and its CSV and Markdown export
+    ShowStrikers(bool),
+    ExportStrikers,
+    PngPathChanged(String),
+    PngDpiChanged(String),
+    PngMarginChanged(String),
+    TogglePngTransparent(bool),
+    ExportPng,
+    ImportPack,
+    ArchiveDaysChanged(String),
+    ArchiveResolved,
+    ArchiveQueryChanged(String),
+    SearchArchive,
+    WorkspacePathChanged(String),
+    OpenWorkspace,
+    SaveWorkspace,
+    SelectFigure(usize),
+    FigureNameChanged(String),
+    FigureLedgerChanged(String),
+    AddFigure,
+    RemoveFigure(usize),
+    ToggleRegionEditor(bool),
+    RegionNameChanged(String),
+    UndoRegionPoint,
+    FinishRegion,
+    DeleteRegion(usize),
+    GlobalQueryChanged(String),
+    GlobalSearch,
+    OpenHit(String, Uuid),
+    DismissReleaseNotes,
+    TakeOverLock,
+    ConfirmAccepted,
+    ConfirmDismissed,
+    SetOverlay(overlay::OverlayOptions),
+    SetAppearance(palette::Appearance),
+    SetCoordinateUnit(units::Unit),
+    SetPrecision(units::Precision),
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    SelectProfile(String),
+    ProfileNameChanged(String),
+    SaveProfile,
     SvgPathChanged(String),
+    // Clockwise when true
+    RotateFigure(bool),
+    MirrorFigure,
+    ToggleLayer(usize, bool),
+    // Figure viewport: lines scrolled over it, a right-button pan, back to
+    // the whole figure, and named views
+    ScrollFigure(f32),
+    PanStarted,
+    PanEnded,
+    FitFigure,
+    SelectView(String),
+    ViewNameChanged(String),
+    SaveView,
+    DeleteView,
+    // Before/after split view on or off, and where one side (true: after)
+    // stands, in days from the first spike
+    Compare(bool),
+    CompareAt(bool, f64),
+    RestoreSnapshot(DateTime<Utc>),
     SavePathChanged(String),
     StrikerChanged(String),
     SpikeMessageChanged(String),
+    WitnessesChanged(String),
+    ToggleRule(usize, bool),
+    ToggleTransform(usize, bool),
+    CustomFieldChanged(String, String),
+    FieldLabelChanged(String),
+    FieldKindChanged(fields::KindChoice),
+    FieldOptionsChanged(String),
+    AddField,
+    RemoveField(usize),
+    FormEdited(usize, fields::FormEdit),
+    WindowResized(Size),
+    CheckFigureFile,
+    StartTour,
+    TourNext,
+    ShowHelp(Option<help::Topic>),
+    ToggleHelp,
+    TourBack,
+    TourEnd,
+
+    // External (FIX): what the queue had, as soon as it had it
+    ExternalArrived(Vec<Vec<ExternalSpike>>),
+    // Ticks while a test spike or the save hook is still to report
+    CheckReports,
+    // A frame was drawn while applied spikes waited for one
+    FrameDrawn(Instant),
+    // Stored FIX days: list them again, pick one, replay or export it
+    RefreshFixDays,
+    ReplayDayChanged(String),
+    ReplayFixDay,
+    ExportFixLog,
+
+    // Diagnostics
+    RunSelfTest,
+    CloseSelfTest,
+
+    // What-if sandbox: start one, run its changes on the ledger, or drop it
+    EnterSandbox,
+    ApplySandbox,
+    DiscardSandbox,
+
+    // Ledger check: look the ledger over, fix one finding or all, close
+    CheckLedger,
+    FixAnomaly(usize),
+    FixAllAnomalies,
+    CloseLedgerCheck,
+
+    // Usage statistics panel
+    ShowUsage(bool),
+    ResetUsage,
+
+    // Intensity: recompute on the timer, half-life typed (days, empty for
+    // no decay), curve shown or not
+    RecomputeIntensity,
+    HalfLifeChanged(String),
+    ShowIntensityCurve(bool),
+
+    // Sessions panel: open FIX connections, one of them ended from here
+    ShowSessions(bool),
+    DisconnectSession(u64),
+
+    // FIX inspector: the last raw messages, one opened up tag by tag
+    ShowInspector(bool),
+    InspectFix(u64),
+    ClearInspector,
+
+    // FIX spikes outside the figure: what becomes of them, and those held
+    // for review accepted or discarded (one, or all of the open ledger's)
+    SetBoundsPolicy(quarantine::Policy),
+    AcceptQuarantined(Uuid),
+    DiscardQuarantined(Option<Uuid>),
+    FixQuarantined(Option<Uuid>),
+    QuarantineFixEdited(quarantine::FixEdit),
+    ResubmitQuarantined,
+
+    // Outbox panel: dead letters of the webhook, drop-copy and upstream FIX,
+    // sent again (one or all) or discarded
+    ShowOutbox(bool),
+    Redrive(Option<(outbox::Channel, Uuid)>),
+    DiscardDeadLetter((outbox::Channel, Uuid)),
+
+    // FIX acceptor: address to listen on, (re)start there, stop
+    FixAddrChanged(String),
+    StartAcceptor,
+    StopAcceptor,
+
+    // Network panel
+    RefreshInterfaces,
+    CopyText(String),
+    SendTestSpike(std::net::SocketAddr),
+}
+
+impl Message {
+    // Messages that would change or write the open ledger; refused while it
+    // is read-only
+    fn mutates_ledger(&self) -> bool {
+        matches!(
+            self,
+            Message::ConfirmSpike
+                | Message::Save
+                | Message::ClearScoped
+                | Message::DeleteEvent(_)
+                | Message::RestoreEvent(_)
+                | Message::EmptyTrash
+                | Message::Undo
+                | Message::MergeLedger
+                | Message::ResolveConflict(..)
+                | Message::ResolveFork(..)
+                | Message::ImportPack
+                | Message::ArchiveResolved
+                | Message::SaveWorkspace
+                | Message::ReplayFixDay
+                | Message::MovePin
+                | Message::BulkTag
+                | Message::BulkOutcome(_)
+                | Message::FixAnomaly(_)
+                | Message::FixAllAnomalies
+                | Message::AcceptQuarantined(_)
+                | Message::ResubmitQuarantined
+                | Message::ApplyReclassify
+                | Message::UndoReclassify
+                | Message::RestoreSnapshot(_)
+        )
+    }
 
-    // External (FIX)
-    PollExternal, // tick to drain channel
-    ExternalArrived(ExternalSpike), // (used if we switch to direct subscription)
+    // Messages that would write files or put another ledger in place, which
+    // a sandbox must be applied or discarded before
+    fn leaves_sandbox(&self) -> bool {
+        matches!(
+            self,
+            Message::Save
+                | Message::Load
+                | Message::LoadSample
+                | Message::MergeLedger
+                | Message::ResolveConflict(..)
+                | Message::ResolveFork(..)
+                | Message::ImportPack
+                | Message::ArchiveResolved
+                | Message::OpenWorkspace
+                | Message::SaveWorkspace
+                | Message::SelectFigure(_)
+                | Message::RemoveFigure(_)
+                | Message::ReplayFixDay
+                | Message::AcceptQuarantined(_)
+                | Message::DiscardQuarantined(_)
+                | Message::ResubmitQuarantined
+                | Message::TakeOverLock
+        )
+    }
+
+    // The feature a message stands for in the usage counts; typing, ticks
+    // and spikes (counted on their own) stand for none
+    fn feature(&self) -> Option<&'static str> {
+        Some(match self {
+            Message::Save => "Save ledger",
+            Message::Load => "Load ledger",
+            Message::LoadSample => "Load sample",
+            Message::StartOpen(_) | Message::StartNew | Message::StartImport => "Start screen",
+            Message::Undo => "Undo",
+            Message::DeleteEvent(_) | Message::ClearScoped => "Delete events",
+            Message::ShowClear(true) | Message::PreviewClear => "Scoped clearing",
+            Message::RestoreEvent(_) | Message::EmptyTrash => "Trash",
+            Message::MergeLedger => "Merge ledger",
+            Message::ResolveConflict(..) => "Resolve conflicts",
+            Message::ResolveFork(..) => "Forked figures",
+            Message::StepEvent(_) => "Step through events",
+            Message::MovePin => "Move pin",
+            Message::BulkTag | Message::BulkOutcome(_) | Message::BulkExport => "Bulk actions",
+            Message::PreviewReclassify | Message::ApplyReclassify | Message::UndoReclassify => "Reclassify",
+            Message::ExportPack => "Export pack",
+            Message::ImportPack => "Import pack",
+            Message::ExportCsv => "Export CSV",
+            Message::ExportReport | Message::ExportTemplate => "Export report",
+            Message::ExportAll => "Batch export",
+            Message::ExportAggregate => "Export aggregates",
+            Message::ShowStrikers(true) => "Striker summary",
+            Message::ExportStrikers => "Export striker summary",
+            Message::ExportPng => "Export PNG",
+            Message::ArchiveResolved => "Archive",
+            Message::SearchArchive => "Search archive",
+            Message::OpenWorkspace => "Open workspace",
+            Message::SaveWorkspace => "Save workspace",
+            Message::AddFigure | Message::RemoveFigure(_) => "Edit figures",
+            Message::FinishRegion | Message::DeleteRegion(_) => "Edit regions",
+            Message::GlobalSearch => "Search all figures",
+            Message::SaveProfile => "Overlay profiles",
+            Message::SelectView(_) | Message::SaveView => "Named views",
+            Message::Compare(true) => "Before/after",
+            Message::RestoreSnapshot(_) => "Restore snapshot",
+            Message::SetAppearance(_) => "Palette",
+            Message::SetCoordinateUnit(_) => "Coordinate units",
+            Message::SetPrecision(_) => "Coordinate precision",
+            Message::ZoomIn | Message::ZoomOut | Message::ZoomReset => "Zoom",
+            Message::RotateFigure(_) | Message::MirrorFigure => "Rotate or mirror figure",
+            Message::AddField | Message::RemoveField(_) => "Edit form fields",
+            Message::StartTour => "Tour",
+            Message::ShowHelp(Some(_)) | Message::ToggleHelp => "Help",
+            Message::ReplayFixDay => "Replay FIX day",
+            Message::ExportFixLog => "Export FIX log",
+            Message::RunSelfTest => "Self-test",
+            Message::StartAcceptor | Message::StopAcceptor => "Acceptor controls",
+            Message::SendTestSpike(_) => "Test spike",
+            Message::CopyText(_) => "Copy settings",
+            Message::ShowUsage(true) => "Usage statistics",
+            Message::Redrive(_) | Message::DiscardDeadLetter(_) => "Outbox",
+            Message::ShowSessions(true) => "Sessions",
+            Message::ShowInspector(true) | Message::InspectFix(_) => "FIX inspector",
+            Message::SetBoundsPolicy(_) => "Out-of-bounds policy",
+            Message::AcceptQuarantined(_)
+            | Message::DiscardQuarantined(_)
+            | Message::FixQuarantined(_)
+            | Message::ResubmitQuarantined => "Quarantine",
+            Message::ShowIntensityCurve(true) => "Intensity curve",
+            Message::EnterSandbox | Message::ApplySandbox => "Sandbox",
+            Message::CheckLedger | Message::FixAnomaly(_) | Message::FixAllAnomalies => "Check ledger",
+            Message::DisconnectSession(_) => "Disconnect session",
+            _ => return None,
+        })
+    }
 }
 
 // -------------------- External spike envelope --------------------
+// What an external message asks of the ledger
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Intent {
+    Strike,
+    // Only `outcome` of the event `id` changes (35=U3)
+    Update,
+    // The event `id` leaves the ledger as a tombstone (35=U4)
+    Cancel,
+}
+
 #[derive(Debug, Clone)]
 struct ExternalSpike {
+    // Ledger event id (9000) when the sender is another Nkisi's drop-copy
+    id: Option<Uuid>,
     pos: (f32, f32),
     who: String,
     message: Option<String>,
     when: Option<DateTime<Utc>>,
+    // Recorded as "External FIX spike" and Pending when not sent
+    purpose: Option<String>,
+    outcome: Option<Outcome>,
+    meta: EventMeta,
+    // SenderCompID (49) and the connection it came in on
+    source: String,
+    // Acceptor that connection was made to (unknown for replays)
+    listener: Option<String>,
+    // When the acceptor framed the message, for latency stats
+    received: Instant,
+    received_at: DateTime<Utc>,
+    // Answers the sender once the spike is recorded or refused (logged-on
+    // FIX sessions only)
+    reply: Option<session::Reply>,
+    // For an update or a cancel, `who` is whoever asked for it and
+    // `message` the reason given
+    intent: Intent,
 }
 
 // -------------------- Update --------------------
 fn update(state: &mut State, message: Message) {
+    if state.read_only.is_some() && message.mutates_ledger() {
+        state.status = "Read-only ledger: load a compatible file to make changes.".into();
+        return;
+    }
+    if state.sandbox.is_some() && message.leaves_sandbox() {
+        state.status = "Apply or discard the sandbox first.".into();
+        return;
+    }
     match message {
         Message::CursorMoved(p) => {
             state.last_cursor = Some(p);
+            if let Some(from) = state.panning.replace(p) {
+                let (sw, sh) = figure::screen_size(state.figure_dims);
+                state.viewport = state.viewport.panned(((p.x - from.x) / sw, (p.y - from.y) / sh));
+            }
+            let at = to_figure(state.figure_dims, state.viewport, p);
+            if let Some((_, to)) = &mut state.dragging {
+                *to = at;
+            }
+            if let Some(stroke) = &mut state.stroke {
+                stroke.extend(at, state.figure_dims.0 / FIGURE_W);
+            }
         }
         Message::ProposeSpike => {
+            // A drag or stroke released outside the figure is abandoned
+            state.dragging = None;
+            state.stroke = None;
             if let Some(p) = state.last_cursor {
-                let nx = (p.x / SCREEN_W) * FIGURE_W;
-                let ny = (p.y / SCREEN_H) * FIGURE_H;
-                let nx = nx.clamp(0.0, FIGURE_W);
-                let ny = ny.clamp(0.0, FIGURE_H);
-                state.pending_pos = Some((nx, ny));
-                state.status = format!("Pending spike at ({:.1}, {:.1}). Confirm or cancel.", nx, ny);
+                let (nx, ny) = to_figure(state.figure_dims, state.viewport, p);
+                if let Some(tool) = state.select_tool {
+                    state.stroke = Some(lasso::Stroke::new(tool, (nx, ny)));
+                    return;
+                }
+                // Pressing on the selected pin picks it up instead
+                let grab = 3.6 * state.figure_dims.0 / FIGURE_W;
+                let held = state
+                    .selected_event
+                    .and_then(|id| state.nkisi.events.iter().find(|e| e.id == id))
+                    .filter(|ev| (ev.pos.0 - nx).hypot(ev.pos.1 - ny) <= grab);
+                if let Some(ev) = held.filter(|_| state.read_only.is_none() && state.region_draft.is_none()) {
+                    state.dragging = Some((ev.id, ev.pos));
+                    state.status = "Moving pin: release where it belongs.".into();
+                    return;
+                }
+                if let Some(draft) = &mut state.region_draft {
+                    draft.push((nx, ny));
+                    state.status = format!("Region vertex {} at {}", draft.len(), coords(state).format((nx, ny)));
+                    return;
+                }
+                let pos = stored(state, (nx, ny));
+                state.pending_pos = Some(pos);
+                state.status = format!("Pending spike at {}. Confirm or cancel.", coords(state).format(pos));
             } else {
                 state.status = "Click ignored (no cursor yet)".into();
             }
         }
         Message::ConfirmSpike => {
             if let Some((nx, ny)) = state.pending_pos.take() {
-                let who = state.striker_input.trim();
-                if who.is_empty() {
-                    state.status = "Please enter a Striker name before confirming.".into();
-                    state.pending_pos = Some((nx, ny));
-                    return;
-                }
-                state.nkisi.pins.push((nx, ny));
-                state.nkisi.events.push(ActivationEvent {
+                let settings = &state.workspace.settings;
+                let layout = fields::layout(&settings.form, &settings.fields);
+                let filled = fields::fill(
+                    &layout,
+                    &settings.fields,
+                    &state.striker_input,
+                    &state.message_input,
+                    &state.witness_input,
+                    &state.custom_inputs,
+                )
+                .and_then(|f| fields::parse(&settings.fields, &f.custom).map(|c| (f, c)));
+                let (filled, custom) = match filled {
+                    Ok(f) => f,
+                    Err(e) => {
+                        state.status = e;
+                        state.pending_pos = Some((nx, ny));
+                        return;
+                    }
+                };
+                let event = ActivationEvent {
                     id: Uuid::new_v4(),
                     date: Utc::now(),
-                    performed_by: who.to_string(),
+                    performed_by: filled.striker.clone(),
                     purpose: ActivationPurpose::Other("Manual spike".into()),
                     outcome: Outcome::Pending,
-                    notes: if state.message_input.trim().is_empty() {
-                        None
-                    } else {
-                        Some(state.message_input.clone())
-                    },
+                    notes: Some(filled.message).filter(|m| !m.is_empty()),
                     pos: (nx, ny),
-                });
+                    meta: EventMeta { witnesses: filled.witnesses, ..EventMeta::default() },
+                    revised: None,
+                    provenance: vec![],
+                    custom,
+                };
+                let broken = rules::check(&settings.rules, &event, state.workspace.active_regions());
+                if !broken.is_empty() {
+                    state.status = format!("Spike not recorded: {}.", broken.join("; "));
+                    state.pending_pos = Some((nx, ny));
+                    return;
+                }
+                let forwarded = event.clone();
+                if let Err(e) = execute(state, Command::Strike(event)) {
+                    state.status = format!("Spike not recorded: {e}.");
+                    return;
+                }
+                if let Some(up) = state.upstream.as_ref().filter(|_| state.sandbox.is_none()) {
+                    up.send(&forwarded);
+                }
+                state.usage.spikes_added("by hand", 1);
+                let region = regions::hit(state.workspace.active_regions(), (nx, ny))
+                    .map_or(String::new(), |r| format!(" in {}", r.name));
                 state.status = format!(
-                    "Spike confirmed at ({:.1}, {:.1}) by {} • total events: {}",
-                    nx, ny, who, state.nkisi.events.len()
+                    "Spike confirmed at {}{} by {} • total events: {}",
+                    coords(state).format((nx, ny)),
+                    region,
+                    filled.striker,
+                    state.nkisi.events.len()
                 );
                 state.message_input.clear();
+                state.witness_input.clear();
+                state.custom_inputs.clear();
             } else {
                 state.status = "No pending spike to confirm.".into();
             }
@@ -198,355 +1239,4586 @@ fn update(state: &mut State, message: Message) {
             state.pending_pos = None;
             state.status = "Pending spike canceled.".into();
         }
-        Message::Save => match save_json(&state.save_path, &state.nkisi) {
-            Ok(_) => state.status = format!("Saved to {}", state.save_path),
-            Err(e) => state.status = format!("Save failed: {e}"),
-        },
-        Message::Load => match load_json(&state.save_path) {
-            Ok(n) => {
-                state.nkisi = n;
+        Message::Save => {
+            if !ensure_lock(state) {
+                return;
+            }
+            match save_ledger(state) {
+                Ok(_) => {
+                    state.recent.remember(&state.save_path);
+                    state.status = format!("Saved to {}", state.save_path);
+                }
+                Err(e) => state.status = format!("Save failed: {e}"),
+            }
+        }
+        Message::Load => {
+            if state.nkisi.events.is_empty() {
+                load_ledger(state);
+            } else {
+                state.confirm = Some(Destructive::LoadOverwrite(state.save_path.clone()));
+            }
+        }
+        Message::LoadSample => {
+            if state.nkisi.events.is_empty() {
+                load_sample(state);
+            } else {
+                state.confirm = Some(Destructive::LoadSample);
+            }
+        }
+        Message::StartPathChanged(p) => state.start_path = p,
+        Message::StartOpen(path) => start_open(state, path.trim()),
+        Message::StartNew => start_new(state),
+        Message::StartImport => start_import(state),
+        Message::ShowClear(on) => {
+            state.show_clear = on;
+            state.clear_preview = None;
+        }
+        Message::ClearScopeEdited(how) => {
+            state.clear_scope.edit(how);
+            state.clear_preview = None;
+        }
+        Message::PreviewClear => state.clear_preview = Some(preview_clear(state)),
+        Message::ClearScoped => {
+            // Taken afresh, so spikes since the preview are counted in
+            if let Ok(p) = preview_clear(state) {
+                state.confirm = Some(Destructive::Clear { ids: p.ids.clone(), what: p.what.clone() });
+                state.clear_preview = Some(Ok(p));
+            }
+        }
+        Message::DeleteEvent(id) => state.confirm = Some(Destructive::DeleteEvent(id)),
+        Message::RestoreEvent(id) => match execute(state, Command::Restore(vec![id])) {
+            Ok(_) => {
+                let who = state.nkisi.events.iter().find(|e| e.id == id).map_or("", |e| e.performed_by.as_str());
                 state.status = format!(
-                    "Loaded {} events / {} pins from {}",
-                    state.nkisi.events.len(),
-                    state.nkisi.pins.len(),
-                    state.save_path
+                    "Restored spike by {} • total events: {}",
+                    who,
+                    state.nkisi.events.len()
                 );
             }
-            Err(e) => state.status = format!("Load failed: {e}"),
+            Err(e) => state.status = format!("Not restored: {e}."),
         },
-        Message::ClearAll => {
-            state.nkisi.pins.clear();
-            state.nkisi.events.clear();
-            state.pending_pos = None;
-            state.status = "Cleared all pins & events.".into();
+        Message::EmptyTrash => state.confirm = Some(Destructive::EmptyTrash),
+        Message::MergePathChanged(p) => state.merge_path = p,
+        Message::MergeLedger => {
+            let source = state.merge_path.trim().to_string();
+            let remote = match load_json(&source) {
+                Ok(n) => n,
+                Err(e) => {
+                    state.status = format!("Merge failed: {source}: {e}");
+                    return;
+                }
+            };
+            merge_copy(state, source, remote);
+            // A figure of the workspace merged here may now be a spare copy
+            find_forks(state);
         }
-        Message::ToggleGrid(v) => state.show_grid = v,
-        Message::SvgPathChanged(p) => state.svg_path = p,
-        Message::SavePathChanged(p) => state.save_path = p,
-        Message::StrikerChanged(s) => state.striker_input = s,
-        Message::SpikeMessageChanged(s) => state.message_input = s,
-
-        // Poll the FIX channel on a timer
-        Message::PollExternal => {
-            let mut count = 0usize;
-            while let Ok(spike) = state.fix_rx.try_recv() {
-                count += 1;
-                let when = spike.when.unwrap_or_else(Utc::now);
-                let who = spike.who;
-                let (nx, ny) = spike.pos;
-
-                state.nkisi.pins.push((nx, ny));
-                state.nkisi.events.push(ActivationEvent {
-                    id: Uuid::new_v4(),
-                    date: when,
-                    performed_by: who.clone(),
-                    purpose: ActivationPurpose::Other("External FIX spike".into()),
-                    outcome: Outcome::Pending,
-                    notes: spike.message.clone(),
-                    pos: (nx, ny),
-                });
+        Message::ResolveFork(i, choice) => resolve_fork(state, i, choice),
+        Message::SelectEvent(id) => {
+            state.selected_event = id;
+            let pos = id.and_then(|id| state.nkisi.events.iter().find(|e| e.id == id)).map(|e| e.pos);
+            (state.move_x_input, state.move_y_input) = pos.map_or_else(Default::default, |p| coords(state).inputs(p));
+        }
+        Message::StepEvent(forward) => {
+            let Some((n, ev)) = step_event(&state.nkisi.events, state.selected_event, forward) else {
+                state.status = match (state.nkisi.events.is_empty(), forward) {
+                    (true, _) => "No events to review.".into(),
+                    (false, true) => "Last event reached.".into(),
+                    (false, false) => "First event reached.".into(),
+                };
+                return;
+            };
+            let (id, pos) = (ev.id, ev.pos);
+            let coords = coords(state);
+            state.status = format!(
+                "Event {n} of {} • {} by {} at {}",
+                state.nkisi.events.len(),
+                ev.date.format("%Y-%m-%d %H:%M"),
+                ev.performed_by,
+                coords.format(pos)
+            );
+            state.selected_event = Some(id);
+            (state.move_x_input, state.move_y_input) = coords.inputs(pos);
+        }
+        Message::FigureReleased => {
+            if let Some((id, to)) = state.dragging.take() {
+                move_pin(state, id, to, "dragged");
             }
-            if count > 0 {
-                state.status = format!("Accepted {count} FIX spike(s). Total events: {}", state.nkisi.events.len());
+            if let Some(stroke) = state.stroke.take() {
+                state.bulk = stroke.select(&state.nkisi.events);
+                state.status = match state.bulk.len() {
+                    0 => format!("{} selected no pins.", stroke.tool),
+                    n => format!("Selected {}: tag, set the outcome or export them below.", confirm::count(n, "pin")),
+                };
             }
         }
-
-        // Not used in the timer-based approach
-        Message::ExternalArrived(_s) => {}
-    }
-}
-
-// -------------------- View --------------------
-fn view(state: &State) -> Element<Message> {
-    // Base SVG (type-annotated to pin Theme generic)
-    let handle = svg::Handle::from_path(&state.svg_path);
-    let base: Svg<'_, Theme> = svg(handle)
-        .width(Length::Fixed(SCREEN_W))
-        .height(Length::Fixed(SCREEN_H));
-
-    // Mouse area over the base: track cursor & emit "ProposeSpike" on click
-    let clickable: Element<Message> =
-        iced::widget::mouse_area::<Message, Theme, Renderer>(base)
-            .on_move(|p| Message::CursorMoved(p))
-            .on_press(Message::ProposeSpike)
-            .into();
-
-    // Overlay pins/grid as another SVG on top
-    let overlay_handle =
-        svg::Handle::from_memory(render_overlay_svg(&state.nkisi, state.show_grid).into_bytes());
-    let overlay_svg: Svg<'_, Theme> = svg(overlay_handle)
-        .width(Length::Fixed(SCREEN_W))
-        .height(Length::Fixed(SCREEN_H));
-    let overlay: Element<Message> = overlay_svg.into();
-
-    let figure: Element<Message> = column![clickable, overlay].spacing(0).into();
-
-    use iced::widget::text; // for text::Style
-    let mut controls_col = column![
-        iced::widget::text("Rustic Nkisi • Spike Ledger (FIX-enabled)").size(22),
-        row![
-            button("Save").on_press(Message::Save),
-            button("Load").on_press(Message::Load),
-            button("Clear All").on_press(Message::ClearAll),
-        ]
-        .spacing(10),
-        row![
-            toggler(state.show_grid)
-                .label("Show grid")
-                .on_toggle(Message::ToggleGrid),
-            iced::widget::text(format!("Intensity: {}", state.nkisi.intensity()))
-        ]
-        .spacing(16),
-        row![
-            iced::widget::text("SVG path:"),
-            text_input("assets/nkisi.svg", &state.svg_path)
-                .on_input(Message::SvgPathChanged)
-                .padding(6),
-        ]
-        .spacing(8),
-        row![
-            iced::widget::text("Save path:"),
-            text_input("nkisi_state.json", &state.save_path)
-                .on_input(Message::SavePathChanged)
-                .padding(6),
-        ]
-        .spacing(8),
-    ]
-        .spacing(8)
-        .align_x(alignment::Horizontal::Left);
-
-    // Pending Spike confirmation panel (for local clicks)
-    if let Some((nx, ny)) = state.pending_pos {
-        let pending = container(
-            column![
-                iced::widget::text("Pending Spike").size(18),
-                iced::widget::text(format!("Position (SVG): x={:.1}, y={:.1}", nx, ny)),
-                row![
-                    iced::widget::text("Striker:"),
-                    text_input("who is adding the spike", &state.striker_input)
-                        .on_input(Message::StrikerChanged)
-                        .padding(6)
-                        .width(Length::Fill),
-                ]
-                .spacing(8),
-                row![
-                    iced::widget::text("Message:"),
-                    text_input("context / reason (optional)", &state.message_input)
-                        .on_input(Message::SpikeMessageChanged)
-                        .padding(6)
-                        .width(Length::Fill),
-                ]
-                .spacing(8),
-                row![
-                    button("Confirm").on_press(Message::ConfirmSpike),
-                    button("Cancel").on_press(Message::CancelSpike),
-                ]
-                .spacing(12),
-            ]
-                .spacing(8),
-        )
-            .padding(12)
-            .style(|_theme: &Theme| {
-                use iced::Border;
-                container::Style {
-                    background: Some(Color::from_rgba(0.15, 0.15, 0.18, 0.9).into()),
-                    border: Border { radius: 12.0.into(), ..Default::default() },
-                    ..Default::default()
+        Message::SetSelectTool(tool) => {
+            state.select_tool = tool;
+            state.stroke = None;
+            if let Some(tool) = tool {
+                state.region_draft = None;
+                state.pending_pos = None;
+                state.status = format!("{tool} select: drag over the figure to pick pins.");
+            }
+        }
+        Message::ClearBulk => state.bulk.clear(),
+        Message::ReclassifyScope(scope) => {
+            state.reclass_scope = Some(scope);
+            state.reclass_preview = None;
+        }
+        Message::ReclassifyKind(kind) => {
+            state.reclass_kind = kind;
+            state.reclass_preview = None;
+        }
+        Message::ReclassifyInput(s) => {
+            state.reclass_input = s;
+            state.reclass_preview = None;
+        }
+        Message::PreviewReclassify => preview_reclassify(state),
+        Message::ApplyReclassify => apply_reclassify(state),
+        Message::UndoReclassify => undo_reclassify(state),
+        Message::BulkTagChanged(s) => state.bulk_tag_input = s,
+        Message::BulkTag => {
+            let tag = state.bulk_tag_input.trim().to_string();
+            if tag.is_empty() {
+                state.status = "Type a tag to add.".into();
+                return;
+            }
+            let note = format!("tagged \"{tag}\" in bulk");
+            bulk_revise(state, &note, |ev| {
+                if ev.meta.tags.contains(&tag) {
+                    return false;
                 }
+                ev.meta.tags.push(tag.clone());
+                true
             });
-
-        controls_col = controls_col.push(pending);
-    }
-
-    // Status line
-    controls_col = controls_col.push(
-        iced::widget::text(&state.status).style(|_| text::Style {
-            color: Some(Color::from_rgb(0.85, 0.85, 0.95)),
-            ..Default::default()
-        }),
-    );
-
-    row![figure, container(controls_col).padding(16)]
-        .spacing(24)
-        .padding(16)
-        .into()
-}
-
-// -------------------- Overlay SVG (pins + grid) --------------------
-fn render_overlay_svg(nkisi: &NkisiNkondi, show_grid: bool) -> String {
-    let mut s = String::new();
-    s.push_str(&format!(
-        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}">"##,
-        FIGURE_W, FIGURE_H
-    ));
-
-    if show_grid {
-        s.push_str(r##"<g stroke="#ffffff22" stroke-width="0.3">"##);
-        for x in (0..=100).step_by(10) {
-            let x = (x as f32) * (FIGURE_W / 100.0);
-            s.push_str(&format!(r#"<line x1="{x}" y1="0" x2="{x}" y2="{FIGURE_H}"/>"#));
         }
-        for y in (0..=150).step_by(10) {
-            let y = (y as f32) * (FIGURE_H / 150.0);
-            s.push_str(&format!(r#"<line x1="0" y1="{y}" x2="{FIGURE_W}" y2="{y}"/>"#));
+        Message::BulkOutcome(outcome) => {
+            let note = format!("outcome set to {outcome:?} in bulk");
+            bulk_revise(state, &note, |ev| {
+                if ev.outcome == outcome {
+                    return false;
+                }
+                ev.outcome = outcome.clone();
+                true
+            });
         }
-        s.push_str("</g>");
-    }
-
-    // Pins
-    s.push_str(r##"<g fill="#ff4d4d" stroke="#00000099" stroke-width="0.4">"##);
-    for &(x, y) in &nkisi.pins {
-        s.push_str(&format!(r#"<circle cx="{x:.2}" cy="{y:.2}" r="1.8"/>"#));
-    }
-    s.push_str("</g></svg>");
-    s
-}
-
-// -------------------- Persistence --------------------
-#[derive(Debug, Error)]
-pub enum IoError {
-    #[error("read error: {0}")]
-    Read(String),
-    #[error("write error: {0}")]
-    Write(String),
-    #[error("parse error: {0}")]
-    Parse(String),
-}
-fn save_json(path: &str, state: &NkisiNkondi) -> Result<(), IoError> {
-    let bytes =
-        serde_json::to_vec_pretty(state).map_err(|e| IoError::Write(e.to_string()))?;
-    std::fs::write(path, bytes).map_err(|e| IoError::Write(e.to_string()))
-}
-fn load_json(path: &str) -> Result<NkisiNkondi, IoError> {
-    let bytes = std::fs::read(path).map_err(|e| IoError::Read(e.to_string()))?;
-    serde_json::from_slice(&bytes).map_err(|e| IoError::Parse(e.to_string()))
-}
-
-// -------------------- FIX acceptor --------------------
-// Minimal FIX “U1 Spike” parser/acceptor.
-// 35=U1 (custom); 55=NKISI; 448=PartyID (who); 58=Text (message);
-// 60=TransactTime (optional ISO); 6010=PosX; 6011=PosY
-fn start_fix_acceptor(addr: &str, tx: Sender<ExternalSpike>) {
-    let addr = addr.to_string();
-    thread::spawn(move || {
-        let listener = TcpListener::bind(&addr).expect("bind FIX acceptor");
-        eprintln!("[FIX] listening on {}", addr);
-
-        for stream in listener.incoming() {
-            match stream {
-                Ok(mut s) => {
-                    let txc = tx.clone();
-                    thread::spawn(move || handle_fix_connection(&mut s, txc));
+        Message::BulkExport => {
+            let locale = match report::Locale::load(&state.report_locale) {
+                Ok(l) => l.with_coords(coords(state)),
+                Err(e) => {
+                    state.status = format!("Selection not exported: {e}");
+                    return;
                 }
-                Err(e) => eprintln!("[FIX] accept error: {e:?}"),
+            };
+            let mut selection = state.nkisi.clone();
+            selection.events.retain(|e| state.bulk.contains(&e.id));
+            let path = format!("{}-selection.csv", state.report_path);
+            let csv = report::csv(&locale, &selection, state.workspace.active_regions());
+            state.status = match report::write(&path, &csv) {
+                Ok(_) => format!("Wrote {} to {path}", confirm::count(selection.events.len(), "event")),
+                Err(e) => format!("Selection not exported: {e}"),
+            };
+        }
+        Message::MoveXChanged(s) => state.move_x_input = s,
+        Message::MoveYChanged(s) => state.move_y_input = s,
+        Message::MovePin => {
+            let Some(id) = state.selected_event else { return };
+            let (Ok(x), Ok(y)) =
+                (state.move_x_input.trim().parse::<f32>(), state.move_y_input.trim().parse::<f32>())
+            else {
+                state.status = "Pin coordinates must be numbers.".into();
+                return;
+            };
+            // Typed in the shown unit
+            let coords = coords(state);
+            let (x, y) = coords.to_figure((x, y));
+            let (fw, fh) = state.figure_dims;
+            if !(0.0..=fw).contains(&x) || !(0.0..=fh).contains(&y) {
+                let (w, h) = coords.convert((fw, fh));
+                let u = coords.suffix();
+                state.status = format!("Pin coordinates must lie within the figure (0–{w:.1}{u}, 0–{h:.1}{u}).");
+                return;
             }
+            move_pin(state, id, (x, y), "corrected");
         }
-    });
-}
-
-fn handle_fix_connection(stream: &mut TcpStream, tx: Sender<ExternalSpike>) {
-    let mut buf = vec![0u8; 8192];
-    let mut acc: Vec<u8> = Vec::new();
-
-    loop {
-        match stream.read(&mut buf) {
-            Ok(0) => break, // closed
-            Ok(n) => {
-                acc.extend_from_slice(&buf[..n]);
-
-                // Try to frame whole messages: search for "10=" and trailing SOH as end marker.
-                // This is simplistic but works for many test feeds.
-                while let Some(end_idx) = find_fix_end(&acc) {
-                    let msg = acc.drain(..=end_idx).collect::<Vec<u8>>();
-                    if let Some(spike) = parse_fix_spike(&msg) {
-                        let _ = tx.send(spike);
+        Message::PackPathChanged(p) => state.pack_path = p,
+        Message::ReportLocaleChanged(l) => state.report_locale = l,
+        Message::ReportPathChanged(p) => state.report_path = p,
+        Message::ReportTemplateChanged(p) => state.report_template = p,
+        Message::PngPathChanged(p) => state.png_path = p,
+        Message::PngDpiChanged(s) => state.png_dpi_input = s,
+        Message::PngMarginChanged(s) => state.png_margin_input = s,
+        Message::TogglePngTransparent(v) => state.png_transparent = v,
+        Message::ExportPng => {
+            let (Ok(dpi), Ok(margin_mm)) = (
+                state.png_dpi_input.trim().parse::<f32>(),
+                state.png_margin_input.trim().parse::<f32>(),
+            ) else {
+                state.status = "DPI and margin (mm) must be numbers.".into();
+                return;
+            };
+            let base = match (oriented_base(state), &state.base_svg) {
+                (Some(oriented), _) => export::Base::Svg(oriented),
+                (None, Some(layers)) => export::Base::Svg(layers.render()),
+                (None, None) => export::Base::Raster(&state.svg_path),
+            };
+            let options = export::RasterOptions { dpi, margin_mm, transparent: state.png_transparent };
+            let overlay = render_overlay_svg(state, false);
+            state.status = match export::png(&state.png_path, base, &overlay, state.figure_dims, options) {
+                Ok((w, h)) => format!("Exported {w}×{h} px at {dpi} DPI to {}", state.png_path),
+                Err(e) => format!("PNG export failed: {e}"),
+            };
+        }
+        Message::BatchDirChanged(s) => state.batch_dir = s,
+        Message::AggregateEpsilonChanged(s) => state.aggregate_epsilon = s,
+        Message::ExportAggregate => {
+            let epsilon = match state.aggregate_epsilon.trim() {
+                "" => None,
+                e => match e.parse::<f64>() {
+                    Ok(e) => Some(e),
+                    Err(_) => {
+                        state.status = "Epsilon must be a number, or empty for exact counts.".into();
+                        return;
                     }
-                }
-            }
-            Err(e) => {
-                eprintln!("[FIX] read error: {e:?}");
-                break;
-            }
+                },
+            };
+            let figure = state.workspace.active_figure().map_or("", |f| f.name.as_str());
+            let path = format!("{}-aggregate.json", state.report_path);
+            let written = aggregate::json(
+                figure,
+                &state.nkisi,
+                state.figure_dims,
+                state.workspace.active_regions(),
+                epsilon,
+            )
+            .and_then(|json| report::write(&path, &json));
+            state.status = match (written, epsilon) {
+                (Ok(()), Some(e)) => format!("Wrote aggregates with ε = {e} noise to {path}"),
+                (Ok(()), None) => format!("Wrote exact aggregates to {path} (no noise)"),
+                (Err(e), _) => format!("Aggregate export failed: {e}"),
+            };
         }
-    }
-}
-
-fn find_fix_end(buf: &[u8]) -> Option<usize> {
-    // Look for "10=" then the next SOH; return that SOH index as end of message
-    let needle = b"10=";
-    let mut i = 0;
-    while i + 3 < buf.len() {
-        if &buf[i..i + 3] == needle {
-            // find next SOH
-            let mut j = i + 3;
-            while j < buf.len() && buf[j] != SOH { j += 1; }
-            if j < buf.len() && buf[j] == SOH {
-                return Some(j);
-            }
+        Message::ShowStrikers(show) => state.show_strikers = show,
+        Message::ExportStrikers => {
+            let rows = strikers::summary(&state.nkisi.events, state.workspace.active_regions());
+            let figure = state.workspace.active_figure().map_or("", |f| f.name.as_str());
+            let csv = format!("{}-strikers.csv", state.report_path);
+            let md = format!("{}-contributors.md", state.report_path);
+            let written = report::write(&csv, &strikers::csv(&rows))
+                .and_then(|()| report::write(&md, &strikers::markdown(figure, &rows)));
+            state.status = match written {
+                Ok(()) => format!("Wrote {} to {csv} and {md}", confirm::count(rows.len(), "striker")),
+                Err(e) => format!("Striker summary not written: {e}"),
+            };
         }
-        i += 1;
+        Message::ExportAll => {
+            let locale = match report::Locale::load(&state.report_locale) {
+                Ok(l) => l.with_coords(coords(state)),
+                Err(e) => {
+                    state.status = format!("Export not written: {e}");
+                    return;
+                }
+            };
+            let options = state.overlay;
+            let look = state.workspace.settings.appearance;
+            let overlay = move |nkisi: &NkisiNkondi, dims, figure_regions: &[regions::Region]| {
+                let shown: &[regions::Region] = if options.regions { figure_regions } else { &[] };
+                overlay_svg(nkisi, dims, shown, options, &look, None, None)
+            };
+            state.status = match batch::run(&state.batch_dir, &state.workspace, &state.nkisi, &locale, &overlay) {
+                Ok(batch::Summary { exported, failed: 0 }) => {
+                    format!("Exported {exported} figure(s) to {}", state.batch_dir)
+                }
+                Ok(batch::Summary { exported, failed }) => format!(
+                    "Exported {exported} figure(s) to {}; {failed} failed (see manifest.json)",
+                    state.batch_dir
+                ),
+                Err(e) => format!("Export all failed: {e}"),
+            };
+        }
+        Message::ExportCsv | Message::ExportReport | Message::ExportTemplate => {
+            let locale = match report::Locale::load(&state.report_locale) {
+                Ok(l) => l.with_coords(coords(state)),
+                Err(e) => {
+                    state.status = format!("Report not written: {e}");
+                    return;
+                }
+            };
+            let regions = state.workspace.active_regions();
+            let figure = state.workspace.active_figure().map_or("", |f| f.name.as_str());
+            let (path, contents) = match message {
+                Message::ExportCsv => {
+                    (format!("{}.csv", state.report_path), report::csv(&locale, &state.nkisi, regions))
+                }
+                Message::ExportReport => {
+                    let html = report::html(&locale, figure, &state.nkisi, regions);
+                    (format!("{}.html", state.report_path), html)
+                }
+                _ => {
+                    let context = report::context(
+                        &locale,
+                        figure,
+                        &state.nkisi,
+                        regions,
+                        &state.workspace.settings.fields,
+                    );
+                    match report::from_template(&state.report_template, &context) {
+                        Ok((out, ext)) => (format!("{}.{ext}", state.report_path), out),
+                        Err(e) => {
+                            state.status = format!("Template {}: {e}", state.report_template);
+                            return;
+                        }
+                    }
+                }
+            };
+            state.status = match report::write(&path, &contents) {
+                Ok(_) => format!(
+                    "Wrote {} ({}) to {path}",
+                    confirm::count(state.nkisi.events.len(), "event"),
+                    locale.tag
+                ),
+                Err(e) => format!("Report not written: {e}"),
+            };
+        }
+        Message::ExportPack => {
+            let Some(key) = &state.pack_key else {
+                state.status = "Set a pack key (--pack-key or NKISI_PACK_KEY) to sign packs.".into();
+                return;
+            };
+            let events: Vec<ActivationEvent> =
+                matching_events(&state.nkisi, &state.note_index, &state.global_query)
+                    .into_iter()
+                    .cloned()
+                    .collect();
+            if events.is_empty() {
+                state.status = "No events match the search to export.".into();
+                return;
+            }
+            let figure = state.workspace.active_figure().map_or("", |f| f.name.as_str());
+            let n = events.len();
+            let pack = pack::new_pack(state.nkisi.id, figure, &state.workspace.name, events);
+            state.status = match pack::write(&state.pack_path, &pack, key) {
+                Ok(_) => format!("Exported {} to {}", confirm::count(n, "event"), state.pack_path),
+                Err(e) => format!("Export failed: {e}"),
+            };
+        }
+        Message::ImportPack => import_pack(state),
+        Message::ResolveConflict(i, how) => {
+            let Some(conflict) = state.conflicts.get(i) else { return };
+            let revise = Command::Revise {
+                event: conflict.resolve(how),
+                note: format!("merge conflict, {}", how.label()),
+            };
+            match execute(state, revise) {
+                Ok(_) => {
+                    state.conflicts.remove(i);
+                    state.status = format!("Conflict resolved: {}", how.label());
+                }
+                Err(e) => {
+                    // Nothing left to resolve against
+                    state.conflicts.remove(i);
+                    state.status = format!("Conflict dropped: {e}.");
+                }
+            }
+        }
+        Message::Undo => match execute(state, Command::Undo) {
+            Ok(_) => {
+                let last = state.journal.entries.last().map(journal::Entry::describe);
+                state.status = format!("Undone: {}", last.unwrap_or_default());
+            }
+            Err(e) => state.status = format!("Nothing undone: {e}."),
+        },
+        Message::ArchiveDaysChanged(s) => state.archive_days_input = s,
+        Message::ArchiveResolved => {
+            let Ok(days) = state.archive_days_input.trim().parse::<i64>() else {
+                state.status = "Archive cutoff must be a whole number of days.".into();
+                return;
+            };
+            let cutoff = Utc::now() - chrono::Duration::days(days);
+            match archive::archive_resolved(&state.nkisi, cutoff, &state.save_path) {
+                Ok(ids) if ids.is_empty() => {
+                    state.status = format!("No resolved events older than {days} days.")
+                }
+                Ok(ids) => {
+                    let n = ids.len();
+                    if let Err(e) = execute(state, Command::Archive(ids)) {
+                        state.status = format!("Archive failed: {e}");
+                        return;
+                    }
+                    state.status = match save_ledger(state) {
+                        Ok(_) => format!(
+                            "Archived {} to {} • active events: {}",
+                            confirm::count(n, "event"),
+                            archive::archive_path(&state.save_path),
+                            state.nkisi.events.len()
+                        ),
+                        Err(e) => format!("Archived {} but saving the ledger failed: {e}", confirm::count(n, "event")),
+                    };
+                }
+                Err(e) => state.status = format!("Archive failed: {e}"),
+            }
+        }
+        Message::ArchiveQueryChanged(s) => state.archive_query = s,
+        Message::SearchArchive => {
+            let path = archive::archive_path(&state.save_path);
+            match archive::search(&path, &state.archive_query) {
+                Ok(found) => {
+                    state.status = format!("{} in {path}", confirm::count(found.len(), "archived match"));
+                    state.archive_results = found;
+                }
+                Err(e) => state.status = format!("Archive search failed: {e}"),
+            }
+        }
+        Message::WorkspacePathChanged(p) => state.workspace_path = p,
+        Message::OpenWorkspace => {
+            if state.nkisi.events.is_empty() {
+                open_workspace(state, state.workspace_path.clone());
+            } else {
+                state.confirm = Some(Destructive::OpenWorkspace(state.workspace_path.clone()));
+            }
+        }
+        Message::SaveWorkspace => {
+            if !ensure_lock(state) {
+                return;
+            }
+            sync_active_figure(state);
+            let saved = workspace::save(&state.workspace_path, &state.workspace)
+                .and_then(|_| save_ledger(state));
+            state.status = match saved {
+                Ok(_) => format!(
+                    "Saved workspace {} ({} figure(s))",
+                    state.workspace_path,
+                    state.workspace.figures.len()
+                ),
+                Err(e) => format!("Workspace save failed: {e}"),
+            };
+        }
+        Message::SelectFigure(i) => switch_figure(state, i),
+        Message::FigureNameChanged(s) => state.figure_name_input = s,
+        Message::FigureLedgerChanged(s) => state.figure_ledger_input = s,
+        Message::AddFigure => {
+            let ledger = state.figure_ledger_input.trim().to_string();
+            if ledger.is_empty() || state.workspace.figure_for_ledger(&ledger).is_some() {
+                state.status = "Enter a ledger file not already in the workspace.".into();
+                return;
+            }
+            let name = match state.figure_name_input.trim() {
+                "" => ledger.clone(),
+                n => n.to_string(),
+            };
+            state.workspace.figures.push(FigureRef {
+                name: name.clone(),
+                ledger,
+                svg: state.svg_path.clone(),
+                regions: vec![],
+                orientation: Default::default(),
+                views: vec![],
+            });
+            state.figure_name_input.clear();
+            state.figure_ledger_input.clear();
+            state.status = format!("Added figure {name} to the workspace");
+            find_forks(state);
+        }
+        Message::RemoveFigure(i) => {
+            if i == state.workspace.active || i >= state.workspace.figures.len() {
+                state.status = "The open figure can't be removed; switch to another first.".into();
+                return;
+            }
+            let fig = remove_figure(state, i);
+            state.status = format!("Removed figure {} (its ledger file is kept)", fig.name);
+        }
+        Message::ToggleRegionEditor(on) => {
+            state.region_draft = on.then(Vec::new);
+            if on {
+                state.pending_pos = None;
+                state.select_tool = None;
+                state.status = "Region editor: click the figure to place vertices.".into();
+            }
+        }
+        Message::RegionNameChanged(s) => state.region_name_input = s,
+        Message::UndoRegionPoint => {
+            if let Some(draft) = &mut state.region_draft {
+                draft.pop();
+            }
+        }
+        Message::FinishRegion => {
+            let name = state.region_name_input.trim().to_string();
+            let Some(draft) = &mut state.region_draft else { return };
+            if name.is_empty() || draft.len() < 3 {
+                state.status = "A region needs a name and at least 3 vertices.".into();
+                return;
+            }
+            let polygon = std::mem::take(draft);
+            let Some(fig) = state.workspace.figures.get_mut(state.workspace.active) else { return };
+            fig.regions.push(regions::Region { name: name.clone(), polygon });
+            state.region_name_input.clear();
+            state.status = format!("Added region {name} • save the workspace to keep it");
+        }
+        Message::DeleteRegion(i) => {
+            if let Some(fig) = state.workspace.figures.get_mut(state.workspace.active) {
+                if i < fig.regions.len() {
+                    let r = fig.regions.remove(i);
+                    state.status = format!("Deleted region {}", r.name);
+                }
+            }
+        }
+        Message::GlobalQueryChanged(s) => state.global_query = s,
+        Message::GlobalSearch => {
+            let live = matching_events(&state.nkisi, &state.note_index, &state.global_query);
+            let ledgers: Vec<String> =
+                state.workspace.figures.iter().map(|f| f.ledger.clone()).collect();
+            let (hits, errors) = state.global_index.search(
+                &ledgers,
+                (&state.save_path, live),
+                &state.global_query,
+            );
+            state.status = if errors.is_empty() {
+                format!(
+                    "{} across {} ledger(s)",
+                    confirm::count(hits.len(), "match"),
+                    ledgers.len().max(1)
+                )
+            } else {
+                format!("{} • skipped {}", confirm::count(hits.len(), "match"), errors.join("; "))
+            };
+            state.global_hits = hits;
+        }
+        Message::OpenHit(path, id) => {
+            state.selected_event = Some(id);
+            if path == state.save_path {
+                state.status = "Selected event is ringed on the figure.".into();
+            } else if let Some(i) = state.workspace.figure_for_ledger(&path) {
+                switch_figure(state, i);
+            } else if state.nkisi.events.is_empty() {
+                state.save_path = path;
+                load_ledger(state);
+            } else {
+                state.confirm = Some(Destructive::LoadOverwrite(path));
+            }
+        }
+        Message::TakeOverLock => {
+            if let Some(ReadOnly::Locked(info)) = &state.read_only {
+                state.confirm = Some(Destructive::TakeOverLock(info.to_string()));
+            }
+        }
+        Message::DismissReleaseNotes => {
+            state.release_notes = None;
+            compat::mark_version_seen();
+        }
+        Message::ConfirmAccepted => {
+            if let Some(action) = state.confirm.take() {
+                apply_destructive(state, action);
+            }
+        }
+        Message::ConfirmDismissed => {
+            state.confirm = None;
+            state.status = "Canceled.".into();
+        }
+        Message::SetOverlay(opts) => {
+            state.overlay = opts;
+            state.active_profile = None;
+        }
+        Message::ZoomIn | Message::ZoomOut | Message::ZoomReset => {
+            let scale = &mut state.workspace.settings.ui_scale;
+            *scale = match message {
+                Message::ZoomIn => *scale + UI_SCALE_STEP,
+                Message::ZoomOut => *scale - UI_SCALE_STEP,
+                _ => 1.0,
+            };
+            *scale = ui_scale(*scale);
+            state.status = format!("Interface at {:.0}% • save the workspace to keep it", *scale * 100.0);
+        }
+        Message::SetAppearance(look) => {
+            state.workspace.settings.appearance = look;
+            let contrast = if look.high_contrast { ", high contrast" } else { "" };
+            state.status = format!("Overlay palette: {}{contrast} • save the workspace to keep it", look.palette);
+        }
+        Message::SetCoordinateUnit(unit) => {
+            state.workspace.settings.coordinate_unit = unit;
+            let coords = coords(state);
+            if let Some(ev) = state.selected_event.and_then(|id| state.nkisi.events.iter().find(|e| e.id == id)) {
+                (state.move_x_input, state.move_y_input) = coords.inputs(ev.pos);
+            }
+            state.status = match coords.unit() == unit {
+                true => format!("Coordinates in {unit} • save the workspace to keep it"),
+                false => "This figure doesn't say how large it prints; coordinates stay in figure units.".into(),
+            };
+        }
+        Message::SetPrecision(precision) => {
+            state.workspace.settings.precision = precision;
+            state.status = format!(
+                "Positions stored {} from now on; those recorded stay as they are • save the workspace to keep it",
+                precision.to_string().to_lowercase()
+            );
+        }
+        Message::SelectProfile(name) => {
+            let profiles = &state.workspace.settings.profiles;
+            if let Some(p) = profiles.iter().find(|p| p.name == name) {
+                state.overlay = p.options;
+                state.active_profile = Some(name);
+            }
+        }
+        Message::ProfileNameChanged(s) => state.profile_name_input = s,
+        Message::SaveProfile => {
+            let name = state.profile_name_input.trim().to_string();
+            if name.is_empty() {
+                state.status = "Name the profile before saving it.".into();
+                return;
+            }
+            let profiles = &mut state.workspace.settings.profiles;
+            match profiles.iter_mut().find(|p| p.name == name) {
+                Some(p) => p.options = state.overlay,
+                None => profiles.push(overlay::OverlayProfile {
+                    name: name.clone(),
+                    options: state.overlay,
+                }),
+            }
+            state.active_profile = Some(name.clone());
+            state.profile_name_input.clear();
+            state.status = format!("Saved overlay profile {name} • save the workspace to keep it");
+        }
+        Message::SvgPathChanged(p) => {
+            state.svg_path = p;
+            reload_base_svg(state);
+        }
+        Message::RotateFigure(_) | Message::MirrorFigure => {
+            let Some(fig) = state.workspace.figures.get_mut(state.workspace.active) else { return };
+            fig.orientation = match message {
+                Message::RotateFigure(clockwise) => fig.orientation.turned(clockwise),
+                _ => fig.orientation.flipped(),
+            };
+            let o = fig.orientation;
+            state.viewport = views::Viewport::default();
+            reload_base_svg(state);
+            state.status = format!("Figure shown at {o} • save the workspace to keep it");
+        }
+        Message::CheckFigureFile => {
+            let mtime = figure::modified(&state.svg_path);
+            if mtime.is_some() && mtime != state.figure_mtime {
+                hot_reload_figure(state);
+            }
+        }
+        Message::ToggleLayer(i, visible) => {
+            if let Some(layer) = state.base_svg.as_mut().and_then(|b| b.layers.get_mut(i)) {
+                layer.visible = visible;
+            }
+        }
+        Message::ScrollFigure(lines) => {
+            let Some(p) = state.last_cursor else { return };
+            let (sw, sh) = figure::screen_size(state.figure_dims);
+            state.viewport = state.viewport.scrolled(lines, (p.x / sw, p.y / sh));
+        }
+        Message::PanStarted => state.panning = state.last_cursor,
+        Message::PanEnded => state.panning = None,
+        Message::FitFigure => state.viewport = views::Viewport::default(),
+        Message::SelectView(name) => {
+            let Some(view) = state.workspace.active_figure().and_then(|f| f.views.iter().find(|v| v.name == name))
+            else {
+                return;
+            };
+            state.viewport = view.viewport;
+            state.overlay = view.overlay;
+            state.active_profile = None;
+            if let Some(base) = &mut state.base_svg {
+                for layer in &mut base.layers {
+                    layer.visible = !view.hidden_layers.contains(&layer.name);
+                }
+            }
+            state.status = format!("View {name} at {}", view.viewport.label());
+            state.active_view = Some(name);
+        }
+        Message::ViewNameChanged(s) => state.view_name_input = s,
+        Message::SaveView => {
+            let name = state.view_name_input.trim().to_string();
+            if name.is_empty() {
+                state.status = "Name the view before saving it.".into();
+                return;
+            }
+            let hidden_layers = state
+                .base_svg
+                .iter()
+                .flat_map(|b| &b.layers)
+                .filter(|l| !l.visible)
+                .map(|l| l.name.clone())
+                .collect();
+            let view = views::View { name: name.clone(), viewport: state.viewport, overlay: state.overlay, hidden_layers };
+            let Some(fig) = state.workspace.figures.get_mut(state.workspace.active) else { return };
+            match fig.views.iter_mut().find(|v| v.name == name) {
+                Some(v) => *v = view,
+                None => fig.views.push(view),
+            }
+            state.active_view = Some(name.clone());
+            state.view_name_input.clear();
+            state.status = format!("Saved view {name} • save the workspace to keep it");
+        }
+        Message::Compare(on) => {
+            state.compare = match compare::span(&state.nkisi, Utc::now()) {
+                Some((before, after)) if on => Some(compare::Split { before, after }),
+                None if on => {
+                    state.status = "No spikes to compare yet.".into();
+                    None
+                }
+                _ => None,
+            };
+        }
+        Message::RestoreSnapshot(t) => {
+            if state.nkisi.events.iter().any(|e| e.date > t) {
+                state.confirm = Some(Destructive::RestoreSnapshot(t));
+            } else {
+                state.status = format!("Nothing was struck after {}.", t.format("%Y-%m-%d %H:%M"));
+            }
+        }
+        Message::CompareAt(after, days) => {
+            let (Some(split), Some(span)) = (&mut state.compare, compare::span(&state.nkisi, Utc::now())) else {
+                return;
+            };
+            let t = compare::at(span, days);
+            if after {
+                split.after = t;
+            } else {
+                split.before = t;
+            }
+        }
+        Message::DeleteView => {
+            let Some(name) = state.active_view.take() else { return };
+            if let Some(fig) = state.workspace.figures.get_mut(state.workspace.active) {
+                fig.views.retain(|v| v.name != name);
+            }
+            state.status = format!("Deleted view {name} • save the workspace to keep it");
+        }
+        Message::SavePathChanged(p) => state.save_path = p,
+        Message::StrikerChanged(s) => state.striker_input = s,
+        Message::SpikeMessageChanged(s) => state.message_input = s,
+        Message::WitnessesChanged(s) => state.witness_input = s,
+        Message::ToggleRule(i, on) => {
+            if let Some(rule) = state.workspace.settings.rules.get_mut(i) {
+                rule.enabled = on;
+                state.status = format!(
+                    "Rule {} • save the workspace to keep it",
+                    if on { "enabled" } else { "disabled" }
+                );
+            }
+        }
+        Message::ToggleTransform(i, on) => {
+            if let Some(t) = state.workspace.ingest.transforms.get_mut(i) {
+                t.enabled = on;
+                state.status = format!(
+                    "Ingest transform {} • save the workspace to keep it",
+                    if on { "enabled" } else { "disabled" }
+                );
+            }
+        }
+        Message::CustomFieldChanged(key, v) => {
+            state.custom_inputs.insert(key, v);
+        }
+        Message::FieldLabelChanged(s) => state.field_label_input = s,
+        Message::FieldKindChanged(k) => state.field_kind = k,
+        Message::FieldOptionsChanged(s) => state.field_options_input = s,
+        Message::AddField => {
+            match fields::define(&state.field_label_input, state.field_kind, &state.field_options_input) {
+                Ok(def) if state.workspace.settings.fields.iter().any(|d| d.key == def.key) => {
+                    state.status = format!("There is already a field called {}.", def.label);
+                }
+                Ok(def) => {
+                    state.status = format!("Added field {} • save the workspace to keep it", def.label);
+                    state.workspace.settings.fields.push(def);
+                    state.field_label_input.clear();
+                    state.field_options_input.clear();
+                }
+                Err(e) => state.status = e,
+            }
+        }
+        Message::FormEdited(i, how) => {
+            let settings = &mut state.workspace.settings;
+            let mut layout = fields::layout(&settings.form, &settings.fields);
+            fields::edit(&mut layout, i, how);
+            settings.form = layout;
+        }
+        Message::RemoveField(i) => {
+            let fields = &mut state.workspace.settings.fields;
+            if i < fields.len() {
+                let def = fields.remove(i);
+                state.status = format!("Removed field {} • values already recorded are kept", def.label);
+            }
+        }
+        Message::WindowResized(size) => state.window_width = size.width,
+        Message::StartTour => state.tour_step = Some(0),
+        Message::TourNext => {
+            let next = state.tour_step.map_or(0, |i| i + 1);
+            if next < tour::STEPS.len() {
+                state.tour_step = Some(next);
+            } else {
+                state.tour_step = None;
+                tour::mark_done();
+            }
+        }
+        Message::TourBack => {
+            state.tour_step = state.tour_step.map(|i| i.saturating_sub(1));
+        }
+        Message::TourEnd => {
+            state.tour_step = None;
+            tour::mark_done();
+        }
+        Message::ShowHelp(topic) => state.help = topic,
+        Message::ToggleHelp => {
+            state.help = match state.help {
+                Some(_) => None,
+                None => Some(help::Topic::Figure),
+            };
+        }
+
+        // Taken in by update_and_copy, once the ledger takes changes
+        Message::ExternalArrived(batches) => state.fix_held.extend(batches),
+        Message::FrameDrawn(at) => state.latency.lock().unwrap_or_else(|e| e.into_inner()).drawn(at),
+        Message::CheckReports => {
+            if let Some(report) = state.test_spike.as_ref().and_then(|rx| rx.try_recv().ok()) {
+                state.status = report;
+                state.test_spike = None;
+            }
+            if let Some(report) = state.save_hook.as_ref().and_then(|hook| hook.finished()) {
+                state.status = report;
+            }
+        }
+        Message::RefreshFixDays => {
+            state.fix_days = state.fix_store.as_ref().map(|store| store.days()).unwrap_or_default();
+        }
+        Message::ReplayDayChanged(day) => state.replay_day = Some(day),
+        Message::ReplayFixDay => match state.replay_day.clone() {
+            Some(day) => replay_fix_store(state, &[day]),
+            None => state.status = "Pick a stored day to replay.".into(),
+        },
+        Message::ExportFixLog => match (state.fix_store.clone(), state.replay_day.clone()) {
+            (Some(store), Some(day)) => {
+                let to = std::path::PathBuf::from(format!("{}-fix-{day}", state.report_path));
+                state.status = match store.export_log(&day, &to) {
+                    Ok(files) => {
+                        format!("Wrote {} to {}", confirm::count(files.len(), "FIX message log"), to.display())
+                    }
+                    Err(e) => format!("FIX log of {day} not written: {e}"),
+                };
+            }
+            _ => state.status = "Pick a stored day to export.".into(),
+        },
+        Message::RunSelfTest => {
+            let setup = diagnose::Setup {
+                figure: state.svg_path.clone(),
+                ledger: state.save_path.clone(),
+                template: state.report_template.clone(),
+                fix_addr: state.acceptor.as_ref().map_or_else(|| state.fix_addr_input.clone(), |a| a.addr.clone()),
+                fix_listening: state.acceptor.is_some(),
+                metrics_addr: state.workspace.ingest.metrics_addr.clone(),
+                metrics_listening: state.metrics_bound.is_some(),
+                drop_copy_listen: state.workspace.ingest.drop_copy_listen.clone(),
+                drop_copy_listening: state.drop_copy_pub.is_some(),
+                ..state.self_test_setup.clone()
+            };
+            let checks = diagnose::run(&setup);
+            let failed = checks.iter().filter(|c| c.outcome.is_err()).count();
+            state.status = match failed {
+                0 => "Self-test passed.".into(),
+                n => format!("Self-test: {n} check(s) failed; see the report."),
+            };
+            state.self_test = Some(checks);
+        }
+        Message::CloseSelfTest => state.self_test = None,
+        Message::ShowUsage(show) => state.show_usage = show,
+        Message::ShowOutbox(show) => state.show_outbox = show,
+        Message::ShowSessions(show) => state.show_sessions = show,
+        Message::ShowInspector(show) => state.show_inspector = show,
+        Message::InspectFix(id) => {
+            state.inspected = if state.inspected == Some(id) { None } else { Some(id) };
+        }
+        Message::ClearInspector => {
+            state.fix_inspector.clear();
+            state.inspected = None;
+        }
+        Message::SetBoundsPolicy(policy) => {
+            state.workspace.ingest.out_of_bounds = policy;
+            state.status = format!("FIX spikes outside the figure: {policy} • save the workspace to keep it");
+        }
+        Message::AcceptQuarantined(id) => accept_quarantined(state, id),
+        Message::DiscardQuarantined(id) => discard_quarantined(state, id),
+        Message::FixQuarantined(id) => {
+            let coords = coords(state);
+            let held = id.and_then(|id| state.quarantine.held.iter().find(|h| h.event.id == id));
+            state.quarantine_fix = held.map(|h| quarantine::Fix::new(h, coords));
+        }
+        Message::QuarantineFixEdited(how) => {
+            if let Some(fix) = &mut state.quarantine_fix {
+                fix.edit(how);
+            }
+        }
+        Message::ResubmitQuarantined => resubmit_quarantined(state),
+        Message::EnterSandbox => enter_sandbox(state),
+        Message::ApplySandbox => leave_sandbox(state, true),
+        Message::DiscardSandbox => leave_sandbox(state, false),
+        Message::CheckLedger => check_ledger(state),
+        Message::FixAnomaly(i) => repair_ledger(state, Some(i)),
+        Message::FixAllAnomalies => repair_ledger(state, None),
+        Message::CloseLedgerCheck => state.health = None,
+        Message::RecomputeIntensity => {
+            let half_life = state.workspace.settings.half_life_days;
+            state.intensity = charge::read(&state.nkisi.events, half_life, Utc::now());
+        }
+        Message::HalfLifeChanged(text) => {
+            let days = text.trim().parse::<f64>().ok().filter(|d| d.is_finite() && *d > 0.0);
+            state.status = match (days, text.trim().is_empty()) {
+                (Some(d), _) => format!("Intensity halves every {d} day(s) • save the workspace to keep it"),
+                (None, true) => "Intensity no longer decays • save the workspace to keep it".into(),
+                (None, false) => "Half-life: a number of days above zero, or empty for no decay.".into(),
+            };
+            if days.is_some() || text.trim().is_empty() {
+                state.workspace.settings.half_life_days = days;
+            }
+            state.half_life_input = text;
+        }
+        Message::ShowIntensityCurve(show) => state.show_intensity_curve = show,
+        Message::DisconnectSession(id) => {
+            state.status = if state.fix_sessions.disconnect(id) {
+                "Disconnecting the FIX session…".into()
+            } else {
+                "That FIX session has already closed.".into()
+            };
+        }
+        Message::Redrive(which) => redrive(state, which),
+        Message::DiscardDeadLetter(key) => {
+            let gone = state.outbox.take_dead(|d| (d.channel, d.event.id) == key);
+            state.status = format!("Discarded {}.", confirm::count(gone.len(), "dead letter"));
+        }
+        Message::ResetUsage => state.confirm = Some(Destructive::ResetUsage),
+        Message::FixAddrChanged(addr) => state.fix_addr_input = addr,
+        Message::StartAcceptor => {
+            let addr = state.fix_addr_input.trim().to_string();
+            start_acceptor(state, addr);
+        }
+        Message::RefreshInterfaces => state.interfaces = reach::interfaces(),
+        Message::CopyText(text) => {
+            state.status = "Copied to the clipboard.".into();
+            state.to_clipboard = Some(text);
+        }
+        Message::SendTestSpike(to) => {
+            let tls = state.acceptor.iter().chain(&state.listeners).any(|a| a.tls && a.bound.port() == to.port());
+            let centre = (state.figure_dims.0 / 2.0, state.figure_dims.1 / 2.0);
+            state.test_spike = Some(reach::test_spike(to, tls, Arc::clone(&state.fix_dict), centre));
+            state.status = format!("Sending a test spike through {to}…");
+        }
+        Message::StopAcceptor => match state.acceptor.take() {
+            Some(running) => {
+                let addr = running.addr.clone();
+                running.stop();
+                state.status = format!("FIX acceptor on {addr} stopped; open sessions were logged out.");
+            }
+            None => state.status = "The FIX acceptor is not running.".into(),
+        },
+    }
+}
+
+// FIX spikes that arrived go into the ledger, unless it takes no changes
+// just now (read-only, a sandbox open, or none picked yet); then they wait
+// until it does
+fn take_fix(state: &mut State) {
+    if state.fix_held.is_empty() || state.read_only.is_some() || state.sandbox.is_some() || state.start_screen {
+        return;
+    }
+    let batches = std::mem::take(&mut state.fix_held);
+    let done = ingest(state, batches, true);
+    if done.added + done.echoes + done.updated + done.cancelled + done.held + done.refused.len() > 0 {
+        state.status =
+            format!("Accepted {} FIX spike(s). Total events: {}{}", done.added, state.nkisi.events.len(), done.notes());
+    }
+}
+
+fn apply_destructive(state: &mut State, action: Destructive) {
+    match action {
+        Destructive::Clear { ids, what } => {
+            let ids: Vec<Uuid> = ids.into_iter().filter(|id| state.nkisi.events.iter().any(|e| e.id == *id)).collect();
+            if what.is_none() {
+                state.pending_pos = None;
+            }
+            state.status = match execute(state, Command::Trash(ids)) {
+                Ok(LedgerEvent::Trashed { ids }) => {
+                    let what = what.map(|w| format!(" {w}")).unwrap_or_default();
+                    format!("Moved {}{what} to the trash.", confirm::count(ids.len(), "event"))
+                }
+                _ => "Nothing to clear.".into(),
+            };
+            if state.show_clear {
+                fulltext::log(state.note_index.flush());
+                state.clear_preview = Some(preview_clear(state));
+            }
+        }
+        Destructive::DeleteEvent(id) => {
+            let who = state.nkisi.events.iter().find(|e| e.id == id).map(|e| e.performed_by.clone());
+            match execute(state, Command::Trash(vec![id])) {
+                Ok(_) => {
+                    state.status = format!(
+                        "Moved spike by {} to the trash • total events: {}",
+                        who.unwrap_or_default(),
+                        state.nkisi.events.len()
+                    );
+                }
+                Err(_) => state.status = "Event already gone.".into(),
+            }
+        }
+        Destructive::EmptyTrash => {
+            state.status = match execute(state, Command::EmptyTrash) {
+                Ok(LedgerEvent::TrashEmptied { ids }) => {
+                    format!("Permanently deleted {}.", confirm::count(ids.len(), "event"))
+                }
+                _ => "The trash is already empty.".into(),
+            };
+        }
+        Destructive::LoadOverwrite(path) => {
+            state.save_path = path;
+            load_ledger(state);
+        }
+        Destructive::LoadSample => load_sample(state),
+        Destructive::RestoreSnapshot(t) => {
+            let later = state.nkisi.events.iter().filter(|e| e.date > t).map(|e| e.id).collect();
+            state.status = match execute(state, Command::Trash(later)) {
+                Ok(LedgerEvent::Trashed { ids }) => format!(
+                    "Restored the ledger as of {} • {} moved to the trash",
+                    t.format("%Y-%m-%d %H:%M"),
+                    confirm::count(ids.len(), "event")
+                ),
+                Ok(_) => "Nothing to restore.".into(),
+                Err(e) => format!("Not restored: {e}."),
+            };
+        }
+        Destructive::OpenWorkspace(path) => {
+            open_workspace(state, path);
+        }
+        Destructive::ResetUsage => {
+            state.usage.reset();
+            state.status = "Usage counts start over from now.".into();
+        }
+        Destructive::TakeOverLock(_) => match lock::take_over(&state.save_path) {
+            Ok(l) => {
+                state.ledger_lock = Some(l);
+                // Start from what the other instance last saved
+                load_ledger(state);
+            }
+            Err(e) => state.status = format!("Take over failed: {e}"),
+        },
+        Destructive::OpenReadOnly(path) => {
+            let loaded = std::fs::read(&path)
+                .map_err(|e| IoError::Read(e.to_string()))
+                .and_then(|b| compat::parse_lenient(&b));
+            match loaded {
+                Ok(n) => {
+                    state.nkisi = n;
+                    state.read_only = Some(ReadOnly::NewerFormat);
+                    fulltext::log(state.note_index.rebuild(&state.nkisi.events));
+                    state.health = None;
+                    state.status = format!(
+                        "Opened {} read-only: {} events (format {})",
+                        path,
+                        state.nkisi.events.len(),
+                        state.nkisi.format_version
+                    );
+                }
+                Err(e) => state.status = format!("Could not read {path} even read-only: {e}"),
+            }
+        }
+    }
+}
+
+// The events the clearing scope takes now
+fn preview_clear(state: &State) -> Result<clear::Preview, String> {
+    let query = &state.global_query;
+    let found: Option<HashSet<Uuid>> = (state.clear_scope.search && !query.trim().is_empty())
+        .then(|| matching_events(&state.nkisi, &state.note_index, query).iter().map(|e| e.id).collect());
+    state.clear_scope.preview(&state.nkisi.events, found.as_ref(), query)
+}
+
+// Live events matching the search box (all of them when it's empty)
+fn matching_events<'a>(
+    nkisi: &'a NkisiNkondi,
+    index: &fulltext::NoteIndex,
+    query: &str,
+) -> Vec<&'a ActivationEvent> {
+    if query.trim().is_empty() {
+        return nkisi.events.iter().collect();
+    }
+    let ids = index.search(query).unwrap_or_else(|e| {
+        eprintln!("[index] search failed: {e}");
+        vec![]
+    });
+    let by_id: HashMap<Uuid, &ActivationEvent> = nkisi.events.iter().map(|e| (e.id, e)).collect();
+    ids.iter().filter_map(|id| by_id.get(id).copied()).collect()
+}
+
+// Run a domain command: decide the event it produces, journal it, apply it
+// to the ledger and keep the note index and drop-copy feed in step
+fn execute(state: &mut State, cmd: Command) -> Result<LedgerEvent, journal::Rejected> {
+    execute_batch(state, vec![cmd]).remove(0)
+}
+
+// Screen point over the figure to figure coordinates, through the viewport
+fn to_figure(dims: (f32, f32), view: views::Viewport, p: Point) -> (f32, f32) {
+    let (sw, sh) = figure::screen_size(dims);
+    view.to_figure(dims, (p.x / sw, p.y / sh))
+}
+
+// Put an event's pin somewhere else, as a revision that keeps the old
+// position in the journal
+fn move_pin(state: &mut State, id: Uuid, to: (f32, f32), how: &str) {
+    let to = stored(state, to);
+    let Some(ev) = state.nkisi.events.iter().find(|e| e.id == id) else { return };
+    let from = ev.pos;
+    if (from.0 - to.0).hypot(from.1 - to.1) < 0.05 {
+        state.status = "Pin not moved.".into();
+        return;
+    }
+    let event = ActivationEvent { pos: to, ..ev.clone() };
+    let note = format!("position {how} from ({:.1}, {:.1})", from.0, from.1);
+    state.status = match execute(state, Command::Revise { event, note }) {
+        Ok(_) => {
+            let coords = coords(state);
+            (state.move_x_input, state.move_y_input) = coords.inputs(to);
+            format!(
+                "Pin moved from {} to {}; the old position stays in the history.",
+                coords.format(from),
+                coords.format(to)
+            )
+        }
+        Err(e) => format!("Pin not moved: {e}"),
+    };
+}
+
+// The event before or after `current` by date (ties in ledger order), with
+// its 1-based place in that order; from no selection, the first or last
+fn step_event(
+    events: &[ActivationEvent],
+    current: Option<Uuid>,
+    forward: bool,
+) -> Option<(usize, &ActivationEvent)> {
+    let mut order: Vec<&ActivationEvent> = events.iter().collect();
+    order.sort_by_key(|e| e.date);
+    let at = current.and_then(|id| order.iter().position(|e| e.id == id));
+    let next = match (at, forward) {
+        (None, true) => 0,
+        (None, false) => order.len().checked_sub(1)?,
+        (Some(i), true) => i + 1,
+        (Some(i), false) => i.checked_sub(1)?,
+    };
+    order.get(next).map(|e| (next + 1, *e))
+}
+
+// The same edit to every event in the bulk selection, one revision each;
+// `edit` returns false for events it leaves as they are
+fn bulk_revise(state: &mut State, note: &str, mut edit: impl FnMut(&mut ActivationEvent) -> bool) {
+    let cmds: Vec<Command> = state
+        .nkisi
+        .events
+        .iter()
+        .filter(|e| state.bulk.contains(&e.id))
+        .filter_map(|e| {
+            let mut event = e.clone();
+            edit(&mut event).then(|| Command::Revise { event, note: note.to_string() })
+        })
+        .collect();
+    if cmds.is_empty() {
+        state.status = "Nothing to change in the selection.".into();
+        return;
+    }
+    let results = execute_batch(state, cmds);
+    let failed = results.iter().filter(|r| r.is_err()).count();
+    state.status = match failed {
+        0 => format!("Revised {}: {note}.", confirm::count(results.len(), "event")),
+        n => format!("Revised {} of {}: {note}; {n} could not be changed.", results.len() - n, results.len()),
+    };
+}
+
+// What reclassifying would change, before anything does
+fn preview_reclassify(state: &mut State) {
+    let Some(scope) = state.reclass_scope.clone() else {
+        state.status = "Pick a region, or select pins, to reclassify.".into();
+        return;
+    };
+    let value = state.reclass_input.trim().to_string();
+    if value.is_empty() {
+        state.status = "Type the purpose or tag to give them.".into();
+        return;
+    }
+    let change = reclassify::Change { kind: state.reclass_kind, value };
+    let regions = state.workspace.active_regions();
+    state.reclass_preview = reclassify::preview(&state.nkisi.events, regions, &state.bulk, &scope, &change);
+    state.status = match &state.reclass_preview {
+        Some(p) => format!(
+            "Preview: {} of {} would change; Apply to revise them.",
+            confirm::count(p.revise.len(), "event"),
+            p.within
+        ),
+        None => format!("{scope} no longer exists."),
+    };
+}
+
+// The previewed change, one revision per event; the round is kept so it
+// can be undone as one
+fn apply_reclassify(state: &mut State) {
+    let Some(p) = state.reclass_preview.take() else { return };
+    let note = reclassify::note(&p.scope, &p.change);
+    let cmds: Vec<Command> = state
+        .nkisi
+        .events
+        .iter()
+        .filter(|e| p.revise.contains(&e.id))
+        .filter_map(|e| {
+            let mut event = e.clone();
+            p.change.apply(&mut event).then(|| Command::Revise { event, note: note.clone() })
+        })
+        .collect();
+    if cmds.is_empty() {
+        state.status = "Nothing left to change; preview again.".into();
+        return;
+    }
+    let before = state.journal.entries.len();
+    let results = execute_batch(state, cmds);
+    let seqs: Vec<u64> = state.journal.entries[before..].iter().map(|e| e.seq).collect();
+    let done = confirm::count(results.iter().filter(|r| r.is_ok()).count(), "event");
+    state.status = format!("Reclassified {done}: {note}.");
+    if !seqs.is_empty() {
+        state.reclassified = Some((state.nkisi.id, format!("{done}, {note}"), seqs));
+    }
+}
+
+// The last round taken back, newest first, while nothing else has been
+// changed since
+fn undo_reclassify(state: &mut State) {
+    let Some((ledger, what, seqs)) = state.reclassified.take() else { return };
+    if ledger != state.nkisi.id {
+        return;
+    }
+    let mut undone = 0;
+    while state.journal.undo_target(ledger).is_ok_and(|e| seqs.contains(&e.seq)) {
+        if execute(state, Command::Undo).is_err() {
+            break;
+        }
+        undone += 1;
+    }
+    state.status = match undone {
+        n if n == seqs.len() => format!("Undone: {what}."),
+        0 => "Changes made since come first: undo those, or revise the events by hand.".into(),
+        n => format!("Undid {n} of {}; changes made since stand in the way of the rest.", seqs.len()),
+    };
+}
+
+// What became of a round of external spikes
+struct Ingested {
+    added: usize,
+    // Already in the ledger (resends, replays)
+    echoes: usize,
+    // Outcomes changed by updates, and spikes their senders cancelled
+    updated: usize,
+    cancelled: usize,
+    refused: Vec<(Uuid, String)>,
+    // Outside the figure or refused, kept in the quarantine
+    held: usize,
+}
+
+impl Ingested {
+    // Status line tail for the skipped and refused spikes
+    fn notes(&self) -> String {
+        let mut notes = String::new();
+        if self.updated > 0 {
+            notes.push_str(&format!(" • {} updated", confirm::count(self.updated, "outcome")));
+        }
+        if self.cancelled > 0 {
+            notes.push_str(&format!(" • {} cancelled", confirm::count(self.cancelled, "spike")));
+        }
+        if self.echoes > 0 {
+            notes.push_str(&format!(" • {} already known, skipped", self.echoes));
+        }
+        if self.held > 0 {
+            notes.push_str(&format!(" • {} kept in the quarantine for review", self.held));
+        }
+        if let Some((_, first)) = self.refused.first() {
+            notes.push_str(&format!(" • {} refused ({first})", self.refused.len()));
+        }
+        notes
+    }
+}
+
+// Record external spikes, one FIX message per batch, and answer their
+// senders. Live spikes count towards the latency stats; replayed ones
+// arrived long ago.
+fn ingest(state: &mut State, batches: Vec<Vec<ExternalSpike>>, live: bool) -> Ingested {
+    let mut strikes = vec![];
+    let mut replies: HashMap<Uuid, session::Reply> = HashMap::new();
+    let mut refused: Vec<(Uuid, String)> = vec![];
+    let mut amendments = vec![];
+    let mut held = 0;
+    let stats = Arc::clone(&state.latency);
+    let mut latency = stats.lock().unwrap_or_else(|e| e.into_inner());
+    for batch in batches {
+        let mut events = vec![];
+        for mut spike in batch {
+            let from = (spike.source.as_str(), spike.listener.as_deref());
+            let new = (spike.intent == Intent::Strike).then_some((&mut spike.pos, &mut spike.message));
+            transform::apply(&state.workspace.ingest.transforms, from, &mut spike.who, new);
+            if spike.intent != Intent::Strike {
+                amendments.push(spike);
+                continue;
+            }
+            if live {
+                let skew = spike
+                    .when
+                    .and_then(|w| (spike.received_at - w).num_microseconds())
+                    .map(|us| us as f64 / 1000.0);
+                latency.record(spike.received, skew);
+            }
+            let when = spike.when.unwrap_or(spike.received_at);
+            let who = spike.who;
+            let id = spike.id.unwrap_or_else(Uuid::new_v4);
+            if let Some(reply) = spike.reply {
+                replies.insert(id, reply);
+            }
+
+            events.push(ActivationEvent {
+                id,
+                date: when,
+                performed_by: who.clone(),
+                purpose: ActivationPurpose::Other(spike.purpose.unwrap_or_else(|| "External FIX spike".into())),
+                outcome: spike.outcome.unwrap_or(Outcome::Pending),
+                notes: spike.message.clone(),
+                pos: spike.pos,
+                meta: spike.meta,
+                revised: None,
+                provenance: vec![Hop {
+                    via: "fix".into(),
+                    source: spike.source,
+                    at: spike.received_at,
+                    listener: spike.listener,
+                }],
+                custom: BTreeMap::new(),
+            });
+        }
+        // Positions outside the figure, as the workspace says
+        let dims = state.figure_dims;
+        let outside = events.iter().find(|e| !quarantine::inside(e.pos, dims)).map(|e| quarantine::why(e.pos, dims));
+        if let Some(why) = outside {
+            match state.workspace.ingest.out_of_bounds {
+                quarantine::Policy::Clamp => events.iter_mut().for_each(|e| e.pos = quarantine::clamp(e.pos, dims)),
+                quarantine::Policy::Reject => {
+                    let why = match events.len() {
+                        1 => why,
+                        n => format!("message of {n} spikes refused; {why}"),
+                    };
+                    held += keep_refused(state, events, &why, &mut replies, &mut refused);
+                    continue;
+                }
+                quarantine::Policy::Quarantine => {
+                    // Those recorded before (a resend, or a replay of one
+                    // accepted) go on, to be answered as already recorded
+                    let (known, new): (Vec<_>, Vec<_>) = events.into_iter().partition(|e| known_event(state, e.id));
+                    held += keep_refused(state, new, &why, &mut replies, &mut refused);
+                    events = known;
+                    events.iter_mut().for_each(|e| e.pos = quarantine::clamp(e.pos, dims));
+                }
+            }
+        }
+        events.iter_mut().for_each(|e| e.pos = stored(state, e.pos));
+        // A message is recorded whole or not at all: one spike the rules
+        // refuse refuses the others with it. Strikes can only fail as
+        // duplicates after this, which a resend expects.
+        let (admitted, turned_away) = admit(state, events);
+        let Some((_, first)) = turned_away.first() else {
+            strikes.extend(admitted);
+            continue;
+        };
+        let why = match admitted.len() + turned_away.len() {
+            1 => first.clone(),
+            n => format!("message of {n} spikes refused; {first}"),
+        };
+        let whole = admitted
+            .into_iter()
+            .filter_map(|c| match c {
+                Command::Strike(ev) => Some(ev),
+                _ => None,
+            })
+            .chain(turned_away.into_iter().map(|(ev, _)| ev))
+            .collect();
+        held += keep_refused(state, whole, &why, &mut replies, &mut refused);
+    }
+    drop(latency);
+    if strikes.is_empty() && refused.is_empty() && amendments.is_empty() {
+        return Ingested { added: 0, echoes: 0, updated: 0, cancelled: 0, refused, held };
+    }
+    let ids: Vec<Uuid> = strikes
+        .iter()
+        .filter_map(|c| match c {
+            Command::Strike(ev) => Some(ev.id),
+            _ => None,
+        })
+        .collect();
+    let results = execute_batch(state, strikes);
+    for (id, why) in &refused {
+        if let Some(reply) = replies.remove(id) {
+            reply.rejected(*id, why);
+        }
+    }
+    for (id, result) in ids.iter().zip(&results) {
+        let Some(reply) = replies.remove(id) else { continue };
+        match result {
+            Ok(_) => reply.accepted(*id, "recorded"),
+            // A resend of one we already have: delivered all the same
+            Err(journal::Rejected::Duplicate) => reply.accepted(*id, "already recorded"),
+            Err(e) => reply.rejected(*id, &e.to_string()),
+        }
+    }
+    let added = results.iter().filter(|r| r.is_ok()).count();
+    state.usage.spikes_added(if live { "FIX" } else { "FIX replay" }, added);
+    let (updated, cancelled) = amend(state, amendments, &mut refused);
+    Ingested { added, echoes: results.len() - added, updated, cancelled, refused, held }
+}
+
+// Spikes turned away, by the rules or for lying outside the figure, kept
+// in the quarantine all the same. With the quarantine policy their senders
+// hear "pending" now and the answer after review; otherwise they are
+// refused as before. Ones already recorded are only refused. Returns how
+// many were kept.
+fn keep_refused(
+    state: &mut State,
+    events: Vec<ActivationEvent>,
+    why: &str,
+    replies: &mut HashMap<Uuid, session::Reply>,
+    refused: &mut Vec<(Uuid, String)>,
+) -> usize {
+    let pending = state.workspace.ingest.out_of_bounds == quarantine::Policy::Quarantine;
+    let mut kept = 0;
+    for ev in events {
+        if known_event(state, ev.id) {
+            refused.push((ev.id, why.to_string()));
+            continue;
+        }
+        let reply = match pending {
+            true => replies.remove(&ev.id).inspect(|r| r.pending(ev.id, &format!("held for review: {why}"))),
+            false => {
+                refused.push((ev.id, why.to_string()));
+                None
+            }
+        };
+        let (at, ledger) = (Utc::now(), state.nkisi.id);
+        state.quarantine.hold(quarantine::Held { event: ev, ledger, why: why.to_string(), at, reply });
+        kept += 1;
+    }
+    kept
+}
+
+fn known_event(state: &State, id: Uuid) -> bool {
+    let n = &state.nkisi;
+    n.events.iter().chain(&n.trash).chain(&n.cancelled).any(|e| e.id == id)
+}
+
+// A held spike recorded after all, at the nearest edge of the figure and
+// subject to the rules like any other; its sender is told, if still there
+fn accept_quarantined(state: &mut State, id: Uuid) {
+    let Some(mut held) = state.quarantine.take(id) else {
+        state.status = "That spike is no longer held.".into();
+        return;
+    };
+    let came = held.event.pos;
+    let mut ev = held.event.clone();
+    ev.pos = stored(state, quarantine::clamp(came, state.figure_dims));
+    ev.provenance.push(Hop {
+        via: "quarantine".into(),
+        source: format!("accepted after review; {}", held.why),
+        at: Utc::now(),
+        listener: None,
+    });
+    let (admitted, refused) = admit(state, vec![ev]);
+    if let Some((_, why)) = refused.into_iter().next() {
+        state.status = format!("The rules still refuse it: {why}. Fix it, discard it, or change the rules.");
+        held.why = why;
+        state.quarantine.hold(held);
+        return;
+    }
+    let who = held.event.performed_by.clone();
+    let result = execute_batch(state, admitted).pop();
+    let reply = held.reply.take();
+    state.status = match result {
+        Some(Ok(_)) => {
+            if let Some(reply) = reply {
+                reply.accepted(id, "recorded after review");
+            }
+            state.usage.spikes_added("FIX quarantine", 1);
+            let coords = coords(state);
+            let to = state.nkisi.events.iter().find(|e| e.id == id).map_or(came, |e| e.pos);
+            format!("Accepted the spike by {who}, moved from {} to {}.", coords.format(came), coords.format(to))
+        }
+        Some(Err(journal::Rejected::Duplicate)) => {
+            if let Some(reply) = reply {
+                reply.accepted(id, "already recorded");
+            }
+            format!("The spike by {who} was already recorded.")
+        }
+        Some(Err(e)) => {
+            if let Some(reply) = reply {
+                reply.rejected(id, &e.to_string());
+            }
+            format!("Could not record the spike by {who}: {e}")
+        }
+        None => String::new(),
+    };
+}
+
+// A held spike with the operator's corrections, through the rules again;
+// the fields stay open if they still refuse it
+fn resubmit_quarantined(state: &mut State) {
+    let Some(fix) = state.quarantine_fix.take() else { return };
+    let (Ok(x), Ok(y)) = (fix.x.trim().parse::<f32>(), fix.y.trim().parse::<f32>()) else {
+        state.status = "Positions must be numbers.".into();
+        state.quarantine_fix = Some(fix);
+        return;
+    };
+    if fix.who.trim().is_empty() {
+        state.status = "A spike needs a striker.".into();
+        state.quarantine_fix = Some(fix);
+        return;
+    }
+    // Typed in the shown unit
+    let coords = coords(state);
+    if !state.quarantine.correct(fix.id, fix.who.trim(), &fix.note, coords.to_figure((x, y)), coords) {
+        state.status = "That spike is no longer held.".into();
+        return;
+    }
+    accept_quarantined(state, fix.id);
+    if state.quarantine.held.iter().any(|h| h.event.id == fix.id) {
+        state.quarantine_fix = Some(fix);
+    }
+}
+
+// Held spikes let go, one or all of the open ledger's; their senders hear
+// they were refused
+fn discard_quarantined(state: &mut State, id: Option<Uuid>) {
+    let ledger = state.nkisi.id;
+    let ids: Vec<Uuid> = match id {
+        Some(id) => vec![id],
+        None => state.quarantine.held.iter().filter(|h| h.ledger == ledger).map(|h| h.event.id).collect(),
+    };
+    let mut discarded = 0;
+    for id in ids {
+        if let Some(held) = state.quarantine.take(id) {
+            if let Some(reply) = held.reply {
+                reply.rejected(id, &format!("discarded after review; {}", held.why));
+            }
+            discarded += 1;
+        }
+    }
+    state.status = format!("Discarded {} from the quarantine.", confirm::count(discarded, "spike"));
+}
+
+// Outcome updates and cancels, after the spikes that came with them. An
+// update is a revision naming who changed the outcome and over which
+// connection; a cancel leaves a tombstone saying the same. One that finds
+// its work done already is answered as done, so a resend or a replay
+// changes nothing. Returns how many outcomes changed and spikes went.
+fn amend(state: &mut State, amendments: Vec<ExternalSpike>, refused: &mut Vec<(Uuid, String)>) -> (usize, usize) {
+    let mut cmds = vec![];
+    let mut replies = vec![];
+    for change in amendments {
+        let Some(id) = change.id else { continue };
+        let answer = |reply: Option<session::Reply>, result: Result<&str, String>| match (reply, result) {
+            (Some(reply), Ok(text)) => reply.accepted(id, text),
+            (Some(reply), Err(why)) => reply.rejected(id, &why),
+            (None, _) => {}
+        };
+        let by = format!("{} ({})", change.who, change.source);
+        let reason = change.message.as_ref().map_or(String::new(), |text| format!(": {text}"));
+        if change.intent == Intent::Cancel {
+            if state.nkisi.cancelled.iter().any(|e| e.id == id) {
+                answer(change.reply, Ok("already cancelled"));
+                continue;
+            }
+            if !state.nkisi.events.iter().chain(&state.nkisi.trash).any(|e| e.id == id) {
+                let why = format!("no event {id} in the ledger");
+                refused.push((id, why.clone()));
+                answer(change.reply, Err(why));
+                continue;
+            }
+            let hop = Hop { via: "cancel".into(), source: by.clone(), at: change.received_at, listener: change.listener };
+            cmds.push(Command::Cancel { id, by: hop, note: format!("cancelled over FIX by {by}{reason}") });
+            replies.push((id, change.reply, Intent::Cancel));
+            continue;
+        }
+        let Some(outcome) = change.outcome else { continue };
+        let Some(ev) = state.nkisi.events.iter().find(|e| e.id == id) else {
+            let why = format!("no event {id} in the ledger");
+            refused.push((id, why.clone()));
+            answer(change.reply, Err(why));
+            continue;
+        };
+        if ev.outcome == outcome {
+            answer(change.reply, Ok("outcome already set"));
+            continue;
+        }
+        let note = format!("outcome {} over FIX by {by}{reason}", fixdict::outcome_code(&outcome));
+        cmds.push(Command::Revise { event: ActivationEvent { outcome, ..ev.clone() }, note });
+        replies.push((id, change.reply, Intent::Update));
+    }
+    if cmds.is_empty() {
+        return (0, 0);
+    }
+    let results = execute_batch(state, cmds);
+    let (mut updated, mut cancelled) = (0, 0);
+    for ((id, reply, intent), result) in replies.into_iter().zip(&results) {
+        let done = if intent == Intent::Cancel { "cancelled" } else { "outcome updated" };
+        match (reply, result) {
+            (Some(reply), Ok(_)) => reply.accepted(id, done),
+            (Some(reply), Err(e)) => reply.rejected(id, &e.to_string()),
+            (None, _) => {}
+        }
+        match result {
+            Ok(_) if intent == Intent::Cancel => cancelled += 1,
+            Ok(_) => updated += 1,
+            Err(e) => refused.push((id, e.to_string())),
+        }
+    }
+    (updated, cancelled)
+}
+
+// Feed the spikes stored on `days` back through the ledger; those it
+// already has are skipped
+fn replay_fix_store(state: &mut State, days: &[String]) {
+    let Some(store) = state.fix_store.clone() else {
+        state.status = "No FIX message store to replay (start with --fix-store).".into();
+        return;
+    };
+    let mut batches = vec![];
+    for day in days {
+        match stored_spikes(&store, day, &state.fix_dict, &state.fix_auth) {
+            Ok(found) => batches.extend(found),
+            Err(e) => {
+                state.status = format!("Replay of {day} failed: {e}");
+                return;
+            }
+        }
+    }
+    let done = ingest(state, batches, false);
+    let what = match days {
+        [day] => day.clone(),
+        _ => format!("{} stored day(s)", days.len()),
+    };
+    state.status =
+        format!("Replayed {what}: {} spike(s) added. Total events: {}{}", done.added, state.nkisi.events.len(), done.notes());
+}
+
+// Strikes for the events that pass the workspace rules, and why the others
+// didn't
+fn admit(state: &State, events: Vec<ActivationEvent>) -> (Vec<Command>, Vec<(ActivationEvent, String)>) {
+    let mut strikes = vec![];
+    let mut refused = vec![];
+    for ev in events {
+        let broken = rules::check(&state.workspace.settings.rules, &ev, state.workspace.active_regions());
+        if broken.is_empty() {
+            strikes.push(Command::Strike(ev));
+        } else {
+            let why = format!("spike by {}: {}", ev.performed_by, broken.join("; "));
+            eprintln!("[rules] refused {why}");
+            refused.push((ev, why));
+        }
+    }
+    (strikes, refused)
+}
+
+// Several commands at once (bursts of FIX spikes); their index changes are
+// staged for the flush at the end of the message
+fn execute_batch(
+    state: &mut State,
+    cmds: Vec<Command>,
+) -> Vec<Result<LedgerEvent, journal::Rejected>> {
+    follow_save_path(state);
+    let mut added: Vec<Uuid> = vec![];
+    let mut removed: Vec<Uuid> = vec![];
+    // Changed by a repair: indexed again, but not news to mirror
+    let mut reindexed: Vec<Uuid> = vec![];
+    let results: Vec<_> = cmds
+        .into_iter()
+        .map(|cmd| {
+            let (event, undoes) = journal::decide(&state.nkisi, &state.journal, cmd)?;
+            let (entry, written) = state.journal.append(state.nkisi.id, event.clone(), undoes);
+            let stamp = entry.stamp();
+            if let Err(e) = written {
+                eprintln!("[journal] {}: {e}; change kept in memory only", state.journal.path);
+            }
+            let before: HashSet<Uuid> = match &event {
+                LedgerEvent::Merged { .. } => state.nkisi.events.iter().map(|e| e.id).collect(),
+                _ => HashSet::new(),
+            };
+            state.nkisi.apply(&event, stamp);
+            match &event {
+                LedgerEvent::Struck { event } => added.push(event.id),
+                LedgerEvent::Restored { ids } => added.extend(ids),
+                LedgerEvent::Retracted { id } => removed.push(*id),
+                LedgerEvent::Cancelled { tombstone, .. } => removed.push(tombstone.id),
+                LedgerEvent::Trashed { ids } | LedgerEvent::Archived { ids } => removed.extend(ids),
+                LedgerEvent::TrashEmptied { .. } => {}
+                LedgerEvent::Repaired { repair } => match *repair {
+                    health::Repair::DropCopy { id } => reindexed.push(id),
+                    health::Repair::Reissue { id, new_id } => reindexed.extend([id, new_id]),
+                    health::Repair::Pin { .. } | health::Repair::Unpin { .. } => {}
+                },
+                LedgerEvent::Revised { event, .. } => {
+                    removed.push(event.id);
+                    added.push(event.id);
+                }
+                LedgerEvent::Merged { .. } => {
+                    let after: HashSet<Uuid> = state.nkisi.events.iter().map(|e| e.id).collect();
+                    added.extend(after.difference(&before));
+                    removed.extend(before.difference(&after));
+                }
+            }
+            Ok(event)
+        })
+        .collect();
+
+    if !removed.is_empty() {
+        fulltext::log(state.note_index.remove(removed));
+    }
+    if !reindexed.is_empty() {
+        fulltext::log(state.note_index.remove(reindexed.clone()));
+        let events = state.nkisi.events.iter().filter(|e| reindexed.contains(&e.id));
+        fulltext::log(state.note_index.add(events));
+    }
+    if !added.is_empty() {
+        let fresh: Vec<&ActivationEvent> =
+            state.nkisi.events.iter().filter(|e| added.contains(&e.id)).collect();
+        fulltext::log(state.note_index.add(fresh.iter().copied()));
+        if state.sandbox.is_none() {
+            mirror(state, fresh);
+        }
+    }
+    results
+}
+
+// Look the open ledger over; what is found stays listed until closed
+fn check_ledger(state: &mut State) {
+    let found = health::scan(&state.nkisi, state.figure_dims, Utc::now());
+    state.status = match found.len() {
+        0 => "Ledger check: nothing wrong found.".into(),
+        n => format!("Ledger check: {} found; see the list for fixes.", confirm::count(n, "problem")),
+    };
+    state.health = Some(found);
+}
+
+// Fix one finding, or all of them in turn (each against the ledger as the
+// previous fix left it), then look again
+fn repair_ledger(state: &mut State, which: Option<usize>) {
+    let Some(found) = state.health.clone() else { return };
+    let mut fixed = 0;
+    for (_, anomaly) in found.iter().enumerate().filter(|(i, _)| which.is_none_or(|w| w == *i)) {
+        let now = Utc::now();
+        let Some(cmd) = health::fix(&state.nkisi, &state.journal, anomaly, state.figure_dims, now) else { continue };
+        fixed += execute_batch(state, vec![cmd]).iter().filter(|r| r.is_ok()).count();
+    }
+    let left = health::scan(&state.nkisi, state.figure_dims, Utc::now());
+    state.status = match left.len() {
+        0 => format!("Ledger check: fixed {}; nothing wrong left.", confirm::count(fixed, "problem")),
+        n => format!("Ledger check: fixed {}; {n} left.", confirm::count(fixed, "problem")),
+    };
+    state.health = Some(left);
+}
+
+fn enter_sandbox(state: &mut State) {
+    if state.sandbox.is_some() {
+        return;
+    }
+    follow_save_path(state);
+    let journal = std::mem::replace(&mut state.journal, journal::Journal::in_memory(&state.save_path));
+    state.sandbox = Some(sandbox::Sandbox { ledger: state.nkisi.clone(), journal });
+    state.status = "Sandbox: changes are only tried out until you apply them; FIX spikes wait meanwhile.".into();
+}
+
+// Back to the real ledger, with the sandbox's changes run on it or not
+fn leave_sandbox(state: &mut State, apply: bool) {
+    let Some(sandbox) = state.sandbox.take() else { return };
+    let tried = std::mem::replace(&mut state.journal, sandbox.journal);
+    state.nkisi = sandbox.ledger;
+    state.pending_pos = None;
+    state.selected_event = None;
+    state.bulk.clear();
+    fulltext::log(state.note_index.rebuild(&state.nkisi.events));
+    state.health = None;
+    let cmds = sandbox::commands(&tried.entries);
+    if !apply {
+        state.status = format!("Sandbox discarded with {}.", confirm::count(cmds.len(), "change"));
+        return;
+    }
+    let struck: Vec<ActivationEvent> = cmds
+        .iter()
+        .filter_map(|c| match c {
+            Command::Strike(ev) => Some(ev.clone()),
+            _ => None,
+        })
+        .collect();
+    let n = cmds.len();
+    let failed: Vec<String> = execute_batch(state, cmds).into_iter().filter_map(|r| r.err()).map(|e| e.to_string()).collect();
+    // Spikes struck in the sandbox were confirmed here too
+    if let Some(up) = &state.upstream {
+        struck.iter().filter(|ev| state.nkisi.knows(ev.id)).for_each(|ev| up.send(ev));
+    }
+    state.status = match failed.first() {
+        None => format!("Sandbox applied: {} • total events: {}", confirm::count(n, "change"), state.nkisi.events.len()),
+        Some(why) => format!("Sandbox applied, {} of {n} change(s) not made: {why}", failed.len()),
+    };
+}
+
+// The journal lives next to the ledger file, so it follows the save path
+fn follow_save_path(state: &mut State) {
+    if state.journal.path != journal::journal_path(&state.save_path) {
+        state.journal = open_journal(&state.save_path);
+    }
+}
+
+fn open_journal(save_path: &str) -> journal::Journal {
+    journal::Journal::open(save_path).unwrap_or_else(|e| {
+        eprintln!("[journal] {}: {e}; starting a new one", journal::journal_path(save_path));
+        journal::Journal::empty(save_path)
+    })
+}
+
+// Write the ledger snapshot, stamped with the last journal entry it holds
+fn save_ledger(state: &mut State) -> Result<(), IoError> {
+    follow_save_path(state);
+    state.nkisi.journal_seq = state.journal.last_seq();
+    save_json(&state.save_path, &state.nkisi)?;
+    if let Some(hook) = &state.save_hook {
+        hook.run(&state.save_path, state.nkisi.events.len());
+    }
+    Ok(())
+}
+
+// Hand newly applied events to the drop-copy feed, if one is running
+fn mirror<'a>(state: &State, events: impl IntoIterator<Item = &'a ActivationEvent> + Clone) {
+    state.feed.publish(events.clone());
+    if let Some(publisher) = &state.drop_copy_pub {
+        publisher.publish(events.clone());
+    }
+    if let Some(hook) = &state.webhook {
+        hook.send(events.clone());
+    }
+    if let Some(dc) = &state.drop_copy {
+        dc.send(events);
+    }
+}
+
+// Dead letters sent again through the integration they failed on, one or
+// all; those whose integration isn't running stay in the outbox
+fn redrive(state: &mut State, which: Option<(outbox::Channel, Uuid)>) {
+    use outbox::Channel;
+    let running = |c: Channel| match c {
+        Channel::Webhook => state.webhook.is_some(),
+        Channel::DropCopy => state.drop_copy.is_some(),
+        Channel::Upstream => state.upstream.is_some(),
+    };
+    let letters = state
+        .outbox
+        .take_dead(|d| running(d.channel) && which.is_none_or(|key| key == (d.channel, d.event.id)));
+    for d in &letters {
+        match d.channel {
+            Channel::Webhook => state.webhook.iter().for_each(|h| h.send([&d.event])),
+            Channel::DropCopy => state.drop_copy.iter().for_each(|dc| dc.send([&d.event])),
+            Channel::Upstream => state.upstream.iter().for_each(|up| up.send(&d.event)),
+        }
+    }
+    let left = state.outbox.dead_count();
+    state.status = match (letters.len(), which) {
+        (0, Some((channel, _))) => format!("The {} isn't running here; the dead letter stays.", channel.name()),
+        (n, _) if left > 0 && which.is_none() => {
+            format!("Re-driving {}; {left} stay (their integration isn't running).", confirm::count(n, "event"))
+        }
+        (n, _) => format!("Re-driving {}.", confirm::count(n, "event")),
+    };
+}
+
+// Make sure we hold the lock on save_path before writing it. When another
+// instance holds it the ledger turns read-only and false is returned.
+fn ensure_lock(state: &mut State) -> bool {
+    if let Some(held) = &state.ledger_lock {
+        if held.ledger == state.save_path {
+            match held.check() {
+                Ok(()) => return true,
+                Err(info) => {
+                    state.status = format!("{} was taken over by {info}; now read-only.", state.save_path);
+                    state.ledger_lock = None;
+                    state.read_only = Some(ReadOnly::Locked(info));
+                    return false;
+                }
+            }
+        }
+    }
+    // Different file (or none yet): release the old lock first
+    state.ledger_lock = None;
+    match lock::acquire(&state.save_path) {
+        Ok(lock::Acquire::Acquired(l)) => {
+            state.ledger_lock = Some(l);
+            true
+        }
+        Ok(lock::Acquire::Held(info)) => {
+            state.status = format!("{} is open in another instance ({info}); read-only.", state.save_path);
+            state.read_only = Some(ReadOnly::Locked(info));
+            false
+        }
+        Err(e) => {
+            // e.g. a read-only directory: carry on unlocked, the write
+            // itself will report the real problem
+            eprintln!("[lock] {e}");
+            true
+        }
+    }
+}
+
+fn load_layers(path: &str) -> Option<layers::LayeredSvg> {
+    let source = std::fs::read_to_string(path).ok()?;
+    layers::LayeredSvg::parse(source).ok()
+}
+
+// Re-read the base figure after its path changed, keeping layer choices
+// and picking up the new coordinate space
+fn reload_base_svg(state: &mut State) {
+    state.figure_mtime = figure::modified(&state.svg_path);
+    let o = orientation(state);
+    match figure::kind(&state.svg_path) {
+        figure::BaseKind::Raster => {
+            state.base_svg = None;
+            state.figure_dims = o.dims(figure::raster_dims(&state.svg_path).unwrap_or_else(figure::default_dims));
+        }
+        figure::BaseKind::Svg => {
+            let mut fresh = load_layers(&state.svg_path);
+            if let (Some(new), Some(old)) = (&mut fresh, &state.base_svg) {
+                new.carry_visibility(old);
+            }
+            state.figure_dims = o.dims(fresh.as_ref().and_then(|b| b.view_box).unwrap_or_else(figure::default_dims));
+            state.base_svg = fresh;
+        }
+    }
+}
+
+// A position as the ledger keeps it: to the workspace's precision, and
+// still inside the figure if it was
+fn stored(state: &State, pos: (f32, f32)) -> (f32, f32) {
+    let snapped = state.workspace.settings.precision.snap(pos);
+    match quarantine::inside(pos, state.figure_dims) {
+        true => quarantine::clamp(snapped, state.figure_dims),
+        false => snapped,
+    }
+}
+
+// How positions are shown, for the open figure
+fn coords(state: &State) -> units::Coords {
+    let printed = state.base_svg.as_ref().and_then(|b| b.printed).map(|mm| orientation(state).dims(mm));
+    units::Coords { unit: state.workspace.settings.coordinate_unit, dims: state.figure_dims, mm: printed }
+}
+
+fn orientation(state: &State) -> figure::Orientation {
+    state.workspace.active_figure().map(|f| f.orientation).unwrap_or_default()
+}
+
+// The figure as displayed when it is turned or mirrored; None when the
+// file can be shown as it is
+fn oriented_base(state: &State) -> Option<String> {
+    (!orientation(state).is_identity()).then(|| framed_base(state))
+}
+
+// The figure as displayed, as an SVG with its viewBox over the whole
+// figure, ready to be turned or cropped to the viewport
+fn framed_base(state: &State) -> String {
+    let o = orientation(state);
+    let rendered;
+    let base = match &state.base_svg {
+        Some(layers) => {
+            rendered = layers.render();
+            export::Base::Svg(rendered)
+        }
+        None => export::Base::Raster(&state.svg_path),
+    };
+    export::orient(&base, o, o.source_dims(state.figure_dims), &figure::href(&state.svg_path))
+}
+
+// The figure file changed on disk. An SVG caught half-written (or broken)
+// keeps the previous artwork until the next change.
+fn hot_reload_figure(state: &mut State) {
+    if figure::kind(&state.svg_path) == figure::BaseKind::Svg
+        && state.base_svg.is_some()
+        && load_layers(&state.svg_path).is_none()
+    {
+        state.figure_mtime = figure::modified(&state.svg_path);
+        eprintln!("[figure] {} changed but could not be parsed; keeping the previous version", state.svg_path);
+        return;
+    }
+    reload_base_svg(state);
+    state.status = format!("Reloaded {}", state.svg_path);
+}
+
+// Copy the live paths/toggles back into the workspace model
+fn sync_active_figure(state: &mut State) {
+    if let Some(fig) = state.workspace.figures.get_mut(state.workspace.active) {
+        fig.ledger = state.save_path.clone();
+        fig.svg = state.svg_path.clone();
+    }
+    state.workspace.settings.overlay = state.overlay;
+}
+
+// Saves the open figure's ledger, then opens figure `i` of the workspace
+fn switch_figure(state: &mut State, i: usize) {
+    if i == state.workspace.active || i >= state.workspace.figures.len() {
+        return;
+    }
+    sync_active_figure(state);
+    if state.read_only.is_none() && ensure_lock(state) {
+        if let Err(e) = save_ledger(state) {
+            state.status = format!("Not switching: saving {} failed: {e}", state.save_path);
+            return;
+        }
+    }
+    state.workspace.active = i;
+    open_active_figure(state);
+}
+
+fn open_active_figure(state: &mut State) {
+    let Some(fig) = state.workspace.active_figure().cloned() else { return };
+    state.save_path = fig.ledger;
+    state.svg_path = fig.svg;
+    reload_base_svg(state);
+    state.viewport = views::Viewport::default();
+    state.active_view = None;
+    state.compare = None;
+    state.pending_pos = None;
+    state.bulk.clear();
+    if std::path::Path::new(&state.save_path).exists() {
+        load_ledger(state);
+    } else {
+        state.nkisi = NkisiNkondi::default();
+        state.journal = open_journal(&state.save_path);
+        state.read_only = None;
+        fulltext::log(state.note_index.rebuild(&state.nkisi.events));
+        state.health = None;
+        state.status = format!("{}: new ledger, saved to {}", fig.name, state.save_path);
+        ensure_lock(state);
+    }
+    state.recent.remember(&state.save_path);
+}
+
+// Another copy of the open figure merged in; events edited differently on
+// each side wait under Conflicts
+fn merge_copy(state: &mut State, source: String, remote: NkisiNkondi) {
+    let conflicts = crdt::merge(&state.nkisi, &remote).conflicts;
+    let before = state.nkisi.events.len();
+    let merge = Command::Merge { source: source.clone(), remote: Box::new(remote) };
+    state.status = match execute(state, merge) {
+        Ok(_) => {
+            state.usage.spikes_added("merge", state.nkisi.events.len().saturating_sub(before));
+            let mut s = format!("Merged {source} • events: {before} → {}", state.nkisi.events.len());
+            if !conflicts.is_empty() {
+                s.push_str(&format!(
+                    " • {} edited differently on each side, see Conflicts",
+                    confirm::count(conflicts.len(), "event")
+                ));
+            }
+            state.conflicts = conflicts;
+            s
+        }
+        Err(e) => format!("Not merged: {e}."),
+    };
+}
+
+// A figure out of the workspace, which must not be the open one
+fn remove_figure(state: &mut State, i: usize) -> FigureRef {
+    let fig = state.workspace.figures.remove(i);
+    if i < state.workspace.active {
+        state.workspace.active -= 1;
+    }
+    state.forks.clear();
+    fig
+}
+
+// Figures of the workspace that are copies of one ledger, named on the
+// status line when there are any
+fn find_forks(state: &mut State) {
+    let open = (state.workspace.active, &state.nkisi);
+    let (forks, unreadable) = forks::find(&state.workspace.figures, open, read_ledger);
+    for e in unreadable {
+        eprintln!("[forks] {e}");
+    }
+    if !forks.is_empty() {
+        state.status.push_str(&format!(
+            " • {} in the workspace, see Forked figures",
+            confirm::count(forks.len(), "forked pair")
+        ));
+    }
+    state.forks = forks;
+}
+
+// The operator's answer to a fork: the figure kept is opened, the other
+// merged into it if asked, then taken out of the workspace
+fn resolve_fork(state: &mut State, i: usize, choice: forks::Choice) {
+    let Some(fork) = state.forks.get(i).cloned() else { return };
+    let keep = match choice {
+        forks::Choice::Ignore => {
+            state.forks.remove(i);
+            state.status = "Both copies stay in the workspace; they are listed again when it is next opened.".into();
+            return;
+        }
+        forks::Choice::MergeInto(keep) | forks::Choice::Keep(keep) => keep,
+    };
+    let drop = fork.other(keep);
+    if keep != state.workspace.active {
+        switch_figure(state, keep);
+        if keep != state.workspace.active {
+            return;
+        }
+    }
+    let gone = state.workspace.figures[drop].clone();
+    let mut done = String::new();
+    if let forks::Choice::MergeInto(_) = choice {
+        let remote = match read_ledger(&gone.ledger) {
+            Ok(n) => n,
+            Err(e) => {
+                state.status = format!("Merge failed: {}: {e}", gone.ledger);
+                return;
+            }
+        };
+        merge_copy(state, gone.ledger.clone(), remote);
+        if !ensure_lock(state) {
+            return;
+        }
+        if let Err(e) = save_ledger(state) {
+            state.status =
+                format!("Merged, but saving {} failed: {e}; {} stays in the workspace", state.save_path, gone.name);
+            return;
+        }
+        done = format!("{} • ", state.status);
+    }
+    remove_figure(state, drop);
+    state.status = format!(
+        "{done}Kept {}; {} left the workspace (its ledger file is kept) • save the workspace to keep it",
+        state.workspace.figures[state.workspace.active].name, gone.name
+    );
+    find_forks(state);
+}
+
+// Spikes from the pack at pack_path into the open ledger, as exported
+// there and through the same checks as any others
+fn import_pack(state: &mut State) {
+    let Some(key) = &state.pack_key else {
+        state.status = "Set the pack key shared with the sender to import packs.".into();
+        return;
+    };
+    let pack = match pack::read(&state.pack_path, key) {
+        Ok(p) => p,
+        Err(e) => {
+            state.status = format!("Import refused: {}: {e}", state.pack_path);
+            return;
+        }
+    };
+    let hop = Hop {
+        via: "pack".into(),
+        source: format!(
+            "{} ({} from {}, exported {})",
+            state.pack_path,
+            pack.figure,
+            pack.exported_by,
+            pack.exported_at.format("%Y-%m-%d %H:%M")
+        ),
+        at: Utc::now(),
+        listener: None,
+    };
+    let events = pack
+        .events
+        .into_iter()
+        .map(|mut ev| {
+            ev.provenance.push(hop.clone());
+            ev
+        })
+        .collect();
+    let (strikes, refused) = admit(state, events);
+    let results = execute_batch(state, strikes);
+    let imported = results.iter().filter(|r| r.is_ok()).count();
+    state.usage.spikes_added("pack", imported);
+    state.status = format!(
+        "Imported {} from {} • {} already here",
+        confirm::count(imported, "event"),
+        pack.exported_by,
+        results.len() - imported
+    );
+    if let Some((_, first)) = refused.first() {
+        state.status.push_str(&format!(" • {} refused ({first})", refused.len()));
+    }
+}
+
+// The ledger picked on the start screen: by path a ledger, a workspace or
+// a pack to import. One that can't be read keeps the screen up, so the
+// blank figure behind it isn't saved over it.
+fn start_open(state: &mut State, path: &str) {
+    if path.is_empty() {
+        state.status = "Type the path of a ledger, a workspace or a spike pack.".into();
+        return;
+    }
+    if !std::path::Path::new(path).exists() {
+        state.status = format!("{path} not found.");
+        return;
+    }
+    if path.ends_with(".nkisipack") {
+        state.start_path = path.to_string();
+        return start_import(state);
+    }
+    if path.ends_with(".nkisiproj") {
+        state.start_screen = !open_workspace(state, path.to_string());
+        return;
+    }
+    match read_ledger(path) {
+        Ok(_) | Err(IoError::Newer { .. }) => {}
+        Err(e) => {
+            state.status = format!("Can't open {path}: {e}");
+            return;
+        }
+    }
+    start_figure(state, path);
+}
+
+fn start_new(state: &mut State) {
+    let path = state.start_path.trim().to_string();
+    if path.is_empty() {
+        state.status = "Type a path for the new ledger, nkisi_state.json say.".into();
+        return;
+    }
+    if std::path::Path::new(&path).exists() {
+        state.status = format!("{path} already exists; Open it, or type another path.");
+        return;
+    }
+    start_figure(state, &path);
+}
+
+// A pack goes into a new ledger beside it, named after it
+fn start_import(state: &mut State) {
+    let pack = state.start_path.trim().to_string();
+    if !std::path::Path::new(&pack).exists() {
+        state.status = format!("{pack} not found.");
+        return;
+    }
+    if state.pack_key.is_none() {
+        state.status = "Set the pack key shared with the sender (pack_key) to import packs.".into();
+        return;
+    }
+    let ledger = std::path::Path::new(&pack).with_extension("json").to_string_lossy().into_owned();
+    if std::path::Path::new(&ledger).exists() {
+        state.status = format!("{ledger} already exists; Open it and import the pack from there.");
+        return;
+    }
+    start_figure(state, &ledger);
+    state.pack_path = pack;
+    import_pack(state);
+}
+
+// The figure of the default workspace, on the ledger at `path`
+fn start_figure(state: &mut State, path: &str) {
+    if let Some(fig) = state.workspace.figures.get_mut(state.workspace.active) {
+        fig.ledger = path.to_string();
+        fig.name = start::figure_name(path);
+    }
+    open_active_figure(state);
+    state.start_screen = false;
+}
+
+fn open_workspace(state: &mut State, path: String) -> bool {
+    match workspace::open(&path) {
+        Ok(ws) => {
+            let moved = ws.ingest.fix_addr != state.workspace.ingest.fix_addr;
+            let relisten = ws.ingest.listeners != state.workspace.ingest.listeners;
+            state.overlay = ws.settings.overlay;
+            state.active_profile = None;
+            state.fix_addr_input = ws.ingest.fix_addr.clone();
+            state.half_life_input = half_life_text(ws.settings.half_life_days);
+            state.workspace = ws;
+            state.workspace_path = path;
+            open_active_figure(state);
+            // A running listener follows the workspace to its address
+            if moved && state.acceptor.is_some() {
+                let opened = std::mem::take(&mut state.status);
+                start_acceptor(state, state.fix_addr_input.clone());
+                state.status = format!("{opened} • {}", state.status);
+            }
+            if relisten {
+                start_listeners(state);
+            }
+            find_forks(state);
+            true
+        }
+        Err(e) => {
+            state.status = format!("Open workspace failed: {e}");
+            false
+        }
+    }
+}
+
+// Listen on `addr`. A listener already running elsewhere keeps going
+// until the new one is up, and keeps going if it can't be; one on the
+// same address has to let go of the port first.
+fn start_acceptor(state: &mut State, addr: String) {
+    let wiring = match &state.fix_wiring {
+        Ok(wiring) => wiring.clone(),
+        Err(why) => {
+            state.status = format!("The FIX acceptor can't start: {why}.");
+            return;
+        }
+    };
+    if let Some(same) = state.acceptor.take_if(|a| a.addr == addr) {
+        same.stop();
+    }
+    match acceptor::Acceptor::start(acceptor::MAIN, &addr, &wiring) {
+        Ok(started) => {
+            if let Some(old) = state.acceptor.replace(started) {
+                old.stop();
+            }
+            let secured = if wiring.tls.is_some() { " (TLS)" } else { "" };
+            state.status = format!("FIX acceptor listening on {addr}{secured}");
+            state.workspace.ingest.fix_addr = addr;
+        }
+        Err(e) => {
+            state.status = format!("FIX acceptor can't listen on {addr}: {e}");
+            if let Some(old) = &state.acceptor {
+                state.status.push_str(&format!("; still on {}", old.addr));
+            }
+        }
+    }
+}
+
+// Bring the workspace's other acceptors up, in place of any running;
+// those that can't be started are named in the status line
+fn start_listeners(state: &mut State) {
+    for running in state.listeners.drain(..) {
+        running.stop();
+    }
+    let Ok(wiring) = &state.fix_wiring else { return };
+    let mut failed = vec![];
+    for l in &state.workspace.ingest.listeners {
+        match acceptor::Acceptor::start(&l.name, &l.addr, wiring) {
+            Ok(started) => state.listeners.push(started),
+            Err(e) => {
+                eprintln!("[FIX] {}: can't listen on {}: {e}", l.name, l.addr);
+                failed.push(format!("{} ({}: {e})", l.name, l.addr));
+            }
+        }
+    }
+    if !failed.is_empty() {
+        state.status.push_str(&format!(" • FIX listener(s) not started: {}", failed.join(", ")));
+    }
+}
+
+// The bundled sample, saved to a file of its own so the ledger it replaces
+// isn't written over, with a journal of its own
+fn load_sample(state: &mut State) {
+    let n = match compat::parse(tour::SAMPLE_LEDGER.as_bytes()) {
+        Ok(n) => n,
+        Err(e) => {
+            state.status = format!("Sample ledger is corrupt: {e}");
+            return;
+        }
+    };
+    state.nkisi = n;
+    state.save_path = tour::SAMPLE_SAVE_PATH.into();
+    state.journal = open_journal(&state.save_path);
+    state.read_only = None;
+    state.conflicts.clear();
+    fulltext::log(state.note_index.rebuild(&state.nkisi.events));
+    state.health = None;
+    state.svg_path = "assets/nkisi.svg".into();
+    reload_base_svg(state);
+    state.pending_pos = None;
+    state.bulk.clear();
+    state.status =
+        format!("Loaded sample ledger ({} events). Save will write it to {}", state.nkisi.events.len(), state.save_path);
+    ensure_lock(state);
+}
+
+fn load_ledger(state: &mut State) {
+    match load_json(&state.save_path) {
+        Ok(n) => {
+            state.nkisi = n;
+            state.read_only = None;
+            state.conflicts.clear();
+            // Changes journaled after this snapshot was saved
+            state.journal = open_journal(&state.save_path);
+            let unsaved: Vec<journal::Entry> = state
+                .journal
+                .since(state.nkisi.id, state.nkisi.journal_seq)
+                .cloned()
+                .collect();
+            for entry in &unsaved {
+                state.nkisi.apply(&entry.event, entry.stamp());
+            }
+            let resynced = health::resync(&mut state.nkisi);
+            fulltext::log(state.note_index.rebuild(&state.nkisi.events));
+            state.health = None;
+            state.status = format!(
+                "Loaded {} events / {} pins from {}",
+                state.nkisi.events.len(),
+                state.nkisi.pins.len(),
+                state.save_path
+            );
+            if !unsaved.is_empty() {
+                state.status.push_str(&format!(
+                    " • recovered {} unsaved change(s) from the journal",
+                    unsaved.len()
+                ));
+            }
+            if let Some(done) = resynced {
+                eprintln!(
+                    "[load] {}: pins out of step with events; added {:?}, removed {:?}",
+                    state.save_path, done.pinned, done.dropped
+                );
+                state.status.push_str(&format!(" • pins repaired: {}; save to keep it", done.describe()));
+            }
+            ensure_lock(state);
+            state.recent.remember(&state.save_path);
+        }
+        Err(IoError::Newer { found, saved_by }) => {
+            // Never let a save overwrite the newer file, even if the
+            // read-only offer is declined
+            state.read_only = Some(ReadOnly::NewerFormat);
+            state.status = format!(
+                "{} was saved by version {saved_by} (format {found}); this build reads format {}.",
+                state.save_path,
+                compat::FORMAT_VERSION
+            );
+            state.confirm = Some(Destructive::OpenReadOnly(state.save_path.clone()));
+        }
+        Err(e) => state.status = format!("Load failed: {e}"),
+    }
+}
+
+// -------------------- View --------------------
+fn view(state: &State) -> Element<'_, Message> {
+    if state.start_screen {
+        let default = workspace::DEFAULT_LEDGER;
+        let found = std::path::Path::new(default).exists() && state.recent.ledgers.iter().all(|o| o.path != default);
+        return with_confirm(
+            state,
+            start::view(start::Screen {
+                recent: &state.recent,
+                found: found.then_some(default),
+                path: &state.start_path,
+                status: &state.status,
+            }),
+        );
+    }
+    let figure = tour::highlight(state.tour_step, tour::TourTarget::Figure, figure_view(state));
+    // The window is measured before the interface zoom applies
+    let compact = state.window_width / state.workspace.settings.ui_scale < COMPACT_BREAKPOINT;
+
+    let mut controls_col = controls_view(state);
+
+    // Pending Spike confirmation panel (for local clicks): inline in the
+    // controls column normally, docked as a bottom sheet in compact mode.
+    let pending = state.pending_pos.map(|pos| pending_panel(state, pos, compact));
+    let (inline, sheet) = if compact { (None, pending) } else { (pending, None) };
+    if let Some(pending) = inline {
+        controls_col = controls_col.push(pending);
+    }
+
+    // Status line
+    controls_col = controls_col.push(tour::highlight(
+        state.tour_step,
+        tour::TourTarget::Status,
+        row![status_line(state), help::hint(help::Topic::Fix)].spacing(8),
+    ));
+
+    if compact {
+        let body = scrollable(
+            column![container(figure).center_x(Length::Fill), controls_col]
+                .spacing(12)
+                .padding(8),
+        )
+        .height(Length::Fill);
+
+        let mut layout = column![body];
+        if let Some(sheet) = sheet {
+            layout = layout.push(sheet);
+        }
+        return with_confirm(state, layout.into());
+    }
+
+    let content = row![figure, container(scrollable(controls_col)).padding(16)]
+        .spacing(24)
+        .padding(16)
+        .into();
+    with_confirm(state, content)
+}
+
+fn with_confirm<'a>(state: &'a State, content: Element<'a, Message>) -> Element<'a, Message> {
+    let content = match state.help {
+        Some(topic) => help::modal(content, topic, &state.workspace.ingest.fix_addr, &state.fix_dict),
+        None => content,
+    };
+    match &state.confirm {
+        Some(action) => confirm::modal(content, action, &state.nkisi, coords(state)),
+        None => content,
+    }
+}
+
+fn figure_view(state: &State) -> Element<'_, Message> {
+    match state.compare {
+        Some(split) => compare_view(state, split),
+        None => figure_pane(state, render_overlay_svg(state, true), true),
+    }
+}
+
+// The figure under `overlay`; only an interactive pane takes clicks, any
+// pane zooms and pans the shared viewport
+fn figure_pane(state: &State, overlay: String, interactive: bool) -> Element<'_, Message> {
+    let (sw, sh) = figure::screen_size(state.figure_dims);
+
+    // Base figure: a photo, or the SVG (type-annotated to pin Theme
+    // generic). The SVG is rendered from the parsed source so hidden layers
+    // drop out and a hot-reloaded file isn't served from the path cache.
+    let view = state.viewport;
+    let base: Element<Message> = match figure::kind(&state.svg_path) {
+        _ if !orientation(state).is_identity() || !view.is_whole() => {
+            let handle = svg::Handle::from_memory(view.crop(framed_base(state), state.figure_dims).into_bytes());
+            let base: Svg<'_, Theme> = svg(handle).width(Length::Fixed(sw)).height(Length::Fixed(sh));
+            base.into()
+        }
+        figure::BaseKind::Raster => iced::widget::image(&state.svg_path)
+            .width(Length::Fixed(sw))
+            .height(Length::Fixed(sh))
+            .into(),
+        figure::BaseKind::Svg => {
+            let handle = match &state.base_svg {
+                Some(base) if base.has_hidden() => {
+                    svg::Handle::from_memory(base.render().into_bytes())
+                }
+                Some(base) => svg::Handle::from_memory(base.source.clone().into_bytes()),
+                None => svg::Handle::from_path(&state.svg_path),
+            };
+            let base: Svg<'_, Theme> = svg(handle)
+                .width(Length::Fixed(sw))
+                .height(Length::Fixed(sh));
+            base.into()
+        }
+    };
+
+    // Mouse area over the base: track cursor & emit "ProposeSpike" on click
+    let mut area = iced::widget::mouse_area::<Message, Theme, Renderer>(base)
+        .on_move(Message::CursorMoved)
+        .on_right_press(Message::PanStarted)
+        .on_right_release(Message::PanEnded)
+        .on_exit(Message::PanEnded)
+        .on_scroll(|delta| {
+            Message::ScrollFigure(match delta {
+                mouse::ScrollDelta::Lines { y, .. } => y,
+                mouse::ScrollDelta::Pixels { y, .. } => y / 40.0,
+            })
+        });
+    if interactive {
+        area = area.on_press(Message::ProposeSpike).on_release(Message::FigureReleased);
+    }
+    let clickable: Element<Message> = area.into();
+
+    // Overlay pins/grid as another SVG on top
+    let overlay_handle = svg::Handle::from_memory(view.crop(overlay, state.figure_dims).into_bytes());
+    let overlay_svg: Svg<'_, Theme> = svg(overlay_handle)
+        .width(Length::Fixed(sw))
+        .height(Length::Fixed(sh));
+    let overlay: Element<Message> = overlay_svg.into();
+
+    column![clickable, overlay].spacing(0).into()
+}
+
+// Two panes at the split's moments, each over a slider moving it
+fn compare_view(state: &State, split: compare::Split) -> Element<'_, Message> {
+    let Some(span) = compare::span(&state.nkisi, Utc::now()) else {
+        return figure_pane(state, render_overlay_svg(state, true), true);
+    };
+    let shown_regions: &[regions::Region] =
+        if state.overlay.regions { state.workspace.active_regions() } else { &[] };
+    let look = &state.workspace.settings.appearance;
+    let total = compare::days(span, span.1);
+    let side = |after: bool, t: DateTime<Utc>| -> Element<'_, Message> {
+        let then = compare::snapshot(&state.nkisi, t);
+        let caption = format!(
+            "{} • {} • {}",
+            if after { "After" } else { "Before" },
+            t.format("%Y-%m-%d"),
+            confirm::count(then.events.len(), "spike")
+        );
+        let overlay = overlay_svg(&then, state.figure_dims, shown_regions, state.overlay, look, None, None);
+        column![
+            iced::widget::text(caption).size(14),
+            figure_pane(state, overlay, false),
+            iced::widget::slider(0.0..=total, compare::days(span, t), move |d| Message::CompareAt(after, d))
+                .step(1.0)
+                .width(Length::Fixed(figure::screen_size(state.figure_dims).0)),
+        ]
+        .spacing(6)
+        .into()
+    };
+    column![
+        row![side(false, split.before), side(true, split.after)].spacing(12),
+        row![
+            button("Restore Before").style(button::danger).on_press(Message::RestoreSnapshot(split.before)),
+            button("Close before/after").style(button::secondary).on_press(Message::Compare(false)),
+        ]
+        .spacing(8),
+    ]
+    .spacing(8)
+    .into()
+}
+
+fn controls_view(state: &State) -> iced::widget::Column<'_, Message> {
+    let mut col = column![row![
+        iced::widget::text("Rustic Nkisi • Spike Ledger (FIX-enabled)").size(22).width(Length::Fill),
+        button("Usage").style(button::secondary).on_press(Message::ShowUsage(!state.show_usage)),
+        button("Strikers").style(button::secondary).on_press(Message::ShowStrikers(!state.show_strikers)),
+        button(iced::widget::text(match state.fix_sessions.count() {
+            0 => "Sessions".to_string(),
+            n => format!("Sessions ({n})"),
+        }))
+        .style(button::secondary)
+        .on_press(Message::ShowSessions(!state.show_sessions)),
+        button("Inspector").style(button::secondary).on_press(Message::ShowInspector(!state.show_inspector)),
+        button("Outbox").style(button::secondary).on_press(Message::ShowOutbox(!state.show_outbox)),
+        button("Self-test").style(button::secondary).on_press(Message::RunSelfTest),
+        button("Help (F1)").style(button::secondary).on_press(Message::ShowHelp(Some(help::Topic::Figure))),
+    ]
+    .spacing(8)
+    .align_y(alignment::Vertical::Center)];
+    let warn = Color::from_rgb(1.0, 0.6, 0.3);
+    match &state.read_only {
+        Some(ReadOnly::NewerFormat) => {
+            col = col.push(
+                iced::widget::text(format!(
+                    "READ-ONLY • saved by version {}",
+                    state.nkisi.saved_by.as_deref().unwrap_or("unknown")
+                ))
+                .color(warn),
+            );
+        }
+        Some(ReadOnly::Locked(info)) => {
+            col = col.push(
+                row![
+                    iced::widget::text(format!("READ-ONLY • open in another instance ({info})"))
+                        .color(warn),
+                    button("Take over").on_press(Message::TakeOverLock),
+                ]
+                .spacing(10)
+                .align_y(alignment::Vertical::Center),
+            );
+        }
+        None => {}
+    }
+    if state.sandbox.is_some() {
+        let changes = state.journal.entries.len();
+        col = col.push(
+            row![
+                iced::widget::text(format!("SANDBOX • {} tried, nothing saved", confirm::count(changes, "change")))
+                    .color(warn),
+                button("Apply").on_press(Message::ApplySandbox),
+                button("Discard").style(button::danger).on_press(Message::DiscardSandbox),
+            ]
+            .spacing(10)
+            .align_y(alignment::Vertical::Center),
+        );
+    }
+    if let Some(checks) = &state.self_test {
+        col = col.push(diagnose::view(checks));
+    }
+    if state.show_usage {
+        col = col.push(usage::view(&state.usage));
+    }
+    if state.show_strikers {
+        col = col.push(strikers::view(strikers::summary(&state.nkisi.events, state.workspace.active_regions())));
+    }
+    if state.show_sessions {
+        col = col.push(sessions::view(&state.fix_sessions));
+    }
+    if state.show_inspector {
+        col = col.push(inspector::view(&state.fix_inspector, state.inspected, &state.fix_dict));
+    }
+    if state.show_outbox {
+        col = col.push(outbox::view(&state.outbox));
+    }
+    if let Some(notes) = &state.release_notes {
+        col = col.push(release_notes_card(notes));
+    }
+    if let Some(i) = state.tour_step {
+        col = col.push(tour::card(i, &state.workspace.ingest.fix_addr));
+    }
+
+    col.push(tour::highlight(
+        state.tour_step,
+        tour::TourTarget::Ledger,
+        row![
+            button("Save").on_press(Message::Save),
+            button("Load").on_press(Message::Load),
+            button("Load sample").on_press(Message::LoadSample),
+            button("Clear…").on_press(Message::ShowClear(!state.show_clear)),
+            button("Sandbox").style(button::secondary).on_press_maybe(state.sandbox.is_none().then_some(Message::EnterSandbox)),
+            button("Check ledger").style(button::secondary).on_press(Message::CheckLedger),
+        ]
+        .spacing(10),
+    ))
+    .push_maybe(state.show_clear.then(|| {
+        clear::view(clear::Panel {
+            scope: &state.clear_scope,
+            preview: state.clear_preview.as_ref(),
+            events: &state.nkisi.events,
+        })
+    }))
+    .push(
+        row![
+            iced::widget::text(match state.workspace.settings.half_life_days {
+                Some(_) => format!("Intensity: {:.1}", state.intensity.now),
+                None => format!("Intensity: {}", state.intensity.now),
+            }),
+            text_input("half-life (days)", &state.half_life_input).on_input(Message::HalfLifeChanged).width(130),
+            button(if state.show_intensity_curve { "Hide curve" } else { "Curve" })
+                .style(button::secondary)
+                .on_press(Message::ShowIntensityCurve(!state.show_intensity_curve)),
+            button("Tour").on_press(Message::StartTour),
+        ]
+        .spacing(16)
+        .align_y(alignment::Vertical::Center),
+    )
+    .push_maybe(state.show_intensity_curve.then(|| intensity_curve(state)))
+    .push(overlay_controls(state))
+    .push(tour::highlight(
+        state.tour_step,
+        tour::TourTarget::Paths,
+        column![
+            row![
+                iced::widget::text("Figure path:"),
+                text_input("assets/nkisi.svg (or .png / .jpg photo)", &state.svg_path)
+                    .on_input(Message::SvgPathChanged)
+                    .padding(6),
+                button("⟲").on_press(Message::RotateFigure(false)),
+                button("⟳").on_press(Message::RotateFigure(true)),
+                button("Mirror").on_press(Message::MirrorFigure),
+                iced::widget::text(orientation(state).to_string()),
+            ]
+            .spacing(8)
+            .align_y(alignment::Vertical::Center),
+            row![
+                iced::widget::text("Save path:"),
+                text_input("nkisi_state.json", &state.save_path)
+                    .on_input(Message::SavePathChanged)
+                    .padding(6),
+            ]
+            .spacing(8),
+        ]
+        .spacing(8),
+    ))
+    .push(acceptor_controls(state))
+    .push(reach::view(
+        state.acceptor.iter().chain(&state.listeners).map(|a| (a.name.as_str(), a.bound, a.tls)).collect(),
+        state.metrics_bound,
+        state.drop_copy_pub.as_ref().map(|p| (p.bound, p.count())),
+        &state.interfaces,
+        state.test_spike.is_some(),
+    ))
+    .push(layers_view(state))
+    .push(events_view(state))
+    .push(details_view(state))
+    .push(bulk_view(state))
+    .push(reclassify::view(reclassify::Panel {
+        scopes: reclassify::scopes(state.workspace.active_regions(), state.bulk.len()),
+        scope: state.reclass_scope.as_ref(),
+        kind: state.reclass_kind,
+        input: &state.reclass_input,
+        preview: state.reclass_preview.as_ref(),
+        events: &state.nkisi.events,
+        applied: state
+            .reclassified
+            .as_ref()
+            .filter(|(ledger, ..)| *ledger == state.nkisi.id)
+            .map(|(_, what, _)| what.as_str()),
+    }))
+    .push(trash_view(state))
+    .push(history_view(state))
+    .push(archive_view(state))
+    .push(fix_store_view(state))
+    .push(workspace_view(state))
+    .push(merge_view(state))
+    .push(conflicts_view(state))
+    .push(health_view(state))
+    .push(quarantine::view(&state.quarantine, state.nkisi.id, coords(state), state.quarantine_fix.as_ref()))
+    .push(pack_view(state))
+    .push(regions_view(state))
+    .push(fields::editor(
+        &state.workspace.settings.fields,
+        &state.field_label_input,
+        state.field_kind,
+        &state.field_options_input,
+    ))
+    .push(report_view(state))
+    .push(png_view(state))
+    .push(rules_view(state))
+    .push(transforms_view(state))
+    .push(fields::builder(
+        &fields::layout(&state.workspace.settings.form, &state.workspace.settings.fields),
+        &state.workspace.settings.fields,
+    ))
+    .push(global_search_view(state))
+    .push(stats_view(state))
+        .spacing(8)
+        .align_x(alignment::Horizontal::Left)
+}
+
+// Where the FIX acceptor listens, and whether it does
+fn acceptor_controls(state: &State) -> Element<'_, Message> {
+    let mut line = row![
+        iced::widget::text("FIX acceptor:"),
+        text_input("0.0.0.0:9898", &state.fix_addr_input)
+            .on_input(Message::FixAddrChanged)
+            .on_submit(Message::StartAcceptor)
+            .padding(6),
+    ]
+    .spacing(8)
+    .align_y(alignment::Vertical::Center);
+    line = match &state.acceptor {
+        Some(running) => line
+            .push(button("Restart").on_press(Message::StartAcceptor))
+            .push(button("Stop").style(button::secondary).on_press(Message::StopAcceptor))
+            .push(iced::widget::text(format!("listening on {}", running.addr))),
+        None => line.push(button("Start").on_press(Message::StartAcceptor)).push(iced::widget::text("stopped")),
+    };
+    line.push(iced::widget::text("Outside the figure:"))
+        .push(pick_list(quarantine::Policy::ALL, Some(state.workspace.ingest.out_of_bounds), Message::SetBoundsPolicy))
+        .into()
+}
+
+fn release_notes_card<'a>(notes: &[(&'a str, &'a [&'a str])]) -> Element<'a, Message> {
+    let title = format!("What's new in {}", compat::APP_VERSION);
+    let mut col = column![iced::widget::text(title).size(18)].spacing(6);
+    for (version, items) in notes {
+        col = col.push(iced::widget::text(*version).size(15));
+        for item in items.iter() {
+            col = col.push(iced::widget::text(format!("• {item}")));
+        }
+    }
+    col = col.push(button("Got it").on_press(Message::DismissReleaseNotes));
+    container(col)
+        .padding(12)
+        .style(|_theme: &Theme| {
+            use iced::Border;
+            container::Style {
+                background: Some(Color::from_rgba(0.12, 0.18, 0.24, 0.95).into()),
+                border: Border { radius: 12.0.into(), ..Default::default() },
+                ..Default::default()
+            }
+        })
+        .into()
+}
+
+fn overlay_controls(state: &State) -> Element<'_, Message> {
+    let o = state.overlay;
+    let look = state.workspace.settings.appearance;
+    let names: Vec<String> =
+        state.workspace.settings.profiles.iter().map(|p| p.name.clone()).collect();
+    let views: Vec<String> =
+        state.workspace.active_figure().map_or(vec![], |f| f.views.iter().map(|v| v.name.clone()).collect());
+
+    column![
+        row![
+            iced::widget::text("Overlay:"),
+            pick_list(names, state.active_profile.clone(), Message::SelectProfile)
+                .placeholder("custom"),
+            text_input("profile name", &state.profile_name_input)
+                .on_input(Message::ProfileNameChanged)
+                .on_submit(Message::SaveProfile)
+                .padding(6)
+                .width(Length::Fixed(140.0)),
+            button("Save profile").on_press(Message::SaveProfile),
+            help::hint(help::Topic::Overlay),
+        ]
+        .spacing(8)
+        .align_y(alignment::Vertical::Center),
+        row![
+            toggler(o.grid)
+                .label("Grid")
+                .on_toggle(move |v| Message::SetOverlay(overlay::OverlayOptions { grid: v, ..o })),
+            toggler(o.outcome_colors)
+                .label("Outcome colors")
+                .on_toggle(move |v| {
+                    Message::SetOverlay(overlay::OverlayOptions { outcome_colors: v, ..o })
+                }),
+            toggler(o.heatmap)
+                .label("Heatmap")
+                .on_toggle(move |v| Message::SetOverlay(overlay::OverlayOptions { heatmap: v, ..o })),
+            toggler(o.labels)
+                .label("Labels")
+                .on_toggle(move |v| Message::SetOverlay(overlay::OverlayOptions { labels: v, ..o })),
+            toggler(o.regions)
+                .label("Regions")
+                .on_toggle(move |v| Message::SetOverlay(overlay::OverlayOptions { regions: v, ..o })),
+        ]
+        .spacing(12)
+        .wrap(),
+        row![
+            iced::widget::text("Palette:"),
+            pick_list(palette::Palette::ALL, Some(look.palette), move |palette| {
+                Message::SetAppearance(palette::Appearance { palette, ..look })
+            }),
+            toggler(look.high_contrast)
+                .label("High contrast")
+                .on_toggle(move |v| Message::SetAppearance(palette::Appearance { high_contrast: v, ..look })),
+            iced::widget::text("Zoom:"),
+            button("−").on_press(Message::ZoomOut),
+            button(iced::widget::text(format!("{:.0}%", state.workspace.settings.ui_scale * 100.0)))
+                .on_press(Message::ZoomReset),
+            button("+").on_press(Message::ZoomIn),
+            iced::widget::text("Coordinates:"),
+            pick_list(units::Unit::ALL, Some(state.workspace.settings.coordinate_unit), Message::SetCoordinateUnit),
+            pick_list(units::Precision::ALL, Some(state.workspace.settings.precision), Message::SetPrecision),
+        ]
+        .spacing(12)
+        .align_y(alignment::Vertical::Center),
+        row![
+            iced::widget::text("View:"),
+            pick_list(views, state.active_view.clone(), Message::SelectView).placeholder("custom"),
+            text_input("view name", &state.view_name_input)
+                .on_input(Message::ViewNameChanged)
+                .on_submit(Message::SaveView)
+                .padding(6)
+                .width(Length::Fixed(140.0)),
+            button("Save view").on_press(Message::SaveView),
+            button("Delete view")
+                .style(button::secondary)
+                .on_press_maybe(state.active_view.as_ref().map(|_| Message::DeleteView)),
+            button("Before/after").style(button::secondary).on_press(Message::Compare(state.compare.is_none())),
+            button(iced::widget::text(format!("Fit ({})", state.viewport.label())))
+                .style(button::secondary)
+                .on_press_maybe((!state.viewport.is_whole()).then_some(Message::FitFigure)),
+        ]
+        .spacing(8)
+        .align_y(alignment::Vertical::Center),
+    ]
+    .spacing(6)
+    .into()
+}
+
+fn layers_view(state: &State) -> Element<'_, Message> {
+    let Some(base) = &state.base_svg else {
+        return column![].into();
+    };
+    let mut toggles = row![iced::widget::text("Layers:")].spacing(12);
+    for (i, layer) in base.layers.iter().enumerate() {
+        toggles = toggles.push(
+            toggler(layer.visible)
+                .label(layer.name.clone())
+                .on_toggle(move |v| Message::ToggleLayer(i, v)),
+        );
+    }
+    toggles.wrap().into()
+}
+
+// Newest events first, each with its own delete button
+fn events_view(state: &State) -> Element<'_, Message> {
+    let mut list = column![].spacing(4);
+    for ev in state.nkisi.events.iter().rev() {
+        let note = ev.notes.as_deref().unwrap_or("");
+        let mut line = format!("{} • {} • {}", ev.date.format("%Y-%m-%d %H:%M"), ev.performed_by, note);
+        for (label, v) in ev.meta.fields() {
+            line.push_str(&format!(" • {label}: {v}"));
+        }
+        for (key, v) in &ev.custom {
+            line.push_str(&format!(" • {}: {v}", fields::label(&state.workspace.settings.fields, key)));
+        }
+        list = list.push(
+            row![
+                iced::widget::text(line)
+                .width(Length::Fill),
+                button("Details").on_press(Message::SelectEvent(Some(ev.id))),
+                button("Delete")
+                    .style(button::danger)
+                    .on_press(Message::DeleteEvent(ev.id)),
+            ]
+            .spacing(8)
+            .align_y(alignment::Vertical::Center),
+        );
+    }
+
+    column![
+        row![
+            iced::widget::text(format!("Events ({})", state.nkisi.events.len())).size(16).width(Length::Fill),
+            // Also ← / → (or k / j) while no text field has focus
+            button("◀").on_press(Message::StepEvent(false)),
+            button("Review ▶").on_press(Message::StepEvent(true)),
+            help::hint(help::Topic::Events),
+        ]
+        .spacing(8)
+        .align_y(alignment::Vertical::Center),
+        scrollable(list).height(Length::Fixed(180.0)),
+    ]
+    .spacing(6)
+    .into()
+}
+
+// Area selection tools and what can be done to the pins they picked
+fn bulk_view(state: &State) -> Element<'_, Message> {
+    let tool = |label, t: Option<lasso::Tool>| {
+        let style = if state.select_tool == t { button::primary } else { button::secondary };
+        button(label).style(style).on_press(Message::SetSelectTool(t))
+    };
+    let mut col = column![row![
+        iced::widget::text("Select pins:"),
+        tool("Off", None),
+        tool("Lasso", Some(lasso::Tool::Lasso)),
+        tool("Box", Some(lasso::Tool::Box)),
+    ]
+    .spacing(8)
+    .align_y(alignment::Vertical::Center)]
+    .spacing(6);
+
+    let picked = state.nkisi.events.iter().filter(|e| state.bulk.contains(&e.id)).count();
+    if picked == 0 {
+        return col.into();
+    }
+    let outcome = |label, o: Outcome| button(label).on_press(Message::BulkOutcome(o));
+    col = col
+        .push(
+            row![
+                iced::widget::text(format!("{} selected", confirm::count(picked, "pin"))).width(Length::Fill),
+                button("Export CSV").on_press(Message::BulkExport),
+                button("Clear").on_press(Message::ClearBulk),
+            ]
+            .spacing(8)
+            .align_y(alignment::Vertical::Center),
+        )
+        .push(
+            row![
+                text_input("tag", &state.bulk_tag_input)
+                    .on_input(Message::BulkTagChanged)
+                    .on_submit(Message::BulkTag)
+                    .padding(6),
+                button("Add tag").on_press(Message::BulkTag),
+            ]
+            .spacing(8),
+        )
+        .push(
+            row![
+                iced::widget::text("Set outcome:"),
+                outcome("Pending", Outcome::Pending),
+                outcome("Resolved", Outcome::Resolved),
+                outcome("Failed", Outcome::Failed),
+            ]
+            .spacing(8)
+            .align_y(alignment::Vertical::Center),
+        );
+    col.into()
+}
+
+// The selected event in full, with the chain of custody that brought it here
+fn details_view(state: &State) -> Element<'_, Message> {
+    let Some(ev) = state.selected_event.and_then(|id| state.nkisi.events.iter().find(|e| e.id == id))
+    else {
+        return column![].into();
+    };
+    let mut col = column![
+        row![
+            iced::widget::text(format!("Event {}", ev.id)).size(16).width(Length::Fill),
+            button("◀ Previous").on_press(Message::StepEvent(false)),
+            button("Next ▶").on_press(Message::StepEvent(true)),
+            button("Close").on_press(Message::SelectEvent(None)),
+        ]
+        .spacing(8)
+        .align_y(alignment::Vertical::Center),
+        iced::widget::text(format!(
+            "{} • struck by {} at {} • {:?}",
+            ev.date.format("%Y-%m-%d %H:%M:%S"),
+            ev.performed_by,
+            coords(state).format(ev.pos),
+            ev.outcome
+        )),
+    ]
+    .spacing(4);
+    if let Some(note) = &ev.notes {
+        col = col.push(iced::widget::text(format!("Notes: {note}")));
+    }
+    for (label, v) in ev.meta.fields() {
+        col = col.push(iced::widget::text(format!("{label}: {v}")));
+    }
+    for (key, v) in &ev.custom {
+        let label = fields::label(&state.workspace.settings.fields, key);
+        col = col.push(iced::widget::text(format!("{label}: {v}")));
+    }
+    col = col.push(iced::widget::text("Provenance").size(14));
+    if ev.provenance.is_empty() {
+        col = col.push(iced::widget::text("Struck in this ledger."));
+    }
+    for (i, hop) in ev.provenance.iter().enumerate() {
+        let listener = hop.listener.as_ref().map(|l| format!(" (listener {l})")).unwrap_or_default();
+        col = col.push(iced::widget::text(format!(
+            "{}. {} via {}{listener} — {}",
+            i + 1,
+            hop.at.format("%Y-%m-%d %H:%M:%S"),
+            hop.via,
+            hop.source
+        )));
+    }
+    col = col.push(iced::widget::text("Position").size(14));
+    col = col.push(
+        row![
+            text_input("x", &state.move_x_input)
+                .on_input(Message::MoveXChanged)
+                .padding(6)
+                .width(Length::Fixed(70.0)),
+            text_input("y", &state.move_y_input)
+                .on_input(Message::MoveYChanged)
+                .padding(6)
+                .width(Length::Fixed(70.0)),
+            button("Move pin").on_press(Message::MovePin),
+        ]
+        .spacing(8),
+    );
+    col = col.push(iced::widget::text("Or drag the ringed pin on the figure.").size(13));
+    container(col)
+        .padding(10)
+        .style(|_theme: &Theme| {
+            use iced::Border;
+            container::Style {
+                background: Some(Color::from_rgba(0.12, 0.14, 0.2, 0.9).into()),
+                border: Border { radius: 10.0.into(), ..Default::default() },
+                ..Default::default()
+            }
+        })
+        .into()
+}
+
+fn pending_panel(state: &State, (nx, ny): (f32, f32), sheet: bool) -> Element<'_, Message> {
+    let panel = container(
+        column![
+            iced::widget::text("Pending Spike").size(18),
+            iced::widget::text(format!("Position (SVG): x={:.1}, y={:.1}", nx, ny)),
+            iced::widget::text(format!(
+                "Region: {}",
+                regions::hit(state.workspace.active_regions(), (nx, ny))
+                    .map_or("—", |r| r.name.as_str())
+            )),
+            fields::form(
+                &fields::layout(&state.workspace.settings.form, &state.workspace.settings.fields),
+                &state.workspace.settings.fields,
+                &state.striker_input,
+                &state.message_input,
+                &state.witness_input,
+                &state.custom_inputs,
+            ),
+            row![
+                button("Confirm").on_press(Message::ConfirmSpike),
+                button("Cancel").on_press(Message::CancelSpike),
+            ]
+            .spacing(12),
+        ]
+            .spacing(8),
+    )
+        .padding(12)
+        .style(move |_theme: &Theme| {
+            use iced::border::{self, Border};
+            // As a bottom sheet only the top corners are rounded
+            let radius = if sheet { border::top(12.0) } else { border::radius(12.0) };
+            container::Style {
+                background: Some(Color::from_rgba(0.15, 0.15, 0.18, 0.9).into()),
+                border: Border { radius, ..Default::default() },
+                ..Default::default()
+            }
+        });
+
+    if sheet {
+        panel.width(Length::Fill).into()
+    } else {
+        panel.into()
+    }
+}
+
+fn intensity_curve(state: &State) -> Element<'_, Message> {
+    if state.intensity.curve.is_empty() {
+        return iced::widget::text("No spikes to plot yet.").size(12).into();
+    }
+    let line = state.workspace.settings.appearance.heat();
+    let handle = svg::Handle::from_memory(charge::curve_svg(&state.intensity, line).into_bytes());
+    svg(handle).width(Length::Fixed(360.0)).height(Length::Fixed(90.0)).into()
+}
+
+// The half-life as typed back into its field
+fn half_life_text(days: Option<f64>) -> String {
+    days.map_or(String::new(), |d| d.to_string())
+}
+
+fn status_line(state: &State) -> Element<'_, Message> {
+    use iced::widget::text; // for text::Style
+    let mut line = state.status.clone();
+    let dropped = state.fix_dropped.load(Ordering::Relaxed);
+    if dropped > 0 {
+        line.push_str(&format!(" • {dropped} malformed FIX frame(s) dropped"));
+    }
+    let queued = state.fix_rx.waiting() + state.fix_held.len();
+    if queued > 0 {
+        line.push_str(&format!(" • {queued} FIX message(s) queued"));
+    }
+    let throttled = state.fix_throttled.load(Ordering::Relaxed);
+    if throttled > 0 {
+        line.push_str(&format!(" • {throttled} FIX message(s) slowed by the rate limit"));
+    }
+    let overflowed = state.fix_overflowed.load(Ordering::Relaxed);
+    if overflowed > 0 {
+        line.push_str(&format!(" • {overflowed} FIX spike(s) refused, ledger queue full"));
+    }
+    let crowded_out = state.fix_crowded_out.load(Ordering::Relaxed);
+    if crowded_out > 0 {
+        line.push_str(&format!(" • {crowded_out} FIX connection(s) refused, too many open"));
+    }
+    let refused = state.fix_refused.load(Ordering::Relaxed);
+    if refused > 0 {
+        line.push_str(&format!(" • {refused} FIX attempt(s) refused by the access list"));
+    }
+    let turned_away = state.net_acl.refused.load(Ordering::Relaxed);
+    if turned_away > 0 {
+        line.push_str(&format!(" • {turned_away} connection(s) refused by the network rules"));
+    }
+    let dead = state.outbox.dead_count();
+    if dead > 0 {
+        line.push_str(&format!(" • {dead} outbound event(s) undelivered (Outbox)"));
+    }
+    if let Some(up) = &state.upstream {
+        let waiting = up.link.waiting.load(Ordering::Relaxed);
+        match (up.link.logged_on.load(Ordering::Relaxed), waiting) {
+            (true, 0) => {}
+            (true, n) => line.push_str(&format!(" • {n} spike(s) being forwarded to {}", up.addr)),
+            (false, n) => line.push_str(&format!(" • upstream {} unreachable, {n} spike(s) waiting", up.addr)),
+        }
+    }
+    iced::widget::text(line)
+        .style(|_| text::Style {
+            color: Some(Color::from_rgb(0.85, 0.85, 0.95)),
+        })
+        .into()
+}
+
+// Trashed events, restorable one by one until the trash is emptied
+fn trash_view(state: &State) -> Element<'_, Message> {
+    let trash = &state.nkisi.trash;
+    let mut header = row![iced::widget::text(format!("Trash ({})", trash.len())).size(16)]
+        .spacing(12)
+        .align_y(alignment::Vertical::Center);
+    if !trash.is_empty() {
+        header = header.push(
+            button("Empty trash")
+                .style(button::danger)
+                .on_press(Message::EmptyTrash),
+        );
+    }
+
+    let mut list = column![].spacing(4);
+    for ev in trash.iter().rev() {
+        list = list.push(
+            row![
+                iced::widget::text(format!(
+                    "{} • {} • {}",
+                    ev.date.format("%Y-%m-%d %H:%M"),
+                    ev.performed_by,
+                    ev.notes.as_deref().unwrap_or("")
+                ))
+                .width(Length::Fill),
+                button("Restore").on_press(Message::RestoreEvent(ev.id)),
+            ]
+            .spacing(8)
+            .align_y(alignment::Vertical::Center),
+        );
+    }
+
+    let mut col = column![header, scrollable(list).height(Length::Fixed(120.0))].spacing(6);
+    // Tombstones: who cancelled each spike and when is its last hop
+    let cancelled = &state.nkisi.cancelled;
+    if !cancelled.is_empty() {
+        let mut list = column![].spacing(4);
+        for ev in cancelled.iter().rev() {
+            let by = ev.provenance.last().map_or(String::new(), |hop| {
+                format!(" • cancelled {} by {}", hop.at.format("%Y-%m-%d %H:%M"), hop.source)
+            });
+            list = list.push(iced::widget::text(format!(
+                "{} • {} • {}{by}",
+                ev.date.format("%Y-%m-%d %H:%M"),
+                ev.performed_by,
+                ev.notes.as_deref().unwrap_or("")
+            )));
+        }
+        col = col.push(iced::widget::text(format!("Cancelled by their senders ({})", cancelled.len())).size(16));
+        col = col.push(scrollable(list).height(Length::Fixed(80.0)));
+    }
+    col.into()
+}
+
+// Journal of the open ledger, newest first, with undo of the latest change
+fn history_view(state: &State) -> Element<'_, Message> {
+    let mut list = column![].spacing(2);
+    for entry in state.journal.history(state.nkisi.id).take(50) {
+        list = list.push(iced::widget::text(entry.describe()).size(13));
+    }
+    column![
+        row![
+            iced::widget::text("History").size(16),
+            button("Undo").on_press(Message::Undo),
+        ]
+        .spacing(12)
+        .align_y(alignment::Vertical::Center),
+        scrollable(list).height(Length::Fixed(100.0)),
+    ]
+    .spacing(6)
+    .into()
+}
+
+fn archive_view(state: &State) -> Element<'_, Message> {
+    let mut results = column![].spacing(4);
+    for ev in &state.archive_results {
+        results = results.push(iced::widget::text(format!(
+            "{} • {} • {}",
+            ev.date.format("%Y-%m-%d %H:%M"),
+            ev.performed_by,
+            ev.notes.as_deref().unwrap_or("")
+        )));
+    }
+
+    column![
+        iced::widget::text("Archive").size(16),
+        row![
+            iced::widget::text("Resolved older than"),
+            text_input("days", &state.archive_days_input)
+                .on_input(Message::ArchiveDaysChanged)
+                .padding(6)
+                .width(Length::Fixed(70.0)),
+            iced::widget::text("days"),
+            button("Archive").on_press(Message::ArchiveResolved),
+        ]
+        .spacing(8)
+        .align_y(alignment::Vertical::Center),
+        row![
+            text_input("search archived strikers / notes", &state.archive_query)
+                .on_input(Message::ArchiveQueryChanged)
+                .on_submit(Message::SearchArchive)
+                .padding(6),
+            button("Search").on_press(Message::SearchArchive),
+        ]
+        .spacing(8),
+        scrollable(results).height(Length::Fixed(100.0)),
+    ]
+    .spacing(6)
+    .into()
+}
+
+// Replay a day of the FIX message store, when there is one
+fn fix_store_view(state: &State) -> Element<'_, Message> {
+    let Some(store) = &state.fix_store else { return column![].into() };
+    column![
+        iced::widget::text("FIX message store").size(16),
+        iced::widget::text(format!("{} • {} day(s) recorded", store.dir().display(), state.fix_days.len())).size(12),
+        row![
+            pick_list(state.fix_days.as_slice(), state.replay_day.as_ref(), Message::ReplayDayChanged)
+                .placeholder("day")
+                .on_open(Message::RefreshFixDays),
+            button("Replay day").on_press(Message::ReplayFixDay),
+            button("Export log").on_press(Message::ExportFixLog),
+        ]
+        .spacing(8)
+        .align_y(alignment::Vertical::Center),
+    ]
+    .spacing(6)
+    .into()
+}
+
+fn workspace_view(state: &State) -> Element<'_, Message> {
+    let mut figures = column![].spacing(4);
+    for (i, fig) in state.workspace.figures.iter().enumerate() {
+        let active = i == state.workspace.active;
+        let label = if active { format!("● {}", fig.name) } else { fig.name.clone() };
+        let mut line = row![
+            button(iced::widget::text(label))
+                .style(if active { button::primary } else { button::text })
+                .on_press(Message::SelectFigure(i)),
+            iced::widget::text(&fig.ledger).width(Length::Fill),
+        ]
+        .spacing(8)
+        .align_y(alignment::Vertical::Center);
+        if !active {
+            line = line.push(button("Remove").on_press(Message::RemoveFigure(i)));
+        }
+        figures = figures.push(line);
     }
-    None
+
+    column![
+        row![iced::widget::text(format!("Workspace • {}", state.workspace.name)).size(16), help::hint(help::Topic::Workspace)]
+            .spacing(8),
+        row![
+            text_input("project.nkisiproj", &state.workspace_path)
+                .on_input(Message::WorkspacePathChanged)
+                .padding(6),
+            button("Open").on_press(Message::OpenWorkspace),
+            button("Save").on_press(Message::SaveWorkspace),
+        ]
+        .spacing(8),
+        figures,
+        forks::view(&state.forks, &state.workspace.figures),
+        row![
+            text_input("figure name", &state.figure_name_input)
+                .on_input(Message::FigureNameChanged)
+                .padding(6),
+            text_input("ledger file", &state.figure_ledger_input)
+                .on_input(Message::FigureLedgerChanged)
+                .on_submit(Message::AddFigure)
+                .padding(6),
+            button("Add figure").on_press(Message::AddFigure),
+        ]
+        .spacing(8),
+    ]
+    .spacing(6)
+    .into()
 }
 
-fn parse_fix_spike(raw: &[u8]) -> Option<ExternalSpike> {
-    // Split by SOH into key=val pairs
-    let mut map: HashMap<i32, String> = HashMap::new();
-    for field in raw.split(|b| *b == SOH) {
-        if field.is_empty() { continue; }
-        if let Some(eq) = field.iter().position(|b| *b == b'=') {
-            let (k, v) = field.split_at(eq);
-            let key = std::str::from_utf8(k).ok()?.parse::<i32>().ok()?;
-            let val = std::str::from_utf8(&v[1..]).ok()?.to_string();
-            map.insert(key, val);
+// Offline copies of this figure edited elsewhere merge without conflicts
+// in the ledger itself; see crdt.rs
+fn merge_view(state: &State) -> Element<'_, Message> {
+    row![
+        iced::widget::text("Merge copy:"),
+        text_input("another party's copy of this ledger", &state.merge_path)
+            .on_input(Message::MergePathChanged)
+            .on_submit(Message::MergeLedger)
+            .padding(6),
+        button("Merge").on_press(Message::MergeLedger),
+    ]
+    .spacing(8)
+    .align_y(alignment::Vertical::Center)
+    .into()
+}
+
+// Export the search selection as a signed pack, or import one
+fn pack_view(state: &State) -> Element<'_, Message> {
+    let scope = if state.global_query.trim().is_empty() {
+        "all events".to_string()
+    } else {
+        format!("events matching \"{}\"", state.global_query.trim())
+    };
+    column![
+        iced::widget::text("Spike packs").size(16),
+        row![
+            text_input("selection.nkisipack", &state.pack_path)
+                .on_input(Message::PackPathChanged)
+                .padding(6),
+            button("Export").on_press(Message::ExportPack),
+            button("Import").on_press(Message::ImportPack),
+        ]
+        .spacing(8),
+        iced::widget::text(format!("Export takes {scope} (see Search all figures)")).size(13),
+    ]
+    .spacing(6)
+    .into()
+}
+
+// Localized CSV and printable report of the active figure
+fn report_view(state: &State) -> Element<'_, Message> {
+    let locales: Vec<String> = report::BUILTIN.iter().map(|l| l.to_string()).collect();
+    column![
+        row![iced::widget::text("Reports").size(16), help::hint(help::Topic::Exports)].spacing(8),
+        row![
+            pick_list(locales, Some(state.report_locale.clone()), Message::ReportLocaleChanged),
+            text_input("report", &state.report_path)
+                .on_input(Message::ReportPathChanged)
+                .padding(6),
+            button("CSV").on_press(Message::ExportCsv),
+            button("Report").on_press(Message::ExportReport),
+        ]
+        .spacing(8),
+        row![
+            text_input("template", &state.report_template)
+                .on_input(Message::ReportTemplateChanged)
+                .padding(6),
+            button("From template").on_press(Message::ExportTemplate),
+        ]
+        .spacing(8),
+        row![
+            text_input("exports", &state.batch_dir)
+                .on_input(Message::BatchDirChanged)
+                .padding(6),
+            button("Export all").on_press(Message::ExportAll),
+        ]
+        .spacing(8),
+        row![
+            iced::widget::text("ε"),
+            text_input("exact", &state.aggregate_epsilon)
+                .on_input(Message::AggregateEpsilonChanged)
+                .padding(6)
+                .width(Length::Fixed(60.0)),
+            button("Aggregates").on_press(Message::ExportAggregate),
+        ]
+        .spacing(8)
+        .align_y(alignment::Vertical::Center),
+        iced::widget::text(
+            "The report is an HTML page; print it from a browser for a PDF. \
+             Export all writes CSV, SVG and PDF for every figure. \
+             Aggregates are counts only, with noise for public sharing (smaller ε, more noise).",
+        )
+        .size(13),
+    ]
+    .spacing(6)
+    .into()
+}
+
+// Print-quality PNG of the figure and overlay
+fn png_view(state: &State) -> Element<'_, Message> {
+    column![
+        iced::widget::text("Image export").size(16),
+        row![
+            text_input("figure.png", &state.png_path)
+                .on_input(Message::PngPathChanged)
+                .padding(6),
+            iced::widget::text("DPI"),
+            text_input("300", &state.png_dpi_input)
+                .on_input(Message::PngDpiChanged)
+                .padding(6)
+                .width(Length::Fixed(60.0)),
+            iced::widget::text("Margin mm"),
+            text_input("5", &state.png_margin_input)
+                .on_input(Message::PngMarginChanged)
+                .padding(6)
+                .width(Length::Fixed(50.0)),
+        ]
+        .spacing(8)
+        .align_y(alignment::Vertical::Center),
+        row![
+            toggler(state.png_transparent)
+                .label("Transparent background")
+                .on_toggle(Message::TogglePngTransparent),
+            button("Export PNG").on_press(Message::ExportPng),
+        ]
+        .spacing(12)
+        .align_y(alignment::Vertical::Center),
+    ]
+    .spacing(6)
+    .into()
+}
+
+// Both versions of each conflicting event side by side, with a pick
+fn conflicts_view(state: &State) -> Element<'_, Message> {
+    if state.conflicts.is_empty() {
+        return column![].into();
+    }
+    let version = |title: &'static str, ev: &ActivationEvent| {
+        column![
+            iced::widget::text(title).size(14),
+            iced::widget::text(format!("Outcome: {:?}", ev.outcome)),
+            iced::widget::text(format!("Notes: {}", ev.notes.as_deref().unwrap_or("—"))),
+            iced::widget::text(format!("At {}", coords(state).format(ev.pos))),
+        ]
+        .spacing(2)
+        .width(Length::FillPortion(1))
+    };
+    let mut list = column![iced::widget::text(format!("Conflicts ({})", state.conflicts.len())).size(16)]
+        .spacing(8);
+    for (i, c) in state.conflicts.iter().enumerate() {
+        list = list.push(
+            column![
+                iced::widget::text(format!(
+                    "{} • {}",
+                    c.local.date.format("%Y-%m-%d %H:%M"),
+                    c.local.performed_by
+                )),
+                row![version("This copy", &c.local), version("Their copy", &c.remote)].spacing(12),
+                row![
+                    button("Keep this").on_press(Message::ResolveConflict(i, crdt::Resolution::Local)),
+                    button("Keep theirs").on_press(Message::ResolveConflict(i, crdt::Resolution::Remote)),
+                    button("Combine").on_press(Message::ResolveConflict(i, crdt::Resolution::Combine)),
+                ]
+                .spacing(8),
+            ]
+            .spacing(4),
+        );
+    }
+    container(list)
+        .padding(10)
+        .style(|_theme: &Theme| {
+            use iced::Border;
+            container::Style {
+                background: Some(Color::from_rgba(0.24, 0.16, 0.10, 0.9).into()),
+                border: Border { radius: 10.0.into(), ..Default::default() },
+                ..Default::default()
+            }
+        })
+        .into()
+}
+
+// What the ledger check found, each with its fix
+fn health_view(state: &State) -> Element<'_, Message> {
+    let Some(found) = &state.health else {
+        return column![].into();
+    };
+    let mut head = row![iced::widget::text(format!("Ledger check ({})", found.len())).size(16).width(Length::Fill)]
+        .spacing(8)
+        .align_y(alignment::Vertical::Center);
+    if !found.is_empty() {
+        head = head.push(button("Fix all").on_press(Message::FixAllAnomalies));
+    }
+    head = head.push(button("Check again").style(button::secondary).on_press(Message::CheckLedger));
+    head = head.push(button("Close").style(button::secondary).on_press(Message::CloseLedgerCheck));
+    let mut list = column![head].spacing(6);
+    if found.is_empty() {
+        list = list.push(iced::widget::text("Nothing wrong found: every event has its pin, dates and positions are sound."));
+    }
+    for (i, anomaly) in found.iter().enumerate() {
+        let mut line = row![iced::widget::text(anomaly.describe(coords(state))).width(Length::Fill)]
+            .spacing(8)
+            .align_y(alignment::Vertical::Center);
+        if let Some(id) = anomaly.event().filter(|id| state.nkisi.events.iter().any(|e| e.id == *id)) {
+            line = line.push(button("Details").style(button::secondary).on_press(Message::SelectEvent(Some(id))));
+        }
+        list = list.push(line.push(button(anomaly.remedy()).on_press(Message::FixAnomaly(i))));
+    }
+    container(list)
+        .padding(10)
+        .style(|_theme: &Theme| {
+            use iced::Border;
+            container::Style {
+                background: Some(Color::from_rgba(0.12, 0.18, 0.22, 0.9).into()),
+                border: Border { radius: 10.0.into(), ..Default::default() },
+                ..Default::default()
+            }
+        })
+        .into()
+}
+
+// Validation rules from the workspace file, each with an on/off switch
+fn rules_view(state: &State) -> Element<'_, Message> {
+    let rules = &state.workspace.settings.rules;
+    let mut col = column![iced::widget::text("Validation rules").size(16)].spacing(4);
+    if rules.is_empty() {
+        col = col.push(iced::widget::text("No rules: add them under settings.rules in the workspace file."));
+    }
+    for (i, c) in rules.iter().enumerate() {
+        col = col.push(
+            toggler(c.enabled)
+                .label(c.rule.describe())
+                .on_toggle(move |v| Message::ToggleRule(i, v)),
+        );
+    }
+    col.into()
+}
+
+fn transforms_view(state: &State) -> Element<'_, Message> {
+    let transforms = &state.workspace.ingest.transforms;
+    let mut col = column![iced::widget::text("Ingest transforms").size(16)].spacing(4);
+    if transforms.is_empty() {
+        col = col.push(iced::widget::text("None: add them under ingest.transforms in the workspace file."));
+    }
+    for (i, t) in transforms.iter().enumerate() {
+        col = col.push(toggler(t.enabled).label(t.describe()).on_toggle(move |v| Message::ToggleTransform(i, v)));
+    }
+    col.into()
+}
+
+fn regions_view(state: &State) -> Element<'_, Message> {
+    let mut list = column![].spacing(4);
+    for (i, r) in state.workspace.active_regions().iter().enumerate() {
+        list = list.push(
+            row![
+                iced::widget::text(format!("{} ({} vertices)", r.name, r.polygon.len()))
+                    .width(Length::Fill),
+                button("Delete").on_press(Message::DeleteRegion(i)),
+            ]
+            .spacing(8)
+            .align_y(alignment::Vertical::Center),
+        );
+    }
+
+    let mut col = column![
+        toggler(state.region_draft.is_some())
+            .label("Edit regions")
+            .on_toggle(Message::ToggleRegionEditor),
+        list,
+    ]
+    .spacing(6);
+
+    if let Some(draft) = &state.region_draft {
+        col = col.push(
+            row![
+                text_input("region name (e.g. torso)", &state.region_name_input)
+                    .on_input(Message::RegionNameChanged)
+                    .on_submit(Message::FinishRegion)
+                    .padding(6),
+                button("Undo point").on_press(Message::UndoRegionPoint),
+                button(iced::widget::text(format!("Finish ({})", draft.len())))
+                    .on_press(Message::FinishRegion),
+            ]
+            .spacing(8),
+        );
+    }
+    col.into()
+}
+
+fn global_search_view(state: &State) -> Element<'_, Message> {
+    let mut hits = column![].spacing(4);
+    for hit in &state.global_hits {
+        let figure = state
+            .workspace
+            .figure_for_ledger(&hit.path)
+            .map_or(hit.path.as_str(), |i| state.workspace.figures[i].name.as_str());
+        hits = hits.push(
+            button(iced::widget::text(format!("{figure} — {}", hit.summary)))
+                .style(button::text)
+                .on_press(Message::OpenHit(hit.path.clone(), hit.event_id)),
+        );
+    }
+
+    column![
+        iced::widget::text("Search all figures").size(16),
+        row![
+            text_input("strikers / notes", &state.global_query)
+                .on_input(Message::GlobalQueryChanged)
+                .on_submit(Message::GlobalSearch)
+                .padding(6),
+            button("Search").on_press(Message::GlobalSearch),
+        ]
+        .spacing(8),
+        scrollable(hits).height(Length::Fixed(120.0)),
+    ]
+    .spacing(6)
+    .into()
+}
+
+// Ingest figures for tuning the FIX pipeline
+fn stats_view(state: &State) -> Element<'_, Message> {
+    let latency = state.latency.lock().unwrap_or_else(|e| e.into_inner());
+    let line = |label: &str, s: Option<latency::Summary>| {
+        iced::widget::text(format!("{label}: {}", s.map_or("no samples yet".into(), |s| s.line())))
+    };
+    column![
+        iced::widget::text(format!("Stats • {} external spike(s) applied", latency.total)).size(16),
+        line("Sender skew (tag 60 → receive)", latency.skew()),
+        line("Receive → apply", latency.apply()),
+        line("Apply → drawn", latency.render()),
+        line("End to end (tag 60 → drawn)", latency.end_to_end()),
+    ]
+    .spacing(4)
+    .into()
+}
+
+// -------------------- Overlay SVG (pins + grid) --------------------
+// `interactive` adds what only makes sense on screen: the region draft and
+// the selection ring
+fn render_overlay_svg(state: &State, interactive: bool) -> String {
+    let shown_regions: &[regions::Region] =
+        if state.overlay.regions { state.workspace.active_regions() } else { &[] };
+    let mut s = overlay_svg(
+        &state.nkisi,
+        state.figure_dims,
+        shown_regions,
+        state.overlay,
+        &state.workspace.settings.appearance,
+        state.region_draft.as_deref().filter(|_| interactive),
+        state.selected_event.filter(|_| interactive).map(|id| (id, state.dragging.map(|(_, to)| to))),
+    );
+    // Area selection goes on top, on screen only
+    if interactive && (state.stroke.is_some() || !state.bulk.is_empty()) {
+        let picked: Vec<(f32, f32)> =
+            state.nkisi.events.iter().filter(|e| state.bulk.contains(&e.id)).map(|e| e.pos).collect();
+        let k = state.figure_dims.0 / FIGURE_W;
+        s.truncate(s.len() - "</svg>".len());
+        s.push_str(&lasso::render(state.stroke.as_ref(), &picked, k));
+        s.push_str("</svg>");
+    }
+    s
+}
+
+// The overlay for any ledger; `shown_regions` is empty when regions are off
+fn overlay_svg(
+    nkisi: &NkisiNkondi,
+    (fw, fh): (f32, f32),
+    shown_regions: &[regions::Region],
+    options: overlay::OverlayOptions,
+    look: &palette::Appearance,
+    draft: Option<&[(f32, f32)]>,
+    // With where the selected pin is being dragged to
+    selected: Option<(Uuid, Option<(f32, f32)>)>,
+) -> String {
+    let mut s = String::new();
+    s.push_str(&format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}">"##,
+        fw, fh
+    ));
+
+    if options.grid {
+        // Square cells, a tenth of the figure width each
+        let step = fw / 10.0;
+        s.push_str(&format!(
+            r#"<g stroke="{}" stroke-width="{:.2}">"#,
+            look.grid(),
+            step * 0.03
+        ));
+        for i in 0..=10 {
+            let x = i as f32 * step;
+            s.push_str(&format!(r#"<line x1="{x}" y1="0" x2="{x}" y2="{fh}"/>"#));
+        }
+        let rows = (fh / step).floor() as u32;
+        for i in 0..=rows {
+            let y = i as f32 * step;
+            s.push_str(&format!(r#"<line x1="0" y1="{y}" x2="{fw}" y2="{y}"/>"#));
+        }
+        s.push_str("</g>");
+    }
+
+    // Marks are sized for a 100-unit-wide figure and scaled from there,
+    // so they stay visible on photos measured in pixels
+    let k = fw / FIGURE_W;
+
+    // The region draft is always drawn while editing
+    s.push_str(&regions::render(shown_regions, draft, k, look));
+
+    if options.heatmap {
+        s.push_str(&overlay::heatmap(&nkisi.events, k, look));
+    }
+
+    // Pins (colored per event outcome when enabled)
+    let (outline, width) = look.pin_outline();
+    let r = look.pin_radius() * k;
+    s.push_str(&format!(r#"<g fill="{}" stroke="{outline}" stroke-width="{:.2}">"#, look.pin(), width * k));
+    if options.outcome_colors {
+        for ev in &nkisi.events {
+            let (x, y) = ev.pos;
+            s.push_str(&format!(
+                r#"<circle cx="{x:.2}" cy="{y:.2}" r="{r:.2}" fill="{}"/>"#,
+                look.outcome(&ev.outcome)
+            ));
+        }
+    } else {
+        for &(x, y) in &nkisi.pins {
+            s.push_str(&format!(r#"<circle cx="{x:.2}" cy="{y:.2}" r="{r:.2}"/>"#));
+        }
+    }
+    s.push_str("</g>");
+
+    if options.labels {
+        s.push_str(&overlay::labels(&nkisi.events, k, look));
+    }
+
+    // Ring around the selected event, following it while dragged, with
+    // guides across the figure so it is easy to find on a busy photo
+    let selected = selected.and_then(|(id, to)| Some((nkisi.events.iter().find(|e| e.id == id)?, to)));
+    if let Some((ev, dragged_to)) = selected {
+        let (x, y) = dragged_to.unwrap_or(ev.pos);
+        let c = look.highlight();
+        s.push_str(&format!(
+            r#"<g stroke="{c}66" stroke-width="{:.2}"><line x1="0" y1="{y:.2}" x2="{fw}" y2="{y:.2}"/><line x1="{x:.2}" y1="0" x2="{x:.2}" y2="{fh}"/></g>"#,
+            0.25 * k
+        ));
+        s.push_str(&format!(
+            r#"<circle cx="{x:.2}" cy="{y:.2}" r="{:.2}" fill="none" stroke="{c}" stroke-width="{:.2}"/>"#,
+            3.6 * k,
+            0.8 * k
+        ));
+    }
+    s.push_str("</svg>");
+    s
+}
+
+// -------------------- Persistence --------------------
+#[derive(Debug, Error)]
+pub enum IoError {
+    #[error("read error: {0}")]
+    Read(String),
+    #[error("write error: {0}")]
+    Write(String),
+    #[error("parse error: {0}")]
+    Parse(String),
+    #[error("saved by a newer version ({saved_by}, format {found})")]
+    Newer { found: u32, saved_by: String },
+}
+fn save_json(path: &str, state: &NkisiNkondi) -> Result<(), IoError> {
+    let bytes =
+        serde_json::to_vec_pretty(state).map_err(|e| IoError::Write(e.to_string()))?;
+    std::fs::write(path, bytes).map_err(|e| IoError::Write(e.to_string()))
+}
+fn load_json(path: &str) -> Result<NkisiNkondi, IoError> {
+    let bytes = std::fs::read(path).map_err(|e| IoError::Read(e.to_string()))?;
+    compat::parse(&bytes)
+}
+
+// A ledger as it stands on disk, unsaved journal entries included, read
+// without taking it over (no lock, no journal kept open)
+fn read_ledger(path: &str) -> Result<NkisiNkondi, IoError> {
+    let mut nkisi = load_json(path)?;
+    let journal = journal::Journal::open(path)?;
+    for entry in journal.since(nkisi.id, nkisi.journal_seq) {
+        nkisi.apply(&entry.event, entry.stamp());
+    }
+    Ok(nkisi)
+}
+
+// -------------------- FIX acceptor --------------------
+// Minimal FIX “U1 Spike” parser/acceptor. Default tags (see fixdict):
+// 35=U1 (custom); 55=NKISI; 448=PartyID (who); 58=Text (message);
+// 60=TransactTime (optional ISO); 6010=PosX; 6011=PosY; 6012=purpose and
+// 6013=outcome (optional); 9000=event id (optional, set by drop-copy so
+// peers mirroring each other don't duplicate). 6009=NoSpikes carries
+// several spikes in one message, each entry starting with 6010.
+fn handle_fix_connection(
+    stream: impl session::Transport + 'static,
+    peer: String,
+    listener: &str,
+    tx: fixqueue::Sender,
+    validation: session::Validation,
+    dict: Arc<fixdict::FixDictionary>,
+) {
+    let source = peer.clone();
+    let auth = Arc::clone(&validation.auth);
+    let stored = validation.store.is_some();
+    let overflowed = Arc::clone(&validation.overflowed);
+    session::run(stream, peer, validation, Arc::clone(&dict), |msg, reply| {
+        let mut spikes = parse_fix_spikes(msg, &dict)?;
+        if let Some(spike) = spikes.iter().find(|s| !auth.admits_party(&s.who)) {
+            let text = format!("striker {} may not send spikes", spike.who);
+            auth.refuse(&source, &text);
+            return Err(refuse(fix::RejectReason::ValueIncorrect, dict.who, &text));
+        }
+        // Only a sender's idempotency key makes a spike one already seen
+        // (the session drops PossDup resends of messages it has read); with
+        // a store the id is the one a replay will give it, which no other
+        // message shares
+        let keyed = fix::tag(&fix::fields(msg), dict.idempotency_key as i32).is_some();
+        for (n, spike) in spikes.iter_mut().enumerate() {
+            if keyed || stored {
+                spike.id = spike.id.or(fixstore::spike_id(msg, n, dict.idempotency_key));
+            }
+            spike.source = format!("{} at {source}", spike.source);
+            spike.listener = Some(listener.to_string());
+            spike.reply = reply.clone();
         }
+        // A full queue holds the connection up, which slows the sender down
+        if let Err(spikes) = tx.send_timeout(spikes, FIX_QUEUE_WAIT) {
+            overflowed.fetch_add(spikes.len() as u64, Ordering::Relaxed);
+            eprintln!("[FIX] {source}: ledger queue full, {} spike(s) refused", spikes.len());
+            for spike in spikes {
+                if let Some(reply) = spike.reply {
+                    reply.rejected(spike.id.unwrap_or_default(), "ledger busy, send again later");
+                }
+            }
+        }
+        Ok(())
+    });
+}
+
+// Spikes of the messages stored on `day`, one batch per message as the
+// acceptor delivered them. Session messages, and spikes that were refused
+// when read or by the access list, are left out as they were live.
+fn stored_spikes(
+    store: &fixstore::Store,
+    day: &str,
+    dict: &fixdict::FixDictionary,
+    auth: &fixauth::FixAuth,
+) -> Result<Vec<Vec<ExternalSpike>>, IoError> {
+    let mut batches = vec![];
+    for (_, record) in store.read_day(day)? {
+        if record.direction != fixstore::Direction::In {
+            continue;
+        }
+        let Ok(mut spikes) = parse_fix_spikes(&record.raw, dict) else { continue };
+        if spikes.iter().any(|s| !auth.admits_party(&s.who)) {
+            continue;
+        }
+        for (n, spike) in spikes.iter_mut().enumerate() {
+            spike.id = spike.id.or(fixstore::spike_id(&record.raw, n, dict.idempotency_key));
+            spike.source = format!("{}, replayed from {day}", spike.source);
+            spike.received_at = record.at;
+        }
+        batches.push(spikes);
+    }
+    Ok(batches)
+}
+
+// PartyRole (452) values in the NoPartyIDs group: Executing Trader is the
+// striker; witnesses use a bilaterally agreed (user-defined) role
+const PARTY_ROLE_STRIKER: &str = "12";
+const PARTY_ROLE_WITNESS: &str = "4000";
+
+// Why a spike can't be read, with the tag at fault
+fn refuse(reason: fix::RejectReason, tag: u32, text: &str) -> fix::Refusal {
+    fix::Refusal::new(reason, Some(tag), text)
+}
+
+fn parse_fix_spikes(raw: &[u8], dict: &fixdict::FixDictionary) -> Result<Vec<ExternalSpike>, fix::Refusal> {
+    use fix::RejectReason::*;
+    // Key=val pairs, in order (repeating groups need it)
+    let mut fields = fix::parse(raw)?;
+    if let Some(spike) = dict.as_spike(&fields) {
+        fields = spike;
+    }
+    // Plain tags by number; the first occurrence wins
+    let mut map: HashMap<i32, String> = HashMap::new();
+    for (k, v) in &fields {
+        map.entry(*k).or_insert_with(|| v.clone());
     }
 
     // Check it’s our message
-    let msg_type = map.get(&35)?; // 35=U1
-    if msg_type != "U1" { return None; }
-    if map.get(&55).map(|s| s.as_str()) != Some("NKISI") { return None; }
-
-    // Required: who (448), pos (6010, 6011)
-    let who = map.get(&448)?.clone();
-    let x: f32 = map.get(&6010)?.parse().ok()?;
-    let y: f32 = map.get(&6011)?.parse().ok()?;
-
-    // Optional message, timestamp
-    let message = map.get(&58).cloned();
-    let when = map.get(&60)
-        .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
-        .map(|dt| dt.with_timezone(&Utc));
-
-    Some(ExternalSpike {
-        pos: (x.clamp(0.0, FIGURE_W), y.clamp(0.0, FIGURE_H)),
+    let msg_type = map.get(&35).ok_or_else(|| refuse(RequiredTagMissing, 35, "MsgType missing"))?; // 35=U1
+    if ![&dict.spike_type, &dict.update_type, &dict.cancel_type].contains(&msg_type) {
+        return Err(fix::Refusal::new(UnsupportedMsgType, Some(35), "unsupported message type"));
+    }
+    let symbol = &dict.symbol;
+    match map.get(&55) {
+        Some(s) if s == symbol => {}
+        Some(other) => return Err(refuse(ValueIncorrect, 55, &format!("Symbol must be {symbol}, not \"{other}\""))),
+        None => return Err(refuse(RequiredTagMissing, 55, &format!("Symbol missing (55={symbol})"))),
+    }
+    let who_tag = dict.who as i32;
+
+    // Parties (453 group of 448 PartyID / 447 PartyIDSource / 452 PartyRole).
+    // The striker is the party with the striker role, else the first one
+    // without a role; without the group a bare 448 names the striker. The
+    // dictionary can move PartyID to another tag.
+    let parties = repeating_group(&fields, 453, &[who_tag, 447, 452]);
+    let has_role = |p: &HashMap<i32, String>, r: Option<&str>| p.get(&452).map(String::as_str) == r;
+    let striker = if parties.is_empty() {
+        None
+    } else {
+        let found = parties
+            .iter()
+            .find(|p| has_role(p, Some(PARTY_ROLE_STRIKER)))
+            .or_else(|| parties.iter().find(|p| has_role(p, None)))
+            .and_then(|p| p.get(&who_tag).cloned());
+        Some(found.ok_or_else(|| refuse(RequiredTagMissing, dict.who, "no party with the striker role (452=12)"))?)
+    };
+    let witnesses: Vec<String> = parties
+        .iter()
+        .filter(|p| has_role(p, Some(PARTY_ROLE_WITNESS)))
+        .filter_map(|p| p.get(&who_tag).cloned())
+        .collect();
+
+    // Optional standard tags: 1=Account, 76=ExecBroker, and the
+    // instrument type as 167=SecurityType or else 461=CFICode
+    let tag = |t: i32| map.get(&t).filter(|v| !v.trim().is_empty()).cloned();
+    let meta = EventMeta {
+        case_ref: tag(1),
+        institution: tag(76),
+        category: tag(167).or_else(|| tag(461)),
+        witnesses,
+        tags: vec![],
+    };
+    let source = map.get(&49).cloned().unwrap_or_else(|| "unknown sender".into());
+    if *msg_type != dict.spike_type {
+        let intent = if *msg_type == dict.update_type { Intent::Update } else { Intent::Cancel };
+        let who = striker
+            .or_else(|| tag(who_tag))
+            .ok_or_else(|| refuse(RequiredTagMissing, dict.who, "PartyID of who asks for the change missing"))?;
+        return amendment(&map, dict, intent, who, source).map(|change| vec![change]);
+    }
+    let spike = |values: &HashMap<i32, String>, who: String| {
+        spike_fields(values, dict).map(|(id, pos, message, when, purpose, outcome)| ExternalSpike {
+            id,
+            pos,
+            who,
+            message,
+            when,
+            purpose,
+            outcome,
+            meta: meta.clone(),
+            source: source.clone(),
+            listener: None,
+            received: Instant::now(),
+            received_at: Utc::now(),
+            reply: None,
+            intent: Intent::Strike,
+        })
+    };
+
+    // Several spikes: a NoSpikes (6009) group whose entries start with the
+    // X tag. Parties and the standard tags above apply to every entry; an
+    // entry may name its own striker with a bare PartyID.
+    let Some(declared) = map.get(&(dict.spikes as i32)) else {
+        let who = match striker {
+            Some(who) => who,
+            None => map
+                .get(&who_tag)
+                .cloned()
+                .ok_or_else(|| refuse(RequiredTagMissing, dict.who, "PartyID of the striker missing"))?,
+        };
+        return Ok(vec![spike(&map, who)?]);
+    };
+    let members = [dict.x, dict.y, dict.who, dict.note, dict.timestamp, dict.purpose, dict.outcome, dict.event_id]
+        .map(|t| t as i32);
+    let entries = repeating_group(&fields, dict.spikes as i32, &members);
+    let count = declared.trim().parse::<usize>().ok().filter(|n| (1..=MAX_SPIKES_PER_MESSAGE).contains(n));
+    if count != Some(entries.len()) {
+        return Err(refuse(
+            IncorrectNumInGroupCount,
+            dict.spikes,
+            &format!("NoSpikes is {declared} but {} spike(s) follow (at most {MAX_SPIKES_PER_MESSAGE})", entries.len()),
+        ));
+    }
+    let mut spikes: Vec<ExternalSpike> = Vec::with_capacity(entries.len());
+    for (n, entry) in entries.iter().enumerate() {
+        let in_entry = |mut r: fix::Refusal| {
+            r.text = format!("spike {}: {}", n + 1, r.text);
+            r
+        };
+        let who = entry
+            .get(&who_tag)
+            .or(striker.as_ref())
+            .cloned()
+            .ok_or_else(|| in_entry(refuse(RequiredTagMissing, dict.who, "PartyID of the striker missing")))?;
+        let s = spike(entry, who).map_err(in_entry)?;
+        if s.id.is_some() && spikes.iter().any(|o| o.id == s.id) {
+            return Err(in_entry(refuse(ValueIncorrect, dict.event_id, "event id repeated in the message")));
+        }
+        spikes.push(s);
+    }
+    Ok(spikes)
+}
+
+// Most spikes one message may carry
+const MAX_SPIKES_PER_MESSAGE: usize = 1000;
+
+// The event id of an update or a cancel, and an update's new outcome, all
+// required; a note (58) goes into the journal entry as the reason
+fn amendment(
+    values: &HashMap<i32, String>,
+    dict: &fixdict::FixDictionary,
+    intent: Intent,
+    who: String,
+    source: String,
+) -> Result<ExternalSpike, fix::Refusal> {
+    use fix::RejectReason::*;
+    let required = |tag: u32, what: &str| {
+        values.get(&(tag as i32)).map(|v| v.trim()).ok_or_else(|| refuse(RequiredTagMissing, tag, &format!("{what} missing")))
+    };
+    let id = Uuid::parse_str(required(dict.event_id, "event id")?)
+        .map_err(|_| refuse(IncorrectDataFormat, dict.event_id, "event id is not a UUID"))?;
+    let outcome = match intent {
+        Intent::Update => Some(
+            fixdict::parse_outcome(required(dict.outcome, "outcome")?)
+                .ok_or_else(|| refuse(ValueIncorrect, dict.outcome, "outcome must be pending, resolved or failed"))?,
+        ),
+        _ => None,
+    };
+    Ok(ExternalSpike {
+        id: Some(id),
+        pos: (0.0, 0.0),
         who,
-        message,
-        when,
+        message: values.get(&(dict.note as i32)).map(|m| m.trim().to_string()).filter(|m| !m.is_empty()),
+        when: None,
+        purpose: None,
+        outcome,
+        meta: EventMeta::default(),
+        source,
+        listener: None,
+        received: Instant::now(),
+        received_at: Utc::now(),
+        reply: None,
+        intent,
     })
 }
 
+type SpikeFields = (Option<Uuid>, (f32, f32), Option<String>, Option<DateTime<Utc>>, Option<String>, Option<Outcome>);
+
+// Per-spike values (event id, position, note, timestamp, purpose, outcome)
+// from the message or one entry of its spike group
+fn spike_fields(values: &HashMap<i32, String>, dict: &fixdict::FixDictionary) -> Result<SpikeFields, fix::Refusal> {
+    use fix::RejectReason::*;
+    // Required: pos (6010, 6011 by default)
+    let coord = |tag: u32| -> Result<f32, fix::Refusal> {
+        let v = values.get(&(tag as i32)).ok_or_else(|| refuse(RequiredTagMissing, tag, "position missing"))?;
+        v.trim()
+            .parse::<f32>()
+            .ok()
+            .filter(|n| n.is_finite())
+            .ok_or_else(|| refuse(IncorrectDataFormat, tag, &format!("\"{v}\" is not a number")))
+    };
+    let x = coord(dict.x)?;
+    let y = coord(dict.y)?;
+
+    // Optional message, timestamp, purpose and outcome
+    let message = values.get(&(dict.note as i32)).cloned();
+    // ISO 8601 or FIX UTCTimestamp; present but unreadable is an error
+    // rather than quietly ignored
+    let when = values
+        .get(&(dict.timestamp as i32))
+        .map(|s| {
+            let s = s.trim();
+            DateTime::parse_from_rfc3339(s)
+                .map(|dt| dt.with_timezone(&Utc))
+                .or_else(|_| chrono::NaiveDateTime::parse_from_str(s, "%Y%m%d-%H:%M:%S%.f").map(|dt| dt.and_utc()))
+                .map_err(|_| {
+                    refuse(IncorrectDataFormat, dict.timestamp, "TransactTime is neither ISO 8601 nor YYYYMMDD-HH:MM:SS")
+                })
+        })
+        .transpose()?;
+    let purpose = values.get(&(dict.purpose as i32)).map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    let outcome = values
+        .get(&(dict.outcome as i32))
+        .map(|s| {
+            fixdict::parse_outcome(s)
+                .ok_or_else(|| refuse(ValueIncorrect, dict.outcome, "outcome must be pending, resolved or failed"))
+        })
+        .transpose()?;
+    let id = values
+        .get(&(dict.event_id as i32))
+        .map(|s| {
+            Uuid::parse_str(s.trim()).map_err(|_| refuse(IncorrectDataFormat, dict.event_id, "event id is not a UUID"))
+        })
+        .transpose()?;
+    Ok((id, (x, y), message, when, purpose, outcome))
+}
+
+// Entries of the repeating group counted by `count_tag`. Each entry starts
+// with `members[0]` and runs while tags belong to the group; at most the
+// announced count of entries is taken.
+fn repeating_group(
+    fields: &[(i32, String)],
+    count_tag: i32,
+    members: &[i32],
+) -> Vec<HashMap<i32, String>> {
+    let Some(start) = fields.iter().position(|(k, _)| *k == count_tag) else {
+        return vec![];
+    };
+    let count: usize = fields[start].1.trim().parse().unwrap_or(0);
+    let mut entries: Vec<HashMap<i32, String>> = vec![];
+    for (k, v) in &fields[start + 1..] {
+        if *k == members[0] {
+            if entries.len() == count {
+                break;
+            }
+            entries.push(HashMap::new());
+        } else if !members.contains(k) {
+            break;
+        }
+        match entries.last_mut() {
+            Some(entry) => {
+                entry.insert(*k, v.clone());
+            }
+            // A member tag before the first delimiter: malformed group
+            None => break,
+        }
+    }
+    entries
+}
+
 
 
 // -------------------- Subscriptions --------------------
-fn subscriptions(_state: &State) -> Subscription<Message> {
-    // Simple timer to poll FIX channel regularly
-    time::every(Duration::from_millis(200))
-        .map(|_| Message::PollExternal)
+fn subscriptions(state: &State) -> Subscription<Message> {
+    let mut subs = vec![
+        // Window size drives the compact layout switch
+        window::resize_events().map(|(_id, size)| Message::WindowResized(size)),
+        // Watch the figure file for edits
+        time::every(Duration::from_secs(1)).map(|_| Message::CheckFigureFile),
+        // Intensity decays between spikes
+        time::every(charge::RECOMPUTE).map(|_| Message::RecomputeIntensity),
+        // Event review; keys typed into a text field don't get here
+        keyboard::on_key_press(review_key),
+        // Zoom works wherever the focus is
+        iced::event::listen_with(zoom_key),
+    ];
+    // FIX spikes as they come; off while the ledger takes no changes, so
+    // they wait in the queue
+    if state.read_only.is_none() && state.sandbox.is_none() && !state.start_screen {
+        subs.push(Subscription::run_with_id("fix-queue", state.fix_rx.arrivals()).map(Message::ExternalArrived));
+    }
+    // The next frame drawn has the pins of the spikes applied since the last
+    if state.latency.lock().unwrap_or_else(|e| e.into_inner()).undrawn() {
+        subs.push(window::frames().map(Message::FrameDrawn));
+    }
+    if state.test_spike.is_some() || state.save_hook.as_ref().is_some_and(|hook| hook.busy()) {
+        subs.push(time::every(Duration::from_millis(200)).map(|_| Message::CheckReports));
+    }
+    Subscription::batch(subs)
+}
+
+// Ctrl/Cmd with +, - or 0
+fn zoom_key(event: iced::Event, _status: iced::event::Status, _window: window::Id) -> Option<Message> {
+    let iced::Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) = event else {
+        return None;
+    };
+    if !modifiers.command() {
+        return None;
+    }
+    match key.as_ref() {
+        keyboard::Key::Character("+" | "=") => Some(Message::ZoomIn),
+        keyboard::Key::Character("-") => Some(Message::ZoomOut),
+        keyboard::Key::Character("0") => Some(Message::ZoomReset),
+        _ => None,
+    }
+}
+
+// Within the limits, in whole steps
+fn ui_scale(scale: f32) -> f32 {
+    if !scale.is_finite() {
+        return 1.0;
+    }
+    ((scale / UI_SCALE_STEP).round() * UI_SCALE_STEP).clamp(UI_SCALE_MIN, UI_SCALE_MAX)
+}
+
+fn review_key(key: keyboard::Key, modifiers: keyboard::Modifiers) -> Option<Message> {
+    use keyboard::key::Named;
+    if modifiers.command() || modifiers.alt() {
+        return None;
+    }
+    match key.as_ref() {
+        keyboard::Key::Named(Named::ArrowRight | Named::ArrowDown) | keyboard::Key::Character("j") => {
+            Some(Message::StepEvent(true))
+        }
+        keyboard::Key::Named(Named::ArrowLeft | Named::ArrowUp) | keyboard::Key::Character("k") => {
+            Some(Message::StepEvent(false))
+        }
+        keyboard::Key::Named(Named::Escape) => Some(Message::SelectEvent(None)),
+        keyboard::Key::Named(Named::F1) => Some(Message::ToggleHelp),
+        _ => None,
+    }
+}
+
+// `update`, then the clipboard write it asked for, which only a task can do
+fn update_and_copy(state: &mut State, message: Message) -> iced::Task<Message> {
+    if let Some(feature) = message.feature() {
+        state.usage.used(feature);
+    }
+    update(state, message);
+    take_fix(state);
+    // One index commit for everything this message changed
+    fulltext::log(state.note_index.flush());
+    let half_life = state.workspace.settings.half_life_days;
+    if state.intensity.stale(&state.nkisi.events, half_life) {
+        state.intensity = charge::read(&state.nkisi.events, half_life, Utc::now());
+    }
+    state.usage.save();
+    state.outbox.save();
+    state.quarantine.save();
+    match state.to_clipboard.take() {
+        Some(text) => iced::clipboard::write(text),
+        None => iced::Task::none(),
+    }
 }
 
 // -------------------- Boot --------------------
 pub fn main() -> iced::Result {
+    // Defaults < nkisi.toml < NKISI_* env < CLI flags
+    let cfg = config::load();
+    // Rules that don't parse shut every listener rather than leave it open
+    let (net_acl, acl_problem) = match netacl::NetAcl::new(&cfg.allow_from, &cfg.deny_from) {
+        Ok(acl) => (acl, None),
+        Err(e) => {
+            eprintln!("[net] {e}; every listener refuses connections");
+            (netacl::NetAcl::shut(), Some(format!("allow_from/deny_from: {e}")))
+        }
+    };
+    let net_acl = Arc::new(net_acl);
+    let self_test = diagnose::Setup {
+        config_problems: cfg.problems.iter().cloned().chain(acl_problem).collect(),
+        workspace: cfg.workspace.clone(),
+        fix_dictionary: cfg.fix_dictionary.clone(),
+        fix_auth: cfg.fix_auth.clone(),
+        tls: (cfg.fix_tls_cert.clone(), cfg.fix_tls_key.clone(), cfg.fix_client_ca.clone()),
+        ..Default::default()
+    };
+    let ws_path = cfg.workspace.clone();
+    let mut ws = match &ws_path {
+        Some(p) => workspace::open(p).unwrap_or_else(|e| {
+            eprintln!("[workspace] {p}: {e}; starting with an empty workspace");
+            Workspace::default()
+        }),
+        None => Workspace::default(),
+    };
+    // Overrides apply to this run; saving the workspace keeps them
+    if let Some(addr) = cfg.fix_addr {
+        ws.ingest.fix_addr = addr;
+    }
+    if let Some(listeners) = cfg.fix_listeners {
+        ws.ingest.listeners =
+            listeners.into_iter().map(|(name, addr)| workspace::Listener { name, addr }).collect();
+    }
+    if let Some(addr) = cfg.drop_copy {
+        ws.ingest.drop_copy = Some(addr);
+    }
+    if let Some(addr) = cfg.drop_copy_listen {
+        ws.ingest.drop_copy_listen = Some(addr);
+    }
+    if let Some(addr) = cfg.forward_to {
+        ws.ingest.forward_to = Some(addr);
+    }
+    if let Some(addr) = cfg.metrics_addr {
+        ws.ingest.metrics_addr = Some(addr);
+    }
+    if let Some(scale) = cfg.ui_scale {
+        ws.settings.ui_scale = scale;
+    }
+    ws.settings.ui_scale = ui_scale(ws.settings.ui_scale);
+    let open_ledger = ws_path.is_some() || cfg.save_path.is_some();
+    if let Some(fig) = ws.figures.get_mut(ws.active) {
+        if let Some(p) = cfg.save_path {
+            fig.ledger = p;
+        }
+        if let Some(p) = cfg.svg_path {
+            fig.svg = p;
+        }
+    }
+
+    // Before anything binds its port
+    if cfg.self_test {
+        let fig = ws.active_figure().cloned().unwrap_or_else(|| Workspace::default().figures[0].clone());
+        let setup = diagnose::Setup {
+            figure: fig.svg,
+            ledger: fig.ledger,
+            template: report::DEFAULT_TEMPLATE.into(),
+            fix_addr: ws.ingest.fix_addr.clone(),
+            metrics_addr: ws.ingest.metrics_addr.clone(),
+            drop_copy_listen: ws.ingest.drop_copy_listen.clone(),
+            ..self_test
+        };
+        let checks = diagnose::run(&setup);
+        diagnose::print(&checks);
+        std::process::exit(if checks.iter().all(|c| c.outcome.is_ok()) { 0 } else { 1 });
+    }
+
     // Start FIX acceptor thread
-    let (fix_tx, fix_rx) = unbounded::<ExternalSpike>();
-    start_fix_acceptor(FIX_ADDR, fix_tx);
+    let (fix_tx, fix_rx) = fixqueue::bounded(FIX_QUEUE);
+    let (auth, auth_error) = match cfg.fix_auth.as_deref().map(fixauth::FixAuth::load).transpose() {
+        Ok(auth) => (auth.unwrap_or_default(), None),
+        Err(e) => (fixauth::FixAuth::default(), Some(e)),
+    };
+    let fix_refused = Arc::clone(&auth.refused);
+    let fix_store = cfg.fix_store.as_deref().map(|dir| Arc::new(fixstore::Store::new(dir)));
+    let validation = session::Validation {
+        lenient: cfg.lenient_fix,
+        auth: Arc::new(auth),
+        acl: Arc::clone(&net_acl),
+        sequences: Arc::new(session::Sequences::load(session::SEQUENCE_FILE)),
+        store: fix_store.clone(),
+        rate_limit: cfg.fix_rate_limit,
+        max_connections: cfg.fix_max_connections,
+        ..Default::default()
+    };
+    let fix_auth = Arc::clone(&validation.auth);
+    if validation.lenient {
+        eprintln!("[FIX] BodyLength and CheckSum checks are off (--lenient-fix)");
+    }
+    let fix_dropped = Arc::clone(&validation.dropped);
+    let fix_throttled = Arc::clone(&validation.throttled);
+    let fix_overflowed = Arc::clone(&validation.overflowed);
+    let fix_crowded_out = Arc::clone(&validation.crowded_out);
+    let fix_sessions = Arc::clone(&validation.sessions);
+    let fix_inspector = Arc::clone(&validation.inspector);
+    let dict = match &cfg.fix_dictionary {
+        Some(path) => fixdict::FixDictionary::load(path).unwrap_or_else(|e| {
+            eprintln!("[FIX] dictionary {path}: {e}; using the built-in tags");
+            fixdict::FixDictionary::default()
+        }),
+        None => fixdict::FixDictionary::default(),
+    };
+    let dict = Arc::new(dict);
+    // A TLS setup or access list that doesn't load leaves the port closed
+    // rather than open to anyone in plaintext
+    let tls = tls::server_config(cfg.fix_tls_cert.as_deref(), cfg.fix_tls_key.as_deref(), cfg.fix_client_ca.as_deref());
+    let fix_wiring = match (auth_error, tls) {
+        (Some(e), _) => Err(format!("access list: {e}")),
+        (None, Err(e)) => Err(format!("TLS: {e}")),
+        (None, Ok(tls)) => Ok(acceptor::Wiring { tx: fix_tx, validation, dict: Arc::clone(&dict), tls }),
+    };
 
-    let init = State::new(fix_rx);
+    let outbox = Arc::new(outbox::Outbox::load());
+    // A proxy that doesn't parse keeps spikes in rather than sending them round it
+    let upstream = match cfg.forward_proxy.as_deref().map(proxy::Proxy::parse).transpose() {
+        Ok(proxy) => ws.ingest.forward_to.as_deref().map(|addr| {
+            initiator::Initiator::start(addr, proxy, fix_store.clone(), Arc::clone(&dict), Arc::clone(&outbox))
+        }),
+        Err(e) => {
+            eprintln!("[FIX out] proxy {e}; confirmed spikes are not forwarded");
+            None
+        }
+    };
+    let drop_copy = ws.ingest.drop_copy.as_deref().map(|addr| {
+        dropcopy::DropCopy::start(addr, Arc::clone(&dict), Arc::clone(&outbox))
+    });
+    let drop_copy_pub = ws.ingest.drop_copy_listen.as_deref().and_then(|addr| {
+        dropcopy::Publisher::start(addr, Arc::clone(&dict), Arc::clone(&net_acl))
+    });
+    let ws_metrics = ws.ingest.metrics_addr.clone();
+    let mut init = State::new(fix_rx, ws, ws_path.clone());
+    init.drop_copy = drop_copy;
+    init.drop_copy_pub = drop_copy_pub;
+    init.webhook = cfg.webhook.as_deref().and_then(|url| {
+        webhook::Webhook::start(url, cfg.webhook_secret.clone(), Arc::clone(&outbox))
+            .inspect_err(|e| eprintln!("[webhook] {e}; no webhook"))
+            .ok()
+    });
+    init.upstream = upstream;
+    // Whatever the last run had not delivered goes out first
+    if let Some(hook) = &init.webhook {
+        hook.send(&outbox.take_pending(outbox::Channel::Webhook));
+    }
+    if let Some(dc) = &init.drop_copy {
+        dc.send(&outbox.take_pending(outbox::Channel::DropCopy));
+    }
+    if let Some(up) = &init.upstream {
+        outbox.take_pending(outbox::Channel::Upstream).iter().for_each(|ev| up.send(ev));
+    }
+    init.outbox = outbox;
+    init.quarantine = quarantine::Quarantine::load();
+    init.fix_dropped = fix_dropped;
+    init.fix_throttled = fix_throttled;
+    init.fix_overflowed = fix_overflowed;
+    init.fix_crowded_out = fix_crowded_out;
+    init.fix_refused = fix_refused;
+    init.net_acl = Arc::clone(&net_acl);
+    init.fix_sessions = fix_sessions;
+    init.fix_inspector = fix_inspector;
+    init.fix_auth = fix_auth;
+    init.fix_days = fix_store.as_ref().map(|store| store.days()).unwrap_or_default();
+    init.fix_store = fix_store;
+    init.self_test_setup = self_test;
+    init.save_hook = cfg.on_save.as_deref().map(hook::Hook::new);
+    init.usage = usage::Usage::load();
+    init.usage.start_session();
+    init.fix_dict = Arc::clone(&dict);
+    init.pack_key = cfg.pack_key;
+    if let Some(locale) = cfg.locale {
+        init.report_locale = report::language(&locale);
+    }
+    if let Some(addr) = &ws_metrics {
+        // Tokens that don't hold up leave the endpoint closed rather than open to anyone
+        match apiauth::ApiTokens::new(cfg.api_tokens) {
+            Ok(tokens) => {
+                let (latency, feed) = (Arc::clone(&init.latency), Arc::clone(&init.feed));
+                init.metrics_bound = latency::serve(addr, latency, feed, Arc::new(tokens), Arc::clone(&net_acl));
+            }
+            Err(e) => eprintln!("[metrics] {e}; metrics and the event feed stay off"),
+        }
+    }
+    init.recent = start::Recent::load();
+    if open_ledger {
+        open_active_figure(&mut init);
+    } else {
+        init.start_screen = true;
+    }
+    if cfg.replay_fix_store {
+        if init.read_only.is_some() {
+            eprintln!("[FIX] ledger is read-only; the message store is not replayed");
+        } else if init.start_screen {
+            eprintln!("[FIX] no ledger is open yet; the message store is not replayed");
+        } else {
+            let days = init.fix_days.clone();
+            replay_fix_store(&mut init, &days);
+            eprintln!("[FIX] {}", init.status);
+        }
+    }
+    // After any replay, so live spikes come after the stored ones
+    init.fix_wiring = fix_wiring;
+    let opened = std::mem::take(&mut init.status);
+    let addr = init.workspace.ingest.fix_addr.clone();
+    start_acceptor(&mut init, addr);
+    if init.acceptor.is_some() {
+        init.status = opened;
+    } else {
+        eprintln!("[FIX] {}", init.status);
+    }
+    start_listeners(&mut init);
     let title = "Rustic Nkisi — Iced 0.13 (FIX-enabled)";
 
-    application(title, update, view)
+    application(title, update_and_copy, view)
         .subscription(subscriptions)
-        .theme(|_| Theme::Dark)
+        .theme(|state: &State| state.workspace.settings.appearance.theme())
+        .scale_factor(|state: &State| f64::from(state.workspace.settings.ui_scale))
         .centered()
         .run_with(move || (init, iced::Task::none()))
 }
\ No newline at end of file
@@ -0,0 +1,82 @@
+// -------------------- Network access rules --------------------
+// Which addresses may connect to any of our listeners: the FIX acceptors,
+// the drop-copy publisher and the metrics address (/metrics and the event
+// feed). Rules are IPs or CIDR ranges from --allow-from and --deny-from
+// (allow_from, deny_from in the config file). A deny rule always wins;
+// with any allow rule, only addresses it covers get in. Turned-away peers
+// are dropped before a byte is read, logged and counted for the status line.
+// The FIX access list (fixauth.rs) narrows the acceptors down further.
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Debug, Default)]
+pub struct NetAcl {
+    allow: Vec<(IpAddr, u32)>,
+    deny: Vec<(IpAddr, u32)>,
+    // Rules that didn't parse close every listener
+    shut: bool,
+    // Connections turned away since startup
+    pub refused: AtomicU64,
+}
+
+impl NetAcl {
+    pub fn new(allow: &[String], deny: &[String]) -> Result<Self, String> {
+        let ranges = |rules: &[String]| {
+            rules
+                .iter()
+                .map(|r| parse_range(r).ok_or(format!("{r} is not an IP address or CIDR range")))
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(Self { allow: ranges(allow)?, deny: ranges(deny)?, ..Default::default() })
+    }
+
+    // Refuses everyone
+    pub fn shut() -> Self {
+        Self { shut: true, ..Default::default() }
+    }
+
+    pub fn admits(&self, ip: IpAddr) -> bool {
+        let covers = |rules: &[(IpAddr, u32)]| rules.iter().any(|&(net, bits)| in_range(ip, net, bits));
+        !self.shut && !covers(&self.deny) && (self.allow.is_empty() || covers(&self.allow))
+    }
+
+    // Whether `peer` may connect to `listener`; a refusal is logged and counted
+    pub fn check(&self, listener: &str, peer: SocketAddr) -> bool {
+        if self.admits(peer.ip()) {
+            return true;
+        }
+        self.refused.fetch_add(1, Ordering::Relaxed);
+        eprintln!("[{listener}] {peer}: refused by the network rules");
+        false
+    }
+}
+
+// "10.1.0.0/16", "::1" or "192.168.1.7" (a single address)
+pub fn parse_range(s: &str) -> Option<(IpAddr, u32)> {
+    let (addr, bits) = match s.trim().split_once('/') {
+        Some((addr, bits)) => (addr.parse::<IpAddr>().ok()?, Some(bits.parse::<u32>().ok()?)),
+        None => (s.trim().parse::<IpAddr>().ok()?, None),
+    };
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+    let bits = bits.unwrap_or(max);
+    (bits <= max).then_some((addr, bits))
+}
+
+pub fn in_range(ip: IpAddr, net: IpAddr, bits: u32) -> bool {
+    // An IPv4 peer may show up mapped into IPv6
+    let ip = match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        v4 => v4,
+    };
+    match (ip, net) {
+        (IpAddr::V4(a), IpAddr::V4(b)) => {
+            let mask = u32::MAX.checked_shl(32 - bits).unwrap_or(0);
+            u32::from(a) & mask == u32::from(b) & mask
+        }
+        (IpAddr::V6(a), IpAddr::V6(b)) => {
+            let mask = u128::MAX.checked_shl(128 - bits).unwrap_or(0);
+            u128::from(a) & mask == u128::from(b) & mask
+        }
+        _ => false,
+    }
+}
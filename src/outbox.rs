@@ -0,0 +1,219 @@
+// -------------------- Outbox --------------------
+// Events on their way out to the webhook, the drop-copy endpoint and the
+// upstream FIX engine. The workers note here what they took on and what
+// they are done with, and what was still waiting is written to OUTBOX_FILE,
+// so a restart hands it back to them instead of losing it. A worker that
+// can't deliver waits before trying again, twice as long after each
+// failure up to MAX_BACKOFF. An event the receiver refused, one that failed
+// MAX_ATTEMPTS times, or the oldest pushed out of a full queue becomes a
+// dead letter: the Outbox panel lists them and sends them again (re-drive)
+// or discards them.
+use chrono::{DateTime, Utc};
+use iced::widget::{button, column, row, text};
+use iced::{Color, Element};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::{confirm, ActivationEvent, IoError, Message};
+
+pub const OUTBOX_FILE: &str = ".nkisi_outbox.json";
+pub const MAX_ATTEMPTS: u32 = 8;
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+// Oldest dead letters are dropped beyond this
+const MAX_DEAD: usize = 10_000;
+// Rows the panel lists
+const SHOWN: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Channel {
+    Webhook,
+    DropCopy,
+    Upstream,
+}
+
+impl Channel {
+    pub fn name(self) -> &'static str {
+        match self {
+            Channel::Webhook => "webhook",
+            Channel::DropCopy => "drop-copy",
+            Channel::Upstream => "upstream FIX",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub channel: Channel,
+    pub event: ActivationEvent,
+    pub attempts: u32,
+    pub error: String,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Stored {
+    // Taken on by a worker, not yet delivered, in the order they were sent
+    #[serde(default)]
+    pending: BTreeMap<Channel, VecDeque<ActivationEvent>>,
+    #[serde(default)]
+    dead: Vec<DeadLetter>,
+}
+
+#[derive(Debug, Default)]
+pub struct Outbox {
+    stored: Mutex<Stored>,
+    // Changed since the last write
+    dirty: AtomicBool,
+}
+
+impl Outbox {
+    // What the last run left, or an empty outbox
+    pub fn load() -> Self {
+        let stored = match std::fs::read(OUTBOX_FILE) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                eprintln!("[outbox] {OUTBOX_FILE}: {e}; starting empty");
+                Stored::default()
+            }),
+            Err(_) => Stored::default(),
+        };
+        Self { stored: Mutex::new(stored), dirty: AtomicBool::new(false) }
+    }
+
+    fn change<T>(&self, f: impl FnOnce(&mut Stored) -> T) -> T {
+        let mut stored = self.stored.lock().unwrap_or_else(|e| e.into_inner());
+        self.dirty.store(true, Ordering::Relaxed);
+        f(&mut stored)
+    }
+
+    pub fn queued(&self, channel: Channel, ev: &ActivationEvent) {
+        self.change(|s| s.pending.entry(channel).or_default().push_back(ev.clone()));
+    }
+
+    pub fn delivered(&self, channel: Channel, id: Uuid) {
+        self.change(|s| take(s, channel, id));
+    }
+
+    pub fn dead(&self, channel: Channel, event: ActivationEvent, attempts: u32, error: &str) {
+        eprintln!("[outbox] {} event {}: {error}; kept as a dead letter", channel.name(), event.id);
+        self.change(|s| {
+            take(s, channel, event.id);
+            s.dead.push(DeadLetter { channel, event, attempts, error: error.to_string(), at: Utc::now() });
+            if s.dead.len() > MAX_DEAD {
+                let over = s.dead.len() - MAX_DEAD;
+                s.dead.drain(..over);
+            }
+        });
+    }
+
+    // What a channel's worker had not delivered when the last run ended; it
+    // is queued again as it is sent to the worker
+    pub fn take_pending(&self, channel: Channel) -> Vec<ActivationEvent> {
+        self.change(|s| s.pending.remove(&channel).map(Vec::from).unwrap_or_default())
+    }
+
+    pub fn dead_count(&self) -> usize {
+        self.stored.lock().map_or(0, |s| s.dead.len())
+    }
+
+    // Dead letters that `pick` chooses, out of the outbox
+    pub fn take_dead(&self, pick: impl Fn(&DeadLetter) -> bool) -> Vec<DeadLetter> {
+        self.change(|s| {
+            let (taken, kept) = std::mem::take(&mut s.dead).into_iter().partition(|d| pick(d));
+            s.dead = kept;
+            taken
+        })
+    }
+
+    // Writes only after a change; a failed write is logged and tried again
+    // with the next one
+    pub fn save(&self) {
+        if !self.dirty.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        let written = self
+            .stored
+            .lock()
+            .map_err(|e| IoError::Write(e.to_string()))
+            .and_then(|s| serde_json::to_vec(&*s).map_err(|e| IoError::Write(e.to_string())))
+            .and_then(|json| std::fs::write(OUTBOX_FILE, json).map_err(|e| IoError::Write(e.to_string())));
+        if let Err(e) = written {
+            eprintln!("[outbox] {OUTBOX_FILE}: {e}");
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+fn take(s: &mut Stored, channel: Channel, id: Uuid) {
+    if let Some(queue) = s.pending.get_mut(&channel) {
+        if let Some(at) = queue.iter().position(|ev| ev.id == id) {
+            queue.remove(at);
+        }
+    }
+}
+
+// Pause before the next try: `first`, doubled after each failure
+pub struct Backoff {
+    first: Duration,
+    failures: u32,
+}
+
+impl Backoff {
+    pub fn new(first: Duration) -> Self {
+        Self { first, failures: 0 }
+    }
+
+    pub fn failed(&mut self) -> Duration {
+        let wait = self.first.saturating_mul(1 << self.failures.min(16)).min(MAX_BACKOFF);
+        self.failures += 1;
+        wait
+    }
+
+    pub fn reset(&mut self) {
+        self.failures = 0;
+    }
+}
+
+pub fn view(outbox: &Outbox) -> Element<'_, Message> {
+    let dim = Color::from_rgb(0.7, 0.7, 0.8);
+    let stored = outbox.stored.lock().unwrap_or_else(|e| e.into_inner());
+    let waiting: usize = stored.pending.values().map(VecDeque::len).sum();
+    let mut col = column![
+        row![
+            text("Outbox").size(16),
+            button("Re-drive all").on_press(Message::Redrive(None)),
+            button("Close").style(button::secondary).on_press(Message::ShowOutbox(false)),
+        ]
+        .spacing(10),
+        text(format!(
+            "{} waiting • {}",
+            confirm::count(waiting, "event"),
+            confirm::count(stored.dead.len(), "dead letter")
+        )),
+    ]
+    .spacing(4);
+    if stored.dead.is_empty() {
+        col = col.push(text("Nothing failed for good.").size(12).color(dim));
+    }
+    for d in stored.dead.iter().rev().take(SHOWN) {
+        let key = (d.channel, d.event.id);
+        col = col.push(
+            row![
+                text(d.at.format("%Y-%m-%d %H:%M").to_string()).size(13).width(120),
+                text(d.channel.name()).size(13).width(90),
+                text(format!("{} by {}", d.event.id, d.event.performed_by)).size(13).width(360),
+                text(format!("{} tries: {}", d.attempts, d.error)).size(13).color(dim).width(iced::Length::Fill),
+                button("Re-drive").on_press(Message::Redrive(Some(key))),
+                button("Discard").style(button::secondary).on_press(Message::DiscardDeadLetter(key)),
+            ]
+            .spacing(8),
+        );
+    }
+    if stored.dead.len() > SHOWN {
+        col = col.push(text(format!("… and {} older", stored.dead.len() - SHOWN)).size(12).color(dim));
+    }
+    col.into()
+}
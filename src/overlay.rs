@@ -0,0 +1,91 @@
+// -------------------- Overlay options & profiles --------------------
+// What the overlay draws on top of the figure, plus named presets of those
+// options that can be switched from a dropdown. Profiles live in the
+// workspace settings.
+use serde::{Deserialize, Serialize};
+
+use crate::palette::Appearance;
+use crate::regions::escape;
+use crate::ActivationEvent;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OverlayOptions {
+    pub grid: bool,
+    pub outcome_colors: bool,
+    pub heatmap: bool,
+    pub labels: bool,
+    pub regions: bool,
+}
+
+impl Default for OverlayOptions {
+    fn default() -> Self {
+        Self { grid: false, outcome_colors: false, heatmap: false, labels: false, regions: true }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OverlayProfile {
+    pub name: String,
+    pub options: OverlayOptions,
+}
+
+pub fn builtin_profiles() -> Vec<OverlayProfile> {
+    vec![
+        OverlayProfile {
+            name: "Working".into(),
+            options: OverlayOptions { grid: true, regions: true, ..OverlayOptions::NONE },
+        },
+        OverlayProfile {
+            name: "Presentation".into(),
+            options: OverlayOptions { outcome_colors: true, ..OverlayOptions::NONE },
+        },
+        OverlayProfile {
+            name: "Analysis".into(),
+            options: OverlayOptions {
+                outcome_colors: true,
+                heatmap: true,
+                labels: true,
+                regions: true,
+                ..OverlayOptions::NONE
+            },
+        },
+    ]
+}
+
+impl OverlayOptions {
+    const NONE: Self = Self {
+        grid: false,
+        outcome_colors: false,
+        heatmap: false,
+        labels: false,
+        regions: false,
+    };
+}
+
+// Soft translucent blobs; overlapping spikes read as hot spots
+pub fn heatmap(events: &[ActivationEvent], k: f32, look: &Appearance) -> String {
+    let mut s = format!(r#"<g fill="{}" fill-opacity="0.12">"#, look.heat());
+    for ev in events {
+        let (x, y) = ev.pos;
+        s.push_str(&format!(r#"<circle cx="{x:.2}" cy="{y:.2}" r="{:.2}"/>"#, 7.0 * k));
+        s.push_str(&format!(r#"<circle cx="{x:.2}" cy="{y:.2}" r="{:.2}"/>"#, 3.5 * k));
+    }
+    s.push_str("</g>");
+    s
+}
+
+// Striker name beside each pin
+pub fn labels(events: &[ActivationEvent], k: f32, look: &Appearance) -> String {
+    let mut s = format!(r##"<g fill="#f0f0f0" font-size="{:.2}"{}>"##, 2.6 * k, look.halo(k));
+    for ev in events {
+        let (x, y) = ev.pos;
+        s.push_str(&format!(
+            r#"<text x="{:.2}" y="{:.2}">{}</text>"#,
+            x + 2.4 * k,
+            y + 0.9 * k,
+            escape(&ev.performed_by)
+        ));
+    }
+    s.push_str("</g>");
+    s
+}
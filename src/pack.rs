@@ -0,0 +1,134 @@
+// -------------------- Spike packs --------------------
+// A pack is a gzip'd JSON bundle of selected events, signed so the
+// receiving institution can tell it arrived unaltered from a holder of the
+// shared pack key (HMAC-SHA1 over the exact payload bytes, from ring).
+// Events keep their ids and provenance; importing adds a hop naming the
+// pack.
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use ring::hmac;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use uuid::Uuid;
+
+use crate::{ActivationEvent, IoError};
+
+pub const EXTENSION: &str = "nkisipack";
+const PACK_FORMAT: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pack {
+    pub format: u32,
+    pub figure_id: Uuid,
+    pub figure: String,
+    // Who made the pack (the exporting workspace)
+    pub exported_by: String,
+    pub exported_at: DateTime<Utc>,
+    pub events: Vec<ActivationEvent>,
+}
+
+// On disk: the payload as the exact string that was signed
+#[derive(Serialize, Deserialize)]
+struct Signed {
+    payload: String,
+    signature: String,
+}
+
+pub fn write(path: &str, pack: &Pack, key: &str) -> Result<(), IoError> {
+    let payload = serde_json::to_string(pack).map_err(|e| IoError::Write(e.to_string()))?;
+    let signature = hex::encode(hmac_sha1(key.as_bytes(), payload.as_bytes()));
+    let json = serde_json::to_vec(&Signed { payload, signature })
+        .map_err(|e| IoError::Write(e.to_string()))?;
+    let mut gz = GzEncoder::new(Vec::new(), Compression::best());
+    gz.write_all(&json).map_err(|e| IoError::Write(e.to_string()))?;
+    let bytes = gz.finish().map_err(|e| IoError::Write(e.to_string()))?;
+    std::fs::write(path, bytes).map_err(|e| IoError::Write(e.to_string()))
+}
+
+// Read a pack, refusing it unless it was signed with `key`
+pub fn read(path: &str, key: &str) -> Result<Pack, IoError> {
+    let bytes = std::fs::read(path).map_err(|e| IoError::Read(e.to_string()))?;
+    let mut json = vec![];
+    GzDecoder::new(bytes.as_slice())
+        .read_to_end(&mut json)
+        .map_err(|e| IoError::Parse(format!("not a spike pack: {e}")))?;
+    let signed: Signed = serde_json::from_slice(&json).map_err(|e| IoError::Parse(e.to_string()))?;
+    let given = hex::decode(signed.signature.trim()).unwrap_or_default();
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, key.as_bytes());
+    if hmac::verify(&key, signed.payload.as_bytes(), &given).is_err() {
+        return Err(IoError::Parse("signature does not match the pack key".into()));
+    }
+    let pack: Pack =
+        serde_json::from_str(&signed.payload).map_err(|e| IoError::Parse(e.to_string()))?;
+    if pack.format > PACK_FORMAT {
+        return Err(IoError::Parse(format!("pack format {} is newer than this build", pack.format)));
+    }
+    Ok(pack)
+}
+
+pub fn new_pack(
+    figure_id: Uuid,
+    figure: &str,
+    exported_by: &str,
+    events: Vec<ActivationEvent>,
+) -> Pack {
+    Pack {
+        format: PACK_FORMAT,
+        figure_id,
+        figure: figure.into(),
+        exported_by: exported_by.into(),
+        exported_at: Utc::now(),
+        events,
+    }
+}
+
+// SHA-1 stays for the packs already signed with it
+pub fn hmac_sha1(key: &[u8], msg: &[u8]) -> Vec<u8> {
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, key);
+    hmac::sign(&key, msg).as_ref().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 2202, section 3
+    #[test]
+    fn hmac_sha1_matches_rfc_2202() {
+        let cases: [(Vec<u8>, Vec<u8>, &str); 7] = [
+            (vec![0x0b; 20], b"Hi There".to_vec(), "b617318655057264e28bc0b6fb378c8ef146be00"),
+            (b"Jefe".to_vec(), b"what do ya want for nothing?".to_vec(), "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79"),
+            (vec![0xaa; 20], vec![0xdd; 50], "125d7342b9ac11cd91a39af48aa17b4f63f175d3"),
+            ((1..=25).collect(), vec![0xcd; 50], "4c9007f4026250c6bc8414f9bf50c86c2d7235da"),
+            (vec![0x0c; 20], b"Test With Truncation".to_vec(), "4c1a03424b55e07fe7f27be1d58bb9324a9a5a04"),
+            (
+                vec![0xaa; 80],
+                b"Test Using Larger Than Block-Size Key - Hash Key First".to_vec(),
+                "aa4ae5e15272d00e95705637ce8a3b55ed402112",
+            ),
+            (
+                vec![0xaa; 80],
+                b"Test Using Larger Than Block-Size Key and Larger Than One Block-Size Data".to_vec(),
+                "e8e99d0f45237d786d6bbaa7965c7808bbff1a91",
+            ),
+        ];
+        for (key, data, digest) in cases {
+            assert_eq!(hex::encode(hmac_sha1(&key, &data)), digest);
+        }
+    }
+
+    #[test]
+    fn packs_open_with_their_key_only() {
+        let path = std::env::temp_dir().join(format!("nkisi_pack_test_{}.{EXTENSION}", std::process::id()));
+        let path = path.to_str().unwrap();
+        let pack = new_pack(Uuid::nil(), "figure", "desk", vec![]);
+        write(path, &pack, "shared").unwrap();
+        let read_back = read(path, "shared");
+        let wrong = read(path, "other");
+        let _ = std::fs::remove_file(path);
+        assert_eq!(read_back.unwrap().exported_by, "desk");
+        assert!(matches!(wrong, Err(IoError::Parse(why)) if why.contains("signature")));
+    }
+}
@@ -0,0 +1,128 @@
+// -------------------- Palettes & contrast --------------------
+// Colors the overlay draws with, chosen in the workspace settings. Besides
+// the original red/green scheme there are two sets that stay apart under
+// red–green and blue–yellow color blindness (Okabe–Ito, Paul Tol's
+// "bright"). High contrast draws pins, labels and regions heavier, with
+// dark outlines so they hold up on busy photos, and switches the app to a
+// black-and-white theme. Exports use the same look as the screen.
+use iced::{Color, Theme};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::Outcome;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Palette {
+    #[default]
+    Standard,
+    OkabeIto,
+    TolBright,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 3] = [Palette::Standard, Palette::OkabeIto, Palette::TolBright];
+}
+
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Palette::Standard => "Standard",
+            Palette::OkabeIto => "Okabe–Ito (color-blind safe)",
+            Palette::TolBright => "Tol bright (color-blind safe)",
+        })
+    }
+}
+
+// Pending, resolved, failed, plain pin, heatmap, highlight, regions
+struct Colors([&'static str; 7]);
+
+const STANDARD: Colors = Colors(["#ff4d4d", "#4dd27a", "#9a9a9a", "#ff4d4d", "#ff7a1a", "#ffd24d", "#4da6ff"]);
+const OKABE_ITO: Colors = Colors(["#e69f00", "#56b4e9", "#999999", "#e69f00", "#d55e00", "#f0e442", "#cc79a7"]);
+const TOL_BRIGHT: Colors = Colors(["#ee6677", "#66ccee", "#bbbbbb", "#ee6677", "#aa3377", "#ccbb44", "#4477aa"]);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Appearance {
+    #[serde(default)]
+    pub palette: Palette,
+    #[serde(default)]
+    pub high_contrast: bool,
+}
+
+impl Appearance {
+    fn colors(&self) -> &'static Colors {
+        match self.palette {
+            Palette::Standard => &STANDARD,
+            Palette::OkabeIto => &OKABE_ITO,
+            Palette::TolBright => &TOL_BRIGHT,
+        }
+    }
+
+    pub fn outcome(&self, outcome: &Outcome) -> &'static str {
+        let c = &self.colors().0;
+        match outcome {
+            Outcome::Pending => c[0],
+            Outcome::Resolved => c[1],
+            Outcome::Failed => c[2],
+        }
+    }
+
+    pub fn pin(&self) -> &'static str {
+        self.colors().0[3]
+    }
+
+    pub fn heat(&self) -> &'static str {
+        self.colors().0[4]
+    }
+
+    // Selection ring, guides and the region being drawn
+    pub fn highlight(&self) -> &'static str {
+        self.colors().0[5]
+    }
+
+    pub fn region(&self) -> &'static str {
+        self.colors().0[6]
+    }
+
+    // Pin outline color and width (figure units at 100 wide)
+    pub fn pin_outline(&self) -> (&'static str, f32) {
+        if self.high_contrast { ("#000000", 0.7) } else { ("#00000099", 0.4) }
+    }
+
+    pub fn pin_radius(&self) -> f32 {
+        if self.high_contrast { 2.2 } else { 1.8 }
+    }
+
+    // Alpha suffix for translucent fills
+    pub fn fill_alpha(&self) -> &'static str {
+        if self.high_contrast { "44" } else { "22" }
+    }
+
+    pub fn grid(&self) -> &'static str {
+        if self.high_contrast { "#ffffff77" } else { "#ffffff22" }
+    }
+
+    // Attributes giving text a dark halo in high contrast
+    pub fn halo(&self, k: f32) -> String {
+        if self.high_contrast {
+            format!(r##" stroke="#000000" stroke-width="{:.2}" paint-order="stroke""##, 0.6 * k)
+        } else {
+            String::new()
+        }
+    }
+
+    pub fn theme(&self) -> Theme {
+        if !self.high_contrast {
+            return Theme::Dark;
+        }
+        Theme::custom(
+            "High contrast".into(),
+            iced::theme::Palette {
+                background: Color::BLACK,
+                text: Color::WHITE,
+                primary: Color::from_rgb(1.0, 0.85, 0.0),
+                success: Color::from_rgb(0.2, 0.9, 0.4),
+                danger: Color::from_rgb(1.0, 0.35, 0.35),
+            },
+        )
+    }
+}
@@ -0,0 +1,160 @@
+// -------------------- PDF --------------------
+// A small PDF writer for batch exports: an A4 page with the figure as an
+// image, followed by pages of plain text (the event list). Only the
+// standard Helvetica font is used, so nothing is embedded; characters it
+// can't show print as "?".
+use flate2::{write::ZlibEncoder, Compression};
+use resvg::tiny_skia::Pixmap;
+use std::io::Write;
+
+use crate::IoError;
+
+// A4 in points
+const PAGE_W: f32 = 595.0;
+const PAGE_H: f32 = 842.0;
+const MARGIN: f32 = 40.0;
+const TITLE_SIZE: f32 = 14.0;
+const FONT_SIZE: f32 = 9.0;
+const LEADING: f32 = 12.0;
+// Characters per line before wrapping, roughly what fits at FONT_SIZE
+const WRAP: usize = 110;
+
+struct Doc {
+    buf: Vec<u8>,
+    offsets: Vec<usize>,
+}
+
+impl Doc {
+    // Objects are numbered in the order they are written, from 1
+    fn object(&mut self, body: &[u8]) {
+        self.offsets.push(self.buf.len());
+        self.buf.extend(format!("{} 0 obj\n", self.offsets.len()).as_bytes());
+        self.buf.extend(body);
+        self.buf.extend(b"\nendobj\n");
+    }
+
+    fn stream(&mut self, dict: &str, data: &[u8]) {
+        let mut body = format!("<< {dict} /Length {} >>\nstream\n", data.len()).into_bytes();
+        body.extend(data);
+        body.extend(b"\nendstream");
+        self.object(&body);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let xref = self.buf.len();
+        let n = self.offsets.len() + 1;
+        self.buf.extend(format!("xref\n0 {n}\n0000000000 65535 f \n").as_bytes());
+        for off in &self.offsets {
+            self.buf.extend(format!("{off:010} 00000 n \n").as_bytes());
+        }
+        self.buf.extend(
+            format!("trailer\n<< /Size {n} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n").as_bytes(),
+        );
+        self.buf
+    }
+}
+
+// A PDF string literal in WinAnsiEncoding
+fn literal(text: &str) -> Vec<u8> {
+    let mut out = vec![b'('];
+    for c in text.chars() {
+        let b = match c {
+            '(' | ')' | '\\' => {
+                out.push(b'\\');
+                c as u8
+            }
+            '—' => 0x97,
+            '–' => 0x96,
+            '…' => 0x85,
+            ' '..='~' | '\u{a0}'..='\u{ff}' => c as u32 as u8,
+            _ => b'?',
+        };
+        out.push(b);
+    }
+    out.push(b')');
+    out
+}
+
+fn deflate(data: &[u8]) -> Result<Vec<u8>, IoError> {
+    let mut enc = ZlibEncoder::new(Vec::new(), Compression::default());
+    enc.write_all(data).map_err(|e| IoError::Write(e.to_string()))?;
+    enc.finish().map_err(|e| IoError::Write(e.to_string()))
+}
+
+fn wrap(line: &str) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+    chars.chunks(WRAP).map(|c| c.iter().collect()).collect()
+}
+
+// Lines of text from the top of a page
+fn text_block(size: f32, top: f32, lines: &[String]) -> Vec<u8> {
+    let mut out = format!("BT /F1 {size} Tf {LEADING} TL {MARGIN} {top} Td\n").into_bytes();
+    for line in lines {
+        out.extend(literal(line));
+        out.extend(b" Tj T*\n");
+    }
+    out.extend(b"ET\n");
+    out
+}
+
+// Title and figure on the first page, then `lines` over as many pages as
+// they need
+pub fn write(path: &str, title: &str, figure: &Pixmap, lines: &[String]) -> Result<(), IoError> {
+    let lines: Vec<String> = lines.iter().flat_map(|l| wrap(l)).collect();
+    let per_page = ((PAGE_H - 2.0 * MARGIN) / LEADING).floor() as usize;
+    let text_pages: Vec<&[String]> = lines.chunks(per_page).collect();
+    let pages = 1 + text_pages.len();
+
+    let mut doc = Doc { buf: b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec(), offsets: vec![] };
+    // 1 catalog, 2 page tree, 3 font, 4 image, then a page and its content
+    // for each page
+    doc.object(b"<< /Type /Catalog /Pages 2 0 R >>");
+    let kids: Vec<String> = (0..pages).map(|i| format!("{} 0 R", 5 + 2 * i)).collect();
+    doc.object(format!("<< /Type /Pages /Kids [{}] /Count {pages} >>", kids.join(" ")).as_bytes());
+    doc.object(b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>");
+
+    let rgb: Vec<u8> = figure
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue()]
+        })
+        .collect();
+    let (iw, ih) = (figure.width(), figure.height());
+    doc.stream(
+        &format!(
+            "/Type /XObject /Subtype /Image /Width {iw} /Height {ih} /ColorSpace /DeviceRGB \
+             /BitsPerComponent 8 /Filter /FlateDecode"
+        ),
+        &deflate(&rgb)?,
+    );
+
+    // The figure fills the space under the title, keeping its proportions
+    let top = PAGE_H - MARGIN - TITLE_SIZE;
+    let (box_w, box_h) = (PAGE_W - 2.0 * MARGIN, top - LEADING - MARGIN);
+    let scale = (box_w / iw as f32).min(box_h / ih as f32);
+    let (w, h) = (iw as f32 * scale, ih as f32 * scale);
+    let (x, y) = ((PAGE_W - w) / 2.0, top - LEADING - h);
+    let mut first = text_block(TITLE_SIZE, top, &[title.to_string()]);
+    first.extend(format!("q {w:.2} 0 0 {h:.2} {x:.2} {y:.2} cm /Im1 Do Q\n").as_bytes());
+
+    let text_top = PAGE_H - MARGIN - FONT_SIZE;
+    let contents =
+        std::iter::once(first).chain(text_pages.iter().map(|chunk| text_block(FONT_SIZE, text_top, chunk)));
+    for (i, content) in contents.enumerate() {
+        doc.object(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {PAGE_W} {PAGE_H}] \
+                 /Resources << /Font << /F1 3 0 R >> /XObject << /Im1 4 0 R >> >> /Contents {} 0 R >>",
+                6 + 2 * i
+            )
+            .as_bytes(),
+        );
+        doc.stream("/Filter /FlateDecode", &deflate(&content)?);
+    }
+    std::fs::write(path, doc.finish()).map_err(|e| IoError::Write(e.to_string()))
+}
@@ -0,0 +1,226 @@
+// -------------------- Outbound proxy --------------------
+// Reaching a FIX counterparty through a proxy, for networks where direct
+// outbound TCP is blocked: the initiator (--forward-proxy) and fixclient
+// (--proxy). The proxy is given as a URL:
+//   socks5://[user:pass@]host:port   SOCKS5; the proxy resolves the target
+//   http://[user:pass@]host:port     HTTP CONNECT, with Basic credentials
+// Once the tunnel is up the stream is an ordinary TcpStream. Only std, so
+// the binaries can share it.
+use std::fmt;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{IpAddr, TcpStream};
+use std::time::Duration;
+
+// How long the proxy may take over each step of setting up the tunnel
+const HANDSHAKE: Duration = Duration::from_secs(10);
+// Longest HTTP response head we wait through
+const MAX_HEAD: usize = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Socks5,
+    Http,
+}
+
+#[derive(Debug, Clone)]
+pub struct Proxy {
+    pub kind: Kind,
+    // host:port of the proxy itself
+    pub addr: String,
+    credentials: Option<(String, String)>,
+}
+
+// The URL without the password, for logs
+impl fmt::Display for Proxy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scheme = match self.kind {
+            Kind::Socks5 => "socks5",
+            Kind::Http => "http",
+        };
+        match &self.credentials {
+            Some((user, _)) => write!(f, "{scheme}://{user}@{}", self.addr),
+            None => write!(f, "{scheme}://{}", self.addr),
+        }
+    }
+}
+
+impl Proxy {
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let (scheme, rest) = url.trim().split_once("://").ok_or(format!("{url}: not a proxy URL"))?;
+        let kind = match scheme.to_ascii_lowercase().as_str() {
+            "socks5" | "socks5h" => Kind::Socks5,
+            "http" => Kind::Http,
+            other => return Err(format!("{other}: proxies are socks5:// or http://")),
+        };
+        let rest = rest.trim_end_matches('/');
+        let (credentials, addr) = match rest.rsplit_once('@') {
+            Some((creds, addr)) => {
+                let (user, pass) = creds.split_once(':').unwrap_or((creds, ""));
+                (Some((user.to_string(), pass.to_string())), addr)
+            }
+            None => (None, rest),
+        };
+        if split_port(addr).is_none() {
+            return Err(format!("{addr}: the proxy needs a host:port"));
+        }
+        Ok(Self { kind, addr: addr.to_string(), credentials })
+    }
+
+    // A stream to `target` (host:port) through the proxy
+    pub fn connect(&self, target: &str) -> io::Result<TcpStream> {
+        let (host, port) = split_port(target).ok_or_else(|| refused(format!("{target}: not a host:port")))?;
+        let mut stream = TcpStream::connect(&self.addr)?;
+        stream.set_read_timeout(Some(HANDSHAKE))?;
+        stream.set_write_timeout(Some(HANDSHAKE))?;
+        match self.kind {
+            Kind::Socks5 => self.socks5(&mut stream, host, port)?,
+            Kind::Http => self.http(&mut stream, host, port)?,
+        }
+        stream.set_read_timeout(None)?;
+        stream.set_write_timeout(None)?;
+        Ok(stream)
+    }
+
+    // RFC 1928, with RFC 1929 username and password when we have them
+    fn socks5(&self, stream: &mut TcpStream, host: &str, port: u16) -> io::Result<()> {
+        let greeting: &[u8] = if self.credentials.is_some() { &[5, 2, 0, 2] } else { &[5, 1, 0] };
+        stream.write_all(greeting)?;
+        let mut choice = [0u8; 2];
+        stream.read_exact(&mut choice)?;
+        match (choice, &self.credentials) {
+            ([5, 0], _) => {}
+            ([5, 2], Some((user, pass))) => {
+                if user.len() > 255 || pass.len() > 255 {
+                    return Err(refused("proxy username or password longer than 255 bytes".into()));
+                }
+                let mut auth = vec![1, user.len() as u8];
+                auth.extend_from_slice(user.as_bytes());
+                auth.push(pass.len() as u8);
+                auth.extend_from_slice(pass.as_bytes());
+                stream.write_all(&auth)?;
+                let mut status = [0u8; 2];
+                stream.read_exact(&mut status)?;
+                if status[1] != 0 {
+                    return Err(io::Error::new(ErrorKind::PermissionDenied, "proxy refused the credentials"));
+                }
+            }
+            ([5, 0xff], _) | ([5, 2], None) => {
+                return Err(io::Error::new(ErrorKind::PermissionDenied, "proxy wants credentials we don't have"));
+            }
+            _ => return Err(refused("not a SOCKS5 proxy".into())),
+        }
+
+        let mut request = vec![5, 1, 0];
+        match host.parse::<IpAddr>() {
+            Ok(IpAddr::V4(ip)) => {
+                request.push(1);
+                request.extend_from_slice(&ip.octets());
+            }
+            Ok(IpAddr::V6(ip)) => {
+                request.push(4);
+                request.extend_from_slice(&ip.octets());
+            }
+            Err(_) if host.len() <= 255 => {
+                request.extend_from_slice(&[3, host.len() as u8]);
+                request.extend_from_slice(host.as_bytes());
+            }
+            Err(_) => return Err(refused(format!("{host}: name too long for SOCKS5"))),
+        }
+        request.extend_from_slice(&port.to_be_bytes());
+        stream.write_all(&request)?;
+
+        let mut reply = [0u8; 4];
+        stream.read_exact(&mut reply)?;
+        if reply[0] != 5 {
+            return Err(refused("not a SOCKS5 proxy".into()));
+        }
+        if reply[1] != 0 {
+            return Err(refused(format!("proxy couldn't connect: {}", socks_error(reply[1]))));
+        }
+        // The address the proxy bound for us, which we don't need
+        let rest = match reply[3] {
+            1 => 4 + 2,
+            4 => 16 + 2,
+            3 => {
+                let mut len = [0u8; 1];
+                stream.read_exact(&mut len)?;
+                len[0] as usize + 2
+            }
+            _ => return Err(refused("proxy answered with an unknown address type".into())),
+        };
+        stream.read_exact(&mut vec![0u8; rest])
+    }
+
+    fn http(&self, stream: &mut TcpStream, host: &str, port: u16) -> io::Result<()> {
+        let authority = if host.contains(':') { format!("[{host}]:{port}") } else { format!("{host}:{port}") };
+        let mut request = format!("CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\n");
+        if let Some((user, pass)) = &self.credentials {
+            let basic = base64(format!("{user}:{pass}").as_bytes());
+            request.push_str(&format!("Proxy-Authorization: Basic {basic}\r\n"));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes())?;
+
+        // A byte at a time, so nothing past the head is taken from the counterparty
+        let mut head = Vec::new();
+        let mut byte = [0u8; 1];
+        while !head.ends_with(b"\r\n\r\n") {
+            if head.len() >= MAX_HEAD {
+                return Err(refused("proxy response head too long".into()));
+            }
+            stream.read_exact(&mut byte)?;
+            head.push(byte[0]);
+        }
+        let head = String::from_utf8_lossy(&head);
+        let status = head.lines().next().unwrap_or("");
+        match status.split_whitespace().nth(1) {
+            Some(code) if code.starts_with('2') => Ok(()),
+            Some("407") => Err(io::Error::new(ErrorKind::PermissionDenied, format!("proxy: {status}"))),
+            _ => Err(refused(format!("proxy: {status}"))),
+        }
+    }
+}
+
+// "host:port", "[::1]:port"; the host comes back without brackets
+fn split_port(addr: &str) -> Option<(&str, u16)> {
+    let (host, port) = addr.rsplit_once(':')?;
+    let host = host.strip_prefix('[').and_then(|h| h.strip_suffix(']')).unwrap_or(host);
+    if host.is_empty() {
+        return None;
+    }
+    Some((host, port.parse().ok()?))
+}
+
+fn refused(why: String) -> io::Error {
+    io::Error::new(ErrorKind::ConnectionRefused, why)
+}
+
+fn socks_error(code: u8) -> &'static str {
+    match code {
+        1 => "general failure",
+        2 => "not allowed by its rules",
+        3 => "network unreachable",
+        4 => "host unreachable",
+        5 => "connection refused",
+        6 => "TTL expired",
+        7 => "command not supported",
+        8 => "address type not supported",
+        _ => "unknown error",
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
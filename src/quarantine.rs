@@ -0,0 +1,251 @@
+// -------------------- Quarantine --------------------
+// What becomes of a FIX spike whose position lies outside the open figure
+// (or isn't a number at all), as the workspace's ingest settings say:
+//   clamp:      moved to the nearest edge and recorded, as it always was
+//   reject:     the message is refused, and its sender told why
+//   quarantine: its spikes are held here for an operator to accept (moved
+//               to the edge then, and checked against the rules like any
+//               spike) or discard; the sender hears "pending" at once and
+//               the final answer when one is given, if still connected
+// Spikes the rules refuse, or refused for lying outside the figure, are
+// kept here as well, so nothing a sender gave us is lost: with quarantine
+// they are held like the others, otherwise their senders are refused at
+// once and accepting one later records it all the same. A held spike may
+// be fixed in review (striker, note, position) and resubmitted, going
+// through the rules again; what was changed joins its provenance.
+// As with the rules, a message goes whole or not at all: one spike out of
+// bounds holds or refuses the others with it. Held spikes are written to
+// QUARANTINE_FILE so a restart doesn't lose them, each with the ledger it
+// was meant for.
+use chrono::{DateTime, Utc};
+use iced::widget::{button, column, row, text, text_input};
+use iced::{Color, Element, Length};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::session::Reply;
+use crate::units::Coords;
+use crate::{confirm, ActivationEvent, Hop, IoError, Message};
+
+pub const QUARANTINE_FILE: &str = ".nkisi_quarantine.json";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Policy {
+    #[default]
+    Clamp,
+    Reject,
+    Quarantine,
+}
+
+impl Policy {
+    pub const ALL: [Policy; 3] = [Policy::Clamp, Policy::Reject, Policy::Quarantine];
+}
+
+impl std::fmt::Display for Policy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Policy::Clamp => "Move to the edge",
+            Policy::Reject => "Refuse",
+            Policy::Quarantine => "Hold for review",
+        })
+    }
+}
+
+pub fn inside((x, y): (f32, f32), (fw, fh): (f32, f32)) -> bool {
+    (0.0..=fw).contains(&x) && (0.0..=fh).contains(&y)
+}
+
+// The nearest point of the figure; a coordinate that isn't a number goes
+// to the middle
+pub fn clamp((x, y): (f32, f32), (fw, fh): (f32, f32)) -> (f32, f32) {
+    let edge = |v: f32, max: f32| if v.is_finite() { v.clamp(0.0, max) } else { max / 2.0 };
+    (edge(x, fw), edge(y, fh))
+}
+
+// Why a position won't do, in figure units as the sender gave it
+pub fn why((x, y): (f32, f32), (fw, fh): (f32, f32)) -> String {
+    format!("position ({x:.1}, {y:.1}) outside the figure (0–{fw}, 0–{fh})")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Held {
+    // As it would be recorded, at the position it came with
+    pub event: ActivationEvent,
+    // The ledger it was meant for
+    pub ledger: Uuid,
+    pub why: String,
+    pub at: DateTime<Utc>,
+    // The sender, while its connection lasts
+    #[serde(skip)]
+    pub reply: Option<Reply>,
+}
+
+#[derive(Debug, Default)]
+pub struct Quarantine {
+    pub held: Vec<Held>,
+    // Changed since the last write
+    dirty: bool,
+}
+
+impl Quarantine {
+    // What the last run left, or nothing held
+    pub fn load() -> Self {
+        let held = match std::fs::read(QUARANTINE_FILE) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                eprintln!("[quarantine] {QUARANTINE_FILE}: {e}; starting empty");
+                vec![]
+            }),
+            Err(_) => vec![],
+        };
+        Self { held, dirty: false }
+    }
+
+    // Held once, however often it is sent again
+    pub fn hold(&mut self, held: Held) {
+        match self.held.iter_mut().find(|h| h.event.id == held.event.id) {
+            Some(h) => h.reply = held.reply.or(h.reply.take()),
+            None => self.held.push(held),
+        }
+        self.dirty = true;
+    }
+
+    // Corrections made in review; what changed joins the spike's provenance
+    pub fn correct(&mut self, id: Uuid, who: &str, note: &str, pos: (f32, f32), coords: Coords) -> bool {
+        let Some(held) = self.held.iter_mut().find(|h| h.event.id == id) else {
+            return false;
+        };
+        let ev = &mut held.event;
+        let mut changed = vec![];
+        if ev.performed_by != who {
+            changed.push(format!("striker {} → {who}", ev.performed_by));
+            ev.performed_by = who.to_string();
+        }
+        let note = Some(note.trim().to_string()).filter(|n| !n.is_empty());
+        if ev.notes != note {
+            changed.push("note".to_string());
+            ev.notes = note;
+        }
+        if coords.format(ev.pos) != coords.format(pos) {
+            changed.push(format!("position {} → {}", coords.format(ev.pos), coords.format(pos)));
+            ev.pos = pos;
+        }
+        if !changed.is_empty() {
+            ev.provenance.push(Hop {
+                via: "quarantine".into(),
+                source: format!("fixed in review: {}", changed.join(", ")),
+                at: Utc::now(),
+                listener: None,
+            });
+            self.dirty = true;
+        }
+        true
+    }
+
+    pub fn take(&mut self, id: Uuid) -> Option<Held> {
+        let i = self.held.iter().position(|h| h.event.id == id)?;
+        self.dirty = true;
+        Some(self.held.remove(i))
+    }
+
+    pub fn save(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        let written = serde_json::to_vec(&self.held)
+            .map_err(|e| IoError::Write(e.to_string()))
+            .and_then(|json| std::fs::write(QUARANTINE_FILE, json).map_err(|e| IoError::Write(e.to_string())));
+        match written {
+            Ok(()) => self.dirty = false,
+            Err(e) => eprintln!("[quarantine] {QUARANTINE_FILE}: {e}"),
+        }
+    }
+}
+
+// A held spike being fixed, as typed in the panel
+#[derive(Debug, Clone)]
+pub struct Fix {
+    pub id: Uuid,
+    pub who: String,
+    pub note: String,
+    pub x: String,
+    pub y: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum FixEdit {
+    Striker(String),
+    Note(String),
+    X(String),
+    Y(String),
+}
+
+impl Fix {
+    pub fn new(held: &Held, coords: Coords) -> Self {
+        let ev = &held.event;
+        let (x, y) = coords.inputs(ev.pos);
+        Self { id: ev.id, who: ev.performed_by.clone(), note: ev.notes.clone().unwrap_or_default(), x, y }
+    }
+
+    pub fn edit(&mut self, how: FixEdit) {
+        match how {
+            FixEdit::Striker(v) => self.who = v,
+            FixEdit::Note(v) => self.note = v,
+            FixEdit::X(v) => self.x = v,
+            FixEdit::Y(v) => self.y = v,
+        }
+    }
+}
+
+// Spikes held for the open ledger, with Fix, Accept and Discard; those
+// meant for another are counted
+pub fn view<'a>(
+    quarantine: &'a Quarantine,
+    ledger: Uuid,
+    coords: Coords,
+    fix: Option<&'a Fix>,
+) -> Element<'a, Message> {
+    let dim = Color::from_rgb(0.7, 0.7, 0.8);
+    let (here, elsewhere): (Vec<&Held>, Vec<&Held>) = quarantine.held.iter().partition(|h| h.ledger == ledger);
+    if here.is_empty() && elsewhere.is_empty() {
+        return column![].into();
+    }
+    let mut head = row![text(format!("Quarantine ({})", here.len())).size(16).width(Length::Fill)].spacing(8);
+    if !here.is_empty() {
+        head = head.push(button("Discard all").style(button::danger).on_press(Message::DiscardQuarantined(None)));
+    }
+    let mut col = column![head].spacing(4);
+    for h in here {
+        let ev = &h.event;
+        col = col.push(
+            row![
+                text(h.at.format("%Y-%m-%d %H:%M:%S").to_string()).size(13).width(140),
+                text(format!("{} at {}", ev.performed_by, coords.format(ev.pos))).size(13).width(260),
+                text(&h.why).size(13).color(dim).width(Length::Fill),
+                button("Fix").style(button::secondary).on_press(Message::FixQuarantined(Some(ev.id))),
+                button("Accept").on_press(Message::AcceptQuarantined(ev.id)),
+                button("Discard").style(button::secondary).on_press(Message::DiscardQuarantined(Some(ev.id))),
+            ]
+            .spacing(8),
+        );
+        if let Some(fix) = fix.filter(|f| f.id == ev.id) {
+            let edit = |how: fn(String) -> FixEdit| move |v| Message::QuarantineFixEdited(how(v));
+            col = col.push(
+                row![
+                    text_input("striker", &fix.who).on_input(edit(FixEdit::Striker)).width(160),
+                    text_input("note", &fix.note).on_input(edit(FixEdit::Note)).width(Length::Fill),
+                    text_input("x", &fix.x).on_input(edit(FixEdit::X)).width(70),
+                    text_input("y", &fix.y).on_input(edit(FixEdit::Y)).width(70),
+                    button("Resubmit").on_press(Message::ResubmitQuarantined),
+                    button("Cancel").style(button::secondary).on_press(Message::FixQuarantined(None)),
+                ]
+                .spacing(8),
+            );
+        }
+    }
+    if !elsewhere.is_empty() {
+        let n = confirm::count(elsewhere.len(), "spike");
+        col = col.push(text(format!("{n} held for other ledgers; open them to review.")).size(12).color(dim));
+    }
+    col.into()
+}
@@ -0,0 +1,218 @@
+// -------------------- Reachability --------------------
+// Where the listeners can actually be reached, for whoever sets up the
+// other end. A listener bound to 0.0.0.0 (or ::) answers on every
+// interface, so each interface address is listed with settings to copy
+// into a FIX engine (QuickFIX style), a metrics scraper or a script reading
+// the event feed; one bound to loopback is flagged, since nothing outside
+// this machine can use it.
+// "Test spike" connects to an address the way a counterparty would, logs
+// on and sends one spike; the outcome comes back on the status line.
+use crossbeam_channel::{bounded, Receiver};
+use iced::widget::{button, column, row, text};
+use iced::{alignment, Color, Element};
+use std::io::Write;
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::diagnose::next_message;
+use crate::fixdict::FixDictionary;
+use crate::fix::{self, tag, Decoder, Header};
+use crate::Message;
+
+const CONNECT: Duration = Duration::from_secs(3);
+// The spike waits for the ledger, which may be busy or held (read-only, sandbox)
+const ANSWER: Duration = Duration::from_secs(5);
+const TEST_SENDER: &str = "NKISI-TEST";
+
+// An address of this machine, and the interface it is on
+#[derive(Debug, Clone)]
+pub struct Interface {
+    pub name: String,
+    pub ip: IpAddr,
+}
+
+pub fn interfaces() -> Vec<Interface> {
+    match if_addrs::get_if_addrs() {
+        Ok(found) => found.into_iter().map(|i| Interface { ip: i.ip(), name: i.name }).collect(),
+        Err(e) => {
+            eprintln!("[network] interfaces: {e}");
+            vec![]
+        }
+    }
+}
+
+// Where a listener bound to `bound` can be reached, with the interface
+fn endpoints(bound: SocketAddr, interfaces: &[Interface]) -> Vec<(String, SocketAddr)> {
+    let ip = bound.ip();
+    if !ip.is_unspecified() {
+        let name = interfaces.iter().find(|i| i.ip == ip).map_or("", |i| i.name.as_str());
+        return vec![(name.to_string(), bound)];
+    }
+    // A v6 wildcard takes IPv4 connections too on the usual dual-stack setup
+    interfaces
+        .iter()
+        .filter(|i| ip.is_ipv6() || i.ip.is_ipv4())
+        .map(|i| (i.name.clone(), SocketAddr::new(i.ip, bound.port())))
+        .collect()
+}
+
+// Session settings for the counterparty's engine
+fn quickfix(to: SocketAddr, tls: bool) -> String {
+    let mut settings = format!("SocketConnectHost={}\nSocketConnectPort={}\nTargetCompID=NKISI", to.ip(), to.port());
+    if tls {
+        settings.push_str("\nSocketUseSSL=Y");
+    }
+    settings
+}
+
+// What a firewall has to let through, and what the binding rules out
+fn guidance(bound: SocketAddr) -> Vec<String> {
+    let port = bound.port();
+    if bound.ip().is_loopback() {
+        return vec![format!(
+            "Bound to {}: only this machine can connect. Listen on 0.0.0.0:{port} for remote counterparties.",
+            bound.ip()
+        )];
+    }
+    vec![
+        format!("Remote counterparties need TCP {port} open inbound on this machine and any firewall on the way."),
+        format!("Linux: sudo ufw allow {port}/tcp"),
+        format!("Windows: New-NetFirewallRule -Direction Inbound -Protocol TCP -LocalPort {port} -Action Allow"),
+    ]
+}
+
+// Send one spike to `to` as a counterparty would; the report arrives on
+// the returned channel. TLS listeners are only checked for a connection.
+pub fn test_spike(to: SocketAddr, tls: bool, dict: Arc<FixDictionary>, pos: (f32, f32)) -> Receiver<String> {
+    let (tx, rx) = bounded(1);
+    thread::spawn(move || {
+        let _ = tx.send(match try_spike(to, tls, &dict, pos) {
+            Ok(report) | Err(report) => report,
+        });
+    });
+    rx
+}
+
+fn try_spike(to: SocketAddr, tls: bool, dict: &FixDictionary, (x, y): (f32, f32)) -> Result<String, String> {
+    let mut stream = TcpStream::connect_timeout(&to, CONNECT).map_err(|e| format!("Can't reach {to}: {e}."))?;
+    if tls {
+        return Ok(format!("{to} accepts connections; counterparties there need TLS (SocketUseSSL=Y)."));
+    }
+    stream.set_read_timeout(Some(ANSWER)).map_err(|e| e.to_string())?;
+    let header = Header { begin: "FIX.4.4", sender: TEST_SENDER, target: "NKISI" };
+    let mut decoder = Decoder::new(true);
+    let logon = [(98, "0".to_string()), (108, "30".to_string()), (141, "Y".to_string())];
+    stream.write_all(&fix::encode(&header, "A", 1, &logon)).map_err(|e| format!("{to}: {e}"))?;
+    let closed = |e| format!("Reached {to}, but the connection was closed ({e}); is this address on the access list?");
+    let answer = next_message(&mut stream, &mut decoder).map_err(closed)?;
+    if tag(&answer, 35) != Some("A") {
+        let why = tag(&answer, 58).unwrap_or("no reason given");
+        return Err(format!("Reached {to}, but the test Logon as {TEST_SENDER} was refused: {why}."));
+    }
+    let spike = [
+        (55, dict.symbol.clone()),
+        (dict.who, "test spike".into()),
+        (dict.x, format!("{x:.1}")),
+        (dict.y, format!("{y:.1}")),
+        (dict.note, format!("Reachability test through {to}")),
+        (11, "reach-test".into()),
+    ];
+    stream.write_all(&fix::encode(&header, &dict.spike_type, 2, &spike)).map_err(|e| format!("{to}: {e}"))?;
+    let outcome = loop {
+        let silent = |e| format!("{to} took the spike but didn't answer ({e}).");
+        let answer = next_message(&mut stream, &mut decoder).map_err(silent)?;
+        // The acknowledgment, or a reject of the spike
+        if tag(&answer, 35).is_some_and(|t| t == dict.ack_type || t == "3" || t == "j") {
+            break answer;
+        }
+    };
+    let _ = stream.write_all(&fix::encode(&header, "5", 3, &[]));
+    let why = tag(&outcome, 58).unwrap_or("");
+    match (tag(&outcome, 35), tag(&outcome, 39)) {
+        (Some(t), Some("0")) if t == dict.ack_type => {
+            Ok(format!("Test spike through {to} recorded as \"test spike\"; delete it when done."))
+        }
+        (Some(t), Some("A")) if t == dict.ack_type => {
+            Ok(format!("Test spike through {to} reached the ledger and is held for review: {why}."))
+        }
+        _ => Err(format!("Reached {to}, but the test spike was refused: {why}.")),
+    }
+}
+
+// The Network panel
+pub fn view<'a>(
+    fix: Vec<(&'a str, SocketAddr, bool)>,
+    metrics: Option<SocketAddr>,
+    // Drop-copy publisher's address and consumers
+    publisher: Option<(SocketAddr, usize)>,
+    interfaces: &'a [Interface],
+    testing: bool,
+) -> Element<'a, Message> {
+    let mut col = column![row![
+        text("Network").size(16),
+        button("Refresh").style(button::secondary).on_press(Message::RefreshInterfaces),
+    ]
+    .spacing(10)
+    .align_y(alignment::Vertical::Center)]
+    .spacing(6);
+    let dim = Color::from_rgb(0.7, 0.7, 0.8);
+
+    if fix.is_empty() {
+        col = col.push(text("FIX acceptor: not listening"));
+    }
+    for (listener, bound, tls) in fix {
+        let secured = if tls { ", TLS" } else { "" };
+        col = col.push(text(format!("FIX acceptor \"{listener}\" bound to {bound}{secured}")));
+        for (name, to) in endpoints(bound, interfaces) {
+            let mut test = button("Test spike").style(button::secondary);
+            if !testing {
+                test = test.on_press(Message::SendTestSpike(to));
+            }
+            col = col.push(
+                row![
+                    text(to.to_string()).width(180),
+                    text(name).width(80).color(dim),
+                    button("Copy settings").on_press(Message::CopyText(quickfix(to, tls))),
+                    test,
+                ]
+                .spacing(8)
+                .align_y(alignment::Vertical::Center),
+            );
+        }
+        for line in guidance(bound) {
+            col = col.push(text(line).size(12).color(dim));
+        }
+    }
+    if let Some(bound) = metrics {
+        col = col.push(text(format!("Metrics bound to {bound}")));
+        for (name, to) in endpoints(bound, interfaces) {
+            let url = format!("http://{to}/metrics");
+            let copy = button("Copy").on_press(Message::CopyText(url.clone()));
+            let feed = button("Copy event feed").style(button::secondary);
+            let feed = feed.on_press(Message::CopyText(format!("curl -N http://{to}/events/stream")));
+            col = col.push(
+                row![text(url).width(260), text(name).width(80).color(dim), copy, feed]
+                    .spacing(8)
+                    .align_y(alignment::Vertical::Center),
+            );
+        }
+    }
+    if let Some((bound, consumers)) = publisher {
+        let consumers = crate::confirm::count(consumers, "consumer");
+        col = col.push(text(format!("Drop-copy publisher bound to {bound}, {consumers} connected")));
+        for (name, to) in endpoints(bound, interfaces) {
+            col = col.push(
+                row![
+                    text(to.to_string()).width(180),
+                    text(name).width(80).color(dim),
+                    button("Copy").on_press(Message::CopyText(to.to_string())),
+                ]
+                .spacing(8)
+                .align_y(alignment::Vertical::Center),
+            );
+        }
+    }
+    col.into()
+}
@@ -0,0 +1,197 @@
+// -------------------- Reclassify --------------------
+// A purpose or a tag given to every event inside a named region, or inside
+// the pins picked with the lasso or box, for regions drawn long after the
+// spikes in them were recorded. Preview first says which events would
+// change (those that already have it are left alone); Apply revises them,
+// each with a note naming the region, so the history keeps what they were;
+// Undo takes the whole round back as long as nothing was changed since.
+use iced::widget::{button, column, pick_list, row, text, text_input};
+use iced::{alignment, Color, Element, Length};
+use uuid::Uuid;
+
+use crate::regions::Region;
+use crate::{confirm, ActivationEvent, ActivationPurpose, Message};
+
+// Events listed under a preview
+const SHOWN: usize = 10;
+
+// Where the events are
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scope {
+    Region(String),
+    Selection,
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scope::Region(name) => write!(f, "Region {name}"),
+            Scope::Selection => f.write_str("Selected pins"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Kind {
+    #[default]
+    Purpose,
+    Tag,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 2] = [Kind::Purpose, Kind::Tag];
+}
+
+impl std::fmt::Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Kind::Purpose => "Set purpose",
+            Kind::Tag => "Add tag",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub kind: Kind,
+    pub value: String,
+}
+
+impl Change {
+    // False when the event has it already
+    pub fn apply(&self, ev: &mut ActivationEvent) -> bool {
+        match self.kind {
+            Kind::Purpose => {
+                let ActivationPurpose::Other(current) = &ev.purpose;
+                if *current == self.value {
+                    return false;
+                }
+                ev.purpose = ActivationPurpose::Other(self.value.clone());
+            }
+            Kind::Tag => {
+                if ev.meta.tags.contains(&self.value) {
+                    return false;
+                }
+                ev.meta.tags.push(self.value.clone());
+            }
+        }
+        true
+    }
+
+    pub fn describe(&self) -> String {
+        match self.kind {
+            Kind::Purpose => format!("purpose set to \"{}\"", self.value),
+            Kind::Tag => format!("tagged \"{}\"", self.value),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Preview {
+    pub scope: Scope,
+    pub change: Change,
+    // Events inside, and those of them the change would revise
+    pub within: usize,
+    pub revise: Vec<Uuid>,
+}
+
+// Scopes to pick from: the figure's regions, and the selection if any
+pub fn scopes(regions: &[Region], selected: usize) -> Vec<Scope> {
+    let mut scopes: Vec<Scope> = regions.iter().map(|r| Scope::Region(r.name.clone())).collect();
+    if selected > 0 {
+        scopes.push(Scope::Selection);
+    }
+    scopes
+}
+
+// What `change` would do within `scope`; None when the region is gone
+pub fn preview(
+    events: &[ActivationEvent],
+    regions: &[Region],
+    selected: &[Uuid],
+    scope: &Scope,
+    change: &Change,
+) -> Option<Preview> {
+    let inside = |ev: &&ActivationEvent| match scope {
+        Scope::Region(name) => regions.iter().any(|r| &r.name == name && r.contains(ev.pos)),
+        Scope::Selection => selected.contains(&ev.id),
+    };
+    if let Scope::Region(name) = scope {
+        regions.iter().find(|r| &r.name == name)?;
+    }
+    let within: Vec<&ActivationEvent> = events.iter().filter(inside).collect();
+    let revise = within.iter().filter(|ev| change.apply(&mut (**ev).clone())).map(|ev| ev.id).collect();
+    Some(Preview { scope: scope.clone(), change: change.clone(), within: within.len(), revise })
+}
+
+// Journal note of each revision
+pub fn note(scope: &Scope, change: &Change) -> String {
+    let place = match scope {
+        Scope::Region(name) => format!("region {name}"),
+        Scope::Selection => "the selected pins".into(),
+    };
+    format!("{} for {place}", change.describe())
+}
+
+pub struct Panel<'a> {
+    pub scopes: Vec<Scope>,
+    pub scope: Option<&'a Scope>,
+    pub kind: Kind,
+    pub input: &'a str,
+    pub preview: Option<&'a Preview>,
+    pub events: &'a [ActivationEvent],
+    // What the last Apply did, while it can be undone
+    pub applied: Option<&'a str>,
+}
+
+pub fn view(panel: Panel<'_>) -> Element<'_, Message> {
+    let dim = Color::from_rgb(0.7, 0.7, 0.8);
+    let mut col = column![row![
+        text("Reclassify:"),
+        pick_list(panel.scopes, panel.scope.cloned(), Message::ReclassifyScope).placeholder("region or selection"),
+        pick_list(Kind::ALL, Some(panel.kind), Message::ReclassifyKind),
+        text_input(if panel.kind == Kind::Purpose { "purpose" } else { "tag" }, panel.input)
+            .on_input(Message::ReclassifyInput)
+            .on_submit(Message::PreviewReclassify)
+            .padding(6),
+        button("Preview").on_press(Message::PreviewReclassify),
+    ]
+    .spacing(8)
+    .align_y(alignment::Vertical::Center)]
+    .spacing(4);
+    if let Some(p) = panel.preview {
+        let head = format!(
+            "{}: {} of {} would be {}",
+            p.scope,
+            confirm::count(p.revise.len(), "event"),
+            p.within,
+            p.change.describe()
+        );
+        let mut line = row![text(head).width(Length::Fill)].spacing(8).align_y(alignment::Vertical::Center);
+        if !p.revise.is_empty() {
+            line = line.push(button("Apply").on_press(Message::ApplyReclassify));
+        }
+        col = col.push(line);
+        for ev in panel.events.iter().filter(|e| p.revise.contains(&e.id)).take(SHOWN) {
+            let ActivationPurpose::Other(purpose) = &ev.purpose;
+            let tags =
+                if ev.meta.tags.is_empty() { String::new() } else { format!(" • {}", ev.meta.tags.join(", ")) };
+            let what = format!("{} • {} • {purpose}{tags}", ev.date.format("%Y-%m-%d %H:%M"), ev.performed_by);
+            col = col.push(text(what).size(12).color(dim));
+        }
+        if p.revise.len() > SHOWN {
+            col = col.push(text(format!("… and {} more", p.revise.len() - SHOWN)).size(12).color(dim));
+        }
+    }
+    if let Some(applied) = panel.applied {
+        col = col.push(
+            row![
+                text(format!("Applied: {applied}")).size(13).color(dim).width(Length::Fill),
+                button("Undo reclassification").style(button::secondary).on_press(Message::UndoReclassify),
+            ]
+            .spacing(8)
+            .align_y(alignment::Vertical::Center),
+        );
+    }
+    col.into()
+}
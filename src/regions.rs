@@ -0,0 +1,109 @@
+// -------------------- Figure regions --------------------
+// Named polygons in SVG space (e.g. "head", "torso") drawn over the figure
+// in the region editor and stored per figure in the workspace. Spikes are
+// hit-tested against them.
+use serde::{Deserialize, Serialize};
+
+use crate::palette::Appearance;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Region {
+    pub name: String,
+    pub polygon: Vec<(f32, f32)>,
+}
+
+impl Region {
+    pub fn contains(&self, pos: (f32, f32)) -> bool {
+        polygon_contains(&self.polygon, pos)
+    }
+
+    fn centroid(&self) -> (f32, f32) {
+        let n = self.polygon.len().max(1) as f32;
+        let (sx, sy) = self
+            .polygon
+            .iter()
+            .fold((0.0, 0.0), |(ax, ay), &(x, y)| (ax + x, ay + y));
+        (sx / n, sy / n)
+    }
+}
+
+// Even-odd ray casting
+pub fn polygon_contains(pts: &[(f32, f32)], (x, y): (f32, f32)) -> bool {
+    if pts.len() < 3 {
+        return false;
+    }
+    let mut inside = false;
+    let mut j = pts.len() - 1;
+    for i in 0..pts.len() {
+        let (xi, yi) = pts[i];
+        let (xj, yj) = pts[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+// First region containing `pos`, if any
+pub fn hit(regions: &[Region], pos: (f32, f32)) -> Option<&Region> {
+    regions.iter().find(|r| r.contains(pos))
+}
+
+// SVG fragment for the regions and, while editing, the draft outline;
+// `k` scales strokes and labels to the figure's coordinate space
+pub fn render(regions: &[Region], draft: Option<&[(f32, f32)]>, k: f32, look: &Appearance) -> String {
+    let mut s = String::new();
+    if !regions.is_empty() {
+        let c = look.region();
+        s.push_str(&format!(
+            r#"<g fill="{c}{}" stroke="{c}{}" stroke-width="{:.2}">"#,
+            look.fill_alpha(),
+            if look.high_contrast { "" } else { "aa" },
+            0.4 * k
+        ));
+        for r in regions {
+            s.push_str(&format!(r#"<polygon points="{}"/>"#, points(&r.polygon)));
+        }
+        s.push_str("</g>");
+        s.push_str(&format!(
+            r##"<g fill="#cfe6ff" font-size="{:.2}" text-anchor="middle"{}>"##,
+            3.0 * k,
+            look.halo(k)
+        ));
+        for r in regions {
+            let (cx, cy) = r.centroid();
+            s.push_str(&format!(
+                r#"<text x="{cx:.2}" y="{cy:.2}">{}</text>"#,
+                escape(&r.name)
+            ));
+        }
+        s.push_str("</g>");
+    }
+    if let Some(pts) = draft {
+        s.push_str(&format!(
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="{:.2}" stroke-dasharray="{:.2}"/>"#,
+            points(pts),
+            look.highlight(),
+            0.5 * k,
+            k
+        ));
+        s.push_str(&format!(r#"<g fill="{}">"#, look.highlight()));
+        for &(x, y) in pts {
+            s.push_str(&format!(r#"<circle cx="{x:.2}" cy="{y:.2}" r="{:.2}"/>"#, 0.8 * k));
+        }
+        s.push_str("</g>");
+    }
+    s
+}
+
+fn points(pts: &[(f32, f32)]) -> String {
+    pts.iter()
+        .map(|(x, y)| format!("{x:.2},{y:.2}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
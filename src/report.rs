@@ -0,0 +1,338 @@
+// -------------------- Reports --------------------
+// CSV and printable HTML reports of a figure's ledger, written in the
+// chosen locale: headings and outcome names come from a translation file,
+// dates and decimals follow its formats, and the CSV delimiter avoids
+// clashing with a comma decimal separator. Translations ship built in
+// (assets/i18n) and a file at the same path on disk takes precedence, so
+// an institution can correct or add a language without a rebuild. Custom
+// layouts come from user templates (see template.rs) rendered against the
+// same localized data. Positions are given in the workspace's coordinate
+// unit when the caller sets one (see units.rs), figure units otherwise.
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::regions::{self, Region};
+use crate::template::{escape_html as escape, Template};
+use crate::units::{Coords, Unit};
+use crate::{fields, ActivationEvent, IoError, NkisiNkondi, Outcome};
+
+pub const BUILTIN: [&str; 3] = ["en", "fr", "pt"];
+// HTML template offered until another is picked
+pub const DEFAULT_TEMPLATE: &str = "assets/templates/report.html";
+const I18N_DIR: &str = "assets/i18n";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Locale {
+    #[serde(skip)]
+    pub tag: String,
+    pub date_format: String,
+    pub decimal_separator: String,
+    pub csv_delimiter: String,
+    #[serde(default)]
+    strings: BTreeMap<String, String>,
+    // Unit positions are given in, for the figure reported on
+    #[serde(skip)]
+    pub coords: Option<Coords>,
+}
+
+fn builtin(tag: &str) -> Option<&'static str> {
+    match tag {
+        "en" => Some(include_str!("../assets/i18n/en.toml")),
+        "fr" => Some(include_str!("../assets/i18n/fr.toml")),
+        "pt" => Some(include_str!("../assets/i18n/pt.toml")),
+        _ => None,
+    }
+}
+
+// Language part of a tag or POSIX locale: "fr_FR.UTF-8" -> "fr"
+pub fn language(tag: &str) -> String {
+    tag.split(['_', '-', '.']).next().unwrap_or("").to_lowercase()
+}
+
+// Locale from the environment (LC_ALL, LANG) when it's one we ship
+pub fn system_tag() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|v| std::env::var(v).ok())
+        .map(|v| language(&v))
+        .find(|l| BUILTIN.contains(&l.as_str()))
+        .unwrap_or_else(|| "en".into())
+}
+
+impl Locale {
+    pub fn load(tag: &str) -> Result<Self, IoError> {
+        let tag = language(tag);
+        let on_disk = std::fs::read_to_string(format!("{I18N_DIR}/{tag}.toml")).ok();
+        let text = match (on_disk.as_deref(), builtin(&tag)) {
+            (Some(t), _) | (None, Some(t)) => t,
+            (None, None) => return Err(IoError::Read(format!("no translation for \"{tag}\""))),
+        };
+        let mut locale: Locale = toml::from_str(text).map_err(|e| IoError::Parse(e.to_string()))?;
+        locale.tag = tag;
+        Ok(locale)
+    }
+
+    // Translated string, or the key itself when the file lacks it
+    pub fn t<'a>(&'a self, key: &'a str) -> &'a str {
+        self.strings.get(key).map_or(key, String::as_str)
+    }
+
+    pub fn date(&self, at: DateTime<Utc>) -> String {
+        at.format(&self.date_format).to_string()
+    }
+
+    pub fn number(&self, v: f32) -> String {
+        format!("{v:.1}").replace('.', &self.decimal_separator)
+    }
+
+    // The same formatting, positions given in `coords`
+    pub fn with_coords(self, coords: Coords) -> Self {
+        Locale { coords: Some(coords), ..self }
+    }
+
+    // Column heading, with the unit on the coordinates when it isn't the
+    // figure's own
+    pub fn heading(&self, key: &str) -> String {
+        let unit = match self.coords.map(|c| c.unit()) {
+            Some(Unit::Percent) => " (%)",
+            Some(Unit::Millimeters) => " (mm)",
+            _ => "",
+        };
+        match key {
+            "col.x" | "col.y" => format!("{}{unit}", self.t(key)),
+            _ => self.t(key).to_string(),
+        }
+    }
+
+    pub fn outcome(&self, outcome: &Outcome) -> &str {
+        self.t(match outcome {
+            Outcome::Pending => "outcome.pending",
+            Outcome::Resolved => "outcome.resolved",
+            Outcome::Failed => "outcome.failed",
+        })
+    }
+}
+
+// One row's worth of report fields, already localized
+#[derive(Serialize)]
+struct Row {
+    #[serde(skip)]
+    id: String,
+    date: String,
+    striker: String,
+    outcome: String,
+    region: String,
+    x: String,
+    y: String,
+    notes: String,
+    witnesses: String,
+    #[serde(skip)]
+    custom: Map<String, Value>,
+}
+
+const COLUMNS: [&str; 8] =
+    ["col.date", "col.striker", "col.outcome", "col.region", "col.x", "col.y", "col.notes", "col.witnesses"];
+
+fn row(locale: &Locale, figure_regions: &[Region], ev: &ActivationEvent) -> Row {
+    let (x, y) = locale.coords.map_or(ev.pos, |c| c.convert(ev.pos));
+    Row {
+        id: ev.id.to_string(),
+        date: locale.date(ev.date),
+        striker: ev.performed_by.clone(),
+        outcome: locale.outcome(&ev.outcome).to_string(),
+        region: regions::hit(figure_regions, ev.pos)
+            .map_or_else(|| locale.t("region.none").to_string(), |r| r.name.clone()),
+        x: locale.number(x),
+        y: locale.number(y),
+        notes: ev.notes.clone().unwrap_or_default(),
+        witnesses: ev.meta.witnesses.join(", "),
+        custom: ev.custom.iter().map(|(k, v)| (k.clone(), json!(v))).collect(),
+    }
+}
+
+impl Row {
+    fn cells(&self) -> [&str; 8] {
+        [
+            &self.date,
+            &self.striker,
+            &self.outcome,
+            &self.region,
+            &self.x,
+            &self.y,
+            &self.notes,
+            &self.witnesses,
+        ]
+    }
+}
+
+fn rows(locale: &Locale, nkisi: &NkisiNkondi, figure_regions: &[Region]) -> Vec<Row> {
+    let mut events: Vec<&ActivationEvent> = nkisi.events.iter().collect();
+    events.sort_by_key(|e| e.date);
+    events.into_iter().map(|e| row(locale, figure_regions, e)).collect()
+}
+
+pub fn csv(locale: &Locale, nkisi: &NkisiNkondi, figure_regions: &[Region]) -> String {
+    let d = locale.csv_delimiter.as_str();
+    let quote = |s: &str| {
+        if s.contains(d) || s.contains('"') || s.contains('\n') {
+            format!("\"{}\"", s.replace('"', "\"\""))
+        } else {
+            s.to_string()
+        }
+    };
+    let mut out = COLUMNS.map(|c| quote(&locale.heading(c))).join(d);
+    out.push('\n');
+    for r in rows(locale, nkisi, figure_regions) {
+        out.push_str(&r.cells().map(quote).join(d));
+        out.push('\n');
+    }
+    out
+}
+
+// Heading and one line per event, for plain-text layouts such as the
+// batch PDF
+pub fn lines(locale: &Locale, nkisi: &NkisiNkondi, figure_regions: &[Region]) -> Vec<String> {
+    std::iter::once(COLUMNS.map(|c| locale.heading(c)).join(" · "))
+        .chain(rows(locale, nkisi, figure_regions).iter().map(|r| r.cells().join(" · ")))
+        .collect()
+}
+
+// Standalone page; print it from a browser for a PDF
+pub fn html(locale: &Locale, figure: &str, nkisi: &NkisiNkondi, figure_regions: &[Region]) -> String {
+    let rows = rows(locale, nkisi, figure_regions);
+    let mut by_outcome: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut by_region: BTreeMap<&str, usize> = BTreeMap::new();
+    for r in &rows {
+        *by_outcome.entry(r.outcome.as_str()).or_default() += 1;
+        *by_region.entry(r.region.as_str()).or_default() += 1;
+    }
+    let counts = |m: &BTreeMap<&str, usize>| {
+        m.iter()
+            .map(|(k, n)| format!("<li>{}: {n}</li>", escape(k)))
+            .collect::<String>()
+    };
+    let t = |k| escape(locale.t(k));
+    let head = COLUMNS.map(|c| format!("<th>{}</th>", escape(&locale.heading(c)))).concat();
+    let body: String = rows
+        .iter()
+        .map(|r| {
+            let cells = r.cells().map(|c| format!("<td>{}</td>", escape(c))).concat();
+            format!("<tr>{cells}</tr>\n")
+        })
+        .collect();
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"{lang}\">\n<head><meta charset=\"utf-8\"><title>{title} — {fig}</title>\n\
+         <style>body{{font-family:sans-serif}} table{{border-collapse:collapse}} td,th{{border:1px solid #999;padding:4px}}\
+         @media print{{.hint{{display:none}}}}</style></head>\n<body>\n\
+         <h1>{title}</h1>\n<p>{figure_l}: {fig}<br>{generated}: {now}</p>\n<p class=\"hint\">{print}</p>\n\
+         <h2>{summary}</h2>\n<p>{total}: {n}</p>\n<h3>{by_outcome_l}</h3>\n<ul>{by_outcome}</ul>\n\
+         <h3>{by_region_l}</h3>\n<ul>{by_region}</ul>\n\
+         <h2>{events}</h2>\n<table>\n<tr>{head}</tr>\n{body}</table>\n</body>\n</html>\n",
+        lang = escape(&locale.tag),
+        title = t("report.title"),
+        fig = escape(figure),
+        figure_l = t("report.figure"),
+        generated = t("report.generated"),
+        now = escape(&locale.date(Utc::now())),
+        print = t("report.print"),
+        summary = t("report.summary"),
+        total = t("report.total"),
+        n = rows.len(),
+        by_outcome_l = t("report.by_outcome"),
+        by_outcome = counts(&by_outcome),
+        by_region_l = t("report.by_region"),
+        by_region = counts(&by_region),
+        events = t("report.events"),
+    )
+}
+
+// Tallies as [{ "name", "count" }], largest first
+fn tally<'a>(names: impl Iterator<Item = &'a str>) -> Value {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for n in names {
+        *counts.entry(n).or_default() += 1;
+    }
+    let mut list: Vec<_> = counts.into_iter().collect();
+    list.sort_by_key(|(_, n)| std::cmp::Reverse(*n));
+    list.into_iter().map(|(name, count)| json!({ "name": name, "count": count })).collect()
+}
+
+// Translations as nested objects: "report.title" -> t.report.title
+fn nested(strings: &BTreeMap<String, String>) -> Value {
+    fn insert(node: &mut Map<String, Value>, parts: &[&str], text: &str) {
+        match parts {
+            [] => {}
+            [leaf] => {
+                node.insert((*leaf).into(), Value::String(text.into()));
+            }
+            [head, rest @ ..] => {
+                let child = node.entry(*head).or_insert_with(|| Value::Object(Map::new()));
+                if let Value::Object(child) = child {
+                    insert(child, rest, text);
+                }
+            }
+        }
+    }
+    let mut root = Map::new();
+    for (key, text) in strings {
+        insert(&mut root, &key.split('.').collect::<Vec<_>>(), text);
+    }
+    Value::Object(root)
+}
+
+// Everything a template can use
+pub fn context(
+    locale: &Locale,
+    figure: &str,
+    nkisi: &NkisiNkondi,
+    figure_regions: &[Region],
+    defs: &[fields::FieldDef],
+) -> Value {
+    let rows = rows(locale, nkisi, figure_regions);
+    let events: Vec<Value> = rows
+        .iter()
+        .map(|r| {
+            let mut ev = serde_json::to_value(r).unwrap_or_default();
+            ev["id"] = json!(r.id);
+            ev["custom"] = Value::Object(r.custom.clone());
+            ev
+        })
+        .collect();
+    json!({
+        "locale": locale.tag,
+        "t": nested(&locale.strings),
+        "generated": locale.date(Utc::now()),
+        "figure": { "name": figure, "id": nkisi.id.to_string() },
+        "coordinates": locale.coords.map_or(Unit::Figure, |c| c.unit()),
+        "fields": defs.iter().map(|d| json!({ "key": d.key, "label": d.label })).collect::<Vec<_>>(),
+        "stats": {
+            "total": rows.len(),
+            "trashed": nkisi.trash.len(),
+            "by_outcome": tally(rows.iter().map(|r| r.outcome.as_str())),
+            "by_region": tally(rows.iter().map(|r| r.region.as_str())),
+            "by_striker": tally(rows.iter().map(|r| r.striker.as_str())),
+        },
+        "events": events,
+    })
+}
+
+// Render a user template; HTML templates get their substitutions escaped.
+// Returns the output's extension, taken from the template's.
+pub fn from_template(template_path: &str, context: &Value) -> Result<(String, String), IoError> {
+    let src = std::fs::read_to_string(template_path).map_err(|e| IoError::Read(e.to_string()))?;
+    let ext = Path::new(template_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("txt")
+        .to_lowercase();
+    let html = matches!(ext.as_str(), "html" | "htm");
+    let template = Template::parse(&src, html).map_err(IoError::Parse)?;
+    Ok((template.render(context), ext))
+}
+
+pub fn write(path: &str, contents: &str) -> Result<(), IoError> {
+    std::fs::write(path, contents).map_err(|e| IoError::Write(e.to_string()))
+}
@@ -0,0 +1,93 @@
+// -------------------- Validation rules --------------------
+// Institution protocol checks run on a spike before it reaches the ledger,
+// both when a pending spike is confirmed and when one arrives from outside
+// (FIX or a spike pack). Rules live in the workspace file; the app lists
+// them and lets each be switched off. Events already in the ledger are
+// never re-checked.
+use serde::{Deserialize, Serialize};
+
+use crate::regions::{self, Region};
+use crate::{ActivationEvent, ActivationPurpose, Outcome};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum Rule {
+    // Spikes whose purpose or category is one of these need a witness
+    WitnessRequired { purposes: Vec<String> },
+    // A failed outcome has to be explained
+    NotesOnFailure,
+    // The named strikers may only strike inside these regions
+    RegionsForRole { role: String, strikers: Vec<String>, regions: Vec<String> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Configured {
+    #[serde(flatten)]
+    pub rule: Rule,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl Rule {
+    // One line for the rules list
+    pub fn describe(&self) -> String {
+        match self {
+            Rule::WitnessRequired { purposes } => {
+                format!("A witness is required for {}", purposes.join(", "))
+            }
+            Rule::NotesOnFailure => "Failed outcomes need notes".into(),
+            Rule::RegionsForRole { role, regions, .. } => {
+                format!("{role} may only strike in {}", regions.join(", "))
+            }
+        }
+    }
+
+    fn violation(&self, ev: &ActivationEvent, figure_regions: &[Region]) -> Option<String> {
+        match self {
+            Rule::WitnessRequired { purposes } => {
+                let ActivationPurpose::Other(purpose) = &ev.purpose;
+                let matches = |s: &str| purposes.iter().any(|p| p.eq_ignore_ascii_case(s.trim()));
+                let hit = std::iter::once(purpose.as_str())
+                    .chain(ev.meta.category.as_deref())
+                    .find(|s| matches(s))?;
+                ev.meta
+                    .witnesses
+                    .is_empty()
+                    .then(|| format!("a witness is required for {hit} spikes"))
+            }
+            Rule::NotesOnFailure => {
+                let blank = ev.notes.as_deref().is_none_or(|n| n.trim().is_empty());
+                (matches!(ev.outcome, Outcome::Failed) && blank)
+                    .then(|| "a failed outcome needs notes explaining it".into())
+            }
+            Rule::RegionsForRole { role, strikers, regions: allowed } => {
+                if !strikers.iter().any(|s| s.eq_ignore_ascii_case(ev.performed_by.trim())) {
+                    return None;
+                }
+                let region = regions::hit(figure_regions, ev.pos);
+                if region.is_some_and(|r| allowed.iter().any(|a| a.eq_ignore_ascii_case(&r.name))) {
+                    return None;
+                }
+                let here = region.map_or("no region".to_string(), |r| r.name.clone());
+                Some(format!(
+                    "{} ({role}) may only strike in {}, not {here}",
+                    ev.performed_by,
+                    allowed.join(", ")
+                ))
+            }
+        }
+    }
+}
+
+// Every enabled rule the event breaks, as messages for the status line
+pub fn check(rules: &[Configured], ev: &ActivationEvent, figure_regions: &[Region]) -> Vec<String> {
+    rules
+        .iter()
+        .filter(|c| c.enabled)
+        .filter_map(|c| c.rule.violation(ev, figure_regions))
+        .collect()
+}
@@ -0,0 +1,43 @@
+// -------------------- Sandbox --------------------
+// What-if mode. The ledger and its journal are set aside and changes go to
+// a copy with a journal kept in memory: spikes added or deleted, outcomes
+// changed, pins moved, undone. Nothing is saved, mirrored or forwarded
+// meanwhile, and FIX spikes wait in their queue. Discard puts the real
+// ledger back as it was; Apply puts it back and runs the sandbox's changes
+// on it as one batch, in the order they were made.
+use crate::journal::{Command, Entry, Journal, LedgerEvent};
+use crate::NkisiNkondi;
+
+pub struct Sandbox {
+    // The real ledger and journal, untouched until Apply
+    pub ledger: NkisiNkondi,
+    pub journal: Journal,
+}
+
+// The commands that made `entries`; undos are undone again in turn
+pub fn commands(entries: &[Entry]) -> Vec<Command> {
+    entries
+        .iter()
+        .filter_map(|e| {
+            if e.undoes.is_some() {
+                return Some(Command::Undo);
+            }
+            Some(match &e.event {
+                LedgerEvent::Struck { event } => Command::Strike(event.clone()),
+                LedgerEvent::Trashed { ids } => Command::Trash(ids.clone()),
+                LedgerEvent::Restored { ids } => Command::Restore(ids.clone()),
+                LedgerEvent::TrashEmptied { .. } => Command::EmptyTrash,
+                LedgerEvent::Revised { event, note, .. } => {
+                    Command::Revise { event: event.clone(), note: note.clone() }
+                }
+                LedgerEvent::Repaired { repair } => Command::Repair(repair.clone()),
+                // Not made in a sandbox: archiving and merging write files,
+                // and cancellations come over FIX, which waits
+                LedgerEvent::Retracted { .. }
+                | LedgerEvent::Archived { .. }
+                | LedgerEvent::Merged { .. }
+                | LedgerEvent::Cancelled { .. } => return None,
+            })
+        })
+        .collect()
+}
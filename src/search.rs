@@ -0,0 +1,107 @@
+// -------------------- Global search --------------------
+// Searches every ledger in the workspace at once. Files are only opened the
+// first time a search needs them and re-read when their mtime changes.
+use std::collections::HashMap;
+use std::time::SystemTime;
+use uuid::Uuid;
+
+use crate::{compat, ActivationEvent, IoError};
+
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub path: String,
+    pub event_id: Uuid,
+    pub summary: String,
+}
+
+struct Entry {
+    id: Uuid,
+    haystack: String, // lowercased striker + notes
+    summary: String,
+}
+
+#[derive(Default)]
+pub struct GlobalIndex {
+    files: HashMap<String, (Option<SystemTime>, Vec<Entry>)>,
+}
+
+impl GlobalIndex {
+    // `live` is the ledger currently open (it may have unsaved events) with
+    // its matches already picked by the full-text index, so its path is
+    // answered from memory rather than disk.
+    pub fn search(
+        &mut self,
+        ledgers: &[String],
+        live: (&str, Vec<&ActivationEvent>),
+        query: &str,
+    ) -> (Vec<Hit>, Vec<String>) {
+        let q = query.trim().to_lowercase();
+        let mut hits = vec![];
+        let mut errors = vec![];
+
+        let (live_path, live_matches) = live;
+        let mut paths: Vec<&str> = vec![live_path];
+        paths.extend(ledgers.iter().map(String::as_str).filter(|p| *p != live_path));
+
+        for path in paths {
+            let entries: Vec<(Uuid, String)> = if path == live_path {
+                live_matches.iter().map(|e| (e.id, summary(e))).collect()
+            } else {
+                if let Err(e) = self.refresh(path) {
+                    errors.push(format!("{path}: {e}"));
+                    continue;
+                }
+                self.files[path]
+                    .1
+                    .iter()
+                    .filter(|e| matches(&e.haystack, &q))
+                    .map(|e| (e.id, e.summary.clone()))
+                    .collect()
+            };
+            hits.extend(entries.into_iter().map(|(event_id, summary)| Hit {
+                path: path.to_string(),
+                event_id,
+                summary,
+            }));
+        }
+        (hits, errors)
+    }
+
+    fn refresh(&mut self, path: &str) -> Result<(), IoError> {
+        let mtime = std::fs::metadata(path)
+            .and_then(|m| m.modified())
+            .map_err(|e| IoError::Read(e.to_string()))?;
+        if let Some((Some(seen), _)) = self.files.get(path) {
+            if *seen == mtime {
+                return Ok(());
+            }
+        }
+        // Search never writes, so newer-format files are read leniently
+        let bytes = std::fs::read(path).map_err(|e| IoError::Read(e.to_string()))?;
+        let nkisi = compat::parse_lenient(&bytes)?;
+        let entries = nkisi
+            .events
+            .iter()
+            .map(|e| Entry { id: e.id, haystack: haystack(e), summary: summary(e) })
+            .collect();
+        self.files.insert(path.to_string(), (Some(mtime), entries));
+        Ok(())
+    }
+}
+
+fn haystack(e: &ActivationEvent) -> String {
+    e.search_text().to_lowercase()
+}
+
+fn matches(haystack: &str, q: &str) -> bool {
+    q.is_empty() || haystack.contains(q)
+}
+
+fn summary(e: &ActivationEvent) -> String {
+    format!(
+        "{} • {} • {}",
+        e.date.format("%Y-%m-%d %H:%M"),
+        e.performed_by,
+        e.notes.as_deref().unwrap_or("")
+    )
+}
@@ -0,0 +1,849 @@
+// -------------------- FIX session layer --------------------
+// One acceptor connection, in one of three kinds. A counterparty that
+// opens with Logon (35=A) gets a real session. Connections that never log
+// on (drop-copy feeds, simulators) stay raw feeds: nothing is sent back
+// and refusals are only logged. One that opens with '<' speaks FIXML
+// (fixml.rs): each message of each document is rewritten as tag=value and
+// read like a raw feed's. Framing, reading and writing of messages are
+// fix.rs's; what is here is the conversation.
+//
+// In a session the Logon is checked (fixauth.rs) and answered. Heartbeats
+// (0) and TestRequests (1) keep it alive in both directions, and
+// SequenceResets (4) are honoured and Logout (5) is acknowledged. A
+// ResendRequest (2) gets the acknowledgments and Rejects in its range sent
+// again with PossDupFlag (43=Y), read back from the store or, without one,
+// from those sent on the same connection; the rest of the range, session
+// messages and anything no longer kept, is gap filled. FIX 5.0
+// counterparties log on with BeginString FIXT.1.1 and name their
+// application version in DefaultApplVerID (1137); the Logon answer
+// confirms it, and a message whose ApplVerID (1128) we can't read is
+// rejected with reason 18.
+//
+// MsgSeqNum (34) is tracked per counterparty (SenderCompID) in both
+// directions and kept across connections and restarts, so a reconnect
+// carries on where the last connection stopped unless the Logon resets it
+// (141=Y). Gaps, including one opened by the Logon itself, are requested
+// for resend. Resent messages (PossDupFlag 43=Y) already seen are skipped,
+// and a number that goes backwards without PossDupFlag ends the session.
+//
+// Each spike is answered with a U2 acknowledgment (or the dictionary's
+// ack_type) once the ledger has recorded it (39=0), refused it (39=8) or
+// held it for review (39=A); a NewOrderSingle (D), when the dictionary
+// maps those, gets an ExecutionReport (8) instead. One that can't be read
+// at all gets a session Reject (3) naming the tag at fault, and a message
+// type we don't take a BusinessMessageReject (j).
+//
+// Every frame, logged on or not, is checked against BodyLength (9) and
+// CheckSum (10); malformed ones are dropped and counted. Hand-typed test
+// feeds can have the check relaxed to framing on "10=…" alone. With a
+// message store (fixstore.rs) every framed message, in or out, is kept.
+// Past the rate limit a connection stops reading until it is allowed the
+// next message, so a flooding sender is slowed down by TCP rather than
+// filling the ledger's queue. FIXML messages are stored and paced the
+// same way.
+//
+// The session runs the same over plain TCP or TLS (tls.rs). Each
+// connection keeps its row in the Sessions panel (sessions.rs) up to date
+// and ends when the panel disconnects it; when the acceptor is stopped,
+// logged-on sessions are sent a Logout.
+use chrono::Utc;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::fix::{encode, fields, tag, Decoder, Fields, Header, Kind, Msg, Raw, RejectReason, Refusal};
+use crate::fixauth::FixAuth;
+use crate::fixdict::FixDictionary;
+use crate::fixml;
+use crate::fixstore::{Direction, Store};
+use crate::inspector::{Inspector, Verdict};
+use crate::netacl::NetAcl;
+use crate::sessions::{Live, Sessions, Snapshot};
+
+const DEFAULT_HEARTBEAT: u64 = 30;
+// How often the connection wakes up to check timers and send acknowledgments
+const TICK: Duration = Duration::from_millis(100);
+// Gaps wider than this are not tracked message by message
+const MAX_GAP: u64 = 10_000;
+// Messages sent on a connection kept to send again, without a store
+const KEPT_SENT: usize = 10_000;
+// Days of the store looked through for messages to send again
+const RESEND_DAYS: usize = 2;
+// A FIXML document that grows past this without ending is thrown away
+const MAX_DOCUMENT: usize = 1 << 20;
+// Most of a malformed message the inspector keeps
+const SHOWN: usize = 4096;
+// Where sequence numbers survive a restart
+pub const SEQUENCE_FILE: &str = ".nkisi_fix_seq.json";
+// Transport of FIX 5.0 and later, which carries the application version
+const FIXT: &str = "FIXT.1.1";
+// ApplVerIDs our spikes can come in, FIX 4.0 to FIX 5.0 SP2
+const APPL_VERSIONS: [(&str, &str); 8] = [
+    ("2", "FIX40"),
+    ("3", "FIX41"),
+    ("4", "FIX42"),
+    ("5", "FIX43"),
+    ("6", "FIX44"),
+    ("7", "FIX50"),
+    ("8", "FIX50SP1"),
+    ("9", "FIX50SP2"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    // Nothing received yet
+    Opening,
+    Active,
+    // No Logon: plain feed, no replies
+    Raw,
+    Closed,
+}
+
+// What a session reads from and writes to; reads must time out so timers
+// and acknowledgments get their turn
+pub trait Transport: Read + Write + Send {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl Transport for TcpStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+struct Session {
+    stream: Box<dyn Transport>,
+    phase: Phase,
+    begin: String,
+    // Our CompIDs as the counterparty addressed us, and theirs
+    sender: String,
+    target: String,
+    out_seq: u64,
+    // Next MsgSeqNum expected from the counterparty
+    in_seq: u64,
+    // Numbers skipped over that we asked to have resent
+    missing: BTreeSet<u64>,
+    heartbeat: Duration,
+    last_in: Instant,
+    last_out: Instant,
+    // TestReqID (112) we are waiting to see echoed, and since when
+    test_pending: Option<(String, Instant)>,
+    peer: String,
+    // Acknowledgments from the ledger, waiting to be sent
+    acks: (Sender<Ack>, Receiver<Ack>),
+    // Message type, symbol and event id tag of acknowledgments
+    dict: Arc<FixDictionary>,
+    auth: Arc<FixAuth>,
+    sequences: Arc<Sequences>,
+    // Logged on as a known counterparty, whose numbers are kept
+    logged_on: bool,
+    store: Option<Arc<Store>>,
+    // Messages sent on this connection by MsgSeqNum, for a ResendRequest
+    // when there is no store to read them from
+    sent: BTreeMap<u64, Vec<u8>>,
+    // DefaultApplVerID agreed at Logon (FIXT sessions only)
+    appl_ver: Option<String>,
+    // Row in the Sessions panel, and what it shows
+    live: Arc<Live>,
+    shown: Snapshot,
+    // What became of the message being handled, for the inspector
+    inspector: Arc<Inspector>,
+    verdict: Verdict,
+}
+
+// What became of a spike
+#[derive(Debug)]
+pub struct Ack {
+    ref_seq: u64,
+    cl_ord_id: Option<String>,
+    order_side: Option<String>,
+    event: Uuid,
+    // OrdStatus (39) and ExecType (150)
+    status: &'static str,
+    text: String,
+}
+
+// OrdStatus values of an acknowledgment: new (recorded), rejected, and
+// pending new (held for an operator to decide)
+const NEW: &str = "0";
+const REJECTED: &str = "8";
+const PENDING_NEW: &str = "A";
+
+// Travels with a spike from a logged-on counterparty; the ledger answers
+// through it once the spike is recorded or refused
+#[derive(Debug, Clone)]
+pub struct Reply {
+    tx: Sender<Ack>,
+    // MsgSeqNum (34) and ClOrdID (11) of the spike
+    ref_seq: u64,
+    cl_ord_id: Option<String>,
+    // Side (54) when the spike came as a NewOrderSingle, which is answered
+    // with a standard ExecutionReport
+    order_side: Option<String>,
+}
+
+impl Reply {
+    pub fn accepted(self, event: Uuid, text: &str) {
+        self.send(event, NEW, text);
+    }
+
+    pub fn rejected(self, event: Uuid, text: &str) {
+        self.send(event, REJECTED, text);
+    }
+
+    // Not decided yet; the same reply answers again once it is
+    pub fn pending(&self, event: Uuid, text: &str) {
+        self.clone().send(event, PENDING_NEW, text);
+    }
+
+    fn send(self, event: Uuid, status: &'static str, text: &str) {
+        let (ref_seq, cl_ord_id, order_side) = (self.ref_seq, self.cl_ord_id, self.order_side);
+        let ack = Ack { ref_seq, cl_ord_id, order_side, event, status, text: text.into() };
+        // The connection may be gone by now; nothing to tell then
+        let _ = self.tx.send(ack);
+    }
+}
+
+// Shared by every connection of the acceptor
+#[derive(Debug, Clone, Default)]
+pub struct Validation {
+    // Skip the BodyLength and CheckSum checks (test feeds)
+    pub lenient: bool,
+    // Frames dropped as malformed since startup
+    pub dropped: Arc<AtomicU64>,
+    // Who may connect, log on and send spikes
+    pub auth: Arc<FixAuth>,
+    // Addresses any listener turns away
+    pub acl: Arc<NetAcl>,
+    pub sequences: Arc<Sequences>,
+    // Copy of every message read or written
+    pub store: Option<Arc<Store>>,
+    // Set when the acceptor stops: sessions log out and close
+    pub closing: Arc<AtomicBool>,
+    // Messages a second one connection may send (0: no limit)
+    pub rate_limit: u32,
+    // Messages held back by the rate limit, and spikes refused because the
+    // ledger's queue stayed full
+    pub throttled: Arc<AtomicU64>,
+    pub overflowed: Arc<AtomicU64>,
+    // Connections open at once, all listeners together (0: no limit), how
+    // many are, and how many were refused for it
+    pub max_connections: usize,
+    pub connections: Arc<AtomicUsize>,
+    pub crowded_out: Arc<AtomicU64>,
+    // Open connections, for the Sessions panel
+    pub sessions: Arc<Sessions>,
+    // Last messages either way, for the FIX inspector
+    pub inspector: Arc<Inspector>,
+}
+
+// Allowance of messages: `rate` a second, in bursts of up to a second's worth
+struct Bucket {
+    rate: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    fn new(rate: u32) -> Option<Self> {
+        (rate > 0).then(|| Self { rate: rate.into(), tokens: rate.into(), last: Instant::now() })
+    }
+
+    // Takes one message's allowance; how long to wait first if it is used up
+    fn take(&mut self) -> Option<Duration> {
+        let now = Instant::now();
+        self.tokens = (self.tokens + now.duration_since(self.last).as_secs_f64() * self.rate).min(self.rate);
+        self.last = now;
+        self.tokens -= 1.0;
+        (self.tokens < 0.0).then(|| Duration::from_secs_f64(-self.tokens / self.rate))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Numbers {
+    // MsgSeqNum expected next from the counterparty, and the last we sent
+    next_in: u64,
+    last_out: u64,
+}
+
+// Sequence numbers of every counterparty that has logged on, by its
+// SenderCompID; written out whenever one of its connections ends
+#[derive(Debug, Default)]
+pub struct Sequences {
+    path: Option<String>,
+    by_sender: Mutex<BTreeMap<String, Numbers>>,
+}
+
+impl Sequences {
+    // A missing or unreadable file starts everyone from 1
+    pub fn load(path: &str) -> Self {
+        let by_sender = match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+                eprintln!("[FIX] {path}: {e}; sequence numbers start over");
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Self { path: Some(path.into()), by_sender: Mutex::new(by_sender) }
+    }
+
+    fn get(&self, sender: &str) -> Option<Numbers> {
+        self.by_sender.lock().ok()?.get(sender).copied()
+    }
+
+    fn keep(&self, sender: &str, numbers: Numbers) {
+        let Ok(mut map) = self.by_sender.lock() else { return };
+        map.insert(sender.to_string(), numbers);
+        let Some(path) = &self.path else { return };
+        let written = serde_json::to_string_pretty(&*map)
+            .map_err(|e| e.to_string())
+            .and_then(|json| std::fs::write(path, json).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            eprintln!("[FIX] {path}: {e}");
+        }
+    }
+}
+
+// "9" -> "FIX50SP2", for ApplVerIDs we take spikes in
+fn appl_version(id: &str) -> Option<&'static str> {
+    APPL_VERSIONS.iter().find(|(v, _)| *v == id).map(|(_, name)| *name)
+}
+
+// Serve one connection until it closes; `deliver` gets each application
+// message, with a way to answer it when the counterparty is logged on, and
+// says why if it could not be read
+pub fn run(
+    stream: impl Transport + 'static,
+    peer: String,
+    validation: Validation,
+    dict: Arc<FixDictionary>,
+    mut deliver: impl FnMut(&[u8], Option<Reply>) -> Result<(), Refusal>,
+) {
+    if let Err(e) = stream.set_read_timeout(Some(TICK)) {
+        eprintln!("[FIX] {peer}: {e}");
+        return;
+    }
+    let mut s = Session {
+        stream: Box::new(stream),
+        phase: Phase::Opening,
+        begin: "FIX.4.4".into(),
+        sender: "NKISI".into(),
+        target: String::new(),
+        out_seq: 0,
+        in_seq: 1,
+        missing: BTreeSet::new(),
+        heartbeat: Duration::from_secs(DEFAULT_HEARTBEAT),
+        last_in: Instant::now(),
+        last_out: Instant::now(),
+        test_pending: None,
+        acks: unbounded(),
+        dict,
+        auth: Arc::clone(&validation.auth),
+        sequences: Arc::clone(&validation.sequences),
+        logged_on: false,
+        store: validation.store.clone(),
+        sent: BTreeMap::new(),
+        appl_ver: None,
+        live: validation.sessions.open(&peer),
+        shown: Snapshot::default(),
+        inspector: Arc::clone(&validation.inspector),
+        verdict: Verdict::Session,
+        peer,
+    };
+    let mut buf = vec![0u8; 8192];
+    // Bytes until it's known which of the two a connection speaks
+    let mut acc: Vec<u8> = vec![];
+    let mut decoder = Decoder::new(validation.lenient);
+    let mut bucket = Bucket::new(validation.rate_limit);
+    let mut slowed = false;
+    // Decided by the first byte that isn't whitespace
+    let mut xml: Option<bool> = None;
+    let mut pace = |peer: &str| {
+        if let Some(wait) = bucket.as_mut().and_then(Bucket::take) {
+            if !slowed {
+                eprintln!("[FIX] {peer}: over {} messages/s, slowing down", validation.rate_limit);
+                slowed = true;
+            }
+            validation.throttled.fetch_add(1, Ordering::Relaxed);
+            thread::sleep(wait);
+        }
+    };
+    while s.phase != Phase::Closed {
+        match s.stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                acc.extend_from_slice(&buf[..n]);
+                s.last_in = Instant::now();
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(e) => {
+                eprintln!("[FIX] {}: read error: {e}", s.peer);
+                break;
+            }
+        }
+        if xml.is_none() {
+            xml = acc.iter().find(|b| !b.is_ascii_whitespace()).map(|b| *b == b'<');
+        }
+        while s.phase != Phase::Closed && xml == Some(true) {
+            let Some(len) = fixml::frame(&acc) else {
+                if acc.len() > MAX_DOCUMENT {
+                    validation.dropped.fetch_add(1, Ordering::Relaxed);
+                    eprintln!("[FIX] {}: FIXML document over {MAX_DOCUMENT} bytes dropped", s.peer);
+                    let why = format!("FIXML document over {MAX_DOCUMENT} bytes");
+                    s.inspector.record(&s.peer, Verdict::Malformed(why), &acc[..SHOWN.min(acc.len())]);
+                    acc.clear();
+                }
+                break;
+            };
+            let doc: Vec<u8> = acc.drain(..len).collect();
+            let messages = match fixml::messages(&doc, &s.dict) {
+                Ok(messages) => messages,
+                Err(why) => {
+                    validation.dropped.fetch_add(1, Ordering::Relaxed);
+                    eprintln!("[FIX] {}: malformed FIXML document dropped: {why}", s.peer);
+                    s.inspector.record(&s.peer, Verdict::Malformed(why), &doc);
+                    continue;
+                }
+            };
+            for (msg_type, sender, body) in messages {
+                pace(&s.peer);
+                let sender = sender.unwrap_or_else(|| s.peer.clone());
+                let header = Header { begin: FIXT, sender: &sender, target: &s.sender };
+                let msg = Msg::new(encode(&header, &msg_type, 0, &body));
+                s.keep_copy(Direction::In, &msg.raw);
+                s.handle(&msg, &mut deliver);
+            }
+        }
+        if xml == Some(false) {
+            decoder.feed(&acc);
+            acc.clear();
+        }
+        while s.phase != Phase::Closed && xml == Some(false) {
+            match decoder.next_raw() {
+                None => break,
+                Some(Raw::Garbled { why, raw }) => {
+                    validation.dropped.fetch_add(1, Ordering::Relaxed);
+                    eprintln!("[FIX] {}: malformed message dropped: {why}", s.peer);
+                    s.inspector.record(&s.peer, Verdict::Malformed(why.into()), &raw[..raw.len().min(SHOWN)]);
+                }
+                Some(Raw::Message(raw)) => {
+                    pace(&s.peer);
+                    s.keep_copy(Direction::In, raw);
+                    s.handle(&Msg::new(raw.to_vec()), &mut deliver);
+                }
+            }
+        }
+        while let Ok(ack) = s.acks.1.try_recv() {
+            s.ack(ack);
+        }
+        if validation.closing.load(Ordering::Relaxed) {
+            if s.phase == Phase::Active {
+                s.send("5", &[(58, "acceptor shutting down".into())]);
+            }
+            break;
+        }
+        if s.live.disconnect_asked() {
+            eprintln!("[FIX] {}: disconnected from the Sessions panel", s.peer);
+            if s.phase == Phase::Active {
+                s.send("5", &[(58, "disconnected by the operator".into())]);
+            }
+            break;
+        }
+        s.tick();
+        s.shown.fixml = xml == Some(true);
+        s.publish();
+    }
+    validation.sessions.close(s.live.id);
+    if s.logged_on {
+        s.sequences.keep(&s.target, Numbers { next_in: s.in_seq, last_out: s.out_seq });
+    }
+    eprintln!("[FIX] {}: connection closed", s.peer);
+}
+
+impl Session {
+    fn handle(&mut self, msg: &Msg, deliver: &mut impl FnMut(&[u8], Option<Reply>) -> Result<(), Refusal>) {
+        let id = self.inspector.record(&self.peer, Verdict::Session, &msg.raw);
+        self.verdict = Verdict::Session;
+        self.dispatch(msg, deliver);
+        self.inspector.settle(id, std::mem::replace(&mut self.verdict, Verdict::Session));
+    }
+
+    fn dispatch(&mut self, msg: &Msg, deliver: &mut impl FnMut(&[u8], Option<Reply>) -> Result<(), Refusal>) {
+        self.shown.received += 1;
+        self.shown.last_in = Some(Utc::now());
+        let (f, msg_type) = (&msg.fields, msg.msg_type());
+        if self.phase == Phase::Opening {
+            if msg.kind() == Kind::Logon {
+                self.logon(f);
+                return;
+            }
+            if self.auth.requires_logon() {
+                self.auth.refuse(&self.peer, "no Logon; only listed senders may send spikes");
+                self.verdict = Verdict::Refused("no Logon".into());
+                self.phase = Phase::Closed;
+                return;
+            }
+            self.phase = Phase::Raw;
+        }
+        if self.phase == Phase::Raw {
+            self.verdict = match deliver(&msg.raw, None) {
+                Ok(()) => Verdict::Read,
+                Err(refusal) => {
+                    eprintln!("[FIX] {}: {msg_type} message dropped: {refusal}", self.peer);
+                    Verdict::Refused(refusal.to_string())
+                }
+            };
+            return;
+        }
+
+        let (seq, poss_dup) = (msg.seq(), msg.poss_dup());
+        // SequenceReset in reset mode applies whatever its own number
+        let gap_fill = msg.get(123) == Some("Y");
+        if msg.kind() == Kind::SequenceReset && !gap_fill {
+            self.reset_to(f);
+            return;
+        }
+        if seq < self.in_seq {
+            if poss_dup && self.missing.remove(&seq) {
+                // A resend we asked for
+            } else if poss_dup {
+                self.verdict = Verdict::Refused("PossDup of a message already read".into());
+                return;
+            } else {
+                let text = format!("MsgSeqNum too low, expecting {} but received {seq}", self.in_seq);
+                eprintln!("[FIX] {}: {text}", self.peer);
+                self.verdict = Verdict::Refused(text.clone());
+                self.send("5", &[(58, text)]);
+                self.phase = Phase::Closed;
+                return;
+            }
+        } else {
+            if seq > self.in_seq {
+                let (from, to) = (self.in_seq, seq - 1);
+                if to - from < MAX_GAP {
+                    self.missing.extend(from..=to);
+                }
+                self.send("2", &[(7, from.to_string()), (16, to.to_string())]);
+            }
+            self.in_seq = seq + 1;
+        }
+
+        // An ApplVerID (1128) that isn't one we take spikes in
+        let unreadable = msg.get(1128).filter(|v| self.appl_ver.is_some() && appl_version(v).is_none());
+        match msg.kind() {
+            Kind::Heartbeat => {
+                self.shown.last_heartbeat = Some(Utc::now());
+                if self.test_pending.as_ref().is_some_and(|(id, _)| msg.get(112) == Some(id)) {
+                    self.test_pending = None;
+                }
+            }
+            Kind::TestRequest => {
+                let id = msg.get(112).unwrap_or("").to_string();
+                self.send("0", &[(112, id)]);
+            }
+            Kind::ResendRequest => self.resend(f),
+            Kind::SequenceReset => {
+                // Gap fill: the skipped numbers carried nothing for us
+                let new: u64 = msg.get(36).and_then(|v| v.parse().ok()).unwrap_or(seq + 1);
+                self.missing.retain(|m| !(seq..new).contains(m));
+                self.in_seq = self.in_seq.max(new);
+            }
+            Kind::Logout => {
+                self.send("5", &[]);
+                self.phase = Phase::Closed;
+            }
+            Kind::Reject => eprintln!("[FIX] {}: session reject: {}", self.peer, msg.get(58).unwrap_or("")),
+            Kind::Logon => {}
+            Kind::Application(_) if unreadable.is_some() => {
+                let v = unreadable.unwrap_or("");
+                let text = format!("ApplVerID {v} not supported");
+                let refusal = Refusal::new(RejectReason::UnsupportedApplVer, Some(1128), text);
+                eprintln!("[FIX] {}: {msg_type} message rejected: {refusal}", self.peer);
+                self.verdict = Verdict::Refused(refusal.to_string());
+                self.reject(seq, msg_type, refusal);
+            }
+            Kind::Application(msg_type) => {
+                let order = msg_type == "D" && self.dict.new_order_single.is_some();
+                let reply = Reply {
+                    tx: self.acks.0.clone(),
+                    ref_seq: seq,
+                    cl_ord_id: msg.get(11).map(str::to_string),
+                    order_side: order.then(|| msg.get(54).unwrap_or("1").to_string()),
+                };
+                self.verdict = Verdict::Read;
+                if let Err(refusal) = deliver(&msg.raw, Some(reply)) {
+                    eprintln!("[FIX] {}: {msg_type} message rejected: {refusal}", self.peer);
+                    self.verdict = Verdict::Refused(refusal.to_string());
+                    self.reject(seq, msg_type, refusal);
+                }
+            }
+        }
+    }
+
+    fn reject(&mut self, seq: u64, msg_type: &str, refusal: Refusal) {
+        if refusal.reason == RejectReason::UnsupportedMsgType {
+            // BusinessRejectReason 3: unsupported message type
+            let fields = [(45, seq.to_string()), (372, msg_type.into()), (380, "3".into()), (58, refusal.text)];
+            self.send("j", &fields);
+            return;
+        }
+        let mut fields = vec![(45, seq.to_string())];
+        if let Some(tag) = refusal.tag {
+            fields.push((371, tag.to_string()));
+        }
+        fields.extend([
+            (372, msg_type.to_string()),
+            (373, (refusal.reason as u32).to_string()),
+            (58, refusal.text),
+        ]);
+        self.send("3", &fields);
+    }
+
+    // ExecutionReport-style: OrdStatus (39) and ExecType (150) are 0 (new),
+    // 8 (rejected) or A (pending new)
+    fn ack(&mut self, ack: Ack) {
+        if self.phase != Phase::Active {
+            return;
+        }
+        let status = ack.status;
+        if ack.order_side.is_some() {
+            self.execution_report(status, ack);
+            return;
+        }
+        let mut fields = vec![(45, ack.ref_seq.to_string())];
+        if let Some(id) = ack.cl_ord_id {
+            fields.push((11, id));
+        }
+        fields.extend([
+            (55, self.dict.symbol.clone()),
+            (self.dict.event_id, ack.event.to_string()),
+            (39, status.into()),
+            (150, status.into()),
+            (58, ack.text),
+        ]);
+        let ack_type = self.dict.ack_type.clone();
+        self.send(&ack_type, &fields);
+    }
+
+    // OrderID (37) is the ledger event id; nothing is ever filled
+    fn execution_report(&mut self, status: &str, ack: Ack) {
+        let mut fields = vec![(37, ack.event.to_string()), (17, Uuid::new_v4().to_string())];
+        if let Some(id) = ack.cl_ord_id {
+            fields.push((11, id));
+        }
+        fields.extend([
+            (150, status.into()),
+            (39, status.into()),
+            (55, self.dict.symbol.clone()),
+            (54, ack.order_side.unwrap_or_default()),
+            (151, "0".into()),
+            (14, "0".into()),
+            (6, "0".into()),
+            (58, ack.text),
+        ]);
+        self.send("8", &fields);
+    }
+
+    fn logon(&mut self, f: &Fields) {
+        self.begin = tag(f, 8).unwrap_or("FIX.4.4").to_string();
+        self.target = tag(f, 49).unwrap_or("").to_string();
+        if let Some(ours) = tag(f, 56) {
+            self.sender = ours.to_string();
+        }
+        if let Err(why) = self.auth.check_logon(f) {
+            self.auth.refuse(&self.peer, &why);
+            self.send("5", &[(58, "Logon refused".into())]);
+            self.phase = Phase::Closed;
+            return;
+        }
+        if self.begin == FIXT {
+            let asked = tag(f, 1137);
+            match asked.and_then(appl_version) {
+                Some(_) => self.appl_ver = asked.map(str::to_string),
+                None => {
+                    let text = match asked {
+                        Some(v) => format!("DefaultApplVerID {v} not supported"),
+                        None => "DefaultApplVerID (1137) required with FIXT.1.1".into(),
+                    };
+                    eprintln!("[FIX] {}: {text}", self.peer);
+                    self.send("5", &[(58, text)]);
+                    self.phase = Phase::Closed;
+                    return;
+                }
+            }
+        }
+        let hb = tag(f, 108).and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_HEARTBEAT);
+        self.heartbeat = Duration::from_secs(hb.max(1));
+        let seq: u64 = tag(f, 34).and_then(|v| v.parse().ok()).unwrap_or(1);
+        let reset = tag(f, 141) == Some("Y");
+        // Where the last connection of this counterparty stopped; a Logon
+        // numbered 1 is a counterparty that started over
+        let known = self.sequences.get(&self.target).filter(|_| !reset && seq > 1);
+        let expected = known.map_or(1, |n| n.next_in);
+        self.out_seq = known.map_or(0, |n| n.last_out);
+        if seq < expected {
+            let text = format!("MsgSeqNum too low, expecting {expected} but received {seq}");
+            eprintln!("[FIX] {}: {text}", self.peer);
+            self.send("5", &[(58, text)]);
+            self.phase = Phase::Closed;
+            return;
+        }
+        self.phase = Phase::Active;
+        self.logged_on = true;
+        self.in_seq = seq + 1;
+        let mut reply = vec![(98, "0".to_string()), (108, hb.to_string())];
+        if reset {
+            reply.push((141, "Y".into()));
+        }
+        let version = match &self.appl_ver {
+            Some(v) => {
+                reply.push((1137, v.clone()));
+                appl_version(v).unwrap_or(v)
+            }
+            None => &self.begin,
+        };
+        eprintln!("[FIX] {}: {} logged on (heartbeat {hb}s, {version})", self.peer, self.target);
+        self.send("A", &reply);
+        // Messages sent while the counterparty was away from us
+        if seq > expected {
+            let (from, to) = (expected, seq - 1);
+            eprintln!("[FIX] {}: missed {from}..={to} since the last connection; asking for a resend", self.peer);
+            if to - from < MAX_GAP {
+                self.missing.extend(from..=to);
+            }
+            self.send("2", &[(7, from.to_string()), (16, to.to_string())]);
+        }
+    }
+
+    fn reset_to(&mut self, f: &Fields) {
+        if let Some(new) = tag(f, 36).and_then(|v| v.parse::<u64>().ok()) {
+            self.missing.retain(|m| *m >= new);
+            self.in_seq = new;
+        }
+    }
+
+    // Acknowledgments and Rejects in the range go again as they were sent,
+    // with PossDupFlag (43) and their OrigSendingTime (122); the session
+    // messages between them, and any no longer kept, are gap filled
+    fn resend(&mut self, f: &Fields) {
+        let next = self.out_seq + 1;
+        let begin = tag(f, 7).and_then(|v| v.parse().ok()).unwrap_or(1).min(next);
+        // EndSeqNo (16) 0 is everything sent since
+        let end = tag(f, 16).and_then(|v| v.parse().ok()).filter(|e| *e != 0).unwrap_or(self.out_seq).min(self.out_seq);
+        let sent = match &self.store {
+            Some(store) => store.sent(&self.target, RESEND_DAYS),
+            None => self.sent.clone(),
+        };
+        let mut from = begin;
+        for (seq, raw) in sent.range(begin..).take_while(|(seq, _)| **seq <= end) {
+            let Some(again) = self.possdup(*seq, raw) else { continue };
+            if *seq > from {
+                self.gap_fill(from, *seq);
+            }
+            self.write(&again);
+            from = seq + 1;
+        }
+        if from <= end || from == begin {
+            self.gap_fill(from, end + 1);
+        }
+    }
+
+    // SequenceReset-GapFill (123=Y) from `seq`, to carry on at `new`
+    fn gap_fill(&mut self, seq: u64, new: u64) {
+        let fill = [(43, "Y".to_string()), (123, "Y".to_string()), (36, new.to_string())];
+        self.write(&self.encode("4", seq, &fill));
+    }
+
+    // `raw` as sent under `seq` again, when it is a message worth resending:
+    // anything but the session messages, Rejects (3) included
+    fn possdup(&self, seq: u64, raw: &[u8]) -> Option<Vec<u8>> {
+        let f = fields(raw);
+        let msg_type = tag(&f, 35)?;
+        if !matches!(Kind::of(msg_type), Kind::Reject | Kind::Application(_)) {
+            return None;
+        }
+        let mut again = vec![(43, "Y".to_string()), (122, tag(&f, 52).unwrap_or_default().to_string())];
+        let header = [8, 9, 10, 34, 35, 43, 49, 52, 56, 97, 122];
+        again.extend(f.iter().filter(|(t, _)| !header.contains(t)).map(|(t, v)| (*t as u32, v.clone())));
+        Some(self.encode(msg_type, seq, &again))
+    }
+
+    // Heartbeat when idle, TestRequest when the other side is quiet,
+    // disconnect when it stays quiet
+    fn tick(&mut self) {
+        if self.phase != Phase::Active {
+            return;
+        }
+        if let Some((_, since)) = &self.test_pending {
+            if since.elapsed() > self.heartbeat {
+                eprintln!("[FIX] {}: no answer to TestRequest; disconnecting", self.peer);
+                self.send("5", &[(58, "heartbeat timeout".into())]);
+                self.phase = Phase::Closed;
+                return;
+            }
+        } else if self.last_in.elapsed() > self.heartbeat + self.heartbeat / 5 {
+            let id = Utc::now().timestamp_millis().to_string();
+            self.send("1", &[(112, id.clone())]);
+            self.test_pending = Some((id, Instant::now()));
+            return;
+        }
+        if self.last_out.elapsed() >= self.heartbeat {
+            self.send("0", &[]);
+        }
+    }
+
+    fn send(&mut self, msg_type: &str, fields: &[(u32, String)]) {
+        self.out_seq += 1;
+        let msg = self.encode(msg_type, self.out_seq, fields);
+        self.write(&msg);
+        if self.store.is_none() {
+            self.sent.insert(self.out_seq, msg);
+            if self.sent.len() > KEPT_SENT {
+                self.sent.pop_first();
+            }
+        }
+    }
+
+    fn write(&mut self, msg: &[u8]) {
+        self.shown.sent += 1;
+        self.keep_copy(Direction::Out, msg);
+        self.inspector.record(&self.peer, Verdict::Sent, msg);
+        if let Err(e) = self.stream.write_all(msg) {
+            eprintln!("[FIX] {}: write failed: {e}", self.peer);
+            self.phase = Phase::Closed;
+        }
+        self.last_out = Instant::now();
+    }
+
+    fn publish(&mut self) {
+        self.shown.comp_id = (self.phase == Phase::Active).then(|| self.target.clone());
+        self.shown.in_seq = self.in_seq;
+        self.shown.out_seq = self.out_seq;
+        self.live.update(self.shown.clone());
+    }
+
+    // Into the store under the counterparty's SenderCompID, taken from its
+    // Logon; raw feeds are filed under their address
+    fn keep_copy(&self, direction: Direction, raw: &[u8]) {
+        let Some(store) = &self.store else { return };
+        let logon = (self.phase == Phase::Opening).then(|| fields(raw)).filter(|f| tag(f, 35) == Some("A"));
+        let who = match logon.as_ref().and_then(|f| tag(f, 49)) {
+            Some(comp_id) => comp_id,
+            None if !self.target.is_empty() => &self.target,
+            None => &self.peer,
+        };
+        store.record(who, direction, raw);
+    }
+
+    fn encode(&self, msg_type: &str, seq: u64, fields: &[(u32, String)]) -> Vec<u8> {
+        let header = Header { begin: &self.begin, sender: &self.sender, target: &self.target };
+        encode(&header, msg_type, seq, fields)
+    }
+}
@@ -0,0 +1,138 @@
+// -------------------- Open FIX sessions --------------------
+// Every connection the acceptors are serving, for the Sessions panel: who
+// it is, how many messages went each way, when the counterparty last sent
+// a Heartbeat and where the sequence numbers stand. Each connection
+// refreshes its row as it goes and takes it out when it closes. Disconnect
+// asks the connection to end: a logged-on session is sent a Logout first,
+// and its sequence numbers are kept as on any close.
+use chrono::{DateTime, Utc};
+use iced::widget::{button, column, row, text};
+use iced::{Color, Element};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::{confirm, Message};
+
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    // SenderCompID once logged on
+    pub comp_id: Option<String>,
+    pub fixml: bool,
+    pub received: u64,
+    pub sent: u64,
+    pub last_in: Option<DateTime<Utc>>,
+    pub last_heartbeat: Option<DateTime<Utc>>,
+    // Next MsgSeqNum expected from the counterparty, and the last we sent
+    pub in_seq: u64,
+    pub out_seq: u64,
+}
+
+#[derive(Debug)]
+pub struct Live {
+    pub id: u64,
+    pub peer: String,
+    pub since: DateTime<Utc>,
+    snapshot: Mutex<Snapshot>,
+    disconnect: AtomicBool,
+}
+
+impl Live {
+    pub fn update(&self, snapshot: Snapshot) {
+        if let Ok(mut s) = self.snapshot.lock() {
+            *s = snapshot;
+        }
+    }
+
+    pub fn disconnect_asked(&self) -> bool {
+        self.disconnect.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Sessions {
+    open: Mutex<Vec<Arc<Live>>>,
+    next_id: AtomicU64,
+}
+
+impl Sessions {
+    pub fn open(&self, peer: &str) -> Arc<Live> {
+        let live = Arc::new(Live {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            peer: peer.to_string(),
+            since: Utc::now(),
+            snapshot: Mutex::default(),
+            disconnect: AtomicBool::new(false),
+        });
+        if let Ok(mut open) = self.open.lock() {
+            open.push(Arc::clone(&live));
+        }
+        live
+    }
+
+    pub fn close(&self, id: u64) {
+        if let Ok(mut open) = self.open.lock() {
+            open.retain(|l| l.id != id);
+        }
+    }
+
+    // False when the session had already gone
+    pub fn disconnect(&self, id: u64) -> bool {
+        let open = self.open.lock().map(|open| open.iter().find(|l| l.id == id).cloned());
+        match open {
+            Ok(Some(live)) => {
+                live.disconnect.store(true, Ordering::Relaxed);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.open.lock().map_or(0, |open| open.len())
+    }
+}
+
+pub fn view(sessions: &Sessions) -> Element<'_, Message> {
+    let dim = Color::from_rgb(0.7, 0.7, 0.8);
+    let open: Vec<Arc<Live>> = sessions.open.lock().map(|open| open.clone()).unwrap_or_default();
+    let mut col = column![row![
+        text(format!("Sessions • {} open", confirm::count(open.len(), "connection"))).size(16),
+        button("Close").style(button::secondary).on_press(Message::ShowSessions(false)),
+    ]
+    .spacing(10),]
+    .spacing(4);
+    if open.is_empty() {
+        col = col.push(text("No FIX connections right now.").size(12).color(dim));
+    }
+    let time = |at: Option<DateTime<Utc>>| at.map_or("-".to_string(), |at| at.format("%H:%M:%S").to_string());
+    for live in open {
+        let s = live.snapshot.lock().map(|s| s.clone()).unwrap_or_default();
+        let who = match (&s.comp_id, s.fixml) {
+            (Some(comp_id), _) => comp_id.clone(),
+            (None, true) => "FIXML feed".into(),
+            (None, false) => "raw feed".into(),
+        };
+        let seq = match s.comp_id {
+            Some(_) => format!("seq in {} • out {}", s.in_seq, s.out_seq),
+            None => String::new(),
+        };
+        let button = if live.disconnect_asked() {
+            button("Disconnecting…").style(button::secondary)
+        } else {
+            button("Disconnect").style(button::danger).on_press(Message::DisconnectSession(live.id))
+        };
+        col = col.push(
+            row![
+                text(live.peer.clone()).size(13).width(170),
+                text(who).size(13).width(130),
+                text(format!("since {}", live.since.format("%H:%M:%S"))).size(13).width(110),
+                text(format!("{} in • {} out", s.received, s.sent)).size(13).width(140),
+                text(format!("last in {} • heartbeat {}", time(s.last_in), time(s.last_heartbeat))).size(13).width(250),
+                text(seq).size(13).color(dim).width(iced::Length::Fill),
+                button,
+            ]
+            .spacing(8),
+        );
+    }
+    col.into()
+}
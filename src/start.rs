@@ -0,0 +1,131 @@
+// -------------------- Start screen --------------------
+// What the app shows when it is launched without a workspace or a save
+// path: the ledgers opened lately on this machine, a new figure, opening a
+// ledger, workspace or spike pack by path, and importing a pack into a
+// ledger of its own. It used to open a blank figure straight away, saved
+// to nkisi_state.json whatever that file held. Nothing is opened, and FIX
+// spikes wait in the queue, until one is picked. The list is kept in
+// RECENT_FILE beside the other .nkisi markers in the working directory.
+use chrono::{DateTime, Local, Utc};
+use iced::widget::{button, column, container, row, text, text_input};
+use iced::{alignment, Color, Element, Length};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::{IoError, Message};
+
+pub const RECENT_FILE: &str = ".nkisi_recent.json";
+// Ledgers listed
+const KEPT: usize = 8;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Recent {
+    // Latest first
+    pub ledgers: Vec<Opened>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Opened {
+    pub path: String,
+    pub at: DateTime<Utc>,
+}
+
+impl Recent {
+    pub fn load() -> Self {
+        match std::fs::read(RECENT_FILE) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                eprintln!("[start] {RECENT_FILE}: {e}; starting the list over");
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    // `path` to the top of the list, which is written straight away
+    pub fn remember(&mut self, path: &str) {
+        if self.ledgers.first().is_some_and(|o| o.path == path) {
+            return;
+        }
+        self.ledgers.retain(|o| o.path != path);
+        self.ledgers.insert(0, Opened { path: path.to_string(), at: Utc::now() });
+        self.ledgers.truncate(KEPT);
+        if let Err(e) = self.save() {
+            eprintln!("[start] {RECENT_FILE}: {e}");
+        }
+    }
+
+    fn save(&self) -> Result<(), IoError> {
+        let bytes = serde_json::to_vec_pretty(self).map_err(|e| IoError::Write(e.to_string()))?;
+        std::fs::write(RECENT_FILE, bytes).map_err(|e| IoError::Write(e.to_string()))
+    }
+}
+
+// The name a figure opened from `path` goes by
+pub fn figure_name(path: &str) -> String {
+    Path::new(path).file_stem().map_or_else(|| path.to_string(), |s| s.to_string_lossy().into_owned())
+}
+
+pub struct Screen<'a> {
+    pub recent: &'a Recent,
+    // A ledger at the old default path, when the list doesn't have it
+    pub found: Option<&'a str>,
+    pub path: &'a str,
+    pub status: &'a str,
+}
+
+pub fn view(screen: Screen<'_>) -> Element<'_, Message> {
+    let dim = Color::from_rgb(0.7, 0.7, 0.8);
+    let mut recent = column![text("Recent ledgers").size(16)].spacing(6);
+    let listed = screen.recent.ledgers.iter().map(|o| (o.path.as_str(), Some(o.at)));
+    let mut any = false;
+    for (path, at) in listed.chain(screen.found.map(|p| (p, None))) {
+        any = true;
+        let exists = Path::new(path).exists();
+        let when = match (exists, at) {
+            (false, _) => "not found".to_string(),
+            (true, Some(at)) => at.with_timezone(&Local).format("opened %Y-%m-%d %H:%M").to_string(),
+            (true, None) => "in this folder".to_string(),
+        };
+        recent = recent.push(
+            row![
+                button(text(figure_name(path)))
+                    .style(button::secondary)
+                    .on_press_maybe(exists.then(|| Message::StartOpen(path.to_string())))
+                    .width(200),
+                text(path).size(13).width(Length::Fill),
+                text(when).size(12).color(dim),
+            ]
+            .spacing(10)
+            .align_y(alignment::Vertical::Center),
+        );
+    }
+    if !any {
+        recent = recent.push(text("None yet.").size(13).color(dim));
+    }
+
+    let typed = screen.path.to_string();
+    let content = column![
+        text("Rustic Nkisi").size(28),
+        text("Pick the ledger to work on. Nothing is opened or saved until you do.").color(dim),
+        recent,
+        text("Or by path").size(16),
+        text_input("a ledger (.json), workspace (.nkisiproj) or spike pack (.nkisipack)", screen.path)
+            .on_input(Message::StartPathChanged)
+            .on_submit(Message::StartOpen(typed.clone()))
+            .padding(6),
+        row![
+            button("Open").on_press(Message::StartOpen(typed)),
+            button("New figure").on_press(Message::StartNew),
+            button("Import pack").style(button::secondary).on_press(Message::StartImport),
+        ]
+        .spacing(10),
+        text("New figure starts an empty ledger at the path typed; Import pack puts a spike pack into a new \
+              ledger beside it.")
+        .size(12)
+        .color(dim),
+        text(screen.status).size(13),
+    ]
+    .spacing(12)
+    .max_width(720);
+    container(content).padding(32).center_x(Length::Fill).into()
+}
@@ -0,0 +1,162 @@
+// -------------------- Striker summary --------------------
+// Who struck the figure, and how much: per striker the events recorded,
+// the first and last, the regions their spikes landed in and how many of
+// them were resolved. Shown as a leaderboard, most events first, and
+// exported as CSV or as a Markdown list of contributors for a project's
+// acknowledgments. Trashed events don't count.
+use chrono::{DateTime, Utc};
+use iced::widget::{button, column, row, text};
+use iced::{Color, Element};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::regions::{self, Region};
+use crate::{confirm, ActivationEvent, Message, Outcome};
+
+// Rows on the leaderboard; exports list everyone
+const SHOWN: usize = 20;
+
+#[derive(Debug, Clone)]
+pub struct Striker {
+    pub name: String,
+    pub events: usize,
+    pub first: DateTime<Utc>,
+    pub last: DateTime<Utc>,
+    // Names of the regions hit, in order
+    pub regions: Vec<String>,
+    pub resolved: usize,
+    pub failed: usize,
+}
+
+impl Striker {
+    // Share of their events resolved, 0 to 1
+    pub fn resolution(&self) -> f32 {
+        self.resolved as f32 / self.events.max(1) as f32
+    }
+
+    fn span(&self) -> String {
+        let (first, last) = (self.first.format("%Y-%m-%d"), self.last.format("%Y-%m-%d"));
+        if self.first.date_naive() == self.last.date_naive() {
+            first.to_string()
+        } else {
+            format!("{first} to {last}")
+        }
+    }
+}
+
+// Most events first, then by name
+pub fn summary(events: &[ActivationEvent], figure_regions: &[Region]) -> Vec<Striker> {
+    let mut by_name: BTreeMap<&str, (Striker, BTreeSet<String>)> = BTreeMap::new();
+    for ev in events {
+        let (s, hit) = by_name.entry(ev.performed_by.as_str()).or_insert_with(|| {
+            let s = Striker {
+                name: ev.performed_by.clone(),
+                events: 0,
+                first: ev.date,
+                last: ev.date,
+                regions: vec![],
+                resolved: 0,
+                failed: 0,
+            };
+            (s, BTreeSet::new())
+        });
+        s.events += 1;
+        s.first = s.first.min(ev.date);
+        s.last = s.last.max(ev.date);
+        match ev.outcome {
+            Outcome::Resolved => s.resolved += 1,
+            Outcome::Failed => s.failed += 1,
+            Outcome::Pending => {}
+        }
+        if let Some(r) = regions::hit(figure_regions, ev.pos) {
+            hit.insert(r.name.clone());
+        }
+    }
+    let mut rows: Vec<Striker> = by_name
+        .into_values()
+        .map(|(mut s, hit)| {
+            s.regions = hit.into_iter().collect();
+            s
+        })
+        .collect();
+    rows.sort_by(|a, b| b.events.cmp(&a.events).then_with(|| a.name.cmp(&b.name)));
+    rows
+}
+
+pub fn csv(rows: &[Striker]) -> String {
+    let quote = |s: &str| {
+        if s.contains(',') || s.contains('"') || s.contains('\n') {
+            format!("\"{}\"", s.replace('"', "\"\""))
+        } else {
+            s.to_string()
+        }
+    };
+    let mut out = "striker,events,first,last,regions,resolved,failed,pending,resolution_rate\n".to_string();
+    for s in rows {
+        let cells = [
+            quote(&s.name),
+            s.events.to_string(),
+            s.first.to_rfc3339(),
+            s.last.to_rfc3339(),
+            quote(&s.regions.join("; ")),
+            s.resolved.to_string(),
+            s.failed.to_string(),
+            (s.events - s.resolved - s.failed).to_string(),
+            format!("{:.3}", s.resolution()),
+        ];
+        out.push_str(&cells.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+// Contributors as a Markdown list, for a project's acknowledgments
+pub fn markdown(figure: &str, rows: &[Striker]) -> String {
+    let escape = |s: &str| {
+        s.chars().fold(String::new(), |mut out, c| {
+            if "\\`*_[]<>#|".contains(c) {
+                out.push('\\');
+            }
+            out.push(c);
+            out
+        })
+    };
+    let mut out = format!("# Contributors to {}\n\nWith thanks to everyone who struck the figure:\n\n", escape(figure));
+    for s in rows {
+        let regions = if s.regions.is_empty() { String::new() } else { format!("; {}", s.regions.join(", ")) };
+        let line = format!("- **{}**: {}, {}{regions}\n", escape(&s.name), confirm::count(s.events, "event"), s.span());
+        out.push_str(&line);
+    }
+    out
+}
+
+pub fn view(rows: Vec<Striker>) -> Element<'static, Message> {
+    let dim = Color::from_rgb(0.7, 0.7, 0.8);
+    let mut col = column![row![
+        text(format!("Strikers ({})", rows.len())).size(16),
+        button("Export").on_press(Message::ExportStrikers),
+        button("Close").style(button::secondary).on_press(Message::ShowStrikers(false)),
+    ]
+    .spacing(10)]
+    .spacing(4);
+    if rows.is_empty() {
+        col = col.push(text("No events yet.").size(12).color(dim));
+    }
+    for (rank, s) in rows.iter().enumerate().take(SHOWN) {
+        let regions = if s.regions.is_empty() { "-".to_string() } else { s.regions.join(", ") };
+        col = col.push(
+            row![
+                text(format!("{}.", rank + 1)).size(13).width(30),
+                text(s.name.clone()).size(13).width(160),
+                text(confirm::count(s.events, "event")).size(13).width(80),
+                text(s.span()).size(13).width(190),
+                text(format!("{:.0}% resolved", s.resolution() * 100.0)).size(13).width(100),
+                text(regions).size(13).color(dim),
+            ]
+            .spacing(8),
+        );
+    }
+    if rows.len() > SHOWN {
+        col = col.push(text(format!("… and {} more in the export", rows.len() - SHOWN)).size(12).color(dim));
+    }
+    col.into()
+}
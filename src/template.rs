@@ -0,0 +1,270 @@
+// -------------------- Report templates --------------------
+// A small subset of Jinja syntax, enough for institutions to lay out their
+// own reports: `{{ path.to.value }}` substitutes (HTML-escaped unless
+// followed by `| safe`), `{% for x in list %}…{% endfor %}` repeats and
+// `{% if value %}…{% else %}…{% endif %}` tests truthiness. Values come
+// from a JSON context built by the report module. It is our own engine,
+// not Jinja: there are no expressions, loop variables, whitespace control
+// or includes. Other tags and filters are refused when the template is
+// read; anything else between {{ }} is taken for a name and renders as
+// nothing.
+use serde_json::Value;
+
+#[derive(Debug)]
+enum Node {
+    Text(String),
+    Var { path: Vec<String>, raw: bool },
+    For { var: String, path: Vec<String>, body: Vec<Node> },
+    If { path: Vec<String>, then: Vec<Node>, otherwise: Vec<Node> },
+}
+
+pub struct Template {
+    nodes: Vec<Node>,
+    // Escape substitutions for HTML output
+    escape: bool,
+}
+
+enum Token<'a> {
+    Text(&'a str),
+    Var(&'a str),
+    Tag(&'a str),
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token<'_>>, String> {
+    let mut out = vec![];
+    let mut rest = src;
+    while let Some(i) = rest.find('{') {
+        let (close, var) = match rest[i..].get(..2) {
+            Some("{{") => ("}}", true),
+            Some("{%") => ("%}", false),
+            _ => {
+                out.push(Token::Text(&rest[..=i]));
+                rest = &rest[i + 1..];
+                continue;
+            }
+        };
+        out.push(Token::Text(&rest[..i]));
+        let body = &rest[i + 2..];
+        let end = body.find(close).ok_or_else(|| format!("unclosed {} near \"{}\"", &rest[i..i + 2], snippet(body)))?;
+        let inner = body[..end].trim();
+        out.push(if var { Token::Var(inner) } else { Token::Tag(inner) });
+        rest = &body[end + 2..];
+    }
+    out.push(Token::Text(rest));
+    Ok(out)
+}
+
+fn snippet(s: &str) -> &str {
+    s.char_indices().nth(20).map_or(s, |(i, _)| &s[..i])
+}
+
+fn path(s: &str) -> Vec<String> {
+    s.split('.').map(|p| p.trim().to_string()).collect()
+}
+
+// Nodes up to one of `ends`, returning which end tag stopped it
+fn parse<'a>(
+    tokens: &mut std::vec::IntoIter<Token<'a>>,
+    ends: &[&str],
+) -> Result<(Vec<Node>, Option<&'a str>), String> {
+    let mut nodes = vec![];
+    while let Some(tok) = tokens.next() {
+        match tok {
+            Token::Text(t) if !t.is_empty() => nodes.push(Node::Text(t.to_string())),
+            Token::Text(_) => {}
+            Token::Var(v) => {
+                let (p, raw) = match v.split_once('|') {
+                    Some((p, f)) if f.trim() == "safe" => (p, true),
+                    Some((_, f)) => return Err(format!("unknown filter \"{}\"", f.trim())),
+                    None => (v, false),
+                };
+                nodes.push(Node::Var { path: path(p), raw });
+            }
+            Token::Tag(tag) => {
+                let words: Vec<&str> = tag.split_whitespace().collect();
+                match words[..] {
+                    [end] if ends.contains(&end) => return Ok((nodes, Some(end))),
+                    [end @ ("else" | "endif" | "endfor")] => {
+                        return Err(format!("{{% {end} %}} without an opening tag"))
+                    }
+                    ["for", var, "in", list] => {
+                        let (body, _) = closed(tokens, &["endfor"], "for")?;
+                        nodes.push(Node::For { var: var.into(), path: path(list), body });
+                    }
+                    ["if", cond] => {
+                        let (then, end) = closed(tokens, &["else", "endif"], "if")?;
+                        let otherwise = if end == "else" { closed(tokens, &["endif"], "if")?.0 } else { vec![] };
+                        nodes.push(Node::If { path: path(cond), then, otherwise });
+                    }
+                    _ => return Err(format!("unknown tag {{% {tag} %}}")),
+                }
+            }
+        }
+    }
+    Ok((nodes, None))
+}
+
+fn closed<'a>(
+    tokens: &mut std::vec::IntoIter<Token<'a>>,
+    ends: &[&str],
+    what: &str,
+) -> Result<(Vec<Node>, &'a str), String> {
+    match parse(tokens, ends)? {
+        (nodes, Some(end)) => Ok((nodes, end)),
+        (_, None) => Err(format!("{{% {what} %}} is never closed")),
+    }
+}
+
+impl Template {
+    pub fn parse(src: &str, escape: bool) -> Result<Self, String> {
+        let mut tokens = tokenize(src)?.into_iter();
+        let (nodes, _) = parse(&mut tokens, &[])?;
+        Ok(Self { nodes, escape })
+    }
+
+    // Unknown names render as nothing, as in Jinja
+    pub fn render(&self, context: &Value) -> String {
+        let mut out = String::new();
+        let mut scopes = vec![context.clone()];
+        self.render_nodes(&self.nodes, &mut scopes, &mut out);
+        out
+    }
+
+    fn render_nodes(&self, nodes: &[Node], scopes: &mut Vec<Value>, out: &mut String) {
+        for node in nodes {
+            match node {
+                Node::Text(t) => out.push_str(t),
+                Node::Var { path, raw } => {
+                    let text = match lookup(scopes, path) {
+                        Some(Value::String(s)) => s.clone(),
+                        Some(Value::Null) | None => String::new(),
+                        Some(v) => v.to_string(),
+                    };
+                    if self.escape && !raw {
+                        out.push_str(&escape_html(&text));
+                    } else {
+                        out.push_str(&text);
+                    }
+                }
+                Node::For { var, path, body } => {
+                    let items = match lookup(scopes, path) {
+                        Some(Value::Array(items)) => items.clone(),
+                        _ => vec![],
+                    };
+                    for item in items {
+                        scopes.push(serde_json::json!({ var.as_str(): item }));
+                        self.render_nodes(body, scopes, out);
+                        scopes.pop();
+                    }
+                }
+                Node::If { path, then, otherwise } => {
+                    let branch = if truthy(lookup(scopes, path)) { then } else { otherwise };
+                    self.render_nodes(branch, scopes, out);
+                }
+            }
+        }
+    }
+}
+
+// Innermost scope first
+fn lookup<'a>(scopes: &'a [Value], path: &[String]) -> Option<&'a Value> {
+    scopes.iter().rev().find_map(|scope| {
+        path.iter().try_fold(scope, |v, key| match v {
+            Value::Object(m) => m.get(key),
+            Value::Array(a) => key.parse::<usize>().ok().and_then(|i| a.get(i)),
+            _ => None,
+        })
+    })
+}
+
+fn truthy(v: Option<&Value>) -> bool {
+    match v {
+        None | Some(Value::Null) => false,
+        Some(Value::Bool(b)) => *b,
+        Some(Value::String(s)) => !s.is_empty(),
+        Some(Value::Array(a)) => !a.is_empty(),
+        Some(Value::Object(o)) => !o.is_empty(),
+        Some(Value::Number(n)) => n.as_f64() != Some(0.0),
+    }
+}
+
+// Safe in text and in attributes quoted either way
+pub fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(src: &str, context: Value) -> String {
+        Template::parse(src, true).unwrap().render(&context)
+    }
+
+    #[test]
+    fn substitutes_paths_escaped_unless_safe() {
+        let context = json!({ "figure": { "name": "<Nkisi> & 'co'", "count": 3 }, "list": ["a", "b"] });
+        assert_eq!(
+            render("{{ figure.name }}|{{figure.name | safe}}|{{ figure.count }}|{{ list.1 }}", context),
+            "&lt;Nkisi&gt; &amp; &#39;co&#39;|<Nkisi> & 'co'|3|b"
+        );
+        let plain = Template::parse("{{ name }}", false).unwrap();
+        assert_eq!(plain.render(&json!({ "name": "a < b" })), "a < b");
+    }
+
+    #[test]
+    fn unknown_names_render_as_nothing() {
+        assert_eq!(render("[{{ missing }}][{{ a.b.c }}][{{ n }}]", json!({ "a": 1, "n": null })), "[][][]");
+    }
+
+    #[test]
+    fn loops_and_conditions_nest() {
+        let src = "{% for s in spikes %}{% if s.note %}{{ s.id }}: {{ s.note }}{% else %}{{ s.id }}{% endif %};\
+                   {% endfor %}{% if none %}x{% else %}done{% endif %}";
+        let context = json!({ "spikes": [{ "id": 1, "note": "oath" }, { "id": 2, "note": "" }], "none": [] });
+        assert_eq!(render(src, context), "1: oath;2;done");
+    }
+
+    #[test]
+    fn loop_variables_shadow_outer_names_inside_the_loop_only() {
+        let context = json!({ "x": "outer", "xs": ["inner"] });
+        assert_eq!(render("{% for x in xs %}{{ x }}{% endfor %} {{ x }}", context), "inner outer");
+    }
+
+    #[test]
+    fn truthiness_follows_jinja() {
+        for (value, shown) in [
+            (json!(0), false),
+            (json!(0.5), true),
+            (json!(""), false),
+            (json!("0"), true),
+            (json!({}), false),
+            (json!(false), false),
+            (json!([0]), true),
+        ] {
+            assert_eq!(render("{% if v %}y{% endif %}", json!({ "v": value })), if shown { "y" } else { "" });
+        }
+    }
+
+    #[test]
+    fn lone_braces_are_text() {
+        assert_eq!(render("a { b } {c}", json!({})), "a { b } {c}");
+    }
+
+    #[test]
+    fn malformed_templates_are_refused() {
+        for (src, error) in [
+            ("{{ name", "unclosed {{ near \" name\""),
+            ("{% if x %}open", "{% if %} is never closed"),
+            ("{% for x in xs %}", "{% for %} is never closed"),
+            ("{% endif %}", "{% endif %} without an opening tag"),
+            ("{% if x %}{% endfor %}", "{% endfor %} without an opening tag"),
+            ("{% include \"x\" %}", "unknown tag {% include \"x\" %}"),
+            ("{%- if x %}{% endif %}", "unknown tag {% - if x %}"),
+            ("{{ name | upper }}", "unknown filter \"upper\""),
+        ] {
+            assert_eq!(Template::parse(src, true).err().as_deref(), Some(error), "{src}");
+        }
+    }
+}
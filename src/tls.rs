@@ -0,0 +1,93 @@
+// -------------------- FIX over TLS --------------------
+// The acceptor speaks plain TCP unless it is given a certificate chain and
+// private key (PEM); then every connection completes a TLS handshake before
+// the first FIX byte. With a client CA as well, counterparties must present
+// a certificate issued by it (mutual TLS) and anyone else is turned away
+// during the handshake. Without one, TLS only hides and protects the
+// traffic: who may connect is still down to the network and the FIX layer.
+use rustls::crypto::{ring, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig, ServerConnection, StreamOwned};
+use std::io;
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::session::Transport;
+use crate::IoError;
+
+// How long a counterparty gets to finish the handshake
+const HANDSHAKE: Duration = Duration::from_secs(10);
+
+pub type TlsStream = StreamOwned<ServerConnection, TcpStream>;
+
+impl Transport for TlsStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.sock.set_read_timeout(timeout)
+    }
+}
+
+// None when no certificate is configured: the acceptor stays plaintext
+pub fn server_config(
+    cert: Option<&str>,
+    key: Option<&str>,
+    client_ca: Option<&str>,
+) -> Result<Option<Arc<ServerConfig>>, IoError> {
+    let (cert, key) = match (cert, key) {
+        (Some(cert), Some(key)) => (cert, key),
+        (None, None) if client_ca.is_some() => {
+            return Err(IoError::Parse("a client CA needs a certificate and key as well".into()))
+        }
+        (None, None) => return Ok(None),
+        _ => return Err(IoError::Parse("TLS needs both a certificate and a key".into())),
+    };
+    let chain = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| IoError::Read(format!("{cert}: {e}")))?;
+    if chain.is_empty() {
+        return Err(IoError::Parse(format!("{cert}: no certificate found")));
+    }
+    let key = PrivateKeyDer::from_pem_file(key).map_err(|e| IoError::Read(format!("{key}: {e}")))?;
+
+    let provider = Arc::new(ring::default_provider());
+    let builder = ServerConfig::builder_with_provider(Arc::clone(&provider))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| IoError::Parse(e.to_string()))?;
+    let builder = match client_ca {
+        Some(ca) => builder.with_client_cert_verifier(client_verifier(ca, provider)?),
+        None => builder.with_no_client_auth(),
+    };
+    let config = builder.with_single_cert(chain, key).map_err(|e| IoError::Parse(format!("{cert}: {e}")))?;
+    Ok(Some(Arc::new(config)))
+}
+
+fn client_verifier(
+    ca: &str,
+    provider: Arc<CryptoProvider>,
+) -> Result<Arc<dyn rustls::server::danger::ClientCertVerifier>, IoError> {
+    let mut roots = RootCertStore::empty();
+    for der in CertificateDer::pem_file_iter(ca).map_err(|e| IoError::Read(format!("{ca}: {e}")))? {
+        let der = der.map_err(|e| IoError::Read(format!("{ca}: {e}")))?;
+        roots.add(der).map_err(|e| IoError::Parse(format!("{ca}: {e}")))?;
+    }
+    if roots.is_empty() {
+        return Err(IoError::Parse(format!("{ca}: no CA certificate found")));
+    }
+    WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+        .build()
+        .map_err(|e| IoError::Parse(format!("{ca}: {e}")))
+}
+
+// Complete the handshake on a fresh connection
+pub fn accept(mut tcp: TcpStream, config: Arc<ServerConfig>) -> io::Result<TlsStream> {
+    tcp.set_read_timeout(Some(HANDSHAKE))?;
+    tcp.set_write_timeout(Some(HANDSHAKE))?;
+    let mut conn = ServerConnection::new(config).map_err(io::Error::other)?;
+    while conn.is_handshaking() {
+        conn.complete_io(&mut tcp)?;
+    }
+    tcp.set_write_timeout(None)?;
+    Ok(StreamOwned::new(conn, tcp))
+}
@@ -0,0 +1,136 @@
+// -------------------- Onboarding tour --------------------
+// A short first-run walkthrough of the spike workflow. Each step names the
+// part of the UI it talks about; `highlight` outlines that part while the
+// step is shown.
+use iced::widget::{button, column, container, row, text};
+use iced::{Border, Color, Element, Theme};
+
+use crate::Message;
+
+// Written once the tour is finished or skipped, so it only opens by itself
+// on the first run.
+pub const TOUR_MARKER: &str = ".nkisi_tour_done";
+
+// Bundled ledger for the "Load sample" button (uses assets/nkisi.svg)
+pub const SAMPLE_LEDGER: &str = include_str!("../assets/sample_ledger.json");
+// Where Save puts it, apart from any real ledger
+pub const SAMPLE_SAVE_PATH: &str = "nkisi_sample.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TourTarget {
+    Figure,
+    Ledger,
+    Paths,
+    Status,
+}
+
+pub struct TourStep {
+    pub title: &'static str,
+    pub body: &'static str,
+    pub target: TourTarget,
+}
+
+pub const STEPS: &[TourStep] = &[
+    TourStep {
+        title: "The figure",
+        body: "Click anywhere on the figure to propose a spike at that spot. \
+               A pending spike form opens next to it.",
+        target: TourTarget::Figure,
+    },
+    TourStep {
+        title: "Confirming a spike",
+        body: "Enter who is striking and an optional message, then Confirm. \
+               The spike becomes a permanent event in the ledger.",
+        target: TourTarget::Figure,
+    },
+    TourStep {
+        title: "The ledger",
+        body: "Save writes every event to the save path as JSON, Load reads \
+               it back. Load sample opens a small ready-made ledger.",
+        target: TourTarget::Ledger,
+    },
+    TourStep {
+        title: "Files",
+        body: "The SVG path selects the figure artwork; the save path selects \
+               the ledger file.",
+        target: TourTarget::Paths,
+    },
+    TourStep {
+        title: "External spikes",
+        body: "Other systems can submit spikes over FIX (35=U1, 55=NKISI, \
+               448=striker, 6010/6011=position). Arrivals are reported here.",
+        target: TourTarget::Status,
+    },
+];
+
+pub fn first_run() -> bool {
+    !std::path::Path::new(TOUR_MARKER).exists()
+}
+
+pub fn mark_done() {
+    let _ = std::fs::write(TOUR_MARKER, b"");
+}
+
+pub fn current(step: Option<usize>) -> Option<&'static TourStep> {
+    step.and_then(|i| STEPS.get(i))
+}
+
+// Card shown at the top of the controls while the tour runs
+pub fn card(i: usize, fix_addr: &str) -> Element<'static, Message> {
+    let step = &STEPS[i];
+    let last = i + 1 == STEPS.len();
+    let body = if step.target == TourTarget::Status {
+        format!("{} Listening on {fix_addr}.", step.body)
+    } else {
+        step.body.to_string()
+    };
+
+    let mut nav = row![].spacing(10);
+    if i > 0 {
+        nav = nav.push(button("Back").on_press(Message::TourBack));
+    }
+    nav = nav.push(button(if last { "Done" } else { "Next" }).on_press(Message::TourNext));
+    if !last {
+        nav = nav.push(button("Skip tour").on_press(Message::TourEnd));
+    }
+
+    container(
+        column![
+            text(format!("Tour {}/{} • {}", i + 1, STEPS.len(), step.title)).size(18),
+            text(body),
+            nav,
+        ]
+        .spacing(8),
+    )
+    .padding(12)
+    .style(|_theme: &Theme| container::Style {
+        background: Some(Color::from_rgba(0.20, 0.18, 0.10, 0.95).into()),
+        border: Border { color: accent(), width: 1.0, radius: 12.0.into() },
+        ..Default::default()
+    })
+    .into()
+}
+
+// Outline `content` when the active step points at `target`
+pub fn highlight<'a>(
+    step: Option<usize>,
+    target: TourTarget,
+    content: impl Into<Element<'a, Message>>,
+) -> Element<'a, Message> {
+    let active = current(step).is_some_and(|s| s.target == target);
+    container(content)
+        .padding(4)
+        .style(move |_theme: &Theme| container::Style {
+            border: if active {
+                Border { color: accent(), width: 2.0, radius: 6.0.into() }
+            } else {
+                Border::default()
+            },
+            ..Default::default()
+        })
+        .into()
+}
+
+fn accent() -> Color {
+    Color::from_rgb(1.0, 0.78, 0.25)
+}
@@ -0,0 +1,105 @@
+// -------------------- Ingest transforms --------------------
+// Normalizing messy feeds before their spikes become events. Transforms
+// live in the workspace file (ingest.transforms); each may be limited to
+// a sender (SenderCompID) or an acceptor, and then:
+//   rename        striker names as sent -> as recorded, any case; also
+//                 whoever asks for an update or a cancel
+//   scale/offset  positions as sent become x * scale + offset, before the
+//                 out-of-figure policy and the rules see them
+//   default_note  the note of a spike sent without one
+// They apply in order, each to what the ones before it left. FIX spikes
+// and replays of stored days go through them; spike packs, merges and
+// spikes struck here don't. The app lists them and lets each be switched
+// off, like the validation rules.
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transform {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sender: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub listener: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rename: BTreeMap<String, String>,
+    #[serde(default = "unit", skip_serializing_if = "is_unit")]
+    pub scale: (f32, f32),
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub offset: (f32, f32),
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_note: Option<String>,
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+}
+
+fn unit() -> (f32, f32) {
+    (1.0, 1.0)
+}
+
+fn is_unit(v: &(f32, f32)) -> bool {
+    *v == unit()
+}
+
+fn is_zero(v: &(f32, f32)) -> bool {
+    *v == (0.0, 0.0)
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl Transform {
+    fn covers(&self, sender: &str, listener: Option<&str>) -> bool {
+        self.enabled
+            && self.sender.as_deref().is_none_or(|s| s == sender)
+            && self.listener.as_deref().is_none_or(|l| Some(l) == listener)
+    }
+
+    // One line for the transforms list
+    pub fn describe(&self) -> String {
+        let mut what = vec![];
+        if !self.rename.is_empty() {
+            what.push(format!("rename {} striker(s)", self.rename.len()));
+        }
+        if !is_unit(&self.scale) {
+            what.push(format!("scale ×{}, ×{}", self.scale.0, self.scale.1));
+        }
+        if !is_zero(&self.offset) {
+            what.push(format!("offset {:+}, {:+}", self.offset.0, self.offset.1));
+        }
+        if let Some(note) = &self.default_note {
+            what.push(format!("note \"{note}\" when none is sent"));
+        }
+        if what.is_empty() {
+            what.push("nothing".into());
+        }
+        let from = match (&self.sender, &self.listener) {
+            (Some(s), Some(l)) => format!("From {s} on {l}"),
+            (Some(s), None) => format!("From {s}"),
+            (None, Some(l)) => format!("On {l}"),
+            (None, None) => "All feeds".into(),
+        };
+        format!("{from}: {}", what.join(", "))
+    }
+}
+
+// Applies the transforms covering `sender` and `listener`: to the name,
+// and for a new spike (`spike`) to its position and note
+pub fn apply(
+    transforms: &[Transform],
+    (sender, listener): (&str, Option<&str>),
+    who: &mut String,
+    mut spike: Option<(&mut (f32, f32), &mut Option<String>)>,
+) {
+    for t in transforms.iter().filter(|t| t.covers(sender, listener)) {
+        if let Some((_, to)) = t.rename.iter().find(|(from, _)| from.trim().eq_ignore_ascii_case(who.trim())) {
+            *who = to.clone();
+        }
+        if let Some((pos, note)) = &mut spike {
+            **pos = (pos.0 * t.scale.0 + t.offset.0, pos.1 * t.scale.1 + t.offset.1);
+            if note.is_none() {
+                **note = t.default_note.clone();
+            }
+        }
+    }
+}
@@ -0,0 +1,156 @@
+// -------------------- Coordinate units --------------------
+// Positions are kept in figure units (the SVG's viewBox, or a photo's
+// pixels) and can be shown as those, as a percentage of the figure's width
+// and height, or in millimetres when the SVG says how large it prints (its
+// width and height with units; plain numbers are CSS pixels, 96 to the
+// inch). One choice, kept with the workspace settings, applies to every
+// panel, the status line and the reports. Journal notes stay in figure
+// units, whatever is shown.
+//
+// Positions can also be stored to a step (0.1 figure units, say), so two
+// clicks, nudges or FIX spikes meant for the same place are the same
+// position, and diffs and duplicate checks aren't thrown by float noise.
+use serde::{Deserialize, Serialize};
+
+const MM_PER_INCH: f32 = 25.4;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Unit {
+    #[default]
+    Figure,
+    Percent,
+    Millimeters,
+}
+
+impl Unit {
+    pub const ALL: [Unit; 3] = [Unit::Figure, Unit::Percent, Unit::Millimeters];
+}
+
+impl std::fmt::Display for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Unit::Figure => "Figure units",
+            Unit::Percent => "% of figure",
+            Unit::Millimeters => "Millimetres",
+        })
+    }
+}
+
+// The chosen unit with what converting to it takes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coords {
+    pub unit: Unit,
+    // Figure size in figure units, and printed in mm when known
+    pub dims: (f32, f32),
+    pub mm: Option<(f32, f32)>,
+}
+
+impl Coords {
+    // Millimetres fall back to figure units for a figure of unknown size
+    pub fn unit(self) -> Unit {
+        match (self.unit, self.mm) {
+            (Unit::Millimeters, None) => Unit::Figure,
+            (unit, _) => unit,
+        }
+    }
+
+    fn per_unit(self) -> (f32, f32) {
+        let (fw, fh) = self.dims;
+        match (self.unit(), self.mm) {
+            (Unit::Percent, _) => (100.0 / fw, 100.0 / fh),
+            (Unit::Millimeters, Some((mw, mh))) => (mw / fw, mh / fh),
+            _ => (1.0, 1.0),
+        }
+    }
+
+    pub fn convert(self, (x, y): (f32, f32)) -> (f32, f32) {
+        let (kx, ky) = self.per_unit();
+        (x * kx, y * ky)
+    }
+
+    // A position typed in the shown unit, back in figure units
+    pub fn to_figure(self, (x, y): (f32, f32)) -> (f32, f32) {
+        let (kx, ky) = self.per_unit();
+        (x / kx, y / ky)
+    }
+
+    // After a number, e.g. "12.5 mm"
+    pub fn suffix(self) -> &'static str {
+        match self.unit() {
+            Unit::Figure => "",
+            Unit::Percent => "%",
+            Unit::Millimeters => " mm",
+        }
+    }
+
+    pub fn format(self, pos: (f32, f32)) -> String {
+        let (x, y) = self.convert(pos);
+        let s = self.suffix();
+        format!("({x:.1}{s}, {y:.1}{s})")
+    }
+
+    // Just the numbers, for an input to edit
+    pub fn inputs(self, pos: (f32, f32)) -> (String, String) {
+        let (x, y) = self.convert(pos);
+        (format!("{x:.1}"), format!("{y:.1}"))
+    }
+}
+
+// Step positions are stored to, in figure units; None keeps them exact
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Precision(pub Option<f32>);
+
+impl Precision {
+    pub const ALL: [Precision; 5] =
+        [Precision(None), Precision(Some(1.0)), Precision(Some(0.1)), Precision(Some(0.01)), Precision(Some(0.001))];
+
+    pub fn is_exact(&self) -> bool {
+        self.step().is_none()
+    }
+
+    fn step(self) -> Option<f64> {
+        self.0.filter(|s| s.is_finite() && *s > 0.0).map(f64::from)
+    }
+
+    // The nearest step; worked in f64 so the same step always gives the
+    // same f32
+    pub fn snap(self, (x, y): (f32, f32)) -> (f32, f32) {
+        let Some(step) = self.step() else { return (x, y) };
+        let snap = |v: f32| if v.is_finite() { ((f64::from(v) / step).round() * step) as f32 } else { v };
+        (snap(x), snap(y))
+    }
+}
+
+impl std::fmt::Display for Precision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.step(), self.0) {
+            (Some(_), Some(step)) => write!(f, "To {step} units"),
+            _ => f.write_str("As placed"),
+        }
+    }
+}
+
+// Printed size in mm from an SVG's width and height attributes
+pub fn printed(width: Option<&str>, height: Option<&str>) -> Option<(f32, f32)> {
+    let (w, h) = (length_mm(width?)?, length_mm(height?)?);
+    (w > 0.0 && h > 0.0).then_some((w, h))
+}
+
+// "210mm", "8.5in", "300" (CSS px); percentages say nothing about print
+fn length_mm(s: &str) -> Option<f32> {
+    let s = s.trim();
+    let split = s.find(|c: char| c.is_ascii_alphabetic() || c == '%').unwrap_or(s.len());
+    let value: f32 = s[..split].trim().parse().ok()?;
+    let per = match &s[split..] {
+        "mm" => 1.0,
+        "cm" => 10.0,
+        "in" => MM_PER_INCH,
+        "pt" => MM_PER_INCH / 72.0,
+        "pc" => MM_PER_INCH / 6.0,
+        "" | "px" => MM_PER_INCH / 96.0,
+        _ => return None,
+    };
+    Some(value * per).filter(|v| v.is_finite())
+}
@@ -0,0 +1,145 @@
+// -------------------- Usage statistics --------------------
+// Counts for reporting on how much the tool is used: sessions, spikes added
+// (and how they came in) and how often each feature was used. They are kept
+// in USAGE_FILE beside the other .nkisi markers in the working directory and
+// never leave the machine; the Usage panel shows them, copies a summary for
+// a report and can start the counts over.
+use chrono::{DateTime, Utc};
+use iced::widget::{button, column, row, text};
+use iced::{Color, Element};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{confirm, IoError, Message};
+
+pub const USAGE_FILE: &str = ".nkisi_usage.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Usage {
+    // When counting started, and the latest session
+    pub since: Option<DateTime<Utc>>,
+    pub last_session: Option<DateTime<Utc>>,
+    pub sessions: u64,
+    // Spikes added, by how they came in ("by hand", "FIX", "pack", ...)
+    #[serde(default)]
+    pub spikes: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub features: BTreeMap<String, u64>,
+    // Counted since the last write
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl Usage {
+    // The counts so far, or fresh ones if there are none (or they can't be read)
+    pub fn load() -> Self {
+        match std::fs::read(USAGE_FILE) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                eprintln!("[usage] {USAGE_FILE}: {e}; counting from scratch");
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn start_session(&mut self) {
+        let now = Utc::now();
+        self.since.get_or_insert(now);
+        self.last_session = Some(now);
+        self.sessions += 1;
+        self.dirty = true;
+    }
+
+    pub fn spikes_added(&mut self, via: &str, n: usize) {
+        if n > 0 {
+            *self.spikes.entry(via.to_string()).or_default() += n as u64;
+            self.dirty = true;
+        }
+    }
+
+    pub fn used(&mut self, feature: &str) {
+        *self.features.entry(feature.to_string()).or_default() += 1;
+        self.dirty = true;
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self { since: Some(Utc::now()), dirty: true, ..Self::default() };
+    }
+
+    // Writes only when something was counted; a failed write is logged and
+    // tried again with the next count
+    pub fn save(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        let written = serde_json::to_vec_pretty(self)
+            .map_err(|e| IoError::Write(e.to_string()))
+            .and_then(|json| std::fs::write(USAGE_FILE, json).map_err(|e| IoError::Write(e.to_string())));
+        match written {
+            Ok(()) => self.dirty = false,
+            Err(e) => eprintln!("[usage] {USAGE_FILE}: {e}"),
+        }
+    }
+
+    fn total_spikes(&self) -> u64 {
+        self.spikes.values().sum()
+    }
+
+    // Features, most used first
+    fn ranked(&self) -> Vec<(&str, u64)> {
+        let mut ranked: Vec<(&str, u64)> = self.features.iter().map(|(f, n)| (f.as_str(), *n)).collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        ranked
+    }
+
+    // Plain text for pasting into a report
+    pub fn summary(&self) -> String {
+        let day = |at: Option<DateTime<Utc>>| at.map_or("-".to_string(), |at| at.format("%Y-%m-%d").to_string());
+        let mut out = format!(
+            "Rustic Nkisi usage since {} (last session {})\nSessions: {}\nSpikes added: {}\n",
+            day(self.since),
+            day(self.last_session),
+            self.sessions,
+            self.total_spikes()
+        );
+        for (via, n) in &self.spikes {
+            out.push_str(&format!("  {via}: {n}\n"));
+        }
+        out.push_str("Features used:\n");
+        for (feature, n) in self.ranked() {
+            out.push_str(&format!("  {feature}: {n}\n"));
+        }
+        out
+    }
+}
+
+pub fn view(usage: &Usage) -> Element<'_, Message> {
+    let since = usage.since.map_or("-".to_string(), |at| at.format("%Y-%m-%d").to_string());
+    let dim = Color::from_rgb(0.7, 0.7, 0.8);
+    let mut col = column![
+        row![
+            text(format!("Usage since {since}")).size(16),
+            button("Copy summary").on_press(Message::CopyText(usage.summary())),
+            button("Reset").style(button::danger).on_press(Message::ResetUsage),
+            button("Close").style(button::secondary).on_press(Message::ShowUsage(false)),
+        ]
+        .spacing(10),
+        text(format!(
+            "{} • {} added",
+            confirm::count(usage.sessions as usize, "session"),
+            confirm::count(usage.total_spikes() as usize, "spike")
+        )),
+        text("Kept on this machine only; nothing is sent anywhere.").size(12).color(dim),
+    ]
+    .spacing(4);
+    for (via, n) in &usage.spikes {
+        col = col.push(row![text(via.as_str()).width(140), text(n.to_string())].spacing(8));
+    }
+    if !usage.features.is_empty() {
+        col = col.push(text("Features used").size(14));
+    }
+    for (feature, n) in usage.ranked() {
+        col = col.push(row![text(feature).width(200), text(n.to_string()).size(13)].spacing(8));
+    }
+    col.into()
+}
@@ -0,0 +1,94 @@
+// -------------------- Figure viewport & named views --------------------
+// The figure can be magnified (scroll over it) and panned (drag with the
+// right button); the viewport is the part of it on screen. A named view
+// keeps a viewport together with the overlay toggles and the hidden layers,
+// so a region looked at in every review is one pick away. Views belong to
+// a figure and are kept in the workspace with it.
+use serde::{Deserialize, Serialize};
+
+use crate::overlay::OverlayOptions;
+
+pub const MAX_ZOOM: f32 = 8.0;
+// Magnification per scroll line
+const SCROLL_STEP: f32 = 1.25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Viewport {
+    pub zoom: f32,
+    // Middle of the view as a fraction of the figure's width and height
+    pub center: (f32, f32),
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self { zoom: 1.0, center: (0.5, 0.5) }
+    }
+}
+
+impl Viewport {
+    pub fn is_whole(&self) -> bool {
+        self.zoom <= 1.0
+    }
+
+    // Within MAX_ZOOM, and never showing anything past the figure's edges
+    fn clamped(self) -> Self {
+        let zoom = if self.zoom.is_finite() { self.zoom.clamp(1.0, MAX_ZOOM) } else { 1.0 };
+        let half = 0.5 / zoom;
+        let c = |v: f32| if v.is_finite() { v.clamp(half, 1.0 - half) } else { 0.5 };
+        Self { zoom, center: (c(self.center.0), c(self.center.1)) }
+    }
+
+    // The part shown, in figure coordinates: x, y, width, height
+    pub fn rect(&self, (fw, fh): (f32, f32)) -> (f32, f32, f32, f32) {
+        let v = self.clamped();
+        let (w, h) = (fw / v.zoom, fh / v.zoom);
+        (v.center.0 * fw - w / 2.0, v.center.1 * fh - h / 2.0, w, h)
+    }
+
+    // Where a point of the figure area on screen, as fractions of its
+    // width and height, falls on the figure
+    pub fn to_figure(self, dims: (f32, f32), (fx, fy): (f32, f32)) -> (f32, f32) {
+        let (x, y, w, h) = self.rect(dims);
+        ((x + fx * w).clamp(0.0, dims.0), (y + fy * h).clamp(0.0, dims.1))
+    }
+
+    // `lines` scrolled over the point at `at` (fractions of the figure area
+    // on screen), which stays where it is
+    pub fn scrolled(self, lines: f32, at: (f32, f32)) -> Self {
+        let zoom = (self.zoom * SCROLL_STEP.powf(lines)).clamp(1.0, MAX_ZOOM);
+        let (x, y) = (self.center.0 - 0.5 / self.zoom, self.center.1 - 0.5 / self.zoom);
+        let (px, py) = (x + at.0 / self.zoom, y + at.1 / self.zoom);
+        let center = (px + (0.5 - at.0) / zoom, py + (0.5 - at.1) / zoom);
+        Self { zoom, center }.clamped()
+    }
+
+    // Dragged by a fraction of the figure area on screen
+    pub fn panned(self, (dx, dy): (f32, f32)) -> Self {
+        Self { zoom: self.zoom, center: (self.center.0 - dx / self.zoom, self.center.1 - dy / self.zoom) }.clamped()
+    }
+
+    // An SVG drawn over the whole figure, cut down to the viewport: the
+    // root's viewBox is the only thing that changes
+    pub fn crop(&self, svg: String, (fw, fh): (f32, f32)) -> String {
+        if self.is_whole() {
+            return svg;
+        }
+        let (x, y, w, h) = self.rect((fw, fh));
+        svg.replacen(&format!(r#"viewBox="0 0 {fw} {fh}""#), &format!(r#"viewBox="{x} {y} {w} {h}""#), 1)
+    }
+
+    pub fn label(&self) -> String {
+        format!("{:.0}%", self.clamped().zoom * 100.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct View {
+    pub name: String,
+    #[serde(default)]
+    pub viewport: Viewport,
+    pub overlay: OverlayOptions,
+    // Base layers switched off, by name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hidden_layers: Vec<String>,
+}
@@ -0,0 +1,231 @@
+// -------------------- Event webhook --------------------
+// Every event that lands in the ledger is POSTed as JSON (the event as
+// saved in the ledger file) to a configured http:// URL, one request per
+// event, from a background thread that queues and retries while the
+// receiver is down (see outbox.rs). A 4xx answer makes the event a dead
+// letter at once: sending it again unchanged would get the same answer.
+//
+// Signature: with a secret set, each request carries
+//   X-Nkisi-Timestamp: <unix seconds when it was sent>
+//   X-Nkisi-Signature-256: sha256=<hex HMAC-SHA256(secret, "<timestamp>.<body>")>
+//   X-Nkisi-Signature: sha1=<hex HMAC-SHA1 of the same>
+// The SHA-1 header is still sent for receivers written against it; new
+// receivers should check X-Nkisi-Signature-256. To verify, recompute the HMAC over the timestamp header, a dot and the
+// raw body bytes as received, compare it with the header in constant time,
+// and refuse timestamps more than a few minutes from now so a captured
+// request can't be replayed later. X-Nkisi-Event names the event id, which
+// stays the same when a request is retried.
+use chrono::Utc;
+use crossbeam_channel::{unbounded, RecvTimeoutError, Sender};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::outbox::{Backoff, Channel, Outbox, MAX_ATTEMPTS};
+use crate::pack::hmac_sha1;
+use ring::hmac;
+use crate::ActivationEvent;
+
+// Oldest events become dead letters beyond this while the receiver is down
+const MAX_QUEUED: usize = 10_000;
+const RETRY: Duration = Duration::from_secs(5);
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Webhook {
+    tx: Sender<ActivationEvent>,
+    outbox: Arc<Outbox>,
+}
+
+// Where to connect, the Host header and the request path
+struct Target {
+    addr: String,
+    host: String,
+    path: String,
+}
+
+impl Webhook {
+    pub fn start(url: &str, secret: Option<String>, outbox: Arc<Outbox>) -> Result<Self, String> {
+        let target = target(url)?;
+        let (tx, rx) = unbounded::<ActivationEvent>();
+        let url = url.to_string();
+        let shared = Arc::clone(&outbox);
+        thread::spawn(move || {
+            let mut queue: VecDeque<ActivationEvent> = VecDeque::new();
+            let mut next_try = Instant::now();
+            let mut backoff = Backoff::new(RETRY);
+            // Failed tries of the event at the front
+            let mut attempts = 0;
+            let signed = if secret.is_some() { ", signed" } else { "" };
+            eprintln!("[webhook] posting events to {url}{signed}");
+            loop {
+                let wait = if queue.is_empty() {
+                    RETRY
+                } else {
+                    next_try.saturating_duration_since(Instant::now()).max(Duration::from_millis(100))
+                };
+                match rx.recv_timeout(wait) {
+                    Ok(ev) => queue.push_back(ev),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+                while queue.len() > MAX_QUEUED {
+                    if let Some(ev) = queue.pop_front() {
+                        shared.dead(Channel::Webhook, ev, 0, "queue full while the receiver was down");
+                    }
+                }
+                if Instant::now() < next_try {
+                    continue;
+                }
+                while let Some(ev) = queue.front() {
+                    let failed = match post(&target, ev, secret.as_deref()) {
+                        Ok(status) if (200..300).contains(&status) => {
+                            shared.delivered(Channel::Webhook, ev.id);
+                            None
+                        }
+                        Ok(status) if (400..500).contains(&status) => {
+                            let ev = ev.clone();
+                            shared.dead(Channel::Webhook, ev, attempts + 1, &format!("refused with {status}"));
+                            None
+                        }
+                        Ok(status) => Some(format!("answered {status}")),
+                        Err(e) => Some(e),
+                    };
+                    if let Some(why) = failed {
+                        attempts += 1;
+                        if attempts < MAX_ATTEMPTS {
+                            let pause = backoff.failed();
+                            eprintln!("[webhook] {url}: {why}; {} event(s) queued, next try in {pause:?}", queue.len());
+                            next_try = Instant::now() + pause;
+                            break;
+                        }
+                        shared.dead(Channel::Webhook, ev.clone(), attempts, &why);
+                    } else {
+                        backoff.reset();
+                    }
+                    attempts = 0;
+                    queue.pop_front();
+                }
+            }
+        });
+        Ok(Self { tx, outbox })
+    }
+
+    pub fn send<'a>(&self, events: impl IntoIterator<Item = &'a ActivationEvent>) {
+        for ev in events {
+            self.outbox.queued(Channel::Webhook, ev);
+            let _ = self.tx.send(ev.clone());
+        }
+    }
+}
+
+// "http://host:port/path"; TLS is left to a proxy in front of the receiver
+fn target(url: &str) -> Result<Target, String> {
+    let Some(rest) = url.strip_prefix("http://") else {
+        return Err(format!("{url}: only http:// webhook URLs are supported"));
+    };
+    let (host, path) = rest.split_once('/').map_or((rest, "/".to_string()), |(h, p)| (h, format!("/{p}")));
+    if host.is_empty() {
+        return Err(format!("{url}: no host"));
+    }
+    let addr = if host.rsplit_once(':').is_some_and(|(_, port)| port.parse::<u16>().is_ok()) {
+        host.to_string()
+    } else {
+        format!("{host}:80")
+    };
+    Ok(Target { addr, host: host.to_string(), path })
+}
+
+// The bytes both signatures cover: "<timestamp>.<body>"
+fn signed(timestamp: i64, body: &[u8]) -> Vec<u8> {
+    let mut signed = format!("{timestamp}.").into_bytes();
+    signed.extend_from_slice(body);
+    signed
+}
+
+pub fn hmac_sha256(key: &[u8], msg: &[u8]) -> Vec<u8> {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+    hmac::sign(&key, msg).as_ref().to_vec()
+}
+
+// As sent in X-Nkisi-Signature-256
+pub fn signature(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    format!("sha256={}", hex::encode(hmac_sha256(secret.as_bytes(), &signed(timestamp, body))))
+}
+
+// As sent in X-Nkisi-Signature, for receivers that predate the SHA-256 one
+pub fn legacy_signature(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    format!("sha1={}", hex::encode(hmac_sha1(secret.as_bytes(), &signed(timestamp, body))))
+}
+
+// The status code the receiver answered with
+fn post(target: &Target, ev: &ActivationEvent, secret: Option<&str>) -> Result<u16, String> {
+    let body = serde_json::to_vec(ev).map_err(|e| e.to_string())?;
+    let mut head = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         X-Nkisi-Event: {}\r\nConnection: close\r\n",
+        target.path,
+        target.host,
+        body.len(),
+        ev.id
+    );
+    if let Some(secret) = secret {
+        let now = Utc::now().timestamp();
+        head.push_str(&format!(
+            "X-Nkisi-Timestamp: {now}\r\nX-Nkisi-Signature-256: {}\r\nX-Nkisi-Signature: {}\r\n",
+            signature(secret, now, &body),
+            legacy_signature(secret, now, &body)
+        ));
+    }
+    head.push_str("\r\n");
+
+    let mut stream = TcpStream::connect(&target.addr).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(|e| e.to_string())?;
+    stream.write_all(head.as_bytes()).and_then(|()| stream.write_all(&body)).map_err(|e| e.to_string())?;
+    let mut status = String::new();
+    BufReader::new(&stream).read_line(&mut status).map_err(|e| e.to_string())?;
+    // "HTTP/1.1 204 No Content"
+    status.split_whitespace().nth(1).and_then(|code| code.parse().ok()).ok_or(format!("bad answer {status:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 4231, test cases 1, 2 and 6
+    #[test]
+    fn hmac_sha256_matches_rfc_4231() {
+        let cases: [(Vec<u8>, &[u8], &str); 3] = [
+            (vec![0x0b; 20], b"Hi There", "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"),
+            (
+                b"Jefe".to_vec(),
+                b"what do ya want for nothing?",
+                "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            ),
+            (
+                vec![0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First",
+                "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+            ),
+        ];
+        for (key, data, digest) in cases {
+            assert_eq!(hex::encode(hmac_sha256(&key, data)), digest);
+        }
+    }
+
+    #[test]
+    fn signatures_cover_timestamp_and_body() {
+        let body = br#"{"id":1}"#;
+        assert_eq!(
+            signature("whsec_test", 1_700_000_000, body),
+            "sha256=2f441ba4b3b2d50d28a9ab9d9fd8880376ecd1eb5d0435401553f5d8d0a5dcf8"
+        );
+        assert_eq!(
+            legacy_signature("whsec_test", 1_700_000_000, body),
+            "sha1=964ba35bc0e8c9c3ac8745dfa639d2bd61f17ac4"
+        );
+        assert_ne!(signature("whsec_test", 1_700_000_001, body), signature("whsec_test", 1_700_000_000, body));
+    }
+}
@@ -0,0 +1,231 @@
+// -------------------- Workspace --------------------
+// A `.nkisiproj` file groups several figures (each a ledger file plus its
+// SVG) with display settings and ingest configuration. Relative paths are
+// resolved against the project file's directory.
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::fields::{FieldDef, FormEntry};
+use crate::figure::Orientation;
+use crate::overlay::{builtin_profiles, OverlayOptions, OverlayProfile};
+use crate::palette::Appearance;
+use crate::quarantine;
+use crate::regions::Region;
+use crate::rules::Configured;
+use crate::transform::Transform;
+use crate::units;
+use crate::views::View;
+use crate::{IoError, FIX_ADDR};
+
+pub const EXTENSION: &str = "nkisiproj";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workspace {
+    pub name: String,
+    pub figures: Vec<FigureRef>,
+    #[serde(default)]
+    pub active: usize,
+    #[serde(default)]
+    pub settings: Settings,
+    #[serde(default)]
+    pub ingest: IngestConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FigureRef {
+    pub name: String,
+    pub ledger: String,
+    pub svg: String,
+    #[serde(default)]
+    pub regions: Vec<Region>,
+    // Turn/mirror of the figure file to match the ledger
+    #[serde(default, skip_serializing_if = "Orientation::is_identity")]
+    pub orientation: Orientation,
+    // Named viewports with their overlay and layers
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub views: Vec<View>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Settings {
+    #[serde(default)]
+    pub overlay: OverlayOptions,
+    #[serde(default = "builtin_profiles")]
+    pub profiles: Vec<OverlayProfile>,
+    // Extra typed inputs on the pending-spike form
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldDef>,
+    // Layout of the pending-spike form (see fields::layout)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub form: Vec<FormEntry>,
+    // Checks a spike must pass before it is recorded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<Configured>,
+    // Palette and contrast of the overlay, on screen and in exports
+    #[serde(default)]
+    pub appearance: Appearance,
+    // Zoom of the whole interface, text and controls alike
+    #[serde(default = "unit_scale")]
+    pub ui_scale: f32,
+    // Days for a spike's share of the intensity to halve; no decay when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub half_life_days: Option<f64>,
+    // How positions are shown: figure units, % of the figure or mm
+    #[serde(default)]
+    pub coordinate_unit: units::Unit,
+    // Step positions are stored to, in figure units; off keeps them as placed
+    #[serde(default, skip_serializing_if = "units::Precision::is_exact")]
+    pub precision: units::Precision,
+}
+
+fn unit_scale() -> f32 {
+    1.0
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            overlay: OverlayOptions::default(),
+            profiles: builtin_profiles(),
+            fields: vec![],
+            form: vec![],
+            rules: vec![],
+            appearance: Appearance::default(),
+            ui_scale: unit_scale(),
+            half_life_days: None,
+            coordinate_unit: units::Unit::default(),
+            precision: units::Precision::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngestConfig {
+    pub fix_addr: String,
+    // Monitoring endpoint (host:port) that receives a FIX copy of every event
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drop_copy: Option<String>,
+    // Where consumers connect for a FIX copy of every event
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drop_copy_listen: Option<String>,
+    // Upstream FIX engine that spikes confirmed here are published to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forward_to: Option<String>,
+    // Where ingest latency metrics are served over HTTP (/metrics)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics_addr: Option<String>,
+    // More acceptors beside fix_addr (which is "main"), e.g. a test feed;
+    // each spike records the one it came in on
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub listeners: Vec<Listener>,
+    // What becomes of a FIX spike outside the figure
+    #[serde(default)]
+    pub out_of_bounds: quarantine::Policy,
+    // Normalizing of incoming spikes, in order (transform.rs)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transforms: Vec<Transform>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Listener {
+    pub name: String,
+    pub addr: String,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        Self {
+            fix_addr: FIX_ADDR.into(),
+            drop_copy: None,
+            drop_copy_listen: None,
+            forward_to: None,
+            metrics_addr: None,
+            listeners: vec![],
+            out_of_bounds: quarantine::Policy::default(),
+            transforms: vec![],
+        }
+    }
+}
+
+// The ledger of the one figure a workspace starts with
+pub const DEFAULT_LEDGER: &str = "nkisi_state.json";
+
+impl Default for Workspace {
+    // What the app used to do implicitly: one figure, one state file
+    fn default() -> Self {
+        Self {
+            name: "Untitled".into(),
+            figures: vec![FigureRef {
+                name: "Nkisi".into(),
+                ledger: DEFAULT_LEDGER.into(),
+                svg: "assets/nkisi.svg".into(),
+                regions: vec![],
+                orientation: Orientation::default(),
+                views: vec![],
+            }],
+            active: 0,
+            settings: Settings::default(),
+            ingest: IngestConfig::default(),
+        }
+    }
+}
+
+impl Workspace {
+    pub fn active_figure(&self) -> Option<&FigureRef> {
+        self.figures.get(self.active)
+    }
+
+    pub fn active_regions(&self) -> &[Region] {
+        self.active_figure().map_or(&[], |f| f.regions.as_slice())
+    }
+
+    pub fn figure_for_ledger(&self, ledger: &str) -> Option<usize> {
+        self.figures.iter().position(|f| f.ledger == ledger)
+    }
+}
+
+pub fn open(path: &str) -> Result<Workspace, IoError> {
+    let bytes = std::fs::read(path).map_err(|e| IoError::Read(e.to_string()))?;
+    let mut ws: Workspace =
+        serde_json::from_slice(&bytes).map_err(|e| IoError::Parse(e.to_string()))?;
+    let base = Path::new(path).parent().unwrap_or(Path::new(""));
+    for fig in &mut ws.figures {
+        fig.ledger = resolve(base, &fig.ledger);
+        fig.svg = resolve(base, &fig.svg);
+    }
+    if ws.active >= ws.figures.len() {
+        ws.active = 0;
+    }
+    Ok(ws)
+}
+
+// Paths under the project directory are written relative to it, so the
+// project folder can be moved as a whole.
+pub fn save(path: &str, ws: &Workspace) -> Result<(), IoError> {
+    let base = Path::new(path).parent().unwrap_or(Path::new(""));
+    let mut out = ws.clone();
+    for fig in &mut out.figures {
+        fig.ledger = relativize(base, &fig.ledger);
+        fig.svg = relativize(base, &fig.svg);
+    }
+    let bytes = serde_json::to_vec_pretty(&out).map_err(|e| IoError::Write(e.to_string()))?;
+    std::fs::write(path, bytes).map_err(|e| IoError::Write(e.to_string()))
+}
+
+fn resolve(base: &Path, p: &str) -> String {
+    if Path::new(p).is_absolute() || base.as_os_str().is_empty() {
+        p.to_string()
+    } else {
+        base.join(p).to_string_lossy().into_owned()
+    }
+}
+
+fn relativize(base: &Path, p: &str) -> String {
+    if base.as_os_str().is_empty() {
+        return p.to_string();
+    }
+    Path::new(p)
+        .strip_prefix(base)
+        .map(|rel| rel.to_string_lossy().into_owned())
+        .unwrap_or_else(|_| p.to_string())
+}